@@ -1,20 +1,60 @@
 use std::path::PathBuf;
 
+/// Chat with the character, or test it with a list of comments.
+///
+/// What is left out is taken from the vtuber's settings in .env.
 #[derive(clap::Parser)]
 pub struct Cli {
-    #[arg(long, env)]
-    pub gemini_api_key: String,
-    pub ai_model: String,
+    /// GEMINI_API_KEY if unset
     #[arg(long)]
-    pub dataset: PathBuf,
-    #[arg(long, default_value = "User")]
-    pub title: String,
+    pub gemini_api_key: Option<String>,
+    /// VTUBER_AI_MODEL if unset
+    pub ai_model: Option<String>,
+    /// VTUBER_AI_DATASET if unset
     #[arg(long)]
-    pub template: PathBuf,
+    pub dataset: Option<PathBuf>,
+    /// VTUBER_AI_USER_TITLE if unset, else "User"
     #[arg(long)]
-    pub character_name: String,
+    pub title: Option<String>,
+    /// VTUBER_AI_SYSTEM_INSTRUCTION_TEMPLATE if unset
     #[arg(long)]
-    pub thinking: bool,
+    pub template: Option<PathBuf>,
+    /// VTUBER_AI_CHARACTER_NAME if unset
+    #[arg(long)]
+    pub character_name: Option<String>,
+    /// VTUBER_AI_THINKING if unset, else off
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub thinking: Option<bool>,
     #[arg(long)]
     pub model: Option<PathBuf>,
+    /// Render the layers chosen for every reply into this directory
+    #[arg(long, requires = "model")]
+    pub render_dir: Option<PathBuf>,
+    /// Shown below the chosen layers, the model's first base layer by default
+    #[arg(long, requires = "render_dir")]
+    pub base_layer: Option<String>,
+    /// The REPL if unset
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Commands {
+    /// Answer every comment of a JSON lines file, like `{"message": "..."}`, and write the
+    /// replies, chosen layers, errors and latency of each as JSON lines
+    Eval {
+        #[arg(long)]
+        input: PathBuf,
+        #[arg(long)]
+        output: PathBuf,
+        /// Send the comments as one conversation instead of each on its own
+        #[arg(long)]
+        keep_history: bool,
+    },
+    /// Answer one message and print the replies as JSON, for scripts
+    Ask {
+        /// Read from stdin if unset
+        #[arg(long)]
+        message: Option<String>,
+    },
 }
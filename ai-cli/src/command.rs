@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+
+const DEFAULT_SESSION: &str = "session.json";
+
+/// A slash command typed into the REPL instead of a message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Write the conversation to a file
+    Save(PathBuf),
+    /// Resume a saved conversation
+    Load(PathBuf),
+    /// Write the conversation as markdown
+    Export(PathBuf),
+    Temperature(f32),
+    Thinking(bool),
+    /// List the layers the model can choose from
+    Layers,
+    /// Forget the conversation
+    Reset,
+    /// Show the rendered system prompt
+    System,
+    /// Switch to another model
+    Model(String),
+}
+
+impl Command {
+    /// The command on the line, or `None` if it is a message.
+    pub fn parse(line: &str) -> Option<anyhow::Result<Self>> {
+        let line = line.trim().strip_prefix('/')?;
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+        let path = |index: usize, default: &str| {
+            PathBuf::from(args.get(index).copied().unwrap_or(default))
+        };
+        Some(match name {
+            "save" => Ok(Command::Save(path(0, DEFAULT_SESSION))),
+            "load" => Ok(Command::Load(path(0, DEFAULT_SESSION))),
+            "export" => match args.first() {
+                Some(&("markdown" | "md")) => Ok(Command::Export(path(1, "session.md"))),
+                _ => Err(anyhow::anyhow!("usage: /export markdown [path]")),
+            },
+            "temp" => match args.first().map(|value| value.parse()) {
+                Some(Ok(temperature)) => Ok(Command::Temperature(temperature)),
+                _ => Err(anyhow::anyhow!("usage: /temp 1.2")),
+            },
+            "thinking" => match args.first() {
+                Some(&"on") => Ok(Command::Thinking(true)),
+                Some(&"off") => Ok(Command::Thinking(false)),
+                _ => Err(anyhow::anyhow!("usage: /thinking on|off")),
+            },
+            "layers" => Ok(Command::Layers),
+            "reset" => Ok(Command::Reset),
+            "system" => Ok(Command::System),
+            "model" => match args.first() {
+                Some(model) => Ok(Command::Model(model.to_string())),
+                None => Err(anyhow::anyhow!("usage: /model gemini-2.0-flash")),
+            },
+            _ => Err(anyhow::anyhow!(
+                "unknown command /{name}, try /save, /load, /export markdown, /temp, /thinking, \
+                 /layers, /reset, /system or /model"
+            )),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::command::Command;
+
+    #[test]
+    fn parse_commands() {
+        assert!(Command::parse("hello /save").is_none());
+        assert_eq!(
+            Command::parse("/save").unwrap().unwrap(),
+            Command::Save(PathBuf::from("session.json"))
+        );
+        assert_eq!(
+            Command::parse(" /load old.json").unwrap().unwrap(),
+            Command::Load(PathBuf::from("old.json"))
+        );
+        assert_eq!(
+            Command::parse("/export markdown chat.md").unwrap().unwrap(),
+            Command::Export(PathBuf::from("chat.md"))
+        );
+        assert!(Command::parse("/export html").unwrap().is_err());
+        assert_eq!(
+            Command::parse("/temp 1.2").unwrap().unwrap(),
+            Command::Temperature(1.2)
+        );
+        assert!(Command::parse("/temp hot").unwrap().is_err());
+        assert_eq!(
+            Command::parse("/thinking off").unwrap().unwrap(),
+            Command::Thinking(false)
+        );
+        assert_eq!(
+            Command::parse("/model gemini-2.0-flash").unwrap().unwrap(),
+            Command::Model("gemini-2.0-flash".to_string())
+        );
+        assert!(Command::parse("/dance").unwrap().is_err());
+    }
+}
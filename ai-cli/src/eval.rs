@@ -0,0 +1,96 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ai::{chat, gemini::Gemini};
+use layer_composer::Model;
+
+use crate::session::Reply;
+
+/// A line of the input file.
+#[derive(Debug, serde::Deserialize)]
+struct EvalCase {
+    /// Copied into the result to match them up
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    message: String,
+}
+
+/// A line of the output file.
+#[derive(Debug, serde::Serialize)]
+struct EvalResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<serde_json::Value>,
+    message: String,
+    replies: Vec<Reply>,
+    /// The request failed or the answer didn't match the response schema
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    latency_ms: u128,
+}
+
+/// Send every comment of `input`, one JSON object like `{"message": "..."}` per line, and
+/// write what came back to `output`. Every comment starts a new conversation unless
+/// `keep_history` is set.
+pub async fn run(
+    llm: &mut Gemini<'_>,
+    model: Option<Arc<Model>>,
+    input: &Path,
+    output: &Path,
+    keep_history: bool,
+) -> anyhow::Result<()> {
+    let mut output = File::create(output)?;
+    let mut count = 0;
+    let mut failed = 0;
+    let mut total_latency = Duration::ZERO;
+
+    for (number, line) in BufReader::new(File::open(input)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let case: EvalCase = serde_json::from_str(&line)
+            .map_err(|err| anyhow::anyhow!("bad case on line {}: {err}", number + 1))?;
+        if !keep_history {
+            llm.clear_history();
+        }
+
+        let started_at = Instant::now();
+        let result = chat(&case.message, llm, model.clone()).await;
+        let latency = started_at.elapsed();
+        total_latency += latency;
+        count += 1;
+
+        let (replies, error) = match result {
+            Ok(responses) => (responses.iter().map(Reply::from).collect(), None),
+            Err(err) => {
+                failed += 1;
+                eprintln!("error: case {}: {err:#}", number + 1);
+                (Vec::new(), Some(format!("{err:#}")))
+            }
+        };
+        let result = EvalResult {
+            id: case.id,
+            message: case.message,
+            replies,
+            error,
+            latency_ms: latency.as_millis(),
+        };
+        serde_json::to_writer(&mut output, &result)?;
+        // keep what is done if the run is stopped
+        writeln!(output)?;
+        output.flush()?;
+    }
+
+    if count > 0 {
+        eprintln!(
+            "{count} cases, {failed} failed, {} ms on average",
+            (total_latency / count).as_millis()
+        );
+    }
+    Ok(())
+}
@@ -1,22 +1,60 @@
-use std::{borrow::Cow, collections::BTreeMap, fs::File, io::Read, sync::Arc};
+use std::{borrow::Cow, collections::BTreeMap, fs::File, io::Read, path::PathBuf, sync::Arc};
 
 use ai::{Dataset, SystemPromptRenderer, chat, gemini::Gemini};
 use clap::Parser;
+use config::{get_env, get_secret, parse_env};
 use layer_composer::Model;
 use rustyline::error::ReadlineError;
 
-use crate::cli::Cli;
+use crate::{
+    cli::{Cli, Commands},
+    command::Command,
+    render::Renderer,
+    session::{Reply, Session},
+};
 
 mod cli;
+mod command;
+mod eval;
+mod render;
+mod session;
 
 pub async fn run() -> anyhow::Result<()> {
     let args = Cli::parse();
+    dotenvy::dotenv().ok();
+
+    // what the flags leave out is read from the vtuber's settings, one by one so a flag covers
+    // a setting missing there
+    let api_key = args
+        .gemini_api_key
+        .map_or_else(|| get_secret("GEMINI_API_KEY"), Ok)?;
+    let ai_model = args
+        .ai_model
+        .map_or_else(|| get_env("VTUBER_AI_MODEL"), Ok)?;
+    let thinking = match args.thinking {
+        Some(thinking) => thinking,
+        None => parse_env("VTUBER_AI_THINKING")?.unwrap_or(false),
+    };
+    let character_name = args
+        .character_name
+        .map_or_else(|| get_env("VTUBER_AI_CHARACTER_NAME"), Ok)?;
+    let title = args
+        .title
+        .or_else(|| get_env("VTUBER_AI_USER_TITLE").ok())
+        .unwrap_or_else(|| "User".to_string());
+    let dataset = args
+        .dataset
+        .map_or_else(|| get_env("VTUBER_AI_DATASET").map(PathBuf::from), Ok)?;
+    let template = args.template.map_or_else(
+        || get_env("VTUBER_AI_SYSTEM_INSTRUCTION_TEMPLATE").map(PathBuf::from),
+        Ok,
+    )?;
+
     // format system instruction
-    let dataset = Dataset::from_reader(&mut File::open(args.dataset)?, false)?;
-    let character_name = args.character_name;
-    let prompt = SystemPromptRenderer::new(&character_name, &args.title, &dataset);
-    let mut template = String::new();
-    File::open(args.template)?.read_to_string(&mut template)?;
+    let dataset = Dataset::from_reader(&mut File::open(dataset)?, false)?;
+    let prompt = SystemPromptRenderer::new(&character_name, &title, &dataset);
+    let mut template_text = String::new();
+    File::open(template)?.read_to_string(&mut template_text)?;
 
     let model = args
         .model
@@ -29,7 +67,7 @@ pub async fn run() -> anyhow::Result<()> {
         .map_err(|_err| anyhow::anyhow!("failed to open the model"))?;
 
     let system_instruction = prompt.format_with_template(
-        &template,
+        &template_text,
         model.as_ref().map(|m| {
             m.layer_descriptions()
                 .iter()
@@ -40,23 +78,61 @@ pub async fn run() -> anyhow::Result<()> {
 
     // create llm instance
     let mut llm = Gemini::new(
-        &args.gemini_api_key,
-        &args.ai_model,
-        Some(Cow::Owned(system_instruction)),
+        &api_key,
+        &ai_model,
+        Some(Cow::Borrowed(system_instruction.as_str())),
     );
-    llm.set_thinking(args.thinking);
+    llm.set_thinking(thinking);
 
     // apply response schema
     llm.set_json_schema::<Vec<ai::AIResponseModel>>();
 
+    let mut renderer = match (&args.render_dir, &model) {
+        (Some(dir), Some(model)) => Some(Renderer::new(model, args.base_layer.clone(), dir)?),
+        _ => None,
+    };
+
+    match &args.command {
+        Some(Commands::Eval {
+            input,
+            output,
+            keep_history,
+        }) => return eval::run(&mut llm, model, input, output, *keep_history).await,
+        Some(Commands::Ask { message }) => {
+            let message = match message {
+                Some(message) => message.clone(),
+                None => std::io::read_to_string(std::io::stdin())?,
+            };
+            let message = message.trim();
+            if message.is_empty() {
+                anyhow::bail!("no message given, pass --message or write it to stdin");
+            }
+            let responses = chat(message, &mut llm, model).await?;
+            let mut replies = Vec::with_capacity(responses.len());
+            for res in &responses {
+                let mut reply = Reply::from(res);
+                if let Some(renderer) = &mut renderer {
+                    reply.image = Some(renderer.render(&res.layers)?);
+                }
+                replies.push(reply);
+            }
+            println!("{}", serde_json::to_string_pretty(&replies)?);
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let mut session = Session::default();
+    // one editor for the whole session, so the arrow keys recall earlier lines
+    let mut rl = rustyline::DefaultEditor::new()?;
     loop {
-        let mut rl = rustyline::DefaultEditor::new()?;
         let readline = rl.readline(">>> ");
         let line = match readline {
             Ok(line) => {
                 if line.trim().is_empty() {
                     continue;
                 }
+                rl.add_history_entry(&line)?;
                 line
             }
             Err(ReadlineError::Interrupted) => {
@@ -74,15 +150,94 @@ pub async fn run() -> anyhow::Result<()> {
                 break;
             }
         };
+        if let Some(command) = Command::parse(&line) {
+            let result = command.and_then(|command| match command {
+                Command::Save(path) => {
+                    session.save(&path)?;
+                    println!("saved {} turns to {}", session.turns.len(), path.display());
+                    Ok(())
+                }
+                Command::Load(path) => {
+                    session = Session::load(&path)?;
+                    llm.clear_history();
+                    for turn in &session.turns {
+                        llm.push_turn(&turn.message, &turn.answer);
+                    }
+                    println!(
+                        "loaded {} turns from {}",
+                        session.turns.len(),
+                        path.display()
+                    );
+                    Ok(())
+                }
+                Command::Export(path) => {
+                    std::fs::write(&path, session.to_markdown(&character_name, &title))?;
+                    println!("exported to {}", path.display());
+                    Ok(())
+                }
+                Command::Temperature(temperature) => {
+                    llm.set_temperature(temperature);
+                    println!("temperature set to {temperature}");
+                    Ok(())
+                }
+                Command::Thinking(thinking) => {
+                    llm.set_thinking(thinking);
+                    println!("thinking {}", if thinking { "on" } else { "off" });
+                    Ok(())
+                }
+                Command::Layers => {
+                    let Some(model) = &model else {
+                        anyhow::bail!("no model given, see --model");
+                    };
+                    for (index, layer) in model.layer_descriptions() {
+                        println!("{index:>3} {}: {}", layer.name, layer.description);
+                    }
+                    Ok(())
+                }
+                Command::Reset => {
+                    llm.clear_history();
+                    session = Session::default();
+                    println!("conversation cleared");
+                    Ok(())
+                }
+                Command::System => {
+                    println!("{system_instruction}");
+                    Ok(())
+                }
+                Command::Model(name) => {
+                    println!("switched to {name}");
+                    llm.set_model(name);
+                    Ok(())
+                }
+            });
+            if let Err(err) = result {
+                eprintln!("error: {err}");
+            }
+            continue;
+        }
+
         let responses = chat(&line, &mut llm, model.clone()).await?;
-        for res in responses {
+        let mut replies = Vec::with_capacity(responses.len());
+        for res in &responses {
             println!(
                 "{} (ja: {}) (layers: {})",
                 res.response,
                 res.japanese_response,
                 res.layers.join(", ")
             );
+            let mut reply = Reply::from(res);
+            if let Some(renderer) = &mut renderer {
+                match renderer.render(&res.layers) {
+                    Ok(path) => {
+                        println!("  -> {}", path.display());
+                        reply.image = Some(path);
+                    }
+                    Err(err) => eprintln!("error: failed to render {:?}: {err}", res.layers),
+                }
+            }
+            replies.push(reply);
         }
+        session.push(&line, llm.last_answer().unwrap_or_default(), replies);
     }
 
     Ok(())
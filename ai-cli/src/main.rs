@@ -1,6 +1,6 @@
 use ai_cli::run;
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()>{
+async fn main() -> anyhow::Result<()> {
     run().await
 }
@@ -0,0 +1,72 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use layer_composer::{LayerManifest, Model};
+
+/// Writes the layers the AI chose for every reply as an image, to check the expressions from
+/// the terminal.
+pub struct Renderer {
+    model: Model,
+    /// Shown below the chosen layers unless the AI chose a base layer itself
+    base_layer: String,
+    dir: PathBuf,
+    next_index: usize,
+}
+
+impl Renderer {
+    /// Render into `dir`, on `base_layer` or the model's first base layer.
+    pub fn new(model: &Model, base_layer: Option<String>, dir: &Path) -> anyhow::Result<Self> {
+        let base_layer = match base_layer {
+            Some(base_layer) => base_layer,
+            None => model
+                .manifest()
+                .layers
+                .iter()
+                .find(|(_, layer)| matches!(layer, LayerManifest::BaseLayer { .. }))
+                .map(|(name, _)| name.clone())
+                .ok_or_else(|| anyhow::anyhow!("the model has no base layer"))?,
+        };
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            model: model.clone(),
+            base_layer,
+            dir: dir.to_path_buf(),
+            next_index: 1,
+        })
+    }
+
+    /// Render the layers into the next free `0001.png`, `0002.png`, ... and return its path.
+    pub fn render(&mut self, layers: &[String]) -> anyhow::Result<PathBuf> {
+        let layers = with_base_layer(&self.model, &self.base_layer, layers);
+        let image = self.model.render(&layers)?;
+
+        let path = loop {
+            let path = self.dir.join(format!("{:04}.png", self.next_index));
+            self.next_index += 1;
+            if !path.exists() {
+                break path;
+            }
+        };
+        image.save(&path)?;
+        Ok(path)
+    }
+}
+
+/// The chosen layers on the base layer, unless one of them is a base layer.
+fn with_base_layer(model: &Model, base_layer: &str, layers: &[String]) -> Vec<String> {
+    let has_base = layers.iter().any(|name| {
+        matches!(
+            model.manifest().layers.get(name),
+            Some(LayerManifest::BaseLayer { .. })
+        )
+    });
+    if has_base {
+        layers.to_vec()
+    } else {
+        std::iter::once(base_layer.to_string())
+            .chain(layers.iter().cloned())
+            .collect()
+    }
+}
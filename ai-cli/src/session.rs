@@ -0,0 +1,107 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use ai::AIResponse;
+
+/// One line the character answered with.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Reply {
+    pub response: String,
+    pub japanese_response: String,
+    /// Names of the chosen layers
+    pub layers: Vec<String>,
+    /// The layers rendered with `--render-dir`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<PathBuf>,
+}
+
+impl From<&AIResponse> for Reply {
+    fn from(res: &AIResponse) -> Self {
+        Self {
+            response: res.response.clone(),
+            japanese_response: res.japanese_response.clone(),
+            layers: res.layers.clone(),
+            image: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Turn {
+    pub message: String,
+    /// The model's answer as it was returned, given back to it when resuming
+    pub answer: String,
+    pub replies: Vec<Reply>,
+}
+
+/// The conversation of a REPL session, saved with `/save` and resumed with `/load`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Session {
+    pub turns: Vec<Turn>,
+}
+
+impl Session {
+    pub fn push(&mut self, message: &str, answer: &str, replies: Vec<Reply>) {
+        self.turns.push(Turn {
+            message: message.to_string(),
+            answer: answer.to_string(),
+            replies,
+        });
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// The conversation for reading, `title` is what the character calls the user.
+    pub fn to_markdown(&self, character_name: &str, title: &str) -> String {
+        let mut markdown = format!("# {character_name}\n");
+        for turn in &self.turns {
+            markdown.push_str(&format!("\n**{title}:** {}\n", turn.message));
+            for reply in &turn.replies {
+                markdown.push_str(&format!(
+                    "\n**{character_name}:** {}\n\n> {}\n",
+                    reply.response, reply.japanese_response
+                ));
+                if !reply.layers.is_empty() {
+                    markdown.push_str(&format!("\n*Layers: {}*\n", reply.layers.join(", ")));
+                }
+                if let Some(image) = &reply.image {
+                    markdown.push_str(&format!("\n![]({})\n", image.display()));
+                }
+            }
+        }
+        markdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ai::AIResponse;
+
+    use crate::session::{Reply, Session};
+
+    #[test]
+    fn export_markdown() {
+        let mut session = Session::default();
+        let reply = Reply::from(&AIResponse {
+            response: "你好".to_string(),
+            japanese_response: "こんにちは".to_string(),
+            layers: vec!["smile.png".to_string()],
+            poll: None,
+            effect: None,
+        });
+        session.push("hello", "[]", vec![reply]);
+        assert_eq!(
+            session.to_markdown("Murasame", "User"),
+            "# Murasame\n\n**User:** hello\n\n**Murasame:** 你好\n\n> こんにちは\n\n*Layers: smile.png*\n"
+        );
+    }
+}
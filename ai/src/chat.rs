@@ -9,6 +9,16 @@ pub struct AIResponse {
     pub response: String,
     pub japanese_response: String,
     pub layers: Vec<String>,
+    /// A poll the AI wants to start
+    pub poll: Option<PollProposal>,
+    /// Name of the voice effect the AI picked
+    pub effect: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PollProposal {
+    pub question: String,
+    pub options: Vec<String>,
 }
 
 pub async fn chat(
@@ -21,6 +31,13 @@ pub async fn chat(
     Ok(responses
         .into_iter()
         .map(move |res| AIResponse {
+            poll: (!res.poll_question.is_empty() && res.poll_options.len() >= 2).then_some(
+                PollProposal {
+                    question: res.poll_question,
+                    options: res.poll_options,
+                },
+            ),
+            effect: (!res.effect.is_empty()).then_some(res.effect),
             response: res.response,
             japanese_response: res.japanese_response,
             layers: res
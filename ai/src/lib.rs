@@ -3,10 +3,15 @@ mod dataset;
 mod llm;
 mod model;
 mod prompt;
+mod tool;
 pub(crate) mod utils;
 
-pub use chat::{AIResponse, chat};
+pub use chat::{AIResponse, PollProposal, chat};
 pub use dataset::{Dataset, Dialogue};
-pub use llm::{LLM, gemini};
-pub use model::{UsageExample, response::AIResponseModel};
+pub use llm::{LLM, gemini, mock};
+pub use model::{
+    UsageExample, moderation::ModerationResponseModel, response::AIResponseModel,
+    topic::TopicResponseModel,
+};
 pub use prompt::SystemPromptRenderer;
+pub use tool::{Tool, parameters_for};
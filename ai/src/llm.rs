@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 
 pub mod gemini;
+pub mod mock;
 
 #[async_trait]
 pub trait LLM {
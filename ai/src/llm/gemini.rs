@@ -1,7 +1,7 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 
 use crate::{
-    LLM,
+    LLM, Tool,
     utils::{inlined_openapi_schema_for, sanitize_for_gemini_response_schema},
 };
 use async_trait::async_trait;
@@ -9,11 +9,34 @@ use schemars::JsonSchema;
 use serde_json::Value as JsonValue;
 
 pub struct Gemini<'a> {
-    api_key: &'a str,
-    model: &'a str,
+    api_key: Cow<'a, str>,
+    model: Cow<'a, str>,
     system_prompt: Option<Cow<'a, str>>,
     chat_history: Vec<Message>,
     generation_config: GenerationConfig,
+    tools: Vec<Arc<dyn Tool>>,
+    last_usage: Option<Usage>,
+}
+
+/// Tokens billed for a message, including the tool calls and thoughts before the answer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// The model may call tools this often for one message, then it has to answer.
+const MAX_TOOL_ROUNDS: usize = 3;
+
+/// What a request lets the model do.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Answer {
+    /// Call the tools or answer in text
+    Tools,
+    /// Answer in text, with the tools declared for the calls made before
+    Text,
+    /// Answer as configured, in the JSON schema if one is set, without tools
+    Final,
 }
 
 pub enum Role {
@@ -62,13 +85,19 @@ impl Default for ThinkingConfig {
 }
 
 impl<'a> Gemini<'a> {
-    pub fn new(api_key: &'a str, model: &'a str, system_prompt: Option<Cow<'a, str>>) -> Self {
+    pub fn new(
+        api_key: impl Into<Cow<'a, str>>,
+        model: impl Into<Cow<'a, str>>,
+        system_prompt: Option<Cow<'a, str>>,
+    ) -> Self {
         Self {
-            api_key,
-            model,
+            api_key: api_key.into(),
+            model: model.into(),
             system_prompt,
             chat_history: Vec::new(),
             generation_config: GenerationConfig::default(),
+            tools: Vec::new(),
+            last_usage: None,
         }
     }
 
@@ -80,6 +109,66 @@ impl<'a> Gemini<'a> {
         }
     }
 
+    pub fn set_temperature(&mut self, temperature: f32) {
+        self.generation_config.temperature = temperature;
+    }
+
+    /// Switch to another model, e.g. `gemini-2.0-flash`, keeping the conversation.
+    pub fn set_model(&mut self, model: impl Into<Cow<'a, str>>) {
+        self.model = model.into();
+    }
+
+    /// Replace the system prompt, keeping the conversation.
+    pub fn set_system_prompt(&mut self, system_prompt: Option<Cow<'a, str>>) {
+        self.system_prompt = system_prompt;
+    }
+
+    /// Let the model call these tools before answering. Only the answers are kept in the
+    /// conversation, not the calls.
+    pub fn set_tools(&mut self, tools: Vec<Arc<dyn Tool>>) {
+        self.tools = tools;
+    }
+
+    /// Forget the conversation, keeping the system prompt and generation config.
+    pub fn clear_history(&mut self) {
+        self.chat_history.clear();
+    }
+
+    /// Tokens of the last message, if the API reported them.
+    pub fn last_usage(&self) -> Option<Usage> {
+        self.last_usage
+    }
+
+    /// The model's answer to the last message, as it was returned.
+    pub fn last_answer(&self) -> Option<&str> {
+        match self.chat_history.last()? {
+            Message {
+                role: Role::Model,
+                parts,
+            } => parts.first().map(|part| match part {
+                MessagePart::Text { text } => text.as_str(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Add a message and its answer to the conversation without asking the model, for resuming
+    /// a saved conversation.
+    pub fn push_turn(&mut self, message: &str, answer: &str) {
+        self.chat_history.push(Message {
+            role: Role::User,
+            parts: vec![MessagePart::Text {
+                text: message.to_string(),
+            }],
+        });
+        self.chat_history.push(Message {
+            role: Role::Model,
+            parts: vec![MessagePart::Text {
+                text: answer.to_string(),
+            }],
+        });
+    }
+
     /// Force JSON output with a custom JSON Schema (as raw serde_json::Value).
     pub fn set_json_schema_value(&mut self, schema: serde_json::Value) {
         self.generation_config.response_mime_type = Some("application/json".to_string());
@@ -120,33 +209,44 @@ pub enum GeminiError {
     },
 }
 
-#[async_trait]
-impl LLM for Gemini<'_> {
-    type Error = GeminiError;
+impl Gemini<'_> {
+    /// Run the tool the model called, failures are told to the model.
+    async fn call_tool(&self, call: json_model::FunctionCall) -> json_model::Part {
+        let result = match self.tools.iter().find(|tool| tool.name() == call.name) {
+            Some(tool) => tool.call(call.args).await,
+            None => Err(anyhow::anyhow!("Unknown tool {}", call.name)),
+        };
+        let response = match result {
+            // the response has to be an object
+            Ok(value @ JsonValue::Object(_)) => value,
+            Ok(value) => serde_json::json!({ "result": value }),
+            Err(e) => serde_json::json!({ "error": e.to_string() }),
+        };
+        json_model::Part::function_response(call.name, response)
+    }
 
-    async fn chat(&mut self, message: &str) -> Result<String, Self::Error> {
+    async fn generate(
+        &self,
+        contents: Vec<json_model::Content>,
+        answer: Answer,
+    ) -> Result<(Vec<json_model::Part>, Option<Usage>), GeminiError> {
         use json_model::*;
 
-        let mut contents = self.chat_history.iter().map(to_content).collect::<Vec<_>>();
-        contents.push(Content {
-            role: Some("user".into()),
-            parts: vec![Part {
-                text: message.to_string(),
-            }],
-        });
-
         let system_instruction = self.system_prompt.as_ref().map(|sys| Content {
             role: None,
-            parts: vec![Part {
-                text: sys.to_string(),
-            }],
+            parts: vec![Part::text(sys.to_string())],
         });
 
+        let final_answer = answer == Answer::Final;
         let mut gen_cfg = GenerationConfigPayload {
             temperature: Some(self.generation_config.temperature),
             thinking_config: None,
-            response_mime_type: self.generation_config.response_mime_type.clone(),
-            response_schema: self.generation_config.response_schema.clone(),
+            response_mime_type: final_answer
+                .then(|| self.generation_config.response_mime_type.clone())
+                .flatten(),
+            response_schema: final_answer
+                .then(|| self.generation_config.response_schema.clone())
+                .flatten(),
         };
         if self.generation_config.thinking_config.thinking_budget >= 0 {
             gen_cfg.thinking_config = Some(ThinkingConfigPayload {
@@ -154,10 +254,34 @@ impl LLM for Gemini<'_> {
             });
         }
 
+        let tools = if self.tools.is_empty() || final_answer {
+            Vec::new()
+        } else {
+            vec![ToolPayload {
+                function_declarations: self
+                    .tools
+                    .iter()
+                    .map(|tool| FunctionDeclaration {
+                        name: tool.name().to_string(),
+                        description: tool.description().to_string(),
+                        parameters: tool.parameters(),
+                    })
+                    .collect(),
+            }]
+        };
+
+        let tool_config = (answer == Answer::Text).then(|| ToolConfigPayload {
+            function_calling_config: FunctionCallingConfig {
+                mode: "NONE".to_string(),
+            },
+        });
+
         let req_body = GenerateContentRequest {
             contents,
             system_instruction,
             generation_config: Some(gen_cfg),
+            tools,
+            tool_config,
             _phantom: std::marker::PhantomData,
         };
 
@@ -176,35 +300,142 @@ impl LLM for Gemini<'_> {
         }
 
         let parsed: GenerateContentResponse = serde_json::from_str(&body)?;
-        let answer = parsed
+        let usage = parsed.usage_metadata.map(|usage| Usage {
+            prompt_tokens: usage.prompt_token_count,
+            output_tokens: usage.candidates_token_count + usage.thoughts_token_count,
+        });
+        let parts = parsed
             .candidates
-            .as_ref()
-            .and_then(|cands| cands.first())
-            .and_then(|c| c.content.as_ref())
-            .and_then(|c| c.parts.as_ref())
-            .map(|parts| {
-                parts
-                    .iter()
-                    .filter_map(|p| p.text.to_owned()) // TODO: avoid copy p.text
-                    .collect::<Vec<_>>()
-                    .join("")
-            })
+            .and_then(|cands| cands.into_iter().next())
+            .and_then(|c| c.content)
+            .and_then(|c| c.parts)
             .unwrap_or_default();
+        Ok((parts, usage))
+    }
 
-        // update local history
-        self.chat_history.push(Message {
-            role: Role::User,
-            parts: vec![MessagePart::Text {
-                text: message.to_string(),
-            }],
-        });
-        self.chat_history.push(Message {
-            role: Role::Model,
-            parts: vec![MessagePart::Text {
-                text: answer.clone(),
-            }],
+    /// Like [`Self::generate`], adding the tokens to `usage`.
+    async fn generate_counted(
+        &self,
+        contents: Vec<json_model::Content>,
+        answer: Answer,
+        usage: &mut Option<Usage>,
+    ) -> Result<Vec<json_model::Part>, GeminiError> {
+        let (parts, round_usage) = self.generate(contents, answer).await?;
+        if let Some(round_usage) = round_usage {
+            let total = usage.get_or_insert_default();
+            total.prompt_tokens += round_usage.prompt_tokens;
+            total.output_tokens += round_usage.output_tokens;
+        }
+        Ok(parts)
+    }
+
+    /// Let the model call the tools, then answer. Gemini 2.x can't call tools while answering
+    /// in a JSON schema, so with a schema the answer is asked for again without the tools.
+    async fn generate_with_tools(
+        &self,
+        mut contents: Vec<json_model::Content>,
+        usage: &mut Option<Usage>,
+    ) -> Result<Vec<json_model::Part>, GeminiError> {
+        use json_model::*;
+
+        let structured = self.generation_config.response_schema.is_some();
+        let mut rounds = 0;
+        loop {
+            let last = rounds == MAX_TOOL_ROUNDS;
+            if last && structured {
+                break;
+            }
+            let answer = if last { Answer::Text } else { Answer::Tools };
+            let parts = self
+                .generate_counted(contents.clone(), answer, usage)
+                .await?;
+            let calls = parts
+                .iter()
+                .filter_map(|p| p.function_call.clone())
+                .collect::<Vec<_>>();
+            if calls.is_empty() || last {
+                if structured {
+                    break;
+                }
+                return Ok(parts);
+            }
+            rounds += 1;
+
+            // the calls go back as they came, with their thought signatures
+            contents.push(Content {
+                role: Some("model".into()),
+                parts,
+            });
+            let mut responses = Vec::new();
+            for call in calls {
+                responses.push(self.call_tool(call).await);
+            }
+            contents.push(Content {
+                role: Some("user".into()),
+                parts: responses,
+            });
+        }
+        self.generate_counted(tool_results_as_text(contents), Answer::Final, usage)
+            .await
+    }
+}
+
+/// The tool calls left out and their results told in text, for a request without tools.
+fn tool_results_as_text(contents: Vec<json_model::Content>) -> Vec<json_model::Content> {
+    contents
+        .into_iter()
+        .filter_map(|mut content| {
+            content.parts = content
+                .parts
+                .into_iter()
+                .filter_map(|part| {
+                    if part.function_call.is_some() {
+                        return None;
+                    }
+                    Some(match &part.function_response {
+                        Some(response) => json_model::Part::text(format!(
+                            "Result of the tool {}: {}",
+                            response.name, response.response
+                        )),
+                        None => part,
+                    })
+                })
+                .collect();
+            (!content.parts.is_empty()).then_some(content)
+        })
+        .collect()
+}
+
+#[async_trait]
+impl LLM for Gemini<'_> {
+    type Error = GeminiError;
+
+    async fn chat(&mut self, message: &str) -> Result<String, Self::Error> {
+        use json_model::*;
+
+        let mut contents = self.chat_history.iter().map(to_content).collect::<Vec<_>>();
+        contents.push(Content {
+            role: Some("user".into()),
+            parts: vec![Part::text(message.to_string())],
         });
 
+        let mut usage: Option<Usage> = None;
+        let parts = if self.tools.is_empty() {
+            self.generate_counted(contents, Answer::Final, &mut usage)
+                .await?
+        } else {
+            self.generate_with_tools(contents, &mut usage).await?
+        };
+        let answer = parts
+            .into_iter()
+            .filter_map(|p| p.text)
+            .collect::<Vec<_>>()
+            .join("");
+
+        // update local history
+        self.push_turn(message, &answer);
+        self.last_usage = usage;
+
         Ok(answer)
     }
 }
@@ -214,14 +445,79 @@ mod json_model {
 
     use crate::gemini::{Message, MessagePart, Role};
 
-    #[derive(Serialize)]
+    /// Sent and received, the API answers in camel case.
+    #[derive(Serialize, Deserialize, Clone, Default)]
     #[serde(rename_all = "snake_case")]
     pub struct Part {
-        pub text: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub text: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none", alias = "functionCall")]
+        pub function_call: Option<FunctionCall>,
+        #[serde(skip_serializing_if = "Option::is_none", skip_deserializing)]
+        pub function_response: Option<FunctionResponse>,
+        #[serde(skip_serializing_if = "Option::is_none", alias = "thoughtSignature")]
+        pub thought_signature: Option<String>,
+    }
+
+    impl Part {
+        pub fn text(text: String) -> Self {
+            Self {
+                text: Some(text),
+                ..Self::default()
+            }
+        }
+
+        pub fn function_response(name: String, response: serde_json::Value) -> Self {
+            Self {
+                function_response: Some(FunctionResponse { name, response }),
+                ..Self::default()
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct FunctionCall {
+        pub name: String,
+        #[serde(default)]
+        pub args: serde_json::Value,
+    }
+
+    #[derive(Serialize, Clone)]
+    pub struct FunctionResponse {
+        pub name: String,
+        pub response: serde_json::Value,
     }
 
     #[derive(Serialize)]
     #[serde(rename_all = "snake_case")]
+    pub struct FunctionDeclaration {
+        pub name: String,
+        pub description: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub parameters: Option<serde_json::Value>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub struct ToolPayload {
+        pub function_declarations: Vec<FunctionDeclaration>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub struct FunctionCallingConfig {
+        /// "AUTO", "ANY" or "NONE"
+        pub mode: String,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub struct ToolConfigPayload {
+        pub function_calling_config: FunctionCallingConfig,
+    }
+
+    #[derive(Serialize, Clone)]
+    #[serde(rename_all = "snake_case")]
     pub struct Content {
         // role: "user" | "model" | "system"
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -263,6 +559,12 @@ mod json_model {
         #[serde(skip_serializing_if = "Option::is_none")]
         pub generation_config: Option<GenerationConfigPayload>,
 
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub tools: Vec<ToolPayload>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub tool_config: Option<ToolConfigPayload>,
+
         #[serde(skip)]
         pub _phantom: std::marker::PhantomData<&'a ()>,
     }
@@ -271,6 +573,16 @@ mod json_model {
     #[serde(rename_all = "snake_case")]
     pub struct GenerateContentResponse {
         pub candidates: Option<Vec<Candidate>>,
+        #[serde(alias = "usageMetadata")]
+        pub usage_metadata: Option<UsageMetadata>,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default, rename_all = "camelCase")]
+    pub struct UsageMetadata {
+        pub prompt_token_count: u64,
+        pub candidates_token_count: u64,
+        pub thoughts_token_count: u64,
     }
 
     #[derive(Deserialize)]
@@ -283,13 +595,7 @@ mod json_model {
     #[derive(Deserialize)]
     #[serde(rename_all = "snake_case")]
     pub struct ContentResp {
-        pub parts: Option<Vec<PartResp>>,
-    }
-
-    #[derive(Deserialize)]
-    #[serde(rename_all = "snake_case")]
-    pub struct PartResp {
-        pub text: Option<String>,
+        pub parts: Option<Vec<Part>>,
     }
 
     pub fn to_content(msg: &Message) -> Content {
@@ -301,9 +607,102 @@ mod json_model {
             .parts
             .iter()
             .map(|p| match p {
-                MessagePart::Text { text } => Part { text: text.clone() },
+                MessagePart::Text { text } => Part::text(text.clone()),
             })
             .collect::<Vec<_>>();
         Content { role, parts }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::gemini::{
+        json_model::{Content, FunctionCall, GenerateContentResponse, Part},
+        tool_results_as_text,
+    };
+
+    #[test]
+    fn send_function_calls_back() {
+        let body = r#"{"candidates": [{"content": {"parts": [{"functionCall": {"name": "current_time", "args": {}}, "thoughtSignature": "abc"}]}}]}"#;
+        let parsed: GenerateContentResponse = serde_json::from_str(body).unwrap();
+        let part = parsed
+            .candidates
+            .unwrap()
+            .remove(0)
+            .content
+            .unwrap()
+            .parts
+            .unwrap()
+            .remove(0);
+        assert_eq!(part.function_call.as_ref().unwrap().name, "current_time");
+        assert_eq!(
+            serde_json::to_value(&part).unwrap(),
+            serde_json::json!({"function_call": {"name": "current_time", "args": {}}, "thought_signature": "abc"})
+        );
+
+        let response = Part::function_response(
+            "current_time".to_string(),
+            serde_json::json!({"time": "12:00"}),
+        );
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            serde_json::json!({"function_response": {"name": "current_time", "response": {"time": "12:00"}}})
+        );
+    }
+
+    #[test]
+    fn tell_the_tool_results_in_text() {
+        let content = |role: &str, parts| Content {
+            role: Some(role.to_string()),
+            parts,
+        };
+        let call = Part {
+            function_call: Some(FunctionCall {
+                name: "current_time".to_string(),
+                args: serde_json::json!({}),
+            }),
+            ..Part::default()
+        };
+        let contents = vec![
+            content("user", vec![Part::text("what time is it".to_string())]),
+            content("model", vec![call]),
+            content(
+                "user",
+                vec![Part::function_response(
+                    "current_time".to_string(),
+                    serde_json::json!({"time": "12:00"}),
+                )],
+            ),
+        ];
+
+        let texts: Vec<_> = tool_results_as_text(contents)
+            .into_iter()
+            .map(|content| {
+                (
+                    content.role.unwrap(),
+                    content.parts[0].text.clone().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                ("user".to_string(), "what time is it".to_string()),
+                (
+                    "user".to_string(),
+                    r#"Result of the tool current_time: {"time":"12:00"}"#.to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_the_usage() {
+        let body = r#"{"candidates": [], "usageMetadata": {"promptTokenCount": 120, "candidatesTokenCount": 30, "totalTokenCount": 150}}"#;
+        let parsed: GenerateContentResponse = serde_json::from_str(body).unwrap();
+        let usage = parsed.usage_metadata.unwrap();
+        assert_eq!(usage.prompt_token_count, 120);
+        assert_eq!(usage.candidates_token_count, 30);
+        assert_eq!(usage.thoughts_token_count, 0);
+    }
+}
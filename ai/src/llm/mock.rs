@@ -0,0 +1,55 @@
+use std::convert::Infallible;
+
+use async_trait::async_trait;
+
+use crate::{AIResponseModel, LLM};
+
+/// Canned lines as (Chinese, Japanese) pairs, few enough that cached voices get reused.
+const LINES: [(&str, &str); 4] = [
+    ("吾辈听到了哦。", "吾輩、聞こえておるぞ。"),
+    ("嗯嗯, 原来如此。", "うむうむ、なるほどのう。"),
+    ("这可真是有趣呢!", "それは面白いのう！"),
+    ("主人, 要好好休息哦。", "ご主人、ちゃんと休むのじゃぞ。"),
+];
+
+/// Answers without calling an API, for trying out the frontend and pacing.
+///
+/// Replies take one to three canned lines in turn and cycle through the given layers.
+pub struct MockLLM {
+    layers: Vec<i32>,
+    turn: usize,
+}
+
+impl MockLLM {
+    pub fn new(layers: Vec<i32>) -> Self {
+        Self { layers, turn: 0 }
+    }
+}
+
+#[async_trait]
+impl LLM for MockLLM {
+    type Error = Infallible;
+
+    async fn chat(&mut self, _message: &str) -> Result<String, Self::Error> {
+        let count = self.turn % 3 + 1;
+        let responses: Vec<_> = (0..count)
+            .map(|i| {
+                let (response, japanese_response) = LINES[(self.turn + i) % LINES.len()];
+                AIResponseModel {
+                    response: response.to_string(),
+                    japanese_response: japanese_response.to_string(),
+                    layers: match self.layers.len() {
+                        0 => Vec::new(),
+                        len => vec![self.layers[(self.turn + i) % len]],
+                    },
+                    poll_question: String::new(),
+                    poll_options: Vec::new(),
+                    effect: String::new(),
+                }
+            })
+            .collect();
+        self.turn += 1;
+
+        Ok(serde_json::to_string(&responses).expect("responses are serializable"))
+    }
+}
@@ -1,6 +1,7 @@
+pub mod moderation;
 pub mod response;
+pub mod topic;
 
 pub trait UsageExample {
     fn generate_example() -> String;
 }
-
@@ -0,0 +1,20 @@
+use schemars::JsonSchema;
+
+use crate::model::UsageExample;
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct ModerationResponseModel {
+    pub allowed: bool,
+    pub reason: String,
+}
+
+impl UsageExample for ModerationResponseModel {
+    fn generate_example() -> String {
+        let entity = Self {
+            allowed: false,
+            reason: "<Short reason why the comment is rejected>".to_string(),
+        };
+
+        serde_json::to_string(&entity).unwrap()
+    }
+}
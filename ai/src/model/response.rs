@@ -7,6 +7,15 @@ pub struct AIResponseModel {
     pub response: String,
     pub japanese_response: String,
     pub layers: Vec<i32>,
+    /// Ask the viewers to vote on this question, leave empty for no poll
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub poll_question: String,
+    /// The answers viewers can vote for, at least two
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub poll_options: Vec<String>,
+    /// Name of a voice effect for dramatic or comedic delivery, leave empty for the plain voice
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub effect: String,
 }
 
 impl UsageExample for AIResponseModel {
@@ -17,9 +26,11 @@ impl UsageExample for AIResponseModel {
                 "<Japanese response goes here, you need to translate the response into Japanese>"
                     .to_string(),
             layers: vec![1, 2, 3],
+            poll_question: String::new(),
+            poll_options: Vec::new(),
+            effect: String::new(),
         };
 
         serde_json::to_string(&entity).unwrap()
     }
 }
-
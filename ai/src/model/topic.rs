@@ -0,0 +1,25 @@
+use schemars::JsonSchema;
+
+use crate::model::UsageExample;
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, JsonSchema)]
+pub struct TopicResponseModel {
+    /// What the conversation is about, a few words
+    pub topic: String,
+    /// Whether it is still about the planned topic, true if none is planned
+    pub on_plan: bool,
+    /// Whether the planned topic has been talked through and the next one can start
+    pub plan_covered: bool,
+}
+
+impl UsageExample for TopicResponseModel {
+    fn generate_example() -> String {
+        let entity = Self {
+            topic: "<The current topic in a few words>".to_string(),
+            on_plan: true,
+            plan_covered: false,
+        };
+
+        serde_json::to_string(&entity).unwrap()
+    }
+}
@@ -0,0 +1,28 @@
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde_json::Value as JsonValue;
+
+use crate::utils::{inlined_openapi_schema_for, sanitize_for_gemini_response_schema};
+
+/// A function the model may call while answering, e.g. to look up the time instead of guessing.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    /// Name the model calls the tool by, e.g. `current_time`
+    fn name(&self) -> &str;
+
+    /// Tells the model what the tool does and when to call it
+    fn description(&self) -> &str;
+
+    /// Schema of the arguments, see [`parameters_for`]. `None` if the tool takes none.
+    fn parameters(&self) -> Option<JsonValue> {
+        None
+    }
+
+    /// The result is handed to the model as it is, errors are shown to it as well.
+    async fn call(&self, args: JsonValue) -> anyhow::Result<JsonValue>;
+}
+
+/// Schema of the arguments struct `T` in the subset the API accepts.
+pub fn parameters_for<T: JsonSchema>() -> JsonValue {
+    sanitize_for_gemini_response_schema(inlined_openapi_schema_for::<T>())
+}
@@ -0,0 +1,49 @@
+use std::{env, fmt::Display, str::FromStr};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Environment variable {0} not found")]
+    Missing(String),
+    #[error("Invalid {name}={value}: {message}")]
+    Invalid {
+        name: String,
+        value: String,
+        message: String,
+    },
+}
+
+pub fn get_env(name: &str) -> Result<String, ConfigError> {
+    env::var(name).map_err(|_| ConfigError::Missing(name.to_string()))
+}
+
+/// A comma separated list, empty if unset.
+pub fn get_list(name: &str) -> Vec<String> {
+    match get_env(name) {
+        Ok(value) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// The variable parsed, nothing if unset.
+pub fn parse_env<T>(name: &str) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let Ok(value) = get_env(name) else {
+        return Ok(None);
+    };
+    value
+        .parse()
+        .map(Some)
+        .map_err(|e: T::Err| ConfigError::Invalid {
+            name: name.to_string(),
+            message: e.to_string(),
+            value,
+        })
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use url::Url;
+
+use crate::{ConfigError, WindowPlacementConfig, get_env, parse_env, template::Setting};
+
+pub struct FrontendConfig {
+    /// Previewed at startup, `FRONTEND_MODEL` or else `VTUBER_RENDER_MODEL`
+    pub model: Option<PathBuf>,
+    /// A vtuber server to show the replies of, `FRONTEND_SERVER`
+    pub server: Option<Url>,
+    /// Index of the character the model belongs to, `FRONTEND_CHARACTER`
+    pub character: usize,
+    /// The screen the window opens on and where it is remembered, `FRONTEND_WINDOW_*`
+    pub window: WindowPlacementConfig,
+}
+
+impl FrontendConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self {
+            model: get_env("FRONTEND_MODEL")
+                .or_else(|_| get_env("VTUBER_RENDER_MODEL"))
+                .ok()
+                .map(PathBuf::from),
+            server: parse_env("FRONTEND_SERVER")?,
+            character: parse_env("FRONTEND_CHARACTER")?.unwrap_or(0),
+            window: WindowPlacementConfig::from_env("FRONTEND")?,
+        })
+    }
+
+    pub fn settings() -> Vec<Setting> {
+        vec![
+            Setting::optional(
+                "FRONTEND_MODEL",
+                "The model previewed at startup, VTUBER_RENDER_MODEL if unset",
+                "./resources/models/murasame-chan-a_0.zip",
+            ),
+            Setting::optional(
+                "FRONTEND_SERVER",
+                "A vtuber server to show the replies of",
+                "http://127.0.0.1:20889",
+            ),
+            Setting::default(
+                "FRONTEND_CHARACTER",
+                "Index of the character the model belongs to",
+                0,
+            ),
+        ]
+    }
+}
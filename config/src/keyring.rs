@@ -0,0 +1,206 @@
+use std::io;
+#[cfg(not(target_os = "macos"))]
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+/// Every secret is stored under this service, with its variable name as the account.
+const SERVICE: &str = "murasame";
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeyringError {
+    #[error("Failed to run {tool}: {source}")]
+    Spawn {
+        tool: &'static str,
+        source: io::Error,
+    },
+    #[error("{tool} failed: {message}")]
+    Failed { tool: &'static str, message: String },
+}
+
+/// Run `command` with `input` on its stdin.
+#[cfg(not(target_os = "macos"))]
+fn run(tool: &'static str, command: &mut Command, input: &str) -> Result<Output, KeyringError> {
+    let spawn = |source| KeyringError::Spawn { tool, source };
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(spawn)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes()).map_err(spawn)?;
+    }
+    child.wait_with_output().map_err(spawn)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn check(tool: &'static str, output: Output) -> Result<Output, KeyringError> {
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(KeyringError::Failed {
+            tool,
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+    }
+}
+
+/// The Secret Service through `secret-tool` from libsecret.
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use std::process::Command;
+
+    use crate::keyring::{KeyringError, SERVICE, check, run};
+
+    const TOOL: &str = "secret-tool";
+
+    pub fn get(name: &str) -> Result<Option<String>, KeyringError> {
+        let output = run(
+            TOOL,
+            Command::new(TOOL).args(["lookup", "service", SERVICE, "name", name]),
+            "",
+        )?;
+        // fails quietly for a missing secret
+        if !output.status.success() && output.stderr.is_empty() {
+            return Ok(None);
+        }
+        let output = check(TOOL, output)?;
+        Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+
+    pub fn set(name: &str, value: &str) -> Result<(), KeyringError> {
+        let label = format!("{SERVICE} {name}");
+        let mut command = Command::new(TOOL);
+        command.args(["store", "--label", &label, "service", SERVICE, "name", name]);
+        check(TOOL, run(TOOL, &mut command, value)?)?;
+        Ok(())
+    }
+
+    pub fn delete(name: &str) -> Result<(), KeyringError> {
+        let mut command = Command::new(TOOL);
+        command.args(["clear", "service", SERVICE, "name", name]);
+        check(TOOL, run(TOOL, &mut command, "")?)?;
+        Ok(())
+    }
+}
+
+/// The login keychain, called directly so the secret isn't passed to another program.
+#[cfg(target_os = "macos")]
+mod platform {
+    use security_framework::passwords;
+
+    use crate::keyring::{KeyringError, SERVICE};
+
+    const TOOL: &str = "keychain";
+    /// `errSecItemNotFound`
+    const NOT_FOUND: i32 = -25300;
+
+    fn failed(error: security_framework::base::Error) -> KeyringError {
+        KeyringError::Failed {
+            tool: TOOL,
+            message: error.to_string(),
+        }
+    }
+
+    pub fn get(name: &str) -> Result<Option<String>, KeyringError> {
+        match passwords::get_generic_password(SERVICE, name) {
+            Ok(value) => Ok(Some(String::from_utf8_lossy(&value).into_owned())),
+            Err(e) if e.code() == NOT_FOUND => Ok(None),
+            Err(e) => Err(failed(e)),
+        }
+    }
+
+    pub fn set(name: &str, value: &str) -> Result<(), KeyringError> {
+        passwords::set_generic_password(SERVICE, name, value.as_bytes()).map_err(failed)
+    }
+
+    pub fn delete(name: &str) -> Result<(), KeyringError> {
+        match passwords::delete_generic_password(SERVICE, name) {
+            Err(e) if e.code() != NOT_FOUND => Err(failed(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The Credential Locker through PowerShell, the name is passed in the environment and the
+/// value on stdin so neither needs quoting.
+#[cfg(windows)]
+mod platform {
+    use std::process::Command;
+
+    use crate::keyring::{KeyringError, SERVICE, check, run};
+
+    const TOOL: &str = "powershell";
+    const VAULT: &str = "$ErrorActionPreference = 'Stop'; \
+        [void][Windows.Security.Credentials.PasswordVault, Windows.Security.Credentials, ContentType = WindowsRuntime]; \
+        $vault = New-Object Windows.Security.Credentials.PasswordVault;";
+    /// Exit code of the scripts for a missing credential
+    const NOT_FOUND: i32 = 3;
+
+    fn powershell(
+        name: &str,
+        script: &str,
+        input: &str,
+    ) -> Result<std::process::Output, KeyringError> {
+        let mut command = Command::new(TOOL);
+        command
+            .args(["-NoProfile", "-NonInteractive", "-Command"])
+            .arg(format!("{VAULT} {script}"))
+            .env("MURASAME_SECRET_SERVICE", SERVICE)
+            .env("MURASAME_SECRET_NAME", name);
+        run(TOOL, &mut command, input)
+    }
+
+    pub fn get(name: &str) -> Result<Option<String>, KeyringError> {
+        let output = powershell(
+            name,
+            "try { $credential = $vault.Retrieve($env:MURASAME_SECRET_SERVICE, $env:MURASAME_SECRET_NAME) } catch { exit 3 }; \
+             $credential.RetrievePassword(); [Console]::Out.Write($credential.Password)",
+            "",
+        )?;
+        if output.status.code() == Some(NOT_FOUND) {
+            return Ok(None);
+        }
+        let output = check(TOOL, output)?;
+        Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+
+    pub fn set(name: &str, value: &str) -> Result<(), KeyringError> {
+        let output = powershell(
+            name,
+            "$vault.Add((New-Object Windows.Security.Credentials.PasswordCredential($env:MURASAME_SECRET_SERVICE, $env:MURASAME_SECRET_NAME, [Console]::In.ReadToEnd())))",
+            value,
+        )?;
+        check(TOOL, output)?;
+        Ok(())
+    }
+
+    pub fn delete(name: &str) -> Result<(), KeyringError> {
+        let output = powershell(
+            name,
+            "try { $vault.Remove($vault.Retrieve($env:MURASAME_SECRET_SERVICE, $env:MURASAME_SECRET_NAME)) } catch { exit 3 }",
+            "",
+        )?;
+        if output.status.code() != Some(NOT_FOUND) {
+            check(TOOL, output)?;
+        }
+        Ok(())
+    }
+}
+
+/// The secret stored as `name`, nothing if there is none.
+pub fn get(name: &str) -> Result<Option<String>, KeyringError> {
+    platform::get(name)
+}
+
+/// Store `value` as `name`, replacing what was stored before.
+pub fn set(name: &str, value: &str) -> Result<(), KeyringError> {
+    platform::set(name, value)
+}
+
+/// Remove the secret stored as `name`, nothing happens if there is none.
+pub fn delete(name: &str) -> Result<(), KeyringError> {
+    platform::delete(name)
+}
@@ -0,0 +1,17 @@
+mod env;
+mod frontend;
+pub mod keyring;
+mod secrets;
+mod services;
+pub mod template;
+mod tts;
+mod vtuber;
+mod window;
+
+pub use env::{ConfigError, get_env, get_list, parse_env};
+pub use frontend::FrontendConfig;
+pub use secrets::{SECRETS, get_secret, redact, secrets_from_keyring};
+pub use services::ServicesConfig;
+pub use tts::TtsServiceConfig;
+pub use vtuber::{TokenPrices, VtuberAiConfig, VtuberCharacterConfig, VtuberRenderConfig};
+pub use window::{Corner, UnknownCorner, WindowPlacementConfig};
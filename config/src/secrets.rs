@@ -0,0 +1,104 @@
+use std::{borrow::Cow, sync::Mutex};
+
+use crate::{ConfigError, get_env, keyring, template::Setting};
+
+/// Variables holding secrets, read from the OS keyring when unset and `MURASAME_KEYRING` is on.
+pub const SECRETS: &[&str] = &[
+    "GEMINI_API_KEY",
+    "VTUBER_STT_API_KEY",
+    "VTUBER_TWITCH_OAUTH_TOKEN",
+    "VTUBER_OBS_PASSWORD",
+    "VTUBER_SERVER_TOKEN",
+];
+
+/// Values shorter than this are too likely to appear by chance to be redacted.
+const MIN_REDACTED_LEN: usize = 4;
+
+/// Every secret read so far, see [`redact`].
+static KNOWN: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub(crate) fn settings() -> Vec<Setting> {
+    vec![Setting::default(
+        "MURASAME_KEYRING",
+        "Read the secrets left unset here from the OS keyring, store them with `murasame config set-secret`",
+        false,
+    )]
+}
+
+fn keyring_enabled() -> bool {
+    get_env("MURASAME_KEYRING").is_ok_and(|value| value == "true")
+}
+
+/// A secret from the environment or else the keyring, remembered to be redacted.
+pub fn get_secret(name: &str) -> Result<String, ConfigError> {
+    let value = match get_env(name) {
+        Ok(value) => value,
+        Err(missing) if keyring_enabled() => match keyring::get(name) {
+            Ok(Some(value)) => value,
+            Ok(None) => return Err(missing),
+            Err(e) => {
+                return Err(ConfigError::Invalid {
+                    name: name.to_string(),
+                    value: "<keyring>".to_string(),
+                    message: e.to_string(),
+                });
+            }
+        },
+        Err(missing) => return Err(missing),
+    };
+    remember(&value);
+    Ok(value)
+}
+
+/// The secrets missing from the environment that are in the keyring, to pass on to the
+/// programs started with it.
+pub fn secrets_from_keyring() -> Vec<(&'static str, String)> {
+    if !keyring_enabled() {
+        return Vec::new();
+    }
+    SECRETS
+        .iter()
+        .filter(|name| get_env(name).is_err())
+        .filter_map(|name| Some((*name, keyring::get(name).ok()??)))
+        .collect()
+}
+
+fn remember(value: &str) {
+    if value.len() < MIN_REDACTED_LEN {
+        return;
+    }
+    let mut known = KNOWN.lock().unwrap();
+    if !known.iter().any(|known| known == value) {
+        known.push(value.to_string());
+    }
+}
+
+/// `text` with every secret read so far replaced, for logs and messages shown on stream.
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let known = KNOWN.lock().unwrap();
+    let mut text = Cow::Borrowed(text);
+    for secret in known.iter() {
+        if text.contains(secret.as_str()) {
+            text = Cow::Owned(text.replace(secret.as_str(), "<redacted>"));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::secrets::{redact, remember};
+
+    #[test]
+    fn redact_known_secrets() {
+        remember("AIzaSyExample");
+        remember("abc");
+        assert_eq!(
+            redact("GET https://example.com/v1?key=AIzaSyExample failed"),
+            "GET https://example.com/v1?key=<redacted> failed"
+        );
+        assert!(matches!(redact("abc is fine"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{ConfigError, get_env, template::Setting};
+
+const DEFAULT_TTS_ADDRESS: &str = "127.0.0.1:20888";
+const DEFAULT_VTUBER_ADDRESS: &str = "127.0.0.1:20889";
+const DEFAULT_TTS_URL: &str = "http://127.0.0.1:20888";
+
+/// Where the programs listen and find each other.
+pub struct ServicesConfig {
+    /// The tts service, `TTS_ADDRESS`
+    pub tts_address: String,
+    /// The vtuber's HTTP server, `VTUBER_SERVER_ADDRESS`
+    pub vtuber_address: String,
+    /// The tts service as the vtuber reaches it, `VTUBER_TTS_API_BASE_URL`
+    pub tts_url: String,
+}
+
+impl ServicesConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self {
+            tts_address: get_env("TTS_ADDRESS").unwrap_or_else(|_| DEFAULT_TTS_ADDRESS.to_string()),
+            vtuber_address: get_env("VTUBER_SERVER_ADDRESS")
+                .unwrap_or_else(|_| DEFAULT_VTUBER_ADDRESS.to_string()),
+            tts_url: get_env("VTUBER_TTS_API_BASE_URL")
+                .unwrap_or_else(|_| DEFAULT_TTS_URL.to_string()),
+        })
+    }
+
+    pub fn settings() -> Vec<Setting> {
+        vec![
+            Setting::default(
+                "TTS_ADDRESS",
+                "Where the tts service listens",
+                DEFAULT_TTS_ADDRESS,
+            ),
+            Setting::default(
+                "VTUBER_SERVER_ADDRESS",
+                "Where the vtuber's HTTP server listens",
+                DEFAULT_VTUBER_ADDRESS,
+            ),
+            Setting::default(
+                "VTUBER_TTS_API_BASE_URL",
+                "The tts service as the vtuber reaches it",
+                DEFAULT_TTS_URL,
+            ),
+        ]
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::Write;
+
+use crate::{
+    FrontendConfig, SECRETS, ServicesConfig, TtsServiceConfig, VtuberAiConfig,
+    VtuberCharacterConfig, VtuberRenderConfig, WindowPlacementConfig, secrets,
+};
+
+/// A variable read by one of the config structs, as written into the template.
+pub struct Setting {
+    pub name: String,
+    /// What it does, a comment line above it
+    pub doc: &'static str,
+    pub value: SettingValue,
+}
+
+pub enum SettingValue {
+    /// Needed to start, set to an example
+    Required(String),
+    /// Commented out with the value used while it is unset
+    Default(String),
+    /// Commented out with an example, nothing is used while it is unset
+    Optional(String),
+}
+
+impl Setting {
+    pub fn required(name: impl Into<String>, doc: &'static str, example: impl ToString) -> Self {
+        Self::new(name, doc, SettingValue::Required(example.to_string()))
+    }
+
+    pub fn default(name: impl Into<String>, doc: &'static str, default: impl ToString) -> Self {
+        Self::new(name, doc, SettingValue::Default(default.to_string()))
+    }
+
+    pub fn optional(name: impl Into<String>, doc: &'static str, example: impl ToString) -> Self {
+        Self::new(name, doc, SettingValue::Optional(example.to_string()))
+    }
+
+    fn new(name: impl Into<String>, doc: &'static str, value: SettingValue) -> Self {
+        Self {
+            name: name.into(),
+            doc,
+            value,
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# {}", self.doc);
+        // secrets are better kept in the keyring than in a file
+        let (commented, value) = match &self.value {
+            SettingValue::Required(value) if SECRETS.contains(&self.name.as_str()) => (true, value),
+            SettingValue::Required(value) => (false, value),
+            SettingValue::Default(value) | SettingValue::Optional(value) => (true, value),
+        };
+        let _ = writeln!(
+            out,
+            "{}{}={}",
+            if commented { "# " } else { "" },
+            self.name,
+            quote(value)
+        );
+    }
+}
+
+/// Numbers and booleans as they are, anything else in quotes.
+fn quote(value: &str) -> String {
+    if value.parse::<f64>().is_ok() || value == "true" || value == "false" {
+        value.to_string()
+    } else {
+        format!("{value:?}")
+    }
+}
+
+/// The settings of every config struct by section.
+fn sections() -> Vec<(&'static str, Vec<Setting>)> {
+    let mut vtuber = VtuberAiConfig::settings();
+    vtuber.extend(VtuberCharacterConfig::settings());
+    vtuber.extend(VtuberRenderConfig::settings());
+    vtuber.extend(WindowPlacementConfig::settings("VTUBER"));
+    let mut frontend = FrontendConfig::settings();
+    frontend.extend(WindowPlacementConfig::settings("FRONTEND"));
+
+    vec![
+        ("services", ServicesConfig::settings()),
+        ("TTS", TtsServiceConfig::settings()),
+        ("vtuber", vtuber),
+        ("frontend", frontend),
+        ("secrets", secrets::settings()),
+    ]
+}
+
+/// A commented .env with the settings needed to start set and the optional ones commented out.
+pub fn render() -> String {
+    let mut out = String::from(
+        "# Written by `murasame config init`, the .env of the repository lists the settings of\n\
+         # the vtuber's optional features too\n",
+    );
+    for (section, settings) in sections() {
+        let _ = writeln!(out, "\n# -- {section} --");
+        for setting in &settings {
+            setting.render(&mut out);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::template::{SettingValue, render, sections};
+
+    #[test]
+    fn render_commented_template() {
+        let template = render();
+        // required ones set, optional ones and ones with defaults commented out
+        assert!(template.contains("\n# -- TTS --\n"));
+        assert!(template.contains("\nGPTSOVITS_API_BASE_URL=\"http://127.0.0.1:9880\"\n"));
+        assert!(template.contains("\n# TTS_ADDRESS=\"127.0.0.1:20888\"\n"));
+        assert!(template.contains("\nVTUBER_AI_THINKING=false\n"));
+        assert!(template.contains("\n# VTUBER_WINDOW_SNAP_DISTANCE=24\n"));
+        assert!(template.contains("\n# FRONTEND_WINDOW_CORNER=\"bottom_right\"\n"));
+        // a secret is required but not written down
+        assert!(template.contains("\n# GEMINI_API_KEY="));
+    }
+
+    #[test]
+    fn document_every_setting_once() {
+        let mut names = Vec::new();
+        for (_, settings) in sections() {
+            for setting in settings {
+                assert!(!setting.doc.is_empty(), "{} has no doc", setting.name);
+                if let SettingValue::Required(value) = &setting.value {
+                    assert!(!value.is_empty(), "{} has no example", setting.name);
+                }
+                assert!(!names.contains(&setting.name), "{} twice", setting.name);
+                names.push(setting.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use crate::{ConfigError, get_env, template::Setting};
+
+const DEFAULT_REF_AUDIO: &str = "./resources/ref_audio.ogg";
+const DEFAULT_REF_TEXT: &str = "ふむ、おぬしが我輩のご主人か?";
+const DEFAULT_VOICE: &str = "default";
+
+/// The tts service's side of GPT-SoVITS.
+pub struct TtsServiceConfig {
+    /// `GPTSOVITS_API_BASE_URL`
+    pub gptsovits_base_url: String,
+    /// Reference audio of the default voice, `TTS_REF_AUDIO`
+    pub ref_audio: PathBuf,
+    /// What is said in the reference audio, `TTS_REF_TEXT`
+    pub ref_text: String,
+    /// A json object of extra voices, `TTS_VOICES`
+    pub voices: Option<PathBuf>,
+    /// `TTS_DEFAULT_VOICE`
+    pub default_voice: String,
+    /// A json object of weight sets, `TTS_WEIGHTS`
+    pub weights: Option<PathBuf>,
+    /// The weight set of the voices without their own weights, `TTS_BASE_WEIGHTS`
+    pub base_weights: Option<String>,
+}
+
+impl TtsServiceConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self {
+            gptsovits_base_url: get_env("GPTSOVITS_API_BASE_URL")?,
+            ref_audio: get_env("TTS_REF_AUDIO")
+                .unwrap_or_else(|_| DEFAULT_REF_AUDIO.to_string())
+                .into(),
+            ref_text: get_env("TTS_REF_TEXT").unwrap_or_else(|_| DEFAULT_REF_TEXT.to_string()),
+            voices: get_env("TTS_VOICES").ok().map(PathBuf::from),
+            default_voice: get_env("TTS_DEFAULT_VOICE")
+                .unwrap_or_else(|_| DEFAULT_VOICE.to_string()),
+            weights: get_env("TTS_WEIGHTS").ok().map(PathBuf::from),
+            base_weights: get_env("TTS_BASE_WEIGHTS").ok(),
+        })
+    }
+
+    pub fn settings() -> Vec<Setting> {
+        vec![
+            Setting::required(
+                "GPTSOVITS_API_BASE_URL",
+                "The GPT-SoVITS api the tts service synthesizes with",
+                "http://127.0.0.1:9880",
+            ),
+            Setting::default(
+                "TTS_REF_AUDIO",
+                "Reference audio of the default voice",
+                DEFAULT_REF_AUDIO,
+            ),
+            Setting::default(
+                "TTS_REF_TEXT",
+                "What is said in the reference audio",
+                DEFAULT_REF_TEXT,
+            ),
+            Setting::optional(
+                "TTS_VOICES",
+                "Extra voices, a json object like {\"name\": {\"ref_audio\": \"...\", \"ref_text\": \"...\", \"prompt_lang\": \"ja\"}}",
+                "./resources/voices.json",
+            ),
+            Setting::default(
+                "TTS_DEFAULT_VOICE",
+                "The voice of requests naming none",
+                DEFAULT_VOICE,
+            ),
+            Setting::optional(
+                "TTS_WEIGHTS",
+                "Weight sets selectable via POST /tts/weights, a json object like {\"name\": {\"gpt_weights\": \"...\", \"sovits_weights\": \"...\"}}",
+                "./resources/weights.json",
+            ),
+            Setting::optional(
+                "TTS_BASE_WEIGHTS",
+                "The weight set loaded for voices without their own weights, required once a voice has some",
+                "base",
+            ),
+        ]
+    }
+}
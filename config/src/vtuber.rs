@@ -0,0 +1,229 @@
+use std::path::PathBuf;
+
+use crate::{ConfigError, get_env, get_list, get_secret, parse_env, template::Setting};
+
+/// The Gemini model the characters talk with, also used by ai-cli.
+pub struct VtuberAiConfig {
+    /// `VTUBER_AI_MODEL`
+    pub model: String,
+    /// `GEMINI_API_KEY`, also looked up in the OS keyring
+    pub api_key: String,
+    /// `VTUBER_AI_THINKING`
+    pub thinking: bool,
+    /// Prices the session reports count the cost with, `VTUBER_AI_PRICE_INPUT` and
+    /// `VTUBER_AI_PRICE_OUTPUT`
+    pub prices: Option<TokenPrices>,
+}
+
+impl VtuberAiConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let prices = match parse_env("VTUBER_AI_PRICE_INPUT")? {
+            Some(input) => Some(TokenPrices {
+                input,
+                output: required("VTUBER_AI_PRICE_OUTPUT")?,
+            }),
+            None => None,
+        };
+        Ok(Self {
+            model: get_env("VTUBER_AI_MODEL")?,
+            api_key: get_secret("GEMINI_API_KEY")?,
+            thinking: required("VTUBER_AI_THINKING")?,
+            prices,
+        })
+    }
+
+    pub fn settings() -> Vec<Setting> {
+        vec![
+            Setting::required(
+                "VTUBER_AI_MODEL",
+                "The Gemini model the characters talk with",
+                "gemini-2.5-flash",
+            ),
+            Setting::required(
+                "GEMINI_API_KEY",
+                "Store it in the OS keyring with `murasame config set-secret GEMINI_API_KEY`",
+                "your api key",
+            ),
+            Setting::required(
+                "VTUBER_AI_THINKING",
+                "Let the model think before answering, slower but more careful",
+                false,
+            ),
+            Setting::optional(
+                "VTUBER_AI_PRICE_INPUT",
+                "USD per million input tokens, the session reports count the cost with it",
+                0.3,
+            ),
+            Setting::optional(
+                "VTUBER_AI_PRICE_OUTPUT",
+                "USD per million output tokens, needed with VTUBER_AI_PRICE_INPUT",
+                2.5,
+            ),
+        ]
+    }
+}
+
+/// USD per million tokens, thoughts are billed as output.
+#[derive(Clone, Copy, Debug)]
+pub struct TokenPrices {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl TokenPrices {
+    pub fn cost(self, prompt_tokens: u64, output_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.input + output_tokens as f64 * self.output) / 1_000_000.0
+    }
+}
+
+/// The main character's persona, the others are listed in `VTUBER_CHARACTERS`.
+pub struct VtuberCharacterConfig {
+    /// `VTUBER_AI_CHARACTER_NAME`
+    pub name: String,
+    /// How the character calls the viewers, `VTUBER_AI_USER_TITLE`
+    pub user_title: Option<String>,
+    /// Lines of the character, `VTUBER_AI_DATASET`
+    pub dataset: PathBuf,
+    /// `VTUBER_AI_SYSTEM_INSTRUCTION_TEMPLATE`
+    pub system_instruction_template: PathBuf,
+    /// `VTUBER_TTS_VOICE`
+    pub voice: Option<String>,
+}
+
+impl VtuberCharacterConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self {
+            name: get_env("VTUBER_AI_CHARACTER_NAME")?,
+            user_title: get_env("VTUBER_AI_USER_TITLE").ok(),
+            dataset: get_env("VTUBER_AI_DATASET")?.into(),
+            system_instruction_template: get_env("VTUBER_AI_SYSTEM_INSTRUCTION_TEMPLATE")?.into(),
+            voice: get_env("VTUBER_TTS_VOICE").ok(),
+        })
+    }
+
+    pub fn settings() -> Vec<Setting> {
+        vec![
+            Setting::required(
+                "VTUBER_AI_CHARACTER_NAME",
+                "Name of the main character",
+                "丛雨",
+            ),
+            Setting::optional(
+                "VTUBER_AI_USER_TITLE",
+                "How the character calls the viewers",
+                "主人",
+            ),
+            Setting::required(
+                "VTUBER_AI_DATASET",
+                "Lines of the character",
+                "./resources/dataset.json",
+            ),
+            Setting::required(
+                "VTUBER_AI_SYSTEM_INSTRUCTION_TEMPLATE",
+                "The prompt template of the character",
+                "./resources/system_instruction_template.txt",
+            ),
+            Setting::optional(
+                "VTUBER_TTS_VOICE",
+                "The tts voice of the character, the service's default voice if unset",
+                "default",
+            ),
+        ]
+    }
+}
+
+/// The model of the main character and the layers it is shown with, `VTUBER_RENDER_*`.
+pub struct VtuberRenderConfig {
+    /// `VTUBER_RENDER_MODEL`, also previewed by the frontend and opened by layer-composer-cli
+    pub model: PathBuf,
+    /// `VTUBER_RENDER_BASE_LAYER`
+    pub base_layer: String,
+    /// `VTUBER_RENDER_MOUTH_LAYERS`
+    pub mouth_layers: Vec<String>,
+    /// `VTUBER_RENDER_BLINK_LAYERS`
+    pub blink_layers: Vec<String>,
+    /// `VTUBER_RENDER_DEFAULT_LAYERS`
+    pub default_layers: Vec<String>,
+    /// `VTUBER_RENDER_REST_LAYERS`
+    pub rest_layers: Vec<String>,
+    /// `VTUBER_RENDER_LISTENING_LAYERS`
+    pub listening_layers: Vec<String>,
+    /// `VTUBER_RENDER_WALK_LEFT_LAYERS`
+    pub walk_left_layers: Vec<String>,
+    /// `VTUBER_RENDER_WALK_RIGHT_LAYERS`
+    pub walk_right_layers: Vec<String>,
+}
+
+impl VtuberRenderConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self {
+            model: get_env("VTUBER_RENDER_MODEL")?.into(),
+            base_layer: get_env("VTUBER_RENDER_BASE_LAYER")?,
+            mouth_layers: get_list("VTUBER_RENDER_MOUTH_LAYERS"),
+            blink_layers: get_list("VTUBER_RENDER_BLINK_LAYERS"),
+            default_layers: get_list("VTUBER_RENDER_DEFAULT_LAYERS"),
+            rest_layers: get_list("VTUBER_RENDER_REST_LAYERS"),
+            listening_layers: get_list("VTUBER_RENDER_LISTENING_LAYERS"),
+            walk_left_layers: get_list("VTUBER_RENDER_WALK_LEFT_LAYERS"),
+            walk_right_layers: get_list("VTUBER_RENDER_WALK_RIGHT_LAYERS"),
+        })
+    }
+
+    pub fn settings() -> Vec<Setting> {
+        vec![
+            Setting::required(
+                "VTUBER_RENDER_MODEL",
+                "The model of the main character",
+                "./resources/models/murasame-chan-a_0.zip",
+            ),
+            Setting::required(
+                "VTUBER_RENDER_BASE_LAYER",
+                "The layer every expression is drawn on",
+                "ムラサメa_0_1951.png",
+            ),
+            Setting::optional(
+                "VTUBER_RENDER_MOUTH_LAYERS",
+                "Lip-sync: mouth layers from closed to open, switched by the loudness of the voice",
+                "mouth_closed.png,mouth_half.png,mouth_open.png",
+            ),
+            Setting::optional(
+                "VTUBER_RENDER_BLINK_LAYERS",
+                "Blinking: eye layers shown one after another every few seconds",
+                "eyes_half.png,eyes_closed.png,eyes_half.png",
+            ),
+            Setting::optional(
+                "VTUBER_RENDER_DEFAULT_LAYERS",
+                "Expression shown at startup and whenever the character has nothing left to say",
+                "ムラサメa_0_1995.png",
+            ),
+            Setting::optional(
+                "VTUBER_RENDER_REST_LAYERS",
+                "Expression the character settles into after a while without comments",
+                "eyes_sleepy.png",
+            ),
+            Setting::optional(
+                "VTUBER_RENDER_LISTENING_LAYERS",
+                "The expression while listening to the host",
+                "ears_up.png",
+            ),
+            Setting::optional(
+                "VTUBER_RENDER_WALK_LEFT_LAYERS",
+                "Shown while walking left",
+                "legs_left.png",
+            ),
+            Setting::optional(
+                "VTUBER_RENDER_WALK_RIGHT_LAYERS",
+                "Shown while walking right",
+                "legs_right.png",
+            ),
+        ]
+    }
+}
+
+fn required<T>(name: &str) -> Result<T, ConfigError>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    parse_env(name)?.ok_or_else(|| ConfigError::Missing(name.to_string()))
+}
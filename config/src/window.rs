@@ -0,0 +1,119 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::{ConfigError, get_env, parse_env, template::Setting};
+
+/// A corner of a screen the window can be kept in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl FromStr for Corner {
+    type Err = UnknownCorner;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top_left" => Ok(Self::TopLeft),
+            "top_right" => Ok(Self::TopRight),
+            "bottom_left" => Ok(Self::BottomLeft),
+            "bottom_right" => Ok(Self::BottomRight),
+            _ => Err(UnknownCorner),
+        }
+    }
+}
+
+impl fmt::Display for Corner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TopLeft => "top_left",
+            Self::TopRight => "top_right",
+            Self::BottomLeft => "bottom_left",
+            Self::BottomRight => "bottom_right",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownCorner;
+
+impl fmt::Display for UnknownCorner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("expected top_left, top_right, bottom_left or bottom_right")
+    }
+}
+
+/// Which screen the window of the vtuber or the frontend opens on and where it is remembered,
+/// read from `<PREFIX>_WINDOW_*`.
+#[derive(Debug, Clone)]
+pub struct WindowPlacementConfig {
+    /// Windows dropped this close to a screen edge stick to it, 0 to turn off, `_SNAP_DISTANCE`
+    pub snap_distance: f32,
+    /// Position and size are saved here and restored on the next launch, nothing is written
+    /// unless set, `_STATE`
+    pub state_file: Option<PathBuf>,
+    /// Index of the screen to open on when there is no saved position, in the order the window
+    /// system lists them, `_MONITOR`
+    pub monitor: Option<usize>,
+    /// Where the window goes on the screen it is opened on or thrown to, `_CORNER`
+    pub corner: Corner,
+}
+
+impl Default for WindowPlacementConfig {
+    fn default() -> Self {
+        Self {
+            snap_distance: 24.0,
+            state_file: None,
+            monitor: None,
+            corner: Corner::default(),
+        }
+    }
+}
+
+impl WindowPlacementConfig {
+    /// `prefix` is the app's, e.g. `VTUBER` for `VTUBER_WINDOW_MONITOR`.
+    pub fn from_env(prefix: &str) -> Result<Self, ConfigError> {
+        let default = Self::default();
+        let name = |key: &str| format!("{prefix}_WINDOW_{key}");
+        Ok(Self {
+            snap_distance: parse_env(&name("SNAP_DISTANCE"))?.unwrap_or(default.snap_distance),
+            state_file: get_env(&name("STATE"))
+                .ok()
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            monitor: parse_env(&name("MONITOR"))?,
+            corner: parse_env(&name("CORNER"))?.unwrap_or(default.corner),
+        })
+    }
+
+    pub fn settings(prefix: &str) -> Vec<Setting> {
+        let default = Self::default();
+        let name = |key: &str| format!("{prefix}_WINDOW_{key}");
+        vec![
+            Setting::default(
+                name("SNAP_DISTANCE"),
+                "Windows dropped this close to a screen edge stick to it, 0 to turn off",
+                default.snap_distance,
+            ),
+            Setting::optional(
+                name("STATE"),
+                "Position and size are saved here and restored on the next launch",
+                format!("./{}-window.json", prefix.to_lowercase()),
+            ),
+            Setting::optional(
+                name("MONITOR"),
+                "Index of the screen to open on when there is no saved position",
+                0,
+            ),
+            Setting::default(
+                name("CORNER"),
+                "Where the window goes on its screen: top_left, top_right, bottom_left or bottom_right",
+                default.corner,
+            ),
+        ]
+    }
+}
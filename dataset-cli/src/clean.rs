@@ -0,0 +1,55 @@
+use std::{collections::HashSet, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::Line;
+
+/// KAG tags like `[ruby text="かな"]` or `[r]`, the ruby's base text follows the tag.
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[[^\]]*\]").unwrap());
+/// Aozora style ruby, `｜漢字《かんじ》`.
+static RUBY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"｜|《[^》]*》").unwrap());
+static SPACES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+").unwrap());
+
+/// The text as shown, without tags and ruby annotations, on one line.
+pub fn strip_markup(text: &str) -> String {
+    let text = TAG.replace_all(text, "");
+    let text = RUBY.replace_all(&text, "");
+    SPACES.replace_all(&text, " ").trim().to_string()
+}
+
+/// Drop the lines said by the same character before, keeping the first.
+pub fn dedup(lines: &mut Vec<Line>) {
+    let mut seen = HashSet::new();
+    lines.retain(|line| seen.insert((line.character.clone(), line.text.clone())));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Line,
+        clean::{dedup, strip_markup},
+    };
+
+    #[test]
+    fn strip_tags_and_ruby() {
+        assert_eq!(
+            strip_markup("[ruby text=\"わがはい\"]吾輩は\n ｜叢雨丸《むらさめまる》[r]の[l]管理者"),
+            "吾輩は 叢雨丸の管理者"
+        );
+    }
+
+    #[test]
+    fn dedup_lines_per_character() {
+        let line = |character: &str, text: &str| Line {
+            character: character.to_string(),
+            text: text.to_string(),
+        };
+        let mut lines = vec![
+            line("ムラサメ", "うむ"),
+            line("将臣", "うむ"),
+            line("ムラサメ", "うむ"),
+        ];
+        dedup(&mut lines);
+        assert_eq!(lines, [line("ムラサメ", "うむ"), line("将臣", "うむ")]);
+    }
+}
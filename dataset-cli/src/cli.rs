@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+/// Turn extracted game scripts into a dataset, `[{"character": "...", "text": "..."}]`.
+#[derive(clap::Parser)]
+pub struct Cli {
+    /// .ks (KAG), .csv (character, text) or .json (a dataset or scn texts) files, or folders
+    /// of them
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+    /// Only print the statistics if unset
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Keep only the lines of these characters
+    #[arg(short, long)]
+    pub character: Vec<String>,
+    /// Speaker of the lines without a name
+    #[arg(long, default_value = "独白")]
+    pub narrator: String,
+    #[arg(long)]
+    pub keep_duplicates: bool,
+}
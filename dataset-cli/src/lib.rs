@@ -0,0 +1,92 @@
+use std::{fs, path::PathBuf};
+
+use clap::Parser;
+
+use crate::cli::Cli;
+
+mod clean;
+mod cli;
+mod source;
+mod stats;
+
+/// A line of the dataset, like ai's `Dataset` reads it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Line {
+    pub character: String,
+    pub text: String,
+}
+
+pub fn run() -> anyhow::Result<()> {
+    let args = Cli::parse();
+
+    let mut lines = Vec::new();
+    for path in scripts(&args.inputs)? {
+        let script = source::read_script(&path, &args.narrator)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+        eprintln!("{}: {} lines", path.display(), script.len());
+        lines.extend(script);
+    }
+    if !args.character.is_empty() {
+        lines.retain(|line| args.character.contains(&line.character));
+    }
+    let read = lines.len();
+    if !args.keep_duplicates {
+        clean::dedup(&mut lines);
+    }
+
+    print_stats(&lines, read - lines.len());
+    if let Some(output) = &args.output {
+        let mut json = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+        serde::Serialize::serialize(
+            &lines,
+            &mut serde_json::Serializer::with_formatter(&mut json, formatter),
+        )?;
+        fs::write(output, json)?;
+        println!("Wrote {} lines to {}", lines.len(), output.display());
+    }
+
+    Ok(())
+}
+
+/// The files to read, folders sorted by name so the dataset follows the story.
+fn scripts(inputs: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut scripts = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let mut files: Vec<PathBuf> = fs::read_dir(input)?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<anyhow::Result<_>>()?;
+            files.retain(|path| path.is_file() && source::is_script(path));
+            files.sort();
+            scripts.extend(files);
+        } else {
+            scripts.push(input.clone());
+        }
+    }
+    Ok(scripts)
+}
+
+fn print_stats(lines: &[Line], duplicates: usize) {
+    let stats = stats::stats(lines);
+    let mut rows: Vec<_> = stats.iter().collect();
+    rows.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.lines));
+
+    println!(
+        "{:>8} {:>10} {:>10}  character",
+        "lines", "chars", "~tokens"
+    );
+    for (character, stats) in &rows {
+        println!(
+            "{:>8} {:>10} {:>10}  {character}",
+            stats.lines, stats.chars, stats.tokens
+        );
+    }
+    let (chars, tokens) = rows.iter().fold((0, 0), |(chars, tokens), (_, stats)| {
+        (chars + stats.chars, tokens + stats.tokens)
+    });
+    println!("{:>8} {chars:>10} {tokens:>10}  total", lines.len());
+    if duplicates > 0 {
+        println!("{duplicates} duplicate lines dropped");
+    }
+}
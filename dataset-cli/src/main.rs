@@ -0,0 +1,5 @@
+use dataset_cli::run;
+
+fn main() {
+    run().unwrap();
+}
@@ -0,0 +1,236 @@
+use std::{fs, path::Path};
+
+use regex::Regex;
+
+use crate::{Line, clean::strip_markup};
+
+/// Read the lines of a script, by its extension.
+pub fn read_script(path: &Path, narrator: &str) -> anyhow::Result<Vec<Line>> {
+    let text = fs::read_to_string(path)?;
+    // scripts saved on windows often start with a BOM
+    let text = text.trim_start_matches('\u{feff}');
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("ks") => Ok(read_kag(text, narrator)),
+        Some("csv") => Ok(read_csv(text, narrator)),
+        Some("json") => read_json(text, narrator),
+        _ => anyhow::bail!("Unknown script format {}", path.display()),
+    }
+}
+
+/// Whether a folder entry is a script to read.
+pub fn is_script(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "ks" || ext == "csv" || ext == "json")
+}
+
+/// KAG scenarios: `#name` sets the speaker, an empty `#` the narrator, and the text up to `[p]`
+/// is one line.
+fn read_kag(text: &str, narrator: &str) -> Vec<Line> {
+    let page_break = Regex::new(r"(?i)\[p\]").unwrap();
+    let mut lines = Vec::new();
+    let mut speaker = narrator.to_string();
+    let mut message = String::new();
+    let mut flush = |speaker: &str, message: &mut String| {
+        let text = strip_markup(message);
+        if !text.is_empty() {
+            lines.push(Line {
+                character: speaker.to_string(),
+                text,
+            });
+        }
+        message.clear();
+    };
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(';') || line.starts_with('@') {
+            continue;
+        }
+        if line.starts_with('*') {
+            flush(&speaker, &mut message);
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('#') {
+            flush(&speaker, &mut message);
+            speaker = match name.trim() {
+                "" => narrator.to_string(),
+                name => name.to_string(),
+            };
+            continue;
+        }
+
+        let mut parts = page_break.split(line).peekable();
+        while let Some(part) = parts.next() {
+            message.push_str(part);
+            if parts.peek().is_some() {
+                flush(&speaker, &mut message);
+            }
+        }
+    }
+    flush(&speaker, &mut message);
+    lines
+}
+
+/// `character,text` rows, with or without that header.
+fn read_csv(text: &str, narrator: &str) -> Vec<Line> {
+    let mut rows = parse_csv(text).into_iter().peekable();
+    let (mut character_column, mut text_column) = (0, 1);
+    if let Some(header) = rows.peek() {
+        let find = |names: &[&str]| {
+            header
+                .iter()
+                .position(|field| names.contains(&field.trim().to_lowercase().as_str()))
+        };
+        if let (Some(character), Some(text)) =
+            (find(&["character", "name", "speaker"]), find(&["text"]))
+        {
+            (character_column, text_column) = (character, text);
+            rows.next();
+        }
+    }
+
+    rows.filter_map(|row| {
+        let text = strip_markup(row.get(text_column)?);
+        let character = row.get(character_column).map(|name| name.trim());
+        (!text.is_empty()).then(|| Line {
+            character: character
+                .filter(|name| !name.is_empty())
+                .unwrap_or(narrator)
+                .to_string(),
+            text,
+        })
+    })
+    .collect()
+}
+
+/// Fields of every row, quoted fields may hold commas, quotes as `""` and newlines.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|row| row.iter().any(|field| !field.trim().is_empty()));
+    rows
+}
+
+/// A dataset like the one this writes, or the scn texts of scripts/scn_text_extractor.py's
+/// input: `{"scenes": [{"texts": [[character, _, text, ...]]}]}`.
+fn read_json(text: &str, narrator: &str) -> anyhow::Result<Vec<Line>> {
+    let json: serde_json::Value = serde_json::from_str(text)?;
+    if json.is_array() {
+        let lines: Vec<Line> = serde_json::from_value(json)?;
+        return Ok(lines
+            .into_iter()
+            .map(|line| Line {
+                text: strip_markup(&line.text),
+                ..line
+            })
+            .filter(|line| !line.text.is_empty())
+            .collect());
+    }
+
+    let Some(scenes) = json.get("scenes").and_then(|scenes| scenes.as_array()) else {
+        anyhow::bail!("Expected a list of lines or scn texts");
+    };
+    let mut lines = Vec::new();
+    for texts in scenes
+        .iter()
+        .filter_map(|scene| scene.get("texts")?.as_array())
+    {
+        for entry in texts {
+            let Some(text) = entry.get(2).and_then(|text| text.as_str()) else {
+                continue;
+            };
+            let text = strip_markup(text);
+            if text.is_empty() {
+                continue;
+            }
+            let character = entry.get(0).and_then(|name| name.as_str());
+            lines.push(Line {
+                character: character.unwrap_or(narrator).to_string(),
+                text,
+            });
+        }
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Line,
+        source::{parse_csv, read_csv, read_json, read_kag},
+    };
+
+    fn line(character: &str, text: &str) -> Line {
+        Line {
+            character: character.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn read_kag_scenario() {
+        let script = "*start\n; a comment\n@bg storage=\"shrine\"\n#\n静かな神社だ。[p]\n#ムラサメ\n「ご主人、[ruby text=\"まった\"]待[ruby text=\"\"]ったか？」[l][r]\n「ふむ」[p]\n";
+        assert_eq!(
+            read_kag(script, "独白"),
+            [
+                line("独白", "静かな神社だ。"),
+                line("ムラサメ", "「ご主人、待ったか？」「ふむ」"),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_csv_rows() {
+        assert_eq!(
+            parse_csv("a,\"b, \"\"c\"\"\"\r\n\nd,e"),
+            [vec!["a", "b, \"c\""], vec!["d", "e"]]
+        );
+        assert_eq!(
+            read_csv("text,speaker\nこんにちは,ムラサメ\n独り言,\n", "独白"),
+            [line("ムラサメ", "こんにちは"), line("独白", "独り言")]
+        );
+        assert_eq!(
+            read_csv("ムラサメ,うむ", "独白"),
+            [line("ムラサメ", "うむ")]
+        );
+    }
+
+    #[test]
+    fn read_json_scripts() {
+        let scn = r#"{"scenes": [{"texts": [["ムラサメ", null, "うむ"], [null, null, "風が吹く"], [null, null, null]]}, {}]}"#;
+        assert_eq!(
+            read_json(scn, "独白").unwrap(),
+            [line("ムラサメ", "うむ"), line("独白", "風が吹く")]
+        );
+        let dataset = r#"[{"character": "将臣", "text": "「搞定」"}]"#;
+        assert_eq!(
+            read_json(dataset, "独白").unwrap(),
+            [line("将臣", "「搞定」")]
+        );
+        assert!(read_json("{}", "独白").is_err());
+    }
+}
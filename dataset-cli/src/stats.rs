@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use crate::Line;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CharacterStats {
+    pub lines: usize,
+    pub chars: usize,
+    pub tokens: usize,
+}
+
+/// Lines, characters and estimated tokens per speaker.
+pub fn stats(lines: &[Line]) -> BTreeMap<&str, CharacterStats> {
+    let mut stats: BTreeMap<&str, CharacterStats> = BTreeMap::new();
+    for line in lines {
+        let entry = stats.entry(&line.character).or_default();
+        entry.lines += 1;
+        entry.chars += line.text.chars().count();
+        entry.tokens += estimate_tokens(&line.text);
+    }
+    stats
+}
+
+/// A rough token count: a token per CJK character, and one per four characters of other text.
+pub fn estimate_tokens(text: &str) -> usize {
+    let (cjk, other) =
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .fold((0, 0usize), |(cjk, other), c| {
+                if is_cjk(c) {
+                    (cjk + 1, other)
+                } else {
+                    (cjk, other + 1)
+                }
+            });
+    cjk + other.div_ceil(4)
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{30ff}' // punctuation, hiragana and katakana
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{ff00}'..='\u{ffef}' // full width forms
+        | '\u{ac00}'..='\u{d7af}')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Line,
+        stats::{CharacterStats, estimate_tokens, stats},
+    };
+
+    #[test]
+    fn count_per_character() {
+        assert_eq!(estimate_tokens("「うむ」 hello"), 4 + 2);
+
+        let lines = [
+            Line {
+                character: "ムラサメ".to_string(),
+                text: "うむ".to_string(),
+            },
+            Line {
+                character: "ムラサメ".to_string(),
+                text: "ok".to_string(),
+            },
+        ];
+        assert_eq!(
+            stats(&lines)["ムラサメ"],
+            CharacterStats {
+                lines: 2,
+                chars: 4,
+                tokens: 3,
+            }
+        );
+    }
+}
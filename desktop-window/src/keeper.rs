@@ -0,0 +1,299 @@
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use config::{Corner, WindowPlacementConfig};
+use eframe::egui::{self, Pos2, Rect, Vec2};
+
+/// The window counts as moved once it stood still this long.
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Where a window of `size` goes to sit in `corner` of `screen`.
+pub fn place(corner: Corner, screen: Rect, size: Vec2) -> Pos2 {
+    let x = match corner {
+        Corner::TopLeft | Corner::BottomLeft => screen.min.x,
+        Corner::TopRight | Corner::BottomRight => screen.max.x - size.x,
+    };
+    let y = match corner {
+        Corner::TopLeft | Corner::TopRight => screen.min.y,
+        Corner::BottomLeft | Corner::BottomRight => screen.max.y - size.y,
+    };
+    egui::pos2(x, y)
+}
+
+/// The corner of `screen` the window sits flush in, if any.
+fn corner_of(window: Rect, screen: Rect) -> Option<Corner> {
+    [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ]
+    .into_iter()
+    .find(|&corner| place(corner, screen, window.size()).distance(window.min) < 1.0)
+}
+
+/// Index of the screen holding the center of `window`.
+fn screen_at(screens: &[Rect], window: Rect) -> Option<usize> {
+    screens
+        .iter()
+        .position(|screen| screen.contains(window.center()))
+}
+
+/// Where the window was, restored on the next launch.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WindowState {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Index of the screen the window was on, in the order the window system lists them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<usize>,
+    /// The corner it was kept in, it goes back there even if the screen's resolution changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corner: Option<Corner>,
+}
+
+impl WindowState {
+    pub fn load(path: &Path) -> Option<Self> {
+        let state = fs::read(path).ok()?;
+        serde_json::from_slice(&state)
+            .inspect_err(|e| log::error!("Ignored bad window state {}: {e}", path.display()))
+            .ok()
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec(self)?)
+    }
+}
+
+/// Where the window opens, a saved corner wins over the saved position so the window stays in
+/// it, then the configured monitor.
+pub fn initial_position(
+    config: &WindowPlacementConfig,
+    screens: &[Rect],
+    saved: Option<WindowState>,
+    size: Vec2,
+) -> Option<Pos2> {
+    if let Some(state) = saved {
+        let size = egui::vec2(state.width, state.height);
+        let screen = state.monitor.and_then(|monitor| screens.get(monitor));
+        return Some(match (screen, state.corner) {
+            (Some(&screen), Some(corner)) => place(corner, screen, size),
+            _ => egui::pos2(state.x, state.y),
+        });
+    }
+    let screen = screens.get(config.monitor?)?;
+    Some(place(config.corner, *screen, size))
+}
+
+/// Move a window touching the screen edges within `distance` flush against them.
+pub fn snap_to_edges(position: Pos2, size: Vec2, screen: Rect, distance: f32) -> Pos2 {
+    let snap = |start: f32, length: f32, min: f32, max: f32| {
+        let end = max - length;
+        if (start - min).abs() <= distance {
+            min
+        } else if (start - end).abs() <= distance {
+            end
+        } else {
+            start
+        }
+    };
+    egui::pos2(
+        snap(position.x, size.x, screen.min.x, screen.max.x),
+        snap(position.y, size.y, screen.min.y, screen.max.y),
+    )
+}
+
+/// Snaps the window to the screen edges after it was dragged and remembers where it is.
+#[derive(Default)]
+pub struct WindowKeeper {
+    config: WindowPlacementConfig,
+    /// In points as the window system reports them, empty where it doesn't tell where they are
+    screens: Vec<Rect>,
+    last: Option<WindowState>,
+    changed_at: Option<Instant>,
+    saved: Option<WindowState>,
+    /// Put the window onto the next screen on the next update
+    throw: bool,
+}
+
+impl WindowKeeper {
+    pub fn new(
+        config: WindowPlacementConfig,
+        screens: Vec<Rect>,
+        saved: Option<WindowState>,
+    ) -> Self {
+        Self {
+            config,
+            screens,
+            last: None,
+            changed_at: None,
+            saved,
+            throw: false,
+        }
+    }
+
+    /// Move the window into the configured corner of the next screen.
+    pub fn throw_to_next_monitor(&mut self) {
+        self.throw = true;
+    }
+
+    /// The screen the window at `outer` is on.
+    pub fn monitor(&self, outer: Rect) -> Option<Rect> {
+        screen_at(&self.screens, outer).map(|screen| self.screens[screen])
+    }
+
+    /// Call every frame.
+    pub fn update(&mut self, ctx: &egui::Context) {
+        let (outer, inner) = ctx.input(|i| (i.viewport().outer_rect, i.viewport().inner_rect));
+        let (Some(outer), Some(inner)) = (outer, inner) else {
+            return;
+        };
+        let current = screen_at(&self.screens, outer);
+        if std::mem::take(&mut self.throw) {
+            if self.screens.is_empty() {
+                log::warn!("The window system doesn't tell where the screens are");
+            } else {
+                let next = current.map_or(0, |current| (current + 1) % self.screens.len());
+                ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(place(
+                    self.config.corner,
+                    self.screens[next],
+                    outer.size(),
+                )));
+                return;
+            }
+        }
+        let screen = current.map(|screen| self.screens[screen]);
+        let state = WindowState {
+            x: outer.min.x,
+            y: outer.min.y,
+            width: inner.width(),
+            height: inner.height(),
+            monitor: current,
+            corner: screen.and_then(|screen| corner_of(outer, screen)),
+        };
+
+        // the window manager moves the window while dragging, wait until it stops
+        if self.last != Some(state) {
+            self.last = Some(state);
+            self.changed_at = Some(Instant::now());
+            ctx.request_repaint_after(SETTLE);
+            return;
+        }
+        if self
+            .changed_at
+            .is_none_or(|changed_at| changed_at.elapsed() < SETTLE)
+        {
+            return;
+        }
+        self.changed_at = None;
+
+        if let Some(screen) = screen
+            && self.config.snap_distance > 0.0
+        {
+            let snapped = snap_to_edges(outer.min, outer.size(), screen, self.config.snap_distance);
+            if snapped != outer.min {
+                // saved once the window arrived
+                ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(snapped));
+                return;
+            }
+        }
+
+        if let Some(path) = &self.config.state_file
+            && self.saved != Some(state)
+        {
+            match state.save(path) {
+                Ok(()) => self.saved = Some(state),
+                Err(e) => log::error!("Failed to save window state: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Corner, WindowPlacementConfig};
+    use eframe::egui;
+
+    use crate::keeper::{WindowKeeper, WindowState, initial_position, snap_to_edges};
+
+    #[test]
+    fn snap_near_edges() {
+        let size = egui::vec2(300.0, 200.0);
+        let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1920.0, 1080.0));
+
+        let snapped = snap_to_edges(egui::pos2(12.0, 870.0), size, screen, 24.0);
+        assert_eq!(snapped, egui::pos2(0.0, 880.0));
+        let snapped = snap_to_edges(egui::pos2(1630.0, -10.0), size, screen, 24.0);
+        assert_eq!(snapped, egui::pos2(1620.0, 0.0));
+        // far from every edge
+        let snapped = snap_to_edges(egui::pos2(500.0, 400.0), size, screen, 24.0);
+        assert_eq!(snapped, egui::pos2(500.0, 400.0));
+    }
+
+    #[test]
+    fn snap_to_the_screen_holding_the_window() {
+        let left = egui::Rect::from_min_size(egui::pos2(-1920.0, 0.0), egui::vec2(1920.0, 1080.0));
+        let right = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(2560.0, 1440.0));
+        let keeper = WindowKeeper::new(WindowPlacementConfig::default(), vec![left, right], None);
+        let size = egui::vec2(300.0, 200.0);
+
+        let outer = egui::Rect::from_min_size(egui::pos2(-1910.0, 870.0), size);
+        let screen = keeper.monitor(outer).unwrap();
+        assert_eq!(screen, left);
+        assert_eq!(
+            snap_to_edges(outer.min, size, screen, 24.0),
+            egui::pos2(-1920.0, 880.0)
+        );
+        let outer = egui::Rect::from_min_size(egui::pos2(2250.0, 1230.0), size);
+        assert_eq!(
+            snap_to_edges(outer.min, size, keeper.monitor(outer).unwrap(), 24.0),
+            egui::pos2(2260.0, 1240.0)
+        );
+        // off every screen
+        let outer = egui::Rect::from_min_size(egui::pos2(5000.0, 0.0), size);
+        assert_eq!(keeper.monitor(outer), None);
+    }
+
+    #[test]
+    fn open_in_saved_corner() {
+        let screens = [
+            egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1920.0, 1080.0)),
+            egui::Rect::from_min_size(egui::pos2(1920.0, 0.0), egui::vec2(2560.0, 1440.0)),
+        ];
+        let config = WindowPlacementConfig {
+            monitor: Some(1),
+            corner: Corner::TopLeft,
+            ..WindowPlacementConfig::default()
+        };
+        let size = egui::vec2(300.0, 200.0);
+
+        assert_eq!(
+            initial_position(&config, &screens, None, size),
+            Some(egui::pos2(1920.0, 0.0))
+        );
+        // the screens are unknown
+        assert_eq!(initial_position(&config, &[], None, size), None);
+        let mut saved = WindowState {
+            x: 500.0,
+            y: 400.0,
+            width: 300.0,
+            height: 200.0,
+            monitor: Some(0),
+            corner: None,
+        };
+        assert_eq!(
+            initial_position(&config, &screens, Some(saved), size),
+            Some(egui::pos2(500.0, 400.0))
+        );
+        saved.corner = Some(Corner::BottomLeft);
+        assert_eq!(
+            initial_position(&config, &screens, Some(saved), size),
+            Some(egui::pos2(0.0, 880.0))
+        );
+    }
+}
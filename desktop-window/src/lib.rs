@@ -0,0 +1,70 @@
+//! Placing the windows of the vtuber and the frontend on the screens.
+
+use std::sync::{Arc, Mutex};
+
+use config::WindowPlacementConfig;
+use eframe::UserEvent;
+use winit::event_loop::EventLoop;
+
+use crate::{
+    keeper::{WindowState, initial_position},
+    lookup::ScreenLookup,
+};
+
+mod keeper;
+mod lookup;
+
+pub use keeper::WindowKeeper;
+
+type DynError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Run an eframe app like [`eframe::run_native`], its window opens where `config` and the saved
+/// state place it. `app_creator` also gets the [`WindowKeeper`] to update every frame.
+pub fn run_native<'a>(
+    app_name: &str,
+    mut options: eframe::NativeOptions,
+    config: WindowPlacementConfig,
+    app_creator: impl FnOnce(
+        &eframe::CreationContext<'_>,
+        WindowKeeper,
+    ) -> Result<Box<dyn eframe::App + 'a>, DynError>
+    + 'a,
+) -> Result<(), eframe::Error> {
+    let saved = config.state_file.as_deref().and_then(WindowState::load);
+    if let Some(state) = saved {
+        options.viewport = options
+            .viewport
+            .with_inner_size([state.width, state.height]);
+    }
+
+    // only the running event loop can tell where the screens are, egui only knows the size of
+    // the current one, so the window is placed once it is about to be created
+    let screens = Arc::new(Mutex::new(Vec::new()));
+    options.window_builder = Some(Box::new({
+        let config = config.clone();
+        let screens = screens.clone();
+        move |viewport| {
+            let size = viewport.inner_size.unwrap_or_default();
+            match initial_position(&config, &screens.lock().unwrap(), saved, size) {
+                Some(position) => viewport.with_position(position),
+                None => viewport,
+            }
+        }
+    }));
+
+    let event_loop = EventLoop::<UserEvent>::with_user_event().build()?;
+    let app = eframe::create_native(
+        app_name,
+        options,
+        Box::new({
+            let screens = screens.clone();
+            move |cc| {
+                let screens = screens.lock().unwrap().clone();
+                app_creator(cc, WindowKeeper::new(config, screens, saved))
+            }
+        }),
+        &event_loop,
+    );
+    event_loop.run_app(&mut ScreenLookup::new(app, screens))?;
+    Ok(())
+}
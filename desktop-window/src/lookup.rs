@@ -0,0 +1,94 @@
+use std::sync::{Arc, Mutex};
+
+use eframe::{
+    EframeWinitApplication, UserEvent,
+    egui::{self, Rect},
+};
+use winit::{
+    application::ApplicationHandler,
+    event::{DeviceEvent, DeviceId, StartCause, WindowEvent},
+    event_loop::ActiveEventLoop,
+    window::WindowId,
+};
+
+/// The screens in points as the window system reports them, empty where it doesn't tell where
+/// they are.
+fn screens(event_loop: &ActiveEventLoop) -> Vec<Rect> {
+    event_loop
+        .available_monitors()
+        .map(|monitor| {
+            let scale = monitor.scale_factor();
+            let position = monitor.position().to_logical::<f32>(scale);
+            let size = monitor.size().to_logical::<f32>(scale);
+            Rect::from_min_size(
+                egui::pos2(position.x, position.y),
+                egui::vec2(size.width, size.height),
+            )
+        })
+        .collect()
+}
+
+/// Runs the eframe app, looking up where the screens are before the window is created, which
+/// only the running event loop can tell.
+pub struct ScreenLookup<'a> {
+    app: EframeWinitApplication<'a>,
+    screens: Arc<Mutex<Vec<Rect>>>,
+}
+
+impl<'a> ScreenLookup<'a> {
+    /// `screens` are filled in before `app` creates its window.
+    pub fn new(app: EframeWinitApplication<'a>, screens: Arc<Mutex<Vec<Rect>>>) -> Self {
+        Self { app, screens }
+    }
+}
+
+impl ApplicationHandler<UserEvent> for ScreenLookup<'_> {
+    fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
+        if cause == StartCause::Init {
+            *self.screens.lock().unwrap() = screens(event_loop);
+        }
+        self.app.new_events(event_loop, cause);
+    }
+
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.resumed(event_loop);
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: UserEvent) {
+        self.app.user_event(event_loop, event);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        self.app.window_event(event_loop, window_id, event);
+    }
+
+    fn device_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        self.app.device_event(event_loop, device_id, event);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.about_to_wait(event_loop);
+    }
+
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.suspended(event_loop);
+    }
+
+    fn exiting(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.exiting(event_loop);
+    }
+
+    fn memory_warning(&mut self, event_loop: &ActiveEventLoop) {
+        self.app.memory_warning(event_loop);
+    }
+}
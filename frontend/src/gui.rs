@@ -1,39 +1,147 @@
-use std::{fs::File, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
 
+use desktop_window::WindowKeeper;
 use eframe::egui::{self, Color32, ColorImage, Image, TextureHandle};
-use image::DynamicImage;
+use layer_composer::{LayerManifest, Model};
 
-#[derive(Debug, Default)]
+use crate::remote::RemoteDisplay;
+
+/// Size of the layer thumbnails in the picker.
+const THUMBNAIL_SIZE: u32 = 48;
+/// Thumbnails are decoded a few at a time so opening a large group doesn't freeze the window.
+const THUMBNAILS_PER_FRAME: usize = 4;
+/// Puts the window into the corner of the next screen while it has focus.
+const NEXT_MONITOR: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::CTRL.plus(egui::Modifiers::ALT),
+    egui::Key::O,
+);
+
+/// A layer listed in the picker.
+#[derive(Debug, Clone)]
+struct LayerEntry {
+    name: String,
+    /// The description, or the name if there is none
+    label: String,
+    is_base: bool,
+}
+
+#[derive(Default)]
 pub struct FrontendApp {
-    // input_text: String,
+    model: Option<Model>,
+    /// Shown below every other layer
+    base_layer: Option<String>,
+    /// Top layers shown on the base layer
+    selected: BTreeSet<String>,
+    /// The model's layers by their manifest group
+    groups: BTreeMap<String, Vec<LayerEntry>>,
+    /// `None` if the layer image couldn't be read
+    thumbnails: HashMap<String, Option<TextureHandle>>,
+    /// Path typed into the model field
+    model_path: String,
+    /// Rendered but not uploaded yet
     image: Option<ColorImage>,
+    /// The last rendered image, every new one is uploaded into it once
+    texture: Option<TextureHandle>,
+    error: Option<String>,
+    /// Shows the replies of a vtuber server instead of the picked layers
+    remote: Option<RemoteDisplay>,
+    window: WindowKeeper,
+}
+
+impl FrontendApp {
+    /// Start with the model at `model_path`, or an empty window to pick one in.
+    pub fn new(
+        model_path: Option<PathBuf>,
+        remote: Option<RemoteDisplay>,
+        window: WindowKeeper,
+    ) -> Self {
+        let mut app = Self {
+            remote,
+            window,
+            ..Default::default()
+        };
+        if let Some(path) = model_path {
+            app.load_model(&path);
+        }
+        app
+    }
 }
 
 impl eframe::App for FrontendApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // a model zip dropped onto the window replaces the current one
+        let dropped = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .find_map(|file| file.path.clone())
+        });
+        if let Some(path) = dropped {
+            self.load_model(&path);
+        }
+
+        if let Some(layers) = self.remote.as_mut().and_then(|remote| remote.update(ctx)) {
+            self.selected = layers.into_iter().collect();
+            self.render_selection();
+        }
+
+        if let Some(image) = self.image.take() {
+            match &mut self.texture {
+                Some(texture) => texture.set(image, Default::default()),
+                texture => {
+                    *texture = Some(ctx.load_texture("final_image", image, Default::default()))
+                }
+            }
+        }
+
+        if ctx.input_mut(|i| i.consume_shortcut(&NEXT_MONITOR)) {
+            self.window.throw_to_next_monitor();
+        }
+        self.window.update(ctx);
+
+        egui::TopBottomPanel::top("model").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Model");
+                ui.text_edit_singleline(&mut self.model_path);
+                if ui.button("Open").clicked() {
+                    let path = PathBuf::from(self.model_path.trim());
+                    self.load_model(&path);
+                }
+            });
+            if let Some(error) = &self.error {
+                ui.colored_label(Color32::RED, error);
+            }
+            if let Some(status) = self.remote.as_ref().and_then(RemoteDisplay::status) {
+                ui.colored_label(Color32::YELLOW, format!("Server: {status}"));
+            }
+        });
+
+        if let Some(subtitle) = self.remote.as_ref().and_then(RemoteDisplay::subtitle) {
+            egui::TopBottomPanel::bottom("subtitle").show(ctx, |ui| {
+                ui.vertical_centered(|ui| ui.label(egui::RichText::new(subtitle).size(18.0)));
+            });
+        }
+
+        if self.model.is_some() {
+            egui::SidePanel::left("layers")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| self.draw_picker(ui));
+                });
+        }
+
         egui::CentralPanel::default()
             .frame(egui::Frame::default().fill(Color32::TRANSPARENT))
             .show(ctx, |ui| {
-                if let Some(image) = &self.image {
-                    let texture: TextureHandle =
-                        ctx.load_texture("final_image", image.clone(), Default::default());
-
+                if let Some(texture) = &self.texture {
                     let new_size = ui.available_size();
-                    let image = Image::new(&texture).fit_to_exact_size(new_size);
+                    let image = Image::new(texture).fit_to_exact_size(new_size);
                     ui.add(image);
-                } else {
-                    // render default image
-                    self.render_image_with_layers(vec!["0_1950", "0_1455", "0_1959"]);
-                }
-                if ui.button("Render 0").clicked() {
-                    self.render_image_with_layers(vec!["0_1950", "0_1455", "0_1959"]);
-                }
-                if ui.button("Render 1").clicked() {
-                    self.render_image_with_layers(vec!["0_1956", "0_1455", "0_1959"])
-                }
-
-                if ui.button("Render 2").clicked() {
-                    self.render_image_with_layers(vec!["0_1957", "0_1455", "0_1959"])
+                } else if self.model.is_none() {
+                    ui.label("Open a model zip or drop one onto the window");
                 }
             });
     }
@@ -44,55 +152,160 @@ impl eframe::App for FrontendApp {
 }
 
 impl FrontendApp {
-    fn render_image_with_layers(&mut self, layers: Vec<impl Into<String>>) {
-        // TODO: allow customize in .json file
-        let layers = layers
-            .into_iter()
-            .map(|s| s.into())
-            .collect::<Vec<String>>();
-
-        let mut final_image: Option<DynamicImage> = None;
-
-        for layer_name in layers.into_iter() {
-            let layer_path = self.get_layer_path(&layer_name);
-            let layer = image::open(layer_path).unwrap();
-            if let Some(prev_layer) = final_image {
-                // parse metadata
-                let metadata = serde_json::from_reader(
-                    File::open(self.get_layer_metadata_path(&layer_name)).unwrap(),
-                )
-                .unwrap();
-                // render the layer
-                final_image =
-                    Some(layer_composer::compose_layers(&prev_layer, &layer, &metadata).into());
-            } else {
-                // use the first layer as the base image
-                final_image = Some(layer);
-            }
+    /// Every group of layers with a thumbnail and a checkbox per layer, base layers are picked
+    /// like radio buttons. The image is rendered again right away on every change.
+    fn draw_picker(&mut self, ui: &mut egui::Ui) {
+        let groups = self.groups.clone();
+        let mut budget = THUMBNAILS_PER_FRAME;
+        let mut changed = false;
+        for (group, layers) in &groups {
+            egui::CollapsingHeader::new(group)
+                .default_open(true)
+                .show(ui, |ui| {
+                    for layer in layers {
+                        ui.horizontal(|ui| {
+                            match self.thumbnail(ui.ctx(), &layer.name, &mut budget) {
+                                Some(texture) => {
+                                    ui.add(Image::new(&texture).fit_to_exact_size(egui::vec2(
+                                        THUMBNAIL_SIZE as f32,
+                                        THUMBNAIL_SIZE as f32,
+                                    )));
+                                }
+                                None => {
+                                    ui.add_space(THUMBNAIL_SIZE as f32);
+                                }
+                            }
+                            let response = if layer.is_base {
+                                ui.radio_value(
+                                    &mut self.base_layer,
+                                    Some(layer.name.clone()),
+                                    &layer.label,
+                                )
+                            } else {
+                                let mut checked = self.selected.contains(&layer.name);
+                                let response = ui.checkbox(&mut checked, &layer.label);
+                                if response.changed() {
+                                    if checked {
+                                        self.selected.insert(layer.name.clone());
+                                    } else {
+                                        self.selected.remove(&layer.name);
+                                    }
+                                }
+                                response
+                            };
+                            changed |= response.on_hover_text(&layer.name).changed();
+                        });
+                    }
+                });
+        }
+        if budget == 0 {
+            // more thumbnails to decode
+            ui.ctx().request_repaint();
+        }
+        if changed {
+            self.render_selection();
         }
-        let rgba = final_image.unwrap().to_rgba8();
-        let (w, h) = rgba.dimensions();
-        let color_image = ColorImage::from_rgba_unmultiplied([w as usize, h as usize], &rgba);
-        self.image = Some(color_image);
     }
 
-    fn get_layer_metadata_path(&self, layer_name: impl Into<String>) -> PathBuf {
-        let layer_name = layer_name.into();
-        let mut layer_path = PathBuf::new();
-        layer_path.push("data");
-        layer_path.push("metadata");
-        layer_path.push(format!("{layer_name}.json"));
+    /// The layer's thumbnail, decoded now if `budget` allows.
+    fn thumbnail(
+        &mut self,
+        ctx: &egui::Context,
+        name: &str,
+        budget: &mut usize,
+    ) -> Option<TextureHandle> {
+        if let Some(thumbnail) = self.thumbnails.get(name) {
+            return thumbnail.clone();
+        }
+        if *budget == 0 {
+            return None;
+        }
+        *budget -= 1;
 
-        layer_path
+        let model = self.model.as_mut()?;
+        let thumbnail = match model.get_image(name) {
+            Ok(image) => {
+                let rgba = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba8();
+                let (w, h) = rgba.dimensions();
+                let color_image =
+                    ColorImage::from_rgba_unmultiplied([w as usize, h as usize], &rgba);
+                Some(ctx.load_texture(format!("thumbnail_{name}"), color_image, Default::default()))
+            }
+            Err(e) => {
+                log::error!("Failed to read layer {name}: {e}");
+                None
+            }
+        };
+        self.thumbnails.insert(name.to_string(), thumbnail.clone());
+        thumbnail
     }
 
-    fn get_layer_path(&self, layer_name: impl Into<String>) -> PathBuf {
-        let layer_name = layer_name.into();
-        let mut layer_path = PathBuf::new();
-        layer_path.push("data");
-        layer_path.push("layers");
-        layer_path.push(format!("ムラサメa_{layer_name}.png"));
+    fn load_model(&mut self, path: &Path) {
+        self.model_path = path.display().to_string();
+        let model = match Model::from_file(path) {
+            Ok(model) => model,
+            Err(e) => {
+                self.error = Some(format!("Failed to load {}: {e}", path.display()));
+                return;
+            }
+        };
 
-        layer_path
+        self.groups.clear();
+        for (name, layer) in &model.manifest().layers {
+            let is_base = matches!(layer, LayerManifest::BaseLayer { .. });
+            let description = match layer {
+                LayerManifest::BaseLayer { description, .. }
+                | LayerManifest::TopLayer { description, .. } => description.as_deref(),
+            };
+            let group = match layer.group() {
+                Some(group) => group,
+                None if is_base => "base",
+                None => "other",
+            };
+            self.groups
+                .entry(group.to_string())
+                .or_default()
+                .push(LayerEntry {
+                    name: name.clone(),
+                    label: description.unwrap_or(name).to_string(),
+                    is_base,
+                });
+        }
+        self.base_layer = self
+            .groups
+            .values()
+            .flatten()
+            .find(|layer| layer.is_base)
+            .map(|layer| layer.name.clone());
+        self.selected.clear();
+        // dropping the handles frees the textures of the last model
+        self.thumbnails.clear();
+        self.model = Some(model);
+        self.error = None;
+        self.render_selection();
+    }
+
+    /// Render the base layer with the selected layers on top.
+    fn render_selection(&mut self) {
+        let Some(model) = &mut self.model else {
+            return;
+        };
+        let layers: Vec<String> = self
+            .base_layer
+            .iter()
+            .chain(&self.selected)
+            .cloned()
+            .collect();
+        match model.render(&layers) {
+            Ok(image) => {
+                let rgba = image.to_rgba8();
+                let (w, h) = rgba.dimensions();
+                let color_image =
+                    ColorImage::from_rgba_unmultiplied([w as usize, h as usize], &rgba);
+                self.image = Some(color_image);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Failed to render {layers:?}: {e}")),
+        }
     }
 }
@@ -1,32 +1,42 @@
+use std::path::PathBuf;
+
+use config::FrontendConfig;
 use eframe::egui;
 
-use crate::gui::FrontendApp;
+use crate::{gui::FrontendApp, remote::RemoteDisplay};
 
 mod gui;
+mod remote;
 
+/// Preview a model, the zip given as the first argument, `FRONTEND_MODEL` or the vtuber's
+/// `VTUBER_RENDER_MODEL`. With `FRONTEND_SERVER` set, the replies of that vtuber server are shown
+/// on the model instead. Ctrl+Alt+O puts the window onto the next screen.
 pub fn run() -> anyhow::Result<()> {
     dotenvy::dotenv()?;
     env_logger::init(); // TODO: add default log level
 
+    let config = FrontendConfig::from_env()?;
+    let model_path = std::env::args().nth(1).map(PathBuf::from).or(config.model);
+
     // init gui
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([320.0, 240.0])
+            .with_inner_size([720.0, 540.0])
             .with_transparent(true)
             .with_window_level(egui::WindowLevel::AlwaysOnTop),
         ..Default::default()
     };
 
-    eframe::run_native(
-        "Murasame-chan",
-        options,
-        Box::new(|cc| {
-            // This gives us image support:
-            egui_extras::install_image_loaders(&cc.egui_ctx);
+    desktop_window::run_native("Murasame-chan", options, config.window, |cc, window| {
+        // This gives us image support:
+        egui_extras::install_image_loaders(&cc.egui_ctx);
 
-            Ok(Box::<FrontendApp>::default())
-        }),
-    )
+        let remote = config
+            .server
+            .map(|server| RemoteDisplay::new(&server, config.character, cc.egui_ctx.clone()))
+            .transpose()?;
+        Ok(Box::new(FrontendApp::new(model_path, remote, window)))
+    })
     .map_err(|err| anyhow::anyhow!("Failed to init egui: {err}"))?;
 
     Ok(())
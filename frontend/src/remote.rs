@@ -0,0 +1,252 @@
+use std::{
+    collections::VecDeque,
+    io::Cursor,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use base64::Engine;
+use eframe::egui;
+use rodio::{Decoder, OutputStream, OutputStreamBuilder, Sink};
+use tungstenite::Message;
+use url::Url;
+
+/// Wait before connecting again after the connection failed or dropped.
+const RECONNECT: Duration = Duration::from_secs(3);
+/// How long a line without voice is shown per character of its text, at least a second.
+const SILENT_CHAR: Duration = Duration::from_millis(150);
+
+/// A line the vtuber replied with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteLine {
+    /// Index of the speaking character
+    pub character: usize,
+    pub text: String,
+    pub layers: Vec<String>,
+    /// Encoded voice, e.g. wav
+    pub voice: Option<Vec<u8>>,
+}
+
+pub enum RemoteEvent {
+    Connected,
+    Disconnected(String),
+    Line(RemoteLine),
+}
+
+/// The events of the vtuber's `/events/ws` stream the frontend cares about.
+#[derive(serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OverlayEvent {
+    Reply {
+        character: usize,
+        text: String,
+        layers: Vec<String>,
+        /// Base64, only sent when asked for with `?audio=true`
+        #[serde(default)]
+        audio: Option<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// The line in an event of the stream, if it is a reply.
+fn parse_line(json: &str) -> Option<RemoteLine> {
+    let event = serde_json::from_str(json)
+        .inspect_err(|e| log::warn!("Ignored bad event: {e}"))
+        .ok()?;
+    let OverlayEvent::Reply {
+        character,
+        text,
+        layers,
+        audio,
+    } = event
+    else {
+        return None;
+    };
+    let voice = audio.and_then(|audio| {
+        base64::engine::general_purpose::STANDARD
+            .decode(audio)
+            .inspect_err(|e| log::warn!("Ignored bad voice: {e}"))
+            .ok()
+    });
+    Some(RemoteLine {
+        character,
+        text,
+        layers,
+        voice,
+    })
+}
+
+/// Follow the events of the vtuber server at `server`, e.g. `ws://127.0.0.1:8080`, connecting
+/// again whenever the connection drops. The window is repainted on every event.
+pub fn spawn_remote(
+    server: &Url,
+    ctx: egui::Context,
+) -> anyhow::Result<mpsc::Receiver<RemoteEvent>> {
+    let url = server.join("events/ws?audio=true")?;
+    let (tx, rx) = mpsc::channel();
+    let send = move |event| {
+        let sent = tx.send(event).is_ok();
+        ctx.request_repaint();
+        sent
+    };
+
+    std::thread::Builder::new()
+        .name("remote".to_string())
+        .spawn(move || {
+            loop {
+                let error = match tungstenite::connect(url.as_str()) {
+                    Ok((mut socket, _)) => {
+                        log::info!("Connected to {url}");
+                        if !send(RemoteEvent::Connected) {
+                            return;
+                        }
+                        loop {
+                            match socket.read() {
+                                Ok(Message::Text(text)) => {
+                                    if let Some(line) = parse_line(&text)
+                                        && !send(RemoteEvent::Line(line))
+                                    {
+                                        return;
+                                    }
+                                }
+                                Ok(_) => {}
+                                Err(e) => break e,
+                            }
+                        }
+                    }
+                    Err(e) => e,
+                };
+                log::warn!("Lost the connection to {url}: {error}");
+                if !send(RemoteEvent::Disconnected(error.to_string())) {
+                    return;
+                }
+                std::thread::sleep(RECONNECT);
+            }
+        })?;
+
+    Ok(rx)
+}
+
+/// Shows the lines a vtuber server replies with, so the pet window can run on another machine
+/// than the AI and TTS pipeline.
+pub struct RemoteDisplay {
+    events: mpsc::Receiver<RemoteEvent>,
+    /// Only the lines of this character are shown
+    character: usize,
+    /// `None` while connected, or why the connection dropped
+    status: Option<String>,
+    queue: VecDeque<RemoteLine>,
+    /// `None` without an output device, lines are shown without voice then
+    audio: Option<(OutputStream, Sink)>,
+    current: Option<RemoteLine>,
+    /// When a line without voice is done
+    silent_until: Option<Instant>,
+}
+
+impl RemoteDisplay {
+    pub fn new(server: &Url, character: usize, ctx: egui::Context) -> anyhow::Result<Self> {
+        let audio = match OutputStreamBuilder::open_default_stream() {
+            Ok(stream) => {
+                let sink = Sink::connect_new(stream.mixer());
+                Some((stream, sink))
+            }
+            Err(e) => {
+                log::warn!("No audio output, the voice is not played: {e}");
+                None
+            }
+        };
+        Ok(Self {
+            events: spawn_remote(server, ctx)?,
+            character,
+            status: Some("Connecting".to_string()),
+            queue: VecDeque::new(),
+            audio,
+            current: None,
+            silent_until: None,
+        })
+    }
+
+    /// Why the server can't be reached, if it can't.
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    /// The text of the line being said.
+    pub fn subtitle(&self) -> Option<&str> {
+        self.current.as_ref().map(|line| line.text.as_str())
+    }
+
+    /// Take the events of the server and play the next line once the current one is done.
+    /// Returns the layers to show when they change, empty once a line is over.
+    pub fn update(&mut self, ctx: &egui::Context) -> Option<Vec<String>> {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                RemoteEvent::Connected => self.status = None,
+                RemoteEvent::Disconnected(error) => self.status = Some(error),
+                RemoteEvent::Line(line) if line.character == self.character => {
+                    self.queue.push_back(line)
+                }
+                RemoteEvent::Line(_) => {}
+            }
+        }
+
+        let now = Instant::now();
+        let playing = match self.silent_until {
+            Some(until) => now < until,
+            None => self.audio.as_ref().is_some_and(|(_, sink)| !sink.empty()),
+        };
+        if self.current.is_some() && playing {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return None;
+        }
+
+        let Some(mut line) = self.queue.pop_front() else {
+            // back to the plain base layer once the last line is done
+            return self.current.take().map(|_| Vec::new());
+        };
+        self.silent_until = match (line.voice.take(), &self.audio) {
+            (Some(voice), Some((_, sink))) => match Decoder::new(Cursor::new(voice)) {
+                Ok(source) => {
+                    sink.append(source);
+                    None
+                }
+                Err(e) => {
+                    log::error!("Failed to decode voice: {e}");
+                    Some(now + silent_duration(&line.text))
+                }
+            },
+            _ => Some(now + silent_duration(&line.text)),
+        };
+        let layers = line.layers.clone();
+        self.current = Some(line);
+        ctx.request_repaint();
+        Some(layers)
+    }
+}
+
+fn silent_duration(text: &str) -> Duration {
+    (SILENT_CHAR * text.chars().count() as u32).max(Duration::from_secs(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::remote::{RemoteLine, parse_line};
+
+    #[test]
+    fn parse_replies() {
+        let line = parse_line(
+            r#"{"type": "reply", "character": 1, "text": "hello", "layers": ["smile.png"], "priority": "normal", "audio_size": 3, "audio": "AQID"}"#,
+        );
+        assert_eq!(
+            line,
+            Some(RemoteLine {
+                character: 1,
+                text: "hello".to_string(),
+                layers: vec!["smile.png".to_string()],
+                voice: Some(vec![1, 2, 3]),
+            })
+        );
+        assert_eq!(parse_line(r#"{"type": "thinking"}"#), None);
+    }
+}
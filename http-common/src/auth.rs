@@ -0,0 +1,116 @@
+use actix_web::{
+    Error,
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{StatusCode, header},
+    middleware::Next,
+    web,
+};
+
+use crate::error::ApiError;
+
+/// Token of the operator, sent as `Authorization: Bearer <token>` to the routes that make the
+/// character say or do something on their own, e.g. notifications.
+#[derive(Debug, Clone, Default)]
+pub struct OperatorToken(pub Option<String>);
+
+impl OperatorToken {
+    fn accepts(&self, authorization: Option<&str>) -> Result<(), ApiError> {
+        let Some(token) = &self.0 else {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "operator_routes_disabled",
+                "Operator routes are disabled, no token is configured",
+            ));
+        };
+        let given = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if constant_time_eq(given.as_bytes(), token.as_bytes()) {
+            Ok(())
+        } else {
+            Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+                "Missing or wrong operator token",
+            ))
+        }
+    }
+}
+
+/// Doesn't tell by its timing how much of a guessed token is right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Middleware letting through only requests with the [`OperatorToken`] of the app data. Without
+/// a configured token every request is refused.
+pub async fn require_token(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let accepted = match request.app_data::<web::Data<OperatorToken>>() {
+        Some(token) => token.accepts(authorization),
+        None => OperatorToken::default().accepts(authorization),
+    };
+    match accepted {
+        Ok(()) => Ok(next.call(request).await?.map_into_boxed_body()),
+        Err(e) => Ok(request.error_response(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App,
+        middleware::from_fn,
+        test::{TestRequest, call_service, init_service},
+        web,
+    };
+
+    use crate::auth::{OperatorToken, require_token};
+
+    #[actix_web::test]
+    async fn check_the_token() {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(OperatorToken(Some("secret".to_string()))))
+                .service(
+                    web::scope("/notify")
+                        .wrap(from_fn(require_token))
+                        .route("", web::post().to(|| async { "ok" })),
+                ),
+        )
+        .await;
+
+        let request = |authorization: Option<&str>| {
+            let request = TestRequest::post().uri("/notify");
+            match authorization {
+                Some(value) => request.insert_header(("Authorization", value)),
+                None => request,
+            }
+            .to_request()
+        };
+        let response = call_service(&app, request(Some("Bearer secret"))).await;
+        assert_eq!(response.status(), 200);
+        let response = call_service(&app, request(Some("Bearer secre"))).await;
+        assert_eq!(response.status(), 401);
+        let response = call_service(&app, request(None)).await;
+        assert_eq!(response.status(), 401);
+
+        let app = init_service(
+            App::new().service(
+                web::scope("/notify")
+                    .wrap(from_fn(require_token))
+                    .route("", web::post().to(|| async { "ok" })),
+            ),
+        )
+        .await;
+        let response = call_service(&app, request(Some("Bearer secret"))).await;
+        assert_eq!(response.status(), 403);
+    }
+}
@@ -0,0 +1,147 @@
+use actix_web::{
+    Error,
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        Method,
+        header::{self, HeaderValue},
+    },
+    middleware::Next,
+    web,
+};
+
+/// Methods allowed in preflight requests, every route of the servers uses one of them.
+const METHODS: &str = "GET, POST, PATCH, DELETE";
+/// Preflight responses are cached by browsers for a day.
+const MAX_AGE: &str = "86400";
+
+/// Origins allowed to call the server from a browser, e.g. a chat widget on another host.
+#[derive(Debug, Clone, Default)]
+pub struct Cors {
+    origins: Vec<String>,
+}
+
+impl Cors {
+    /// `*` allows any origin, none disables CORS.
+    pub fn new(origins: Vec<String>) -> Self {
+        Self { origins }
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed.trim_end_matches('/') == origin)
+    }
+}
+
+/// Middleware answering preflight requests and adding the CORS headers for the origins of the
+/// [`Cors`] in the app data. Requests from other origins are passed on unchanged, browsers
+/// then refuse to read the responses.
+pub async fn cors(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let origin = request
+        .headers()
+        .get(header::ORIGIN)
+        .filter(|origin| {
+            let cors = request.app_data::<web::Data<Cors>>();
+            cors.is_some_and(|cors| origin.to_str().is_ok_and(|origin| cors.allows(origin)))
+        })
+        .cloned();
+    let Some(origin) = origin else {
+        return Ok(next.call(request).await?.map_into_boxed_body());
+    };
+
+    let preflight = request.method() == Method::OPTIONS
+        && request
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+    let mut response = if preflight {
+        let allowed_headers = request
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .cloned();
+        let mut response = request.into_response(actix_web::HttpResponse::NoContent().finish());
+        let headers = response.headers_mut();
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static(METHODS),
+        );
+        if let Some(allowed_headers) = allowed_headers {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+        }
+        headers.insert(
+            header::ACCESS_CONTROL_MAX_AGE,
+            HeaderValue::from_static(MAX_AGE),
+        );
+        response
+    } else {
+        next.call(request).await?.map_into_boxed_body()
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+    headers.insert(
+        header::ACCESS_CONTROL_EXPOSE_HEADERS,
+        HeaderValue::from_static("x-request-id"),
+    );
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App,
+        middleware::from_fn,
+        test::{TestRequest, call_service, init_service},
+        web,
+    };
+
+    use crate::cors::{Cors, cors};
+
+    #[actix_web::test]
+    async fn allow_origins() {
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(cors))
+                .app_data(web::Data::new(Cors::new(vec![
+                    "https://widget.example/".to_string(),
+                ])))
+                .route("/comments/add", web::post().to(|| async { "ok" })),
+        )
+        .await;
+
+        let request = TestRequest::default()
+            .method(actix_web::http::Method::OPTIONS)
+            .uri("/comments/add")
+            .insert_header(("Origin", "https://widget.example"))
+            .insert_header(("Access-Control-Request-Method", "POST"))
+            .insert_header(("Access-Control-Request-Headers", "content-type"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), 204);
+        let headers = response.headers();
+        assert_eq!(
+            headers.get("access-control-allow-origin").unwrap(),
+            "https://widget.example"
+        );
+        assert_eq!(
+            headers.get("access-control-allow-headers").unwrap(),
+            "content-type"
+        );
+
+        let request = TestRequest::post()
+            .uri("/comments/add")
+            .insert_header(("Origin", "https://elsewhere.example"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), 200);
+        assert!(
+            !response
+                .headers()
+                .contains_key("access-control-allow-origin")
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+
+use actix_web::{
+    Error, HttpMessage, ResponseError,
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        StatusCode,
+        header::{CONTENT_TYPE, HeaderName, HeaderValue},
+    },
+    middleware::Next,
+};
+
+/// Header carrying the request id, taken from the request if a proxy set it.
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Id of the request, in its extensions for handlers to log.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// An error with a code for clients to match on, e.g. `invalid_input`.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// The request is well-formed but its content isn't accepted.
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_input", message)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+}
+
+/// Body of every error response.
+#[derive(serde::Serialize, schemars::JsonSchema, Debug)]
+pub struct ErrorBody {
+    /// [`ApiError`]'s code, or the status' reason in snake case, e.g. `payload_too_large`
+    pub code: String,
+    pub message: String,
+    pub request_id: String,
+}
+
+impl ErrorBody {
+    fn new(error: &Error, status: StatusCode, request_id: &str) -> Self {
+        let code = match error.as_error::<ApiError>() {
+            Some(error) => error.code.to_string(),
+            None => status
+                .canonical_reason()
+                .unwrap_or("error")
+                .to_lowercase()
+                .replace([' ', '-'], "_"),
+        };
+        Self {
+            code,
+            message: error.to_string(),
+            request_id: request_id.to_string(),
+        }
+    }
+}
+
+/// Middleware giving every request an id and replacing the bodies of errors, whether
+/// returned by handlers or extractors, with an [`ErrorBody`].
+pub async fn json_errors(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{:016x}", fastrand::u64(..)));
+    request
+        .extensions_mut()
+        .insert(RequestId(request_id.clone()));
+
+    let response = next.call(request).await?.map_into_boxed_body();
+    let (http_request, response) = response.into_parts();
+    let mut response = match response.error() {
+        Some(error) => {
+            let body = ErrorBody::new(error, response.status(), &request_id);
+            let body = serde_json::to_vec(&body).expect("error body is serializable");
+            let mut response = response.set_body(BoxBody::new(body));
+            response
+                .headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            response
+        }
+        None => response,
+    };
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID, value);
+    }
+
+    Ok(ServiceResponse::new(http_request, response))
+}
+
+/// Accept `text` if it isn't blank and at most `max_chars` long.
+pub fn check_text(field: &str, text: &str, max_chars: usize) -> Result<(), ApiError> {
+    if text.trim().is_empty() {
+        return Err(ApiError::invalid(format!("{field} is empty")));
+    }
+    let chars = text.chars().count();
+    if chars > max_chars {
+        return Err(ApiError::invalid(format!(
+            "{field} is {chars} characters long, at most {max_chars} are allowed"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App,
+        middleware::from_fn,
+        test::{
+            TestRequest, call_and_read_body, call_and_read_body_json, call_service, init_service,
+            read_body_json,
+        },
+        web,
+    };
+
+    use crate::error::{ApiError, check_text, json_errors};
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        text: String,
+    }
+
+    async fn echo(body: web::Json<Body>) -> Result<String, ApiError> {
+        check_text("text", &body.text, 5)?;
+        Ok(body.into_inner().text)
+    }
+
+    #[test]
+    fn check_texts() {
+        assert!(check_text("text", "hello", 5).is_ok());
+        assert!(check_text("text", "  \n", 5).is_err());
+        // characters, not bytes
+        assert!(check_text("text", "ムラサメです", 6).is_ok());
+        assert!(check_text("text", "ムラサメです", 5).is_err());
+    }
+
+    #[actix_web::test]
+    async fn errors_as_json() {
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(json_errors))
+                .app_data(web::JsonConfig::default().limit(32))
+                .route("/echo", web::post().to(echo)),
+        )
+        .await;
+
+        let request = TestRequest::post()
+            .uri("/echo")
+            .insert_header(("x-request-id", "abc"))
+            .set_json(serde_json::json!({ "text": "" }))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), 400);
+        assert_eq!(response.headers().get("x-request-id").unwrap(), "abc");
+        let body: serde_json::Value = read_body_json(response).await;
+        assert_eq!(body["code"], "invalid_input");
+        assert_eq!(body["message"], "text is empty");
+        assert_eq!(body["request_id"], "abc");
+
+        let request = TestRequest::post()
+            .uri("/echo")
+            .set_json(serde_json::json!({ "text": "a".repeat(100) }))
+            .to_request();
+        let body: serde_json::Value = call_and_read_body_json(&app, request).await;
+        assert_eq!(body["code"], "payload_too_large");
+        assert_eq!(body["request_id"].as_str().unwrap().len(), 16);
+
+        let request = TestRequest::post()
+            .uri("/echo")
+            .set_json(serde_json::json!({ "text": "hi" }))
+            .to_request();
+        assert_eq!(call_and_read_body(&app, request).await, "hi");
+    }
+}
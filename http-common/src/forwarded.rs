@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::{Ready, ready},
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use actix_web::{FromRequest, HttpRequest, dev::Payload, http::StatusCode, web};
+
+use crate::error::ApiError;
+
+/// Clients seen within a minute are forgotten after it once this many are tracked.
+const MAX_TRACKED_CLIENTS: usize = 1000;
+
+/// Reverse proxies, e.g. nginx, whose `X-Forwarded-For` headers are believed.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+/// Address of the client, behind the [`TrustedProxies`] in the app data if any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientAddr(pub Option<IpAddr>);
+
+impl ClientAddr {
+    fn resolve(peer: Option<IpAddr>, forwarded_for: Option<&str>, trusted: &[IpAddr]) -> Self {
+        let Some(mut client) = peer else {
+            return Self(None);
+        };
+        // each proxy appends the address it got the request from, so walk back from the end
+        // until an address not set by a trusted proxy
+        let mut forwarded = forwarded_for
+            .unwrap_or_default()
+            .rsplit(',')
+            .map(|addr| addr.trim().parse::<IpAddr>());
+        while trusted.contains(&client) {
+            match forwarded.next() {
+                Some(Ok(addr)) => client = addr,
+                _ => break,
+            }
+        }
+        Self(Some(client))
+    }
+}
+
+impl FromRequest for ClientAddr {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Infallible>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let trusted = req
+            .app_data::<web::Data<TrustedProxies>>()
+            .map(|proxies| proxies.0.as_slice())
+            .unwrap_or_default();
+        let forwarded_for = req
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok());
+        ready(Ok(Self::resolve(
+            req.peer_addr().map(|addr| addr.ip()),
+            forwarded_for,
+            trusted,
+        )))
+    }
+}
+
+/// Limits how many requests each [`ClientAddr`] sends a minute, e.g. comments from a widget
+/// that can claim any username.
+#[derive(Debug)]
+pub struct ClientLimiter {
+    per_minute: u32,
+    /// When the minute of each client started and its requests in it
+    clients: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl ClientLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a request, refused once the client sent too many this minute. Clients without a
+    /// known address aren't limited.
+    pub fn check(&self, client: ClientAddr, now: Instant) -> Result<(), ApiError> {
+        const MINUTE: Duration = Duration::from_secs(60);
+
+        let Some(addr) = client.0 else {
+            return Ok(());
+        };
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() >= MAX_TRACKED_CLIENTS {
+            clients.retain(|_, (start, _)| now.duration_since(*start) < MINUTE);
+        }
+        let (start, count) = clients.entry(addr).or_insert((now, 0));
+        if now.duration_since(*start) >= MINUTE {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= self.per_minute {
+            return Err(ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limited",
+                format!("At most {} requests a minute", self.per_minute),
+            ));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::IpAddr,
+        time::{Duration, Instant},
+    };
+
+    use crate::forwarded::{ClientAddr, ClientLimiter};
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn resolve_client_behind_proxies() {
+        let proxies = [ip("127.0.0.1"), ip("10.0.0.2")];
+        let resolve = |peer: &str, forwarded_for| {
+            ClientAddr::resolve(Some(ip(peer)), forwarded_for, &proxies).0
+        };
+
+        // direct connections can't claim another address
+        assert_eq!(
+            resolve("203.0.113.7", Some("198.51.100.1")),
+            Some(ip("203.0.113.7"))
+        );
+        assert_eq!(
+            resolve("127.0.0.1", Some("198.51.100.1, 203.0.113.7, 10.0.0.2")),
+            Some(ip("203.0.113.7"))
+        );
+        // the proxy itself, e.g. a health check
+        assert_eq!(resolve("127.0.0.1", None), Some(ip("127.0.0.1")));
+        assert_eq!(resolve("127.0.0.1", Some("garbage")), Some(ip("127.0.0.1")));
+    }
+
+    #[test]
+    fn limit_each_client() {
+        let limiter = ClientLimiter::new(2);
+        let start = Instant::now();
+        let client = ClientAddr(Some(ip("203.0.113.7")));
+        assert!(limiter.check(client, start).is_ok());
+        assert!(limiter.check(client, start).is_ok());
+        assert!(limiter.check(client, start).is_err());
+        // others have their own count
+        assert!(
+            limiter
+                .check(ClientAddr(Some(ip("198.51.100.1"))), start)
+                .is_ok()
+        );
+        assert!(limiter.check(ClientAddr(None), start).is_ok());
+        assert!(
+            limiter
+                .check(client, start + Duration::from_secs(60))
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,8 @@
+pub mod auth;
+pub mod cors;
+pub mod error;
+pub mod forwarded;
+pub mod openapi;
+/// Helpers for the tests of the services, see the `test-support` feature.
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing;
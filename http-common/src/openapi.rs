@@ -0,0 +1,315 @@
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, web};
+use schemars::{JsonSchema, Schema, SchemaGenerator, generate::SchemaSettings};
+use serde_json::{Map, Value, json};
+
+use crate::error::ErrorBody;
+
+/// Docs page rendering `openapi.json` next to it, also behind a base path. It has no assets
+/// of its own, so it works without internet access.
+const DOCS_PAGE: &str = include_str!("docs.html");
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// One route of a service, described from the types its handler extracts and returns.
+pub struct Operation {
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    body: Option<SchemaFn>,
+    query: Option<SchemaFn>,
+    path_parameters: Vec<(&'static str, &'static str)>,
+    responses: Vec<(u16, &'static str, Option<&'static str>, Option<SchemaFn>)>,
+}
+
+impl Operation {
+    fn new(method: &'static str, path: &'static str, summary: &'static str) -> Self {
+        Self {
+            method,
+            path,
+            summary,
+            body: None,
+            query: None,
+            path_parameters: Vec::new(),
+            responses: Vec::new(),
+        }
+    }
+
+    pub fn get(path: &'static str, summary: &'static str) -> Self {
+        Self::new("get", path, summary)
+    }
+
+    pub fn post(path: &'static str, summary: &'static str) -> Self {
+        Self::new("post", path, summary)
+    }
+
+    pub fn patch(path: &'static str, summary: &'static str) -> Self {
+        Self::new("patch", path, summary)
+    }
+
+    pub fn delete(path: &'static str, summary: &'static str) -> Self {
+        Self::new("delete", path, summary)
+    }
+
+    /// JSON request body.
+    pub fn body<T: JsonSchema>(mut self) -> Self {
+        self.body = Some(SchemaGenerator::subschema_for::<T>);
+        self
+    }
+
+    /// Query string, one parameter per field of `T`.
+    pub fn query<T: JsonSchema>(mut self) -> Self {
+        self.query = Some(SchemaGenerator::subschema_for::<T>);
+        self
+    }
+
+    /// A `{name}` segment of the path, `kind` is its JSON type.
+    pub fn path_parameter(mut self, name: &'static str, kind: &'static str) -> Self {
+        self.path_parameters.push((name, kind));
+        self
+    }
+
+    /// A response without a described body, an [`ErrorBody`] for errors.
+    pub fn response(mut self, status: u16, description: &'static str) -> Self {
+        self.responses.push((status, description, None, None));
+        self
+    }
+
+    /// A response with a body of some other media type, e.g. `audio/wav`.
+    pub fn content(
+        mut self,
+        status: u16,
+        description: &'static str,
+        media_type: &'static str,
+    ) -> Self {
+        self.responses
+            .push((status, description, Some(media_type), None));
+        self
+    }
+
+    /// A JSON response body.
+    pub fn json<T: JsonSchema>(mut self, status: u16, description: &'static str) -> Self {
+        self.responses.push((
+            status,
+            description,
+            Some("application/json"),
+            Some(SchemaGenerator::subschema_for::<T>),
+        ));
+        self
+    }
+
+    fn to_value(&self, generator: &mut SchemaGenerator) -> Value {
+        let mut operation = Map::new();
+        operation.insert("summary".into(), self.summary.into());
+
+        let mut parameters: Vec<Value> = self
+            .path_parameters
+            .iter()
+            .map(|(name, kind)| {
+                json!({ "name": name, "in": "path", "required": true, "schema": { "type": kind } })
+            })
+            .collect();
+        if let Some(query) = self.query {
+            parameters.extend(query_parameters(generator, query));
+        }
+        if !parameters.is_empty() {
+            operation.insert("parameters".into(), parameters.into());
+        }
+
+        if let Some(body) = self.body {
+            operation.insert(
+                "requestBody".into(),
+                json!({
+                    "required": true,
+                    "content": { "application/json": { "schema": body(generator) } },
+                }),
+            );
+        }
+
+        let mut responses = Map::new();
+        for (status, description, media_type, schema) in &self.responses {
+            let mut response = json!({ "description": description });
+            if let Some(media_type) = media_type {
+                let schema = match schema {
+                    Some(schema) => schema(generator).to_value(),
+                    None => json!({ "type": "string", "format": "binary" }),
+                };
+                response["content"] = json!({ *media_type: { "schema": schema } });
+            } else if *status >= 400 {
+                // see `error::json_errors`
+                let schema = generator.subschema_for::<ErrorBody>();
+                response["content"] = json!({ "application/json": { "schema": schema } });
+            }
+            responses.insert(status.to_string(), response);
+        }
+        operation.insert("responses".into(), responses.into());
+
+        operation.into()
+    }
+}
+
+/// The fields of a query struct as OpenAPI parameters.
+fn query_parameters(generator: &mut SchemaGenerator, query: SchemaFn) -> Vec<Value> {
+    let schema = query(generator).to_value();
+    // structs are referenced, look them up among the definitions
+    let schema = match schema["$ref"]
+        .as_str()
+        .and_then(|path| path.rsplit('/').next())
+    {
+        Some(name) => generator.definitions()[name].clone(),
+        None => schema,
+    };
+    let required = schema["required"].as_array().cloned().unwrap_or_default();
+    let Some(properties) = schema["properties"].as_object() else {
+        return Vec::new();
+    };
+
+    properties
+        .iter()
+        .map(|(name, property)| {
+            let mut parameter = json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(&Value::from(name.as_str())),
+                "schema": property,
+            });
+            if let Some(description) = property.get("description") {
+                parameter["description"] = description.clone();
+            }
+            parameter
+        })
+        .collect()
+}
+
+/// An OpenAPI 3.0 document listing the operations of a service.
+pub struct ApiDoc {
+    title: &'static str,
+    version: &'static str,
+    operations: Vec<Operation>,
+}
+
+impl ApiDoc {
+    pub fn new(title: &'static str, version: &'static str) -> Self {
+        Self {
+            title,
+            version,
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn operation(mut self, operation: Operation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    /// Method and path of every operation, e.g. to check them against the routes.
+    pub fn routes(&self) -> impl Iterator<Item = (&'static str, &'static str)> + '_ {
+        self.operations
+            .iter()
+            .map(|operation| (operation.method, operation.path))
+    }
+
+    pub fn to_value(&self) -> Value {
+        let mut generator = SchemaSettings::openapi3().into_generator();
+        let mut paths = Map::new();
+        for operation in &self.operations {
+            let path = paths
+                .entry(operation.path)
+                .or_insert_with(|| Value::Object(Map::new()));
+            path[operation.method] = operation.to_value(&mut generator);
+        }
+
+        json!({
+            "openapi": "3.0.3",
+            "info": { "title": self.title, "version": self.version },
+            "paths": paths,
+            "components": { "schemas": generator.take_definitions(true) },
+        })
+    }
+}
+
+/// Serve the document at `/openapi.json` and a page showing it at `/docs`.
+pub fn configure(config: &mut web::ServiceConfig, doc: &ApiDoc) {
+    let document = Arc::new(doc.to_value());
+    config
+        .route(
+            "/openapi.json",
+            web::get().to(move || {
+                let document = document.clone();
+                async move { HttpResponse::Ok().json(&*document) }
+            }),
+        )
+        .route(
+            "/docs",
+            web::get().to(|| async {
+                HttpResponse::Ok()
+                    .content_type("text/html; charset=utf-8")
+                    .body(DOCS_PAGE)
+            }),
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::JsonSchema;
+
+    use crate::openapi::{ApiDoc, Operation};
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Query {
+        /// Only the last ones
+        recent: bool,
+        #[serde(default)]
+        limit: Option<u32>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Body {
+        text: String,
+    }
+
+    #[test]
+    fn describe_operations() {
+        let doc = ApiDoc::new("test", "0.1.0")
+            .operation(
+                Operation::get("/items/{id}", "Get an item")
+                    .path_parameter("id", "integer")
+                    .query::<Query>()
+                    .response(404, "No such item"),
+            )
+            .operation(
+                Operation::post("/items", "Add an item")
+                    .body::<Body>()
+                    .json::<Body>(200, "The added item"),
+            );
+        let value = doc.to_value();
+
+        let get = &value["paths"]["/items/{id}"]["get"];
+        let parameters = get["parameters"].as_array().unwrap();
+        assert_eq!(parameters.len(), 3);
+        assert_eq!(parameters[1]["name"], "limit");
+        assert_eq!(parameters[1]["required"], false);
+        assert_eq!(parameters[2]["description"], "Only the last ones");
+        assert_eq!(
+            get["responses"]["404"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ErrorBody"
+        );
+
+        let post = &value["paths"]["/items"]["post"];
+        assert_eq!(
+            post["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/Body"
+        );
+        assert_eq!(
+            value["components"]["schemas"]["Body"]["required"][0],
+            "text"
+        );
+        assert_eq!(
+            doc.routes().collect::<Vec<_>>(),
+            [("get", "/items/{id}"), ("post", "/items")]
+        );
+    }
+}
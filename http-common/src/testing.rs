@@ -0,0 +1,117 @@
+use actix_web::{
+    App,
+    http::{Method, StatusCode},
+    test, web,
+};
+
+use crate::{auth::OperatorToken, openapi::ApiDoc};
+
+const TOKEN: &str = "test";
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+/// Differences between the routes `configure` registers, the list of them and the document, for
+/// a test keeping the three in sync. `routes` are methods and paths like [`ApiDoc::routes`], every
+/// other method on their paths has to be refused. Routes behind the operator token get one.
+pub async fn route_mismatches(
+    configure: fn(&mut web::ServiceConfig),
+    routes: &[(&str, &str)],
+    doc: &ApiDoc,
+) -> Vec<String> {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(OperatorToken(Some(TOKEN.to_string()))))
+            .configure(configure),
+    )
+    .await;
+    let routed = async |method: &str, path: &str| {
+        // path parameters are filled in, missing app data fails the handlers, unknown routes
+        // fail before them
+        let uri = path
+            .split('/')
+            .map(|segment| {
+                if segment.starts_with('{') {
+                    "1"
+                } else {
+                    segment
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let request = test::TestRequest::default()
+            .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+            .uri(&uri)
+            .insert_header(("Authorization", format!("Bearer {TOKEN}")))
+            .to_request();
+        let status = test::call_service(&app, request).await.status();
+        status != StatusCode::NOT_FOUND && status != StatusCode::METHOD_NOT_ALLOWED
+    };
+
+    let mut mismatches = Vec::new();
+    let documented: Vec<(&str, &str)> = doc.routes().collect();
+    for route @ (method, path) in &documented {
+        if !routes.contains(route) {
+            mismatches.push(format!("{method} {path} is documented but not listed"));
+        }
+    }
+    for route @ (method, path) in routes {
+        if !documented.contains(route) {
+            mismatches.push(format!("{method} {path} is not documented"));
+        }
+        if !routed(method, path).await {
+            mismatches.push(format!("{method} {path} has no route"));
+        }
+    }
+
+    let mut paths: Vec<&str> = routes.iter().map(|(_, path)| *path).collect();
+    paths.sort();
+    paths.dedup();
+    for path in paths {
+        for method in METHODS {
+            if !routes.contains(&(method, path)) && routed(method, path).await {
+                mismatches.push(format!("{method} {path} is routed but not listed"));
+            }
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{HttpResponse, web};
+
+    use crate::{
+        openapi::{ApiDoc, Operation},
+        testing::route_mismatches,
+    };
+
+    fn configure_items(config: &mut web::ServiceConfig) {
+        config.service(
+            web::scope("items")
+                .route("", web::post().to(HttpResponse::Ok))
+                .route("{id}", web::get().to(HttpResponse::Ok))
+                .route("{id}", web::delete().to(HttpResponse::Ok)),
+        );
+    }
+
+    #[actix_web::test]
+    async fn find_route_mismatches() {
+        let doc = ApiDoc::new("test", "0.1.0")
+            .operation(Operation::post("/items", "Add an item"))
+            .operation(Operation::get("/items/{id}", "Get an item"))
+            .operation(Operation::patch("/items/{id}", "Edit an item"));
+        let routes = [
+            ("post", "/items"),
+            ("get", "/items/{id}"),
+            ("put", "/items/{id}"),
+        ];
+        assert_eq!(
+            route_mismatches(configure_items, &routes, &doc).await,
+            [
+                "patch /items/{id} is documented but not listed",
+                "put /items/{id} is not documented",
+                "put /items/{id} has no route",
+                "delete /items/{id} is routed but not listed",
+            ]
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use layer_composer::Model;
+
+/// One image of a batch.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct Job {
+    pub layers: Vec<String>,
+    /// Relative to the job file
+    pub output: PathBuf,
+}
+
+/// Read the jobs by name from a .toml file, or JSON otherwise.
+pub fn load_jobs(path: &Path) -> anyhow::Result<BTreeMap<String, Job>> {
+    let text = fs::read_to_string(path)?;
+    let mut jobs: BTreeMap<String, Job> = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&text)?
+    } else {
+        serde_json::from_str(&text)?
+    };
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    for job in jobs.values_mut() {
+        job.output = dir.join(&job.output);
+    }
+    Ok(jobs)
+}
+
+/// Render every job on `threads` threads, returns the failed jobs with their errors.
+pub fn render_all(
+    model: &Model,
+    jobs: &BTreeMap<String, Job>,
+    threads: usize,
+) -> Vec<(String, anyhow::Error)> {
+    let jobs: Vec<(&String, &Job)> = jobs.iter().collect();
+    let next = AtomicUsize::new(0);
+    let failed = Mutex::new(Vec::new());
+    let (jobs, next, failed_ref) = (&jobs, &next, &failed);
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, jobs.len().max(1)) {
+            let mut model = model.clone();
+            scope.spawn(move || {
+                while let Some(&(name, job)) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    match render_job(&mut model, job) {
+                        Ok(()) => println!("{name} -> {}", job.output.display()),
+                        Err(e) => failed_ref.lock().unwrap().push((name.clone(), e)),
+                    }
+                }
+            });
+        }
+    });
+
+    let mut failed = failed.into_inner().unwrap();
+    failed.sort_by(|(a, _), (b, _)| a.cmp(b));
+    failed
+}
+
+fn render_job(model: &mut Model, job: &Job) -> anyhow::Result<()> {
+    if let Some(dir) = job.output.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    model.render(&job.layers)?.save(&job.output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::batch::{Job, load_jobs};
+
+    #[test]
+    fn load_json_and_toml_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("jobs.json"),
+            r#"{"smile": {"layers": ["base.png", "smile.png"], "output": "out/smile.png"}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("jobs.toml"),
+            "[smile]\nlayers = [\"base.png\", \"smile.png\"]\noutput = \"out/smile.png\"\n",
+        )
+        .unwrap();
+
+        let expected = Job {
+            layers: vec!["base.png".to_string(), "smile.png".to_string()],
+            output: dir.path().join("out/smile.png"),
+        };
+        for file in ["jobs.json", "jobs.toml"] {
+            let jobs = load_jobs(&dir.path().join(file)).unwrap();
+            assert_eq!(jobs.len(), 1);
+            assert_eq!(jobs["smile"], expected);
+        }
+        assert_ne!(expected.output, PathBuf::from("out/smile.png"));
+    }
+}
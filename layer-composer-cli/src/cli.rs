@@ -19,13 +19,89 @@ pub enum Commands {
         output: PathBuf,
     },
     Render {
+        /// The vtuber's VTUBER_RENDER_MODEL if unset
         #[arg(long)]
-        model: PathBuf,
+        model: Option<PathBuf>,
         #[arg(long)]
         output: PathBuf,
         layers: Vec<String>,
     },
+    /// Render every job of a JSON or TOML file, mapping job names to layers and an output path
+    RenderBatch {
+        /// The vtuber's VTUBER_RENDER_MODEL if unset
+        #[arg(long)]
+        model: Option<PathBuf>,
+        jobs: PathBuf,
+        /// Images rendered at once, the number of CPUs by default
+        #[arg(short = 'j', long)]
+        threads: Option<usize>,
+    },
     ModelInfo {
+        /// The vtuber's VTUBER_RENDER_MODEL if unset
+        path: Option<PathBuf>,
+    },
+    /// List the layers of a model with their type, group, description, bindings and image size
+    ListLayers {
+        /// The vtuber's VTUBER_RENDER_MODEL if unset
+        path: Option<PathBuf>,
+    },
+    /// Show everything about one layer of a model
+    Inspect { path: PathBuf, layer: String },
+    /// Serve a web page to browse the layers of a model and render them from a browser
+    Preview {
+        /// The vtuber's VTUBER_RENDER_MODEL if unset
+        path: Option<PathBuf>,
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        #[arg(short, long, default_value_t = 20890)]
+        port: u16,
+    },
+    /// Zip a model folder with manifest.json, layers/ and metadata/
+    Pack {
+        dir: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Extract a model into a folder to edit it, see pack
+    Unpack {
         path: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Show the layers and hit areas added, removed or changed from one version of a model to
+    /// another
+    Diff { old: PathBuf, new: PathBuf },
+    /// Render expressions into one image with labels, every base layer with every top layer by
+    /// default
+    Sheet {
+        /// The vtuber's VTUBER_RENDER_MODEL if unset
+        #[arg(long)]
+        model: Option<PathBuf>,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Render the named expressions of a render-batch job file instead
+        #[arg(long)]
+        jobs: Option<PathBuf>,
+        /// Only the expressions showing layers added or changed since this version of the model
+        #[arg(long)]
+        since: Option<PathBuf>,
+        /// A .ttf or .otf font for the labels, Ubuntu Light by default. Characters it lacks are
+        /// drawn with a CJK font of the system.
+        #[arg(long)]
+        font: Option<PathBuf>,
+        /// Width and height of every image in pixels
+        #[arg(long, default_value_t = 256)]
+        cell: u32,
+        #[arg(long)]
+        columns: Option<u32>,
+    },
+    /// Check a model for missing images, unparsable metadata, dangling bindings and groups named
+    /// alike, exits with 1 on any issue
+    Validate {
+        /// The vtuber's VTUBER_RENDER_MODEL if unset
+        path: Option<PathBuf>,
+        /// Only fail on errors
+        #[arg(long)]
+        allow_warnings: bool,
     },
 }
@@ -0,0 +1,177 @@
+use std::{
+    collections::BTreeSet,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use layer_composer::{LayerManifest, Model};
+
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Added,
+    Removed,
+    /// What differs, e.g. `image` or `description`
+    Changed(Vec<&'static str>),
+}
+
+/// The layers and hit areas that differ between two versions of a model, by name.
+pub fn diff_models(old: &Model, new: &Model) -> Vec<(String, Change)> {
+    let (old_manifest, new_manifest) = (old.manifest(), new.manifest());
+    let mut changes = Vec::new();
+
+    let names: BTreeSet<&String> = old_manifest
+        .layers
+        .keys()
+        .chain(new_manifest.layers.keys())
+        .collect();
+    for name in names {
+        let change = match (old_manifest.layers.get(name), new_manifest.layers.get(name)) {
+            (None, Some(_)) => Change::Added,
+            (Some(_), None) => Change::Removed,
+            (Some(old_layer), Some(new_layer)) => {
+                let mut fields = changed_fields(old_layer, new_layer);
+                if image_hash(old, name) != image_hash(new, name) {
+                    fields.insert(0, "image");
+                }
+                if fields.is_empty() {
+                    continue;
+                }
+                Change::Changed(fields)
+            }
+            (None, None) => unreachable!(),
+        };
+        changes.push((name.clone(), change));
+    }
+
+    let areas: BTreeSet<&String> = old_manifest
+        .hit_areas
+        .keys()
+        .chain(new_manifest.hit_areas.keys())
+        .collect();
+    for name in areas {
+        let change = match (
+            old_manifest.hit_areas.get(name),
+            new_manifest.hit_areas.get(name),
+        ) {
+            (None, Some(_)) => Change::Added,
+            (Some(_), None) => Change::Removed,
+            (Some(old_area), Some(new_area)) if old_area != new_area => {
+                Change::Changed(vec!["hit area"])
+            }
+            _ => continue,
+        };
+        changes.push((format!("hit area {name}"), change));
+    }
+
+    changes
+}
+
+/// The parts of a layer's manifest entry that differ.
+fn changed_fields(old: &LayerManifest, new: &LayerManifest) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    match (old, new) {
+        (
+            LayerManifest::BaseLayer {
+                offset: old_offset,
+                description: old_description,
+                bindings: old_bindings,
+                ..
+            },
+            LayerManifest::BaseLayer {
+                offset,
+                description,
+                bindings,
+                ..
+            },
+        ) => {
+            if old_offset != offset {
+                fields.push("offset");
+            }
+            if old_description != description {
+                fields.push("description");
+            }
+            if old_bindings != bindings {
+                fields.push("bindings");
+            }
+        }
+        (
+            LayerManifest::TopLayer {
+                description: old_description,
+                metadata: old_metadata,
+                bindings: old_bindings,
+                ..
+            },
+            LayerManifest::TopLayer {
+                description,
+                metadata,
+                bindings,
+                ..
+            },
+        ) => {
+            if old_metadata != metadata {
+                fields.push("metadata");
+            }
+            if old_description != description {
+                fields.push("description");
+            }
+            if old_bindings != bindings {
+                fields.push("bindings");
+            }
+        }
+        _ => fields.push("type"),
+    }
+    if old.group() != new.group() {
+        fields.push("group");
+    }
+    fields
+}
+
+/// Hash of the image file, or nothing if it can't be read.
+fn image_hash(model: &Model, layer_name: &str) -> Option<u64> {
+    let bytes = model.read_layer(layer_name).ok()?;
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use layer_composer::{LayerManifest, TopLayerMetadata};
+
+    use crate::diff::changed_fields;
+
+    #[test]
+    fn compare_layer_manifests() {
+        let base = LayerManifest::BaseLayer {
+            offset: [0, 0],
+            description: Some("Kimono".to_string()),
+            bindings: Vec::new(),
+            group: None,
+        };
+        let top = LayerManifest::TopLayer {
+            description: Some("Smile".to_string()),
+            metadata: TopLayerMetadata::default(),
+            bindings: Vec::new(),
+            group: Some("mouth".to_string()),
+        };
+        assert!(changed_fields(&top, &top.clone()).is_empty());
+        assert_eq!(changed_fields(&base, &top), ["type", "group"]);
+
+        let LayerManifest::TopLayer {
+            description,
+            mut metadata,
+            bindings,
+            ..
+        } = top.clone()
+        else {
+            unreachable!()
+        };
+        metadata.x = 10;
+        let moved = LayerManifest::TopLayer {
+            description,
+            metadata,
+            bindings,
+            group: None,
+        };
+        assert_eq!(changed_fields(&top, &moved), ["metadata", "group"]);
+    }
+}
@@ -1,16 +1,33 @@
-use std::{fs::File, path::PathBuf};
+use std::{
+    fs::File,
+    net::TcpListener,
+    path::{Path, PathBuf},
+};
 
+use ab_glyph::FontVec;
 use clap::{CommandFactory, Parser};
-use layer_composer::{LayerMetadata, Model, compose_layers};
+use config::VtuberRenderConfig;
+use layer_composer::{LayerManifest, LayerMetadata, Model, ModelBuilder, Severity, compose_layers};
 use zip::ZipArchive;
 
-use crate::cli::Cli;
+use crate::{
+    cli::Cli,
+    diff::Change,
+    sheet::{Expression, Fonts},
+    table::format_table,
+};
 
+mod batch;
 mod cli;
+mod diff;
+mod preview;
+mod sheet;
+mod table;
 
 pub fn run() -> anyhow::Result<()> {
     // parse command
     let args = Cli::parse();
+    dotenvy::dotenv().ok();
 
     match args.command {
         Some(cli::Commands::RenderSingle {
@@ -21,15 +38,77 @@ pub fn run() -> anyhow::Result<()> {
         }) => {
             render_single(&base_layer, &top_layer, &metadata, &output)?;
         }
+        Some(cli::Commands::RenderBatch {
+            model,
+            jobs,
+            threads,
+        }) => {
+            render_batch(&model_path(model)?, &jobs, threads)?;
+        }
         Some(cli::Commands::ModelInfo { path }) => {
-            model_info(&path)?;
+            model_info(&model_path(path)?)?;
+        }
+        Some(cli::Commands::ListLayers { path }) => {
+            list_layers(&model_path(path)?)?;
+        }
+        Some(cli::Commands::Inspect { path, layer }) => {
+            inspect(&path, &layer)?;
+        }
+        Some(cli::Commands::Preview { path, host, port }) => {
+            let path = model_path(path)?;
+            let model = Model::from_file(&path)?;
+            let listener = TcpListener::bind((host.as_str(), port))?;
+            println!(
+                "Previewing {} at http://{}/",
+                path.display(),
+                listener.local_addr()?
+            );
+            preview::serve(model, listener)?;
+        }
+        Some(cli::Commands::Pack { dir, output }) => {
+            ModelBuilder::from_dir(&dir)?.build(File::create(&output)?)?;
+            println!("Packed {} into {}", dir.display(), output.display());
+        }
+        Some(cli::Commands::Unpack { path, output }) => {
+            ZipArchive::new(File::open(&path)?)?.extract(&output)?;
+            println!("Unpacked {} into {}", path.display(), output.display());
+        }
+        Some(cli::Commands::Diff { old, new }) => {
+            diff(&old, &new)?;
+        }
+        Some(cli::Commands::Sheet {
+            model,
+            output,
+            jobs,
+            since,
+            font,
+            cell,
+            columns,
+        }) => {
+            render_sheet(
+                &model_path(model)?,
+                &output,
+                jobs.as_deref(),
+                since.as_deref(),
+                font.as_deref(),
+                cell,
+                columns,
+            )?;
+        }
+        Some(cli::Commands::Validate {
+            path,
+            allow_warnings,
+        }) => {
+            if !validate(&model_path(path)?, allow_warnings)? {
+                std::process::exit(1);
+            }
         }
         Some(cli::Commands::Render {
             model,
             output,
             layers,
         }) => {
-            render(&model, &output, &layers)?;
+            render(&model_path(model)?, &output, &layers)?;
         }
         None => {
             Cli::command().print_long_help()?;
@@ -39,6 +118,14 @@ pub fn run() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The given model, else the one of the vtuber's main character.
+fn model_path(path: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    match path {
+        Some(path) => Ok(path),
+        None => Ok(VtuberRenderConfig::from_env()?.model),
+    }
+}
+
 fn render(model: &PathBuf, output: &PathBuf, layers: &[String]) -> anyhow::Result<()> {
     // parse the model
     let mut model = Model::from_reader(File::open(model)?)?;
@@ -50,6 +137,25 @@ fn render(model: &PathBuf, output: &PathBuf, layers: &[String]) -> anyhow::Resul
     Ok(())
 }
 
+fn render_batch(model: &Path, jobs: &Path, threads: Option<usize>) -> anyhow::Result<()> {
+    let model = Model::from_file(model)?;
+    let jobs = batch::load_jobs(jobs)?;
+    let threads = match threads {
+        Some(threads) => threads,
+        None => std::thread::available_parallelism()?.get(),
+    };
+
+    let failed = batch::render_all(&model, &jobs, threads);
+    for (name, e) in &failed {
+        eprintln!("{name}: {e}");
+    }
+    if !failed.is_empty() {
+        anyhow::bail!("{} of {} jobs failed", failed.len(), jobs.len());
+    }
+
+    Ok(())
+}
+
 fn model_info(path: &PathBuf) -> anyhow::Result<()> {
     // Open zip file
     let mut zip = ZipArchive::new(File::open(path)?)?;
@@ -60,6 +166,228 @@ fn model_info(path: &PathBuf) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn render_sheet(
+    model: &Path,
+    output: &Path,
+    jobs: Option<&Path>,
+    since: Option<&Path>,
+    font: Option<&Path>,
+    cell: u32,
+    columns: Option<u32>,
+) -> anyhow::Result<()> {
+    let mut model = Model::from_file(model)?;
+    let mut expressions = match jobs {
+        Some(jobs) => batch::load_jobs(jobs)?
+            .into_iter()
+            .map(|(label, job)| Expression {
+                label,
+                layers: job.layers,
+            })
+            .collect(),
+        None => sheet::combinations(&model),
+    };
+    if let Some(since) = since {
+        let changed: Vec<String> = diff::diff_models(&Model::from_file(since)?, &model)
+            .into_iter()
+            .filter(|(_, change)| *change != Change::Removed)
+            .map(|(name, _)| name)
+            .collect();
+        expressions.retain(|expression| {
+            expression
+                .layers
+                .iter()
+                .any(|layer| changed.contains(layer))
+        });
+    }
+    if expressions.is_empty() {
+        anyhow::bail!("Nothing to render");
+    }
+    let font = match font {
+        Some(path) => FontVec::try_from_vec(std::fs::read(path)?)?,
+        None => FontVec::try_from_vec(epaint_default_fonts::UBUNTU_LIGHT.to_vec())?,
+    };
+    let fonts = Fonts::with_cjk_fallback(font);
+
+    sheet::render_sheet(&mut model, &expressions, &fonts, cell, columns).save(output)?;
+    println!(
+        "Rendered {} expressions into {}",
+        expressions.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+fn diff(old: &Path, new: &Path) -> anyhow::Result<()> {
+    let changes = diff::diff_models(&Model::from_file(old)?, &Model::from_file(new)?);
+
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    for (name, change) in &changes {
+        match change {
+            Change::Added => {
+                added += 1;
+                println!("+ {name}");
+            }
+            Change::Removed => {
+                removed += 1;
+                println!("- {name}");
+            }
+            Change::Changed(fields) => {
+                changed += 1;
+                println!("~ {name} ({})", fields.join(", "));
+            }
+        }
+    }
+    println!("{added} added, {removed} removed, {changed} changed");
+
+    Ok(())
+}
+
+/// Print the issues of a model, returns whether it passed.
+fn validate(path: &PathBuf, allow_warnings: bool) -> anyhow::Result<bool> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+    let issues = layer_composer::validate_model(&mut zip)?;
+
+    for issue in &issues {
+        println!("{issue}");
+    }
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    println!(
+        "{}: {errors} error(s), {warnings} warning(s)",
+        path.display()
+    );
+
+    Ok(errors == 0 && (allow_warnings || warnings == 0))
+}
+
+fn list_layers(path: &PathBuf) -> anyhow::Result<()> {
+    let model = Model::from_file(path)?;
+
+    let rows: Vec<Vec<String>> = model
+        .manifest()
+        .layers
+        .iter()
+        .map(|(name, layer)| {
+            let (kind, description, bindings) = match layer {
+                LayerManifest::BaseLayer {
+                    description,
+                    bindings,
+                    ..
+                } => ("base", description, bindings),
+                LayerManifest::TopLayer {
+                    description,
+                    bindings,
+                    ..
+                } => ("top", description, bindings),
+            };
+            vec![
+                name.clone(),
+                kind.to_string(),
+                layer.group().unwrap_or("-").to_string(),
+                description.as_deref().unwrap_or("-").to_string(),
+                or_dash(bindings.join(", ")),
+                image_size(&model, name),
+            ]
+        })
+        .collect();
+    print!(
+        "{}",
+        format_table(
+            &["name", "type", "group", "description", "bindings", "size"],
+            &rows
+        )
+    );
+
+    Ok(())
+}
+
+fn inspect(path: &PathBuf, layer_name: &str) -> anyhow::Result<()> {
+    let model = Model::from_file(path)?;
+    let manifest = model.manifest();
+    let Some(layer) = manifest.layers.get(layer_name) else {
+        anyhow::bail!("No layer {layer_name} in the model, see list-layers");
+    };
+
+    println!("name:        {layer_name}");
+    match layer {
+        LayerManifest::BaseLayer {
+            offset,
+            description,
+            bindings,
+            ..
+        } => {
+            println!("type:        base");
+            println!("description: {}", description.as_deref().unwrap_or("-"));
+            println!("bindings:    {}", or_dash(bindings.join(", ")));
+            println!("offset:      {}, {}", offset[0], offset[1]);
+        }
+        LayerManifest::TopLayer {
+            description,
+            metadata,
+            bindings,
+            ..
+        } => {
+            println!("type:        top");
+            println!("description: {}", description.as_deref().unwrap_or("-"));
+            println!("bindings:    {}", or_dash(bindings.join(", ")));
+            println!("position:    {}, {}", metadata.x, metadata.y);
+            println!(
+                "scaled:      {}x{} from {}x{}, scale {}",
+                metadata.scaled_width,
+                metadata.scaled_height,
+                metadata.original_width,
+                metadata.original_height,
+                metadata.scale
+            );
+            println!("opacity:     {}", metadata.opacity);
+        }
+    }
+    println!("group:       {}", layer.group().unwrap_or("-"));
+    println!("image:       {}", image_size(&model, layer_name));
+
+    let bound_by: Vec<&str> = manifest
+        .layers
+        .iter()
+        .filter(|(_, other)| match other {
+            LayerManifest::BaseLayer { bindings, .. }
+            | LayerManifest::TopLayer { bindings, .. } => {
+                bindings.iter().any(|binding| binding == layer_name)
+            }
+        })
+        .map(|(name, _)| name.as_str())
+        .collect();
+    println!("bound by:    {}", or_dash(bound_by.join(", ")));
+    let hit_areas: Vec<&str> = manifest
+        .hit_areas
+        .iter()
+        .filter(|(_, area)| area.layers.iter().any(|layer| layer == layer_name))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    println!("hit areas:   {}", or_dash(hit_areas.join(", ")));
+
+    Ok(())
+}
+
+/// `WxH`, or why the image can't be read.
+fn image_size(model: &Model, layer_name: &str) -> String {
+    match model.image_size(layer_name) {
+        Ok((width, height)) => format!("{width}x{height}"),
+        Err(e) => format!("error: {e}"),
+    }
+}
+
+fn or_dash(text: String) -> String {
+    if text.is_empty() {
+        "-".to_string()
+    } else {
+        text
+    }
+}
+
 fn render_single(
     base_layer: &PathBuf,
     top_layer: &PathBuf,
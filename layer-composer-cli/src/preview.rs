@@ -0,0 +1,128 @@
+use std::{io::Cursor, net::TcpListener};
+
+use actix_web::{App, HttpResponse, HttpServer, Responder, ResponseError, http::StatusCode, web};
+use layer_composer::{LayerManifest, Model, RenderError};
+
+const PAGE: &str = include_str!("preview.html");
+
+#[derive(serde::Serialize)]
+struct LayerInfo<'a> {
+    name: &'a str,
+    /// `base` or `top`
+    kind: &'static str,
+    group: Option<&'a str>,
+    description: Option<&'a str>,
+}
+
+#[derive(serde::Deserialize)]
+struct RenderQuery {
+    /// Comma separated layer names
+    #[serde(default)]
+    layers: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum PreviewError {
+    #[error("{0}")]
+    Render(#[from] RenderError),
+    #[error("Failed to encode the image: {0}")]
+    Encode(#[from] image::ImageError),
+    #[error("Render task failed: {0}")]
+    Blocking(#[from] actix_web::error::BlockingError),
+}
+
+impl ResponseError for PreviewError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            PreviewError::Render(_) => StatusCode::BAD_REQUEST,
+            PreviewError::Encode(_) | PreviewError::Blocking(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
+/// Serve a page to browse the layers of `model` and render them on demand, until stopped.
+pub fn serve(model: Model, listener: TcpListener) -> anyhow::Result<()> {
+    let model = web::Data::new(model);
+    actix_web::rt::System::new().block_on(
+        HttpServer::new(move || {
+            App::new()
+                .app_data(model.clone())
+                .route("/", web::get().to(page))
+                .route("/layers", web::get().to(layers))
+                .route("/render", web::get().to(render))
+        })
+        .listen(listener)?
+        .run(),
+    )?;
+    Ok(())
+}
+
+async fn page() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(PAGE)
+}
+
+async fn layers(model: web::Data<Model>) -> impl Responder {
+    let layers: Vec<LayerInfo> = model
+        .manifest()
+        .layers
+        .iter()
+        .map(|(name, layer)| {
+            let (kind, description) = match layer {
+                LayerManifest::BaseLayer { description, .. } => ("base", description),
+                LayerManifest::TopLayer { description, .. } => ("top", description),
+            };
+            LayerInfo {
+                name,
+                kind,
+                group: layer.group(),
+                description: description.as_deref(),
+            }
+        })
+        .collect();
+    HttpResponse::Ok().json(layers)
+}
+
+async fn render(
+    query: web::Query<RenderQuery>,
+    model: web::Data<Model>,
+) -> Result<impl Responder, PreviewError> {
+    let layers = parse_layers(&query.layers);
+    let mut model = model.get_ref().clone();
+    let png = web::block(move || -> Result<Vec<u8>, PreviewError> {
+        let mut png = Vec::new();
+        model
+            .render(&layers)?
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+        Ok(png)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().content_type("image/png").body(png))
+}
+
+fn parse_layers(layers: &str) -> Vec<String> {
+    layers
+        .split(',')
+        .map(str::trim)
+        .filter(|layer| !layer.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::preview::parse_layers;
+
+    #[test]
+    fn parse_layer_list() {
+        assert_eq!(
+            parse_layers("base.png, smile.png,"),
+            ["base.png", "smile.png"]
+        );
+        assert!(parse_layers("").is_empty());
+    }
+}
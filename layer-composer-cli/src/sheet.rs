@@ -0,0 +1,220 @@
+use ab_glyph::{Font, FontVec, GlyphId, PxScale, ScaleFont, point};
+use font_kit::{
+    family_name::FamilyName, handle::Handle, properties::Properties, source::SystemSource,
+};
+use image::{Rgba, RgbaImage, imageops};
+use layer_composer::{LayerManifest, Model};
+
+/// Height of the label under every image.
+const LABEL_HEIGHT: u32 = 24;
+const LABEL_SIZE: f32 = 16.0;
+const PADDING: u32 = 4;
+/// Fonts for the Japanese and Chinese descriptions, the first one installed is used.
+const CJK_FONTS: [&str; 6] = [
+    "Noto Sans CJK JP",
+    "Source Han Sans JP",
+    "Hiragino Sans",
+    "Yu Gothic",
+    "Noto Sans CJK SC",
+    "WenQuanYi Zen Hei",
+];
+
+/// Fonts tried in order for every character of a label.
+pub struct Fonts(Vec<FontVec>);
+
+impl Fonts {
+    /// `primary` followed by a CJK font of the system, if there is one.
+    pub fn with_cjk_fallback(primary: FontVec) -> Self {
+        let mut fonts = vec![primary];
+        match load_cjk_font() {
+            Some(font) => fonts.push(font),
+            None => eprintln!("No CJK font found, Japanese labels will be missing"),
+        }
+        Self(fonts)
+    }
+
+    /// Index of the first font with a glyph for `c`, the primary one if none has.
+    fn pick(&self, c: char) -> usize {
+        self.0
+            .iter()
+            .position(|font| font.glyph_id(c) != GlyphId(0))
+            .unwrap_or(0)
+    }
+}
+
+fn load_cjk_font() -> Option<FontVec> {
+    let families: Vec<FamilyName> = CJK_FONTS
+        .iter()
+        .map(|name| FamilyName::Title(name.to_string()))
+        .collect();
+    let handle = SystemSource::new()
+        .select_best_match(&families, &Properties::new())
+        .ok()?;
+    let (bytes, index) = match handle {
+        Handle::Memory { bytes, font_index } => (bytes.to_vec(), font_index),
+        Handle::Path { path, font_index } => (std::fs::read(path).ok()?, font_index),
+    };
+    FontVec::try_from_vec_and_index(bytes, index).ok()
+}
+
+/// An image of the sheet and its label.
+pub struct Expression {
+    pub label: String,
+    pub layers: Vec<String>,
+}
+
+/// Every base layer with every top layer, labeled with the top layer's description.
+pub fn combinations(model: &Model) -> Vec<Expression> {
+    let layers = &model.manifest().layers;
+    let mut expressions = Vec::new();
+    for (base, _) in layers
+        .iter()
+        .filter(|(_, layer)| matches!(layer, LayerManifest::BaseLayer { .. }))
+    {
+        for (top, layer) in layers.iter() {
+            let LayerManifest::TopLayer { description, .. } = layer else {
+                continue;
+            };
+            expressions.push(Expression {
+                label: description.clone().unwrap_or_else(|| top.clone()),
+                layers: vec![base.clone(), top.clone()],
+            });
+        }
+    }
+    expressions
+}
+
+/// Render the expressions into a grid of `cell` pixel squares with their labels below, the
+/// ones that fail to render are left out with a warning.
+pub fn render_sheet(
+    model: &mut Model,
+    expressions: &[Expression],
+    fonts: &Fonts,
+    cell: u32,
+    columns: Option<u32>,
+) -> RgbaImage {
+    let count = expressions.len().max(1) as u32;
+    let columns = columns
+        .unwrap_or_else(|| (count as f64).sqrt().ceil() as u32)
+        .clamp(1, count);
+    let rows = count.div_ceil(columns);
+    let (cell_width, cell_height) = (cell + PADDING * 2, cell + LABEL_HEIGHT + PADDING * 2);
+    let mut sheet = RgbaImage::from_pixel(
+        columns * cell_width,
+        rows * cell_height,
+        Rgba([255, 255, 255, 255]),
+    );
+
+    for (i, expression) in expressions.iter().enumerate() {
+        let image = match model.render(&expression.layers) {
+            Ok(image) => image,
+            Err(e) => {
+                eprintln!("Skipping {}: {e}", expression.label);
+                continue;
+            }
+        };
+        let (x, y) = (
+            i as u32 % columns * cell_width + PADDING,
+            i as u32 / columns * cell_height + PADDING,
+        );
+        let thumbnail = image.resize(cell, cell, imageops::FilterType::Triangle);
+        imageops::overlay(
+            &mut sheet,
+            &thumbnail,
+            (x + (cell - thumbnail.width()) / 2) as i64,
+            (y + cell - thumbnail.height()) as i64,
+        );
+        draw_label(&mut sheet, fonts, &expression.label, x, y + cell, cell);
+    }
+    sheet
+}
+
+/// Black text at `x`, `y`, cut at `max_width` pixels.
+fn draw_label(sheet: &mut RgbaImage, fonts: &Fonts, text: &str, x: u32, y: u32, max_width: u32) {
+    let scale = PxScale::from(LABEL_SIZE);
+    let primary = fonts.0[0].as_scaled(scale);
+    let baseline = y as f32 + (LABEL_HEIGHT as f32 + primary.ascent() + primary.descent()) / 2.0;
+    let mut caret = x as f32;
+    let mut previous = None;
+    for c in text.chars() {
+        let index = fonts.pick(c);
+        let font = fonts.0[index].as_scaled(scale);
+        let id = font.glyph_id(c);
+        // kerning pairs only make sense within a font
+        if let Some((previous_index, previous)) = previous
+            && previous_index == index
+        {
+            caret += font.kern(previous, id);
+        }
+        if caret + font.h_advance(id) > (x + max_width) as f32 {
+            break;
+        }
+        let glyph = id.with_scale_and_position(font.scale(), point(caret, baseline));
+        if let Some(outlined) = font.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let (px, py) = (
+                    bounds.min.x as i64 + gx as i64,
+                    bounds.min.y as i64 + gy as i64,
+                );
+                if px < 0 || py < 0 || px >= sheet.width() as i64 || py >= sheet.height() as i64 {
+                    return;
+                }
+                let pixel = sheet.get_pixel_mut(px as u32, py as u32);
+                for channel in &mut pixel.0[..3] {
+                    *channel = (*channel as f32 * (1.0 - coverage)) as u8;
+                }
+            });
+        }
+        caret += font.h_advance(id);
+        previous = Some((index, id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ab_glyph::FontVec;
+    use image::{Rgba, RgbaImage};
+
+    use crate::sheet::{Fonts, draw_label};
+
+    fn ubuntu_light() -> FontVec {
+        FontVec::try_from_vec(epaint_default_fonts::UBUNTU_LIGHT.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn pick_the_first_font_with_the_glyph() {
+        let emoji = FontVec::try_from_vec(epaint_default_fonts::NOTO_EMOJI_REGULAR.to_vec());
+        let fonts = Fonts(vec![ubuntu_light(), emoji.unwrap()]);
+        assert_eq!(fonts.pick('a'), 0);
+        assert_eq!(fonts.pick('😀'), 1);
+        // nothing has it, drawn as the missing glyph of the primary font
+        assert_eq!(fonts.pick('\u{10FFFF}'), 0);
+    }
+
+    #[test]
+    fn draw_labels_within_the_cell() {
+        let fonts = Fonts(vec![ubuntu_light()]);
+        let mut sheet = RgbaImage::from_pixel(200, 60, Rgba([255, 255, 255, 255]));
+        draw_label(
+            &mut sheet,
+            &fonts,
+            "Gentle smile, and a very long text",
+            10,
+            20,
+            80,
+        );
+
+        let inked: Vec<(u32, u32)> = sheet
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel.0[0] < 128)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert!(!inked.is_empty());
+        assert!(
+            inked
+                .iter()
+                .all(|&(x, y)| (10..90).contains(&x) && (20..44).contains(&y))
+        );
+    }
+}
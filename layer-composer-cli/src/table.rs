@@ -0,0 +1,55 @@
+use unicode_width::UnicodeWidthStr;
+
+/// Align the rows under the header in columns as wide as their longest cell, counting CJK
+/// characters twice as wide.
+pub fn format_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.width()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    let format_row = |cells: &mut dyn Iterator<Item = &str>| {
+        let line = cells
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.width())))
+            .collect::<Vec<_>>()
+            .join("  ");
+        format!("{}\n", line.trim_end())
+    };
+    let mut table = format_row(&mut header.iter().copied());
+    for row in rows {
+        table.push_str(&format_row(&mut row.iter().map(String::as_str)));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::table::format_table;
+
+    #[test]
+    fn align_columns() {
+        let rows = vec![
+            vec!["base.png".to_string(), "base".to_string(), "".to_string()],
+            vec![
+                "笑顔.png".to_string(),
+                "top".to_string(),
+                "face".to_string(),
+            ],
+            vec![
+                "eyes_closed.png".to_string(),
+                "top".to_string(),
+                "eyes".to_string(),
+            ],
+        ];
+        assert_eq!(
+            format_table(&["name", "type", "group"], &rows),
+            "name             type  group\n\
+             base.png         base\n\
+             笑顔.png         top   face\n\
+             eyes_closed.png  top   eyes\n"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Seek, Write},
+    path::Path,
+};
+
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::model::{ModelError, json_model};
+
+/// The folders of a model zip next to its manifest.json.
+const FOLDERS: [&str; 2] = ["layers", "metadata"];
+
+/// Writes a model zip out of a manifest and the layer images and metadata it refers to.
+pub struct ModelBuilder {
+    manifest: Vec<u8>,
+    /// By path in the zip, e.g. `layers/smile.png`
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl ModelBuilder {
+    pub fn new(manifest: Vec<u8>) -> Self {
+        Self {
+            manifest,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// A model laid out like the zip: manifest.json, layers/ and metadata/.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let dir = dir.as_ref();
+        let mut builder = Self::new(fs::read(dir.join("manifest.json"))?);
+        for folder in FOLDERS {
+            let path = dir.join(folder);
+            if !path.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(path)? {
+                let entry = entry?;
+                if !entry.file_type()?.is_file() {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().into_owned();
+                builder = builder.file(format!("{folder}/{name}"), fs::read(entry.path())?);
+            }
+        }
+        Ok(builder)
+    }
+
+    pub fn layer(self, name: &str, image: Vec<u8>) -> Self {
+        self.file(format!("layers/{name}"), image)
+    }
+
+    pub fn metadata(self, name: &str, metadata: Vec<u8>) -> Self {
+        self.file(format!("metadata/{name}"), metadata)
+    }
+
+    pub fn file(mut self, path: String, bytes: Vec<u8>) -> Self {
+        self.files.insert(path, bytes);
+        self
+    }
+
+    /// Write the zip, failing if the manifest can't be parsed.
+    pub fn build<W: Write + Seek>(self, writer: W) -> Result<W, ModelError> {
+        serde_json::from_slice::<json_model::Root>(&self.manifest)?;
+
+        let mut zip = ZipWriter::new(writer);
+        let options = SimpleFileOptions::default();
+        zip.start_file("manifest.json", options)?;
+        zip.write_all(&self.manifest)?;
+        for (path, bytes) in &self.files {
+            zip.start_file(path.as_str(), options)?;
+            zip.write_all(bytes)?;
+        }
+        Ok(zip.finish()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{LayerManifest, Model, ModelBuilder};
+
+    #[test]
+    fn build_a_loadable_model() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(4, 4)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let metadata = r#"{"top_layer": {"x": 1, "y": 1, "original_width": 2, "original_height": 2,
+            "scaled_width": 2, "scaled_height": 2, "scale": 1.0, "opacity": 1.0}}"#;
+        let manifest = r#"{"layers": {
+            "base.png": {"type": "base_layer", "offset": [0, 0]},
+            "smile.png": {"metadata": "smile.json", "group": "mouth"}
+        }}"#;
+
+        let zip = ModelBuilder::new(manifest.as_bytes().to_vec())
+            .layer("base.png", png.clone())
+            .layer("smile.png", png)
+            .metadata("smile.json", metadata.as_bytes().to_vec())
+            .build(Cursor::new(Vec::new()))
+            .unwrap();
+        let mut model = Model::from_bytes(zip.into_inner()).unwrap();
+
+        assert!(matches!(
+            model.manifest().layers["smile.png"],
+            LayerManifest::TopLayer { .. }
+        ));
+        let image = model
+            .render(&["base.png".to_string(), "smile.png".to_string()])
+            .unwrap();
+        assert_eq!((image.width(), image.height()), (4, 4));
+
+        assert!(
+            ModelBuilder::new(b"{}".to_vec())
+                .build(Cursor::new(Vec::new()))
+                .is_err()
+        );
+    }
+}
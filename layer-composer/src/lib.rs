@@ -1,9 +1,13 @@
+mod builder;
 mod compose;
 mod metadata;
 mod model;
+mod validate;
 
+pub use builder::ModelBuilder;
 pub use compose::{compose_layers, compose_layers_from_model};
 pub use metadata::{LayerMetadata, TopLayerMetadata};
 pub use model::{
-    LayerManifest, Model, ModelError, ModelManifest, RenderError, parse_model_manifest,
+    HitArea, LayerManifest, Model, ModelError, ModelManifest, RenderError, parse_model_manifest,
 };
+pub use validate::{Issue, Severity, validate_model};
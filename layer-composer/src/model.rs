@@ -9,14 +9,18 @@ use zip::{ZipArchive, result::ZipError};
 
 use crate::{LayerMetadata, TopLayerMetadata, compose::ComposeError, compose_layers_from_model};
 
-mod json_model {
+pub(crate) mod json_model {
     use std::collections::HashMap;
 
     use serde::Deserialize;
 
+    use crate::HitArea;
+
     #[derive(Debug, Deserialize)]
     pub struct Root {
         pub layers: HashMap<String, Layer>,
+        #[serde(default)]
+        pub hit_areas: HashMap<String, HitArea>,
     }
 
     #[derive(Debug, Deserialize)]
@@ -28,6 +32,8 @@ mod json_model {
             description: Option<String>,
             #[serde(default)]
             bindings: Vec<String>,
+            #[serde(default)]
+            group: Option<String>,
         },
         BaseLayer {
             #[serde(rename = "type")]
@@ -37,6 +43,8 @@ mod json_model {
             description: Option<String>,
             #[serde(default)]
             bindings: Vec<String>,
+            #[serde(default)]
+            group: Option<String>,
         },
     }
 
@@ -50,22 +58,56 @@ mod json_model {
 #[derive(Clone, Debug)]
 pub struct ModelManifest {
     pub layers: BTreeMap<String, LayerManifest>, // we care the order of the layers
+    pub hit_areas: BTreeMap<String, HitArea>,
 }
 
-#[derive(Clone, Debug)]
+/// A part of the character that reacts to the pointer, e.g. the head.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+pub struct HitArea {
+    /// x, y, width and height in pixels of the rendered image
+    pub rect: [u32; 4],
+    /// Expression shown for a moment when touched
+    #[serde(default)]
+    pub layers: Vec<String>,
+    /// Told to the character when touched, e.g. "The user patted your head"
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+impl HitArea {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let [left, top, width, height] = self.rect.map(|value| value as f32);
+        (left..left + width).contains(&x) && (top..top + height).contains(&y)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum LayerManifest {
     BaseLayer {
         offset: [i32; 2],
         description: Option<String>,
         bindings: Vec<String>,
+        group: Option<String>,
     },
     TopLayer {
         description: Option<String>,
         metadata: TopLayerMetadata,
         bindings: Vec<String>,
+        group: Option<String>,
     },
 }
 
+impl LayerManifest {
+    /// What the layer belongs to, e.g. `eyes` or `mouth`, for listing the layers in tools.
+    pub fn group(&self) -> Option<&str> {
+        match self {
+            LayerManifest::BaseLayer { group, .. } | LayerManifest::TopLayer { group, .. } => {
+                group.as_deref()
+            }
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ModelError {
     #[error("Failed to open zip: {0}")]
@@ -82,17 +124,21 @@ pub enum ModelError {
     IOError(#[from] std::io::Error),
 }
 
+/// The manifest.json as written, before missing layers are left out.
+pub(crate) fn read_manifest_json<T: std::io::Read + std::io::Seek>(
+    model_zip: &mut ZipArchive<T>,
+) -> Result<json_model::Root, ModelError> {
+    // get manifest.json
+    let mut manifest_entry = model_zip
+        .by_name("manifest.json")
+        .map_err(|_err| ModelError::NoManifest)?;
+    Ok(serde_json::from_reader(&mut manifest_entry)?)
+}
+
 pub fn parse_model_manifest<T: std::io::Read + std::io::Seek>(
     model_zip: &mut ZipArchive<T>,
 ) -> Result<ModelManifest, ModelError> {
-    // parse manifest json
-    let manifest: json_model::Root = {
-        // get manifest.json
-        let mut manifest_entry = model_zip
-            .by_name("manifest.json")
-            .map_err(|_err| ModelError::NoManifest)?;
-        serde_json::from_reader(&mut manifest_entry)?
-    };
+    let manifest = read_manifest_json(model_zip)?;
 
     let mut layers: BTreeMap<String, LayerManifest> = BTreeMap::new();
 
@@ -110,6 +156,7 @@ pub fn parse_model_manifest<T: std::io::Read + std::io::Seek>(
                 metadata,
                 description,
                 bindings,
+                group,
             } => {
                 let metadata: LayerMetadata = {
                     let Ok(mut entry) = model_zip.by_name(format!("metadata/{metadata}").as_str())
@@ -124,6 +171,7 @@ pub fn parse_model_manifest<T: std::io::Read + std::io::Seek>(
                     description: description.to_owned(),
                     metadata: metadata.top_layer,
                     bindings: bindings.to_owned(),
+                    group: group.to_owned(),
                 }
             }
             json_model::Layer::BaseLayer {
@@ -131,15 +179,20 @@ pub fn parse_model_manifest<T: std::io::Read + std::io::Seek>(
                 offset,
                 description,
                 bindings,
+                group,
             } => LayerManifest::BaseLayer {
                 offset: *offset,
                 description: description.to_owned(),
                 bindings: bindings.to_owned(),
+                group: group.to_owned(),
             },
         };
         layers.insert(layer_filename.to_string(), layer_manifest);
     }
-    Ok(ModelManifest { layers })
+    Ok(ModelManifest {
+        layers,
+        hit_areas: manifest.hit_areas.into_iter().collect(),
+    })
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -302,6 +355,25 @@ impl Model {
     }
 
     pub fn get_image(&mut self, layer_name: &str) -> Result<DynamicImage, ModelError> {
+        let buf = self.read_layer(layer_name)?;
+
+        // read image
+        let image = image::load_from_memory(&buf)?;
+
+        Ok(image)
+    }
+
+    /// Width and height of the layer's image, read from its header without decoding it.
+    pub fn image_size(&self, layer_name: &str) -> Result<(u32, u32), ModelError> {
+        let buf = self.read_layer(layer_name)?;
+        let size = image::ImageReader::new(Cursor::new(buf))
+            .with_guessed_format()?
+            .into_dimensions()?;
+        Ok(size)
+    }
+
+    /// The layer's image file as stored in the zip.
+    pub fn read_layer(&self, layer_name: &str) -> Result<Vec<u8>, ModelError> {
         // get the entry
         let mut zip = self.open_zip()?;
         let mut entry = zip
@@ -311,10 +383,6 @@ impl Model {
         // read to bytes
         let mut buf = Vec::new();
         entry.read_to_end(&mut buf)?;
-
-        // read image
-        let image = image::load_from_memory(&buf)?;
-
-        Ok(image)
+        Ok(buf)
     }
 }
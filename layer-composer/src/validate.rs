@@ -0,0 +1,223 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    io::{Cursor, Read, Seek},
+};
+
+use zip::ZipArchive;
+
+use crate::{
+    LayerMetadata,
+    model::{ModelError, json_model, read_manifest_json},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The model loads, but probably not as meant
+    Warning,
+    /// The layer is left out when the model is loaded, or can't be rendered
+    Error,
+}
+
+/// A problem found in a model zip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    /// The layer or hit area it is about
+    pub subject: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match &self.subject {
+            Some(subject) => write!(f, "{severity}: {subject}: {}", self.message),
+            None => write!(f, "{severity}: {}", self.message),
+        }
+    }
+}
+
+/// Check every layer of a model zip: missing or unreadable images and metadata, bindings to
+/// layers that don't exist, hit areas showing unknown layers and groups named alike. Loading a
+/// model skips broken layers silently, this tells why they are gone.
+pub fn validate_model<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<Vec<Issue>, ModelError> {
+    let manifest = read_manifest_json(zip)?;
+    let mut issues = Vec::new();
+    let mut issue = |severity, subject: &str, message: String| {
+        issues.push(Issue {
+            severity,
+            subject: Some(subject.to_string()),
+            message,
+        })
+    };
+
+    // sorted, so the report reads the same every time
+    let layers: BTreeMap<&String, &json_model::Layer> = manifest.layers.iter().collect();
+    let mut groups: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+    for (name, layer) in &layers {
+        match read_entry(zip, &format!("layers/{name}")) {
+            Some(bytes) => {
+                if let Err(e) = image::ImageReader::new(Cursor::new(bytes))
+                    .with_guessed_format()
+                    .map_err(image::ImageError::from)
+                    .and_then(|reader| reader.into_dimensions())
+                {
+                    issue(Severity::Error, name, format!("unreadable image: {e}"));
+                }
+            }
+            None => issue(Severity::Error, name, format!("no image at layers/{name}")),
+        }
+
+        let (bindings, group) = match layer {
+            json_model::Layer::TopLayer {
+                metadata,
+                bindings,
+                group,
+                ..
+            } => {
+                let path = format!("metadata/{metadata}");
+                match read_entry(zip, &path) {
+                    Some(bytes) => {
+                        if let Err(e) = serde_json::from_slice::<LayerMetadata>(&bytes) {
+                            issue(Severity::Error, name, format!("unparsable {path}: {e}"));
+                        }
+                    }
+                    None => issue(Severity::Error, name, format!("no metadata at {path}")),
+                }
+                (bindings, group)
+            }
+            json_model::Layer::BaseLayer {
+                bindings, group, ..
+            } => (bindings, group),
+        };
+        for binding in bindings {
+            if !manifest.layers.contains_key(binding) {
+                issue(
+                    Severity::Error,
+                    name,
+                    format!("bound to {binding}, which is not a layer"),
+                );
+            }
+        }
+        if let Some(group) = group {
+            groups
+                .entry(group.trim().to_lowercase())
+                .or_default()
+                .insert(group);
+        }
+    }
+
+    let hit_areas: BTreeMap<_, _> = manifest.hit_areas.iter().collect();
+    for (name, area) in hit_areas {
+        for layer in &area.layers {
+            if !manifest.layers.contains_key(layer) {
+                issue(
+                    Severity::Warning,
+                    name,
+                    format!("shows {layer}, which is not a layer"),
+                );
+            }
+        }
+    }
+    for spellings in groups.values().filter(|spellings| spellings.len() > 1) {
+        let spellings: Vec<&str> = spellings.iter().copied().collect();
+        issues.push(Issue {
+            severity: Severity::Warning,
+            subject: None,
+            message: format!("groups named alike: {}", spellings.join(", ")),
+        });
+    }
+
+    Ok(issues)
+}
+
+fn read_entry<R: Read + Seek>(zip: &mut ZipArchive<R>, path: &str) -> Option<Vec<u8>> {
+    let mut entry = zip.by_name(path).ok()?;
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+    use crate::validate::{Severity, validate_model};
+
+    #[test]
+    fn report_broken_layers() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(2, 2)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let manifest = r#"{
+            "layers": {
+                "base.png": {"type": "base_layer", "offset": [0, 0], "bindings": ["gone.png"], "group": "Body"},
+                "eyes.png": {"metadata": "eyes.json", "group": "body "},
+                "mouth.png": {"metadata": "mouth.json"}
+            },
+            "hit_areas": {"head": {"rect": [0, 0, 1, 1], "layers": ["blush.png"]}}
+        }"#;
+
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, bytes) in [
+            ("manifest.json", manifest.as_bytes()),
+            ("layers/base.png", &png),
+            ("layers/eyes.png", b"not an image"),
+            ("metadata/eyes.json", b"{}"),
+        ] {
+            writer
+                .start_file(path, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(bytes).unwrap();
+        }
+        let mut zip = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        let issues: Vec<_> = validate_model(&mut zip)
+            .unwrap()
+            .into_iter()
+            .map(|issue| (issue.severity, issue.to_string()))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                (
+                    Severity::Error,
+                    "error: base.png: bound to gone.png, which is not a layer".to_string()
+                ),
+                (
+                    Severity::Error,
+                    "error: eyes.png: unreadable image: The image format could not be determined"
+                        .to_string()
+                ),
+                (
+                    Severity::Error,
+                    "error: eyes.png: unparsable metadata/eyes.json: missing field `top_layer` at line 1 column 2"
+                        .to_string()
+                ),
+                (
+                    Severity::Error,
+                    "error: mouth.png: no image at layers/mouth.png".to_string()
+                ),
+                (
+                    Severity::Error,
+                    "error: mouth.png: no metadata at metadata/mouth.json".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    "warning: head: shows blush.png, which is not a layer".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    "warning: groups named alike: Body, body ".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+/// Run the desktop pet and its tools, all configured by one .env.
+#[derive(clap::Parser)]
+#[command(version)]
+pub struct Cli {
+    /// The programs run in its folder, so relative paths in it work from anywhere
+    #[arg(long, global = true, default_value = ".env")]
+    pub env_file: PathBuf,
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Commands {
+    /// Start the tts service, then the vtuber app, and restart them when they exit or stop
+    /// answering
+    Up {
+        /// Use a tts service running elsewhere
+        #[arg(long)]
+        no_tts: bool,
+    },
+    /// The vtuber app alone, see `murasame vtuber -- --help`
+    Vtuber {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// The tts service alone
+    Tts {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Chat with the character in the terminal (ai-cli)
+    Chat {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Render and inspect models (layer-composer-cli)
+    Render {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum ConfigCommand {
+    /// Write a .env with the settings needed to start and the optional ones commented out
+    Init {
+        #[arg(short, long, default_value = ".env")]
+        output: PathBuf,
+        /// Replace an existing file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Store an API key or token in the OS keyring, read from stdin, see MURASAME_KEYRING
+    SetSecret {
+        #[arg(value_parser = clap::builder::PossibleValuesParser::new(config::SECRETS))]
+        name: String,
+    },
+    /// Remove a secret from the OS keyring
+    DeleteSecret {
+        #[arg(value_parser = clap::builder::PossibleValuesParser::new(config::SECRETS))]
+        name: String,
+    },
+}
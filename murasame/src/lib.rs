@@ -0,0 +1,112 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use config::{ServicesConfig, keyring};
+use env_logger::Env;
+use tokio::process::Command;
+
+use crate::{
+    cli::{Cli, Commands, ConfigCommand},
+    service::Service,
+};
+
+mod cli;
+mod service;
+
+pub async fn run() -> anyhow::Result<()> {
+    let args = Cli::parse();
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    if let Commands::Config { command } = &args.command {
+        return run_config_command(command);
+    }
+
+    // loaded once here, the programs inherit it and find the same file in their folder
+    let dir = match load_env(&args.env_file) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::warn!("Failed to load {}: {e}", args.env_file.display());
+            env::current_dir()?
+        }
+    };
+
+    let (name, args) = match args.command {
+        Commands::Up { no_tts } => {
+            let config = ServicesConfig::from_env()?;
+            let mut services = Vec::new();
+            if !no_tts {
+                services.push(Service::new("tts", binary("tts"), Some(config.tts_address)));
+            }
+            services.push(Service::new(
+                "vtuber",
+                binary("vtuber"),
+                Some(config.vtuber_address),
+            ));
+            return service::supervise(services, &dir).await;
+        }
+        Commands::Vtuber { args } => ("vtuber", args),
+        Commands::Tts { args } => ("tts", args),
+        Commands::Chat { args } => ("ai-cli", args),
+        Commands::Render { args } => ("layer-composer-cli", args),
+        Commands::Config { .. } => unreachable!(),
+    };
+
+    let status = Command::new(binary(name))
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to run {name}: {e}"))?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+fn run_config_command(command: &ConfigCommand) -> anyhow::Result<()> {
+    match command {
+        ConfigCommand::Init { output, force } => {
+            if output.exists() && !force {
+                anyhow::bail!("{} exists, replace it with --force", output.display());
+            }
+            fs::write(output, config::template::render())?;
+            println!("Wrote {}", output.display());
+        }
+        ConfigCommand::SetSecret { name } => {
+            eprintln!("Enter {name}:");
+            let mut value = String::new();
+            io::stdin().read_line(&mut value)?;
+            let value = value.trim_end_matches(['\r', '\n']);
+            anyhow::ensure!(!value.is_empty(), "{name} is empty");
+            keyring::set(name, value)?;
+            println!("Stored {name} in the keyring");
+        }
+        ConfigCommand::DeleteSecret { name } => {
+            keyring::delete(name)?;
+            println!("Removed {name} from the keyring");
+        }
+    }
+    Ok(())
+}
+
+/// Load the .env into the environment, returns its folder. The secrets it leaves out are
+/// taken from the keyring if enabled, so every program finds them.
+fn load_env(path: &Path) -> anyhow::Result<PathBuf> {
+    let path = fs::canonicalize(path)?;
+    dotenvy::from_path(&path)?;
+    for (name, value) in config::secrets_from_keyring() {
+        // SAFETY: set before any program is started, the same way dotenvy sets the .env
+        unsafe { env::set_var(name, value) };
+    }
+    Ok(path.parent().map(Path::to_path_buf).unwrap_or_default())
+}
+
+/// A program of the workspace, next to this one or else on the PATH.
+fn binary(name: &str) -> PathBuf {
+    let file = format!("{name}{}", env::consts::EXE_SUFFIX);
+    env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(&file)))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(file))
+}
@@ -0,0 +1,6 @@
+use murasame::run;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    run().await
+}
@@ -0,0 +1,227 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use tokio::{
+    net::TcpStream,
+    process::{Child, Command},
+    time::Instant,
+};
+
+/// How often the services are checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// Time to start listening before a service counts as unhealthy.
+const STARTUP_GRACE: Duration = Duration::from_secs(60);
+/// Failed health checks in a row before a service is restarted.
+const MAX_FAILED_CHECKS: u32 = 3;
+/// A service running this long is fine again, its restart delay starts over.
+const STABLE: Duration = Duration::from_secs(60);
+/// Time to shut down after Ctrl+C before being killed.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A program kept running by the launcher.
+pub struct Service {
+    name: &'static str,
+    binary: PathBuf,
+    /// Where it listens, checked to tell it is healthy
+    address: Option<String>,
+    child: Option<Child>,
+    started_at: Instant,
+    restarts: u32,
+    restart_at: Option<Instant>,
+    failed_checks: u32,
+}
+
+impl Service {
+    pub fn new(name: &'static str, binary: PathBuf, address: Option<String>) -> Self {
+        Self {
+            name,
+            binary,
+            address,
+            child: None,
+            started_at: Instant::now(),
+            restarts: 0,
+            restart_at: None,
+            failed_checks: 0,
+        }
+    }
+
+    fn start(&mut self, dir: &Path) {
+        log::info!("Starting {}", self.name);
+        self.started_at = Instant::now();
+        self.failed_checks = 0;
+        self.restart_at = None;
+        match Command::new(&self.binary)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => self.child = Some(child),
+            Err(e) => {
+                log::error!(
+                    "Failed to start {} ({}): {e}",
+                    self.name,
+                    self.binary.display()
+                );
+                self.schedule_restart();
+            }
+        }
+    }
+
+    fn schedule_restart(&mut self) {
+        let delay = restart_delay(self.restarts);
+        log::warn!("Restarting {} in {}s", self.name, delay.as_secs());
+        self.restarts += 1;
+        self.restart_at = Some(Instant::now() + delay);
+    }
+
+    async fn is_listening(&self) -> bool {
+        let Some(address) = &self.address else {
+            return true;
+        };
+        matches!(
+            tokio::time::timeout(Duration::from_secs(2), TcpStream::connect(address)).await,
+            Ok(Ok(_))
+        )
+    }
+
+    /// Wait until the service listens, at most the startup grace.
+    async fn wait_until_listening(&mut self) {
+        if self.address.is_none() {
+            return;
+        }
+        let deadline = Instant::now() + STARTUP_GRACE;
+        while Instant::now() < deadline {
+            // left to the checks to restart
+            let Some(child) = &mut self.child else {
+                return;
+            };
+            if matches!(child.try_wait(), Ok(Some(_))) {
+                return;
+            }
+            if self.is_listening().await {
+                log::info!("{} is up", self.name);
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        log::warn!(
+            "{} doesn't listen yet, starting the others anyway",
+            self.name
+        );
+    }
+
+    /// Restart the service once it failed or stopped answering. A service that exited on its
+    /// own, e.g. the window was closed, is left stopped.
+    async fn check(&mut self, dir: &Path) {
+        if let Some(restart_at) = self.restart_at {
+            if Instant::now() >= restart_at {
+                self.start(dir);
+            }
+            return;
+        }
+        let Some(child) = &mut self.child else {
+            return;
+        };
+
+        let status = child.try_wait();
+        match status {
+            Ok(Some(status)) if status.success() => {
+                log::info!("{} exited, not restarting it", self.name);
+                self.child = None;
+            }
+            // killed by a signal as well
+            Ok(Some(status)) => {
+                log::warn!("{} exited with {status}", self.name);
+                self.child = None;
+                if self.started_at.elapsed() >= STABLE {
+                    self.restarts = 0;
+                }
+                self.schedule_restart();
+            }
+            Ok(None) => {
+                if self.started_at.elapsed() < STARTUP_GRACE || self.is_listening().await {
+                    self.failed_checks = 0;
+                    return;
+                }
+                self.failed_checks += 1;
+                log::warn!("{} doesn't answer ({})", self.name, self.failed_checks);
+                // reaped and restarted by the next check
+                if self.failed_checks >= MAX_FAILED_CHECKS
+                    && let Some(child) = &mut self.child
+                    && let Err(e) = child.start_kill()
+                {
+                    log::error!("Failed to kill {}: {e}", self.name);
+                }
+            }
+            Err(e) => log::error!("Failed to check {}: {e}", self.name),
+        }
+    }
+
+    async fn stop(&mut self) {
+        let Some(child) = &mut self.child else {
+            return;
+        };
+        // Ctrl+C reached the services too, give them a moment to shut down
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, child.wait())
+            .await
+            .is_err()
+        {
+            log::warn!("Killing {}", self.name);
+            let _ = child.kill().await;
+        }
+    }
+}
+
+/// Start the services in order, each once the previous one listens, and keep them running
+/// until Ctrl+C.
+pub async fn supervise(mut services: Vec<Service>, dir: &Path) -> anyhow::Result<()> {
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = keep_running(&mut services, dir) => {}
+    }
+
+    log::info!("Shutting down");
+    for service in services.iter_mut().rev() {
+        service.stop().await;
+    }
+    Ok(())
+}
+
+async fn keep_running(services: &mut [Service], dir: &Path) {
+    for service in services.iter_mut() {
+        service.start(dir);
+        service.wait_until_listening().await;
+    }
+
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        for service in services.iter_mut() {
+            service.check(dir).await;
+        }
+    }
+}
+
+/// Twice as long after every restart in a row, at most a minute.
+fn restart_delay(restarts: u32) -> Duration {
+    Duration::from_secs(2u64.saturating_pow(restarts).min(60))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::service::restart_delay;
+
+    #[test]
+    fn back_off_restarts() {
+        assert_eq!(restart_delay(0), Duration::from_secs(1));
+        assert_eq!(restart_delay(3), Duration::from_secs(8));
+        assert_eq!(restart_delay(10), Duration::from_secs(60));
+        assert_eq!(restart_delay(100), Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+
+/// Synthesize a line through the tts service, or GPT-SoVITS directly, and play or save it.
+#[derive(clap::Parser)]
+pub struct Cli {
+    /// Read from stdin if empty
+    pub text: Vec<String>,
+    /// The vtuber's VTUBER_TTS_API_BASE_URL if unset
+    #[arg(long)]
+    pub service: Option<String>,
+    /// Skip the tts service and call GPT-SoVITS with the voices configured for it in .env
+    #[arg(long)]
+    pub direct: bool,
+    /// The default voice of the tts service if unset
+    #[arg(long)]
+    pub voice: Option<String>,
+    /// Speech rate, 1.0 is the natural speed
+    #[arg(long)]
+    pub speed: Option<f32>,
+    /// Save the audio here
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Play the audio, the default unless saving it
+    #[arg(long)]
+    pub play: bool,
+}
@@ -0,0 +1,143 @@
+use std::{
+    io::{Cursor, Read},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use clap::Parser;
+use config::{ServicesConfig, TtsServiceConfig};
+use rodio::{Decoder, OutputStreamBuilder, Sink, Source};
+use tts::{
+    config::{VoicesConfig, WeightsConfig},
+    weights::LoadedWeights,
+};
+
+use crate::cli::Cli;
+
+mod cli;
+
+pub async fn run() -> anyhow::Result<()> {
+    // the service url and the voices for --direct may come from .env
+    dotenvy::dotenv().ok();
+    let args = Cli::parse();
+
+    let text = if args.text.is_empty() {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        args.text.join(" ")
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        anyhow::bail!("Nothing to say");
+    }
+
+    let started_at = Instant::now();
+    let audio = if args.direct {
+        synthesize_direct(text, args.voice.as_deref(), args.speed).await?
+    } else {
+        let service = match args.service {
+            Some(service) => service,
+            None => ServicesConfig::from_env()?.tts_url,
+        };
+        let mut client = tts_client::TtsClient::new(service);
+        client.set_speed(args.speed);
+        client.generate(text, args.voice.as_deref()).await?
+    };
+    let latency = started_at.elapsed();
+
+    let decoded = Decoder::new(Cursor::new(audio.clone()));
+    let duration = decoded
+        .as_ref()
+        .ok()
+        .and_then(|decoder| decoder.total_duration());
+    eprintln!("{}", summary(latency, duration, audio.len()));
+
+    if let Some(output) = &args.output {
+        std::fs::write(output, &audio)?;
+        eprintln!("Saved to {}", output.display());
+    }
+    if args.play || args.output.is_none() {
+        let stream = OutputStreamBuilder::open_default_stream()?;
+        let sink = Sink::connect_new(stream.mixer());
+        sink.append(decoded?);
+        sink.sleep_until_end();
+    }
+
+    Ok(())
+}
+
+/// Call GPT-SoVITS like the tts service does, with its voices and weights.
+async fn synthesize_direct(
+    text: &str,
+    voice_name: Option<&str>,
+    speed: Option<f32>,
+) -> anyhow::Result<Bytes> {
+    let env = TtsServiceConfig::from_env()?;
+    let voices = VoicesConfig::new(&env)?;
+    let weights = WeightsConfig::new(&env)?;
+    let client = tts::TtsClient::new(env.gptsovits_base_url);
+    let voice = voices
+        .get(voice_name)
+        .ok_or_else(|| anyhow::anyhow!("Unknown voice {}", voice_name.unwrap_or_default()))?;
+
+    // nothing is known to be loaded yet, the base weights are switched to for voices without
+    // their own
+    LoadedWeights::new(weights.base)
+        .ensure(
+            &client,
+            voice.gpt_weights.as_deref(),
+            voice.sovits_weights.as_deref(),
+        )
+        .await?;
+    Ok(client
+        .generate_tts(
+            text,
+            "ja",
+            &voice.ref_audio,
+            &voice.ref_text,
+            &voice.prompt_lang,
+            speed.unwrap_or(1.0),
+        )
+        .await?)
+}
+
+/// How long synthesis took for how much audio.
+fn summary(latency: Duration, duration: Option<Duration>, size: usize) -> String {
+    let latency = latency.as_secs_f32();
+    let size = size as f32 / 1024.0;
+    match duration {
+        Some(duration) => {
+            let duration = duration.as_secs_f32();
+            format!(
+                "Synthesized {duration:.2}s of audio ({size:.1} KiB) in {latency:.2}s, {:.2}x real time",
+                duration / latency
+            )
+        }
+        None => format!("Synthesized {size:.1} KiB of audio in {latency:.2}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::summary;
+
+    #[test]
+    fn summarize_timing() {
+        assert_eq!(
+            summary(
+                Duration::from_millis(500),
+                Some(Duration::from_secs(2)),
+                2048
+            ),
+            "Synthesized 2.00s of audio (2.0 KiB) in 0.50s, 4.00x real time"
+        );
+        assert_eq!(
+            summary(Duration::from_secs(1), None, 512),
+            "Synthesized 0.5 KiB of audio in 1.00s"
+        );
+    }
+}
@@ -0,0 +1,6 @@
+use tts_cli::run;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    run().await
+}
@@ -0,0 +1,44 @@
+//! Bodies of the tts service's routes, shared by its handlers and [`crate::TtsClient`] so
+//! they can't drift apart. Their schemas also make up the service's OpenAPI document.
+
+use std::collections::BTreeMap;
+
+/// Body of `POST /tts/generate`.
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+pub struct GenerateRequest {
+    pub text: String,
+    /// One of the configured voices, the default voice if unset
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// Speech rate, 1.0 is the natural speed
+    #[serde(default)]
+    pub speed: Option<f32>,
+}
+
+/// Body of `POST /tts/weights`.
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+pub struct SetWeightsRequest {
+    /// One of the configured weight sets
+    pub name: String,
+}
+
+/// A named pair of GPT/SoVITS weights.
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+pub struct WeightSet {
+    pub gpt_weights: String,
+    pub sovits_weights: String,
+}
+
+/// The weights currently loaded into GPT-SoVITS, unknown until the service switched them.
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone, Default)]
+pub struct LoadedWeights {
+    pub gpt: Option<String>,
+    pub sovits: Option<String>,
+}
+
+/// Response of `GET /tts/weights`.
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+pub struct WeightsResponse {
+    pub available: BTreeMap<String, WeightSet>,
+    pub loaded: LoadedWeights,
+}
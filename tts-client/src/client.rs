@@ -1,9 +1,11 @@
 use bytes::Bytes;
-use serde_json::json;
+
+use crate::api::{GenerateRequest, SetWeightsRequest};
 
 pub struct TtsClient {
     base_url: String,
     client: reqwest::Client,
+    speed: Option<f32>,
 }
 
 impl TtsClient {
@@ -11,20 +13,46 @@ impl TtsClient {
         Self {
             base_url: base_url.into(),
             client: reqwest::Client::new(),
+            speed: None,
         }
     }
 
-    pub async fn generate(&self, text: &str) -> Result<Bytes, reqwest::Error> {
-        // generate body
-        let body = json!({
-            "text": text
-        });
+    /// Speech rate sent with every request, the service default if `None`.
+    pub fn set_speed(&mut self, speed: Option<f32>) {
+        self.speed = speed;
+    }
+
+    pub fn speed(&self) -> Option<f32> {
+        self.speed
+    }
+
+    pub async fn generate(&self, text: &str, voice: Option<&str>) -> Result<Bytes, reqwest::Error> {
+        let body = GenerateRequest {
+            text: text.to_string(),
+            voice: voice.map(str::to_string),
+            speed: self.speed,
+        };
         self.client
             .post(format!("{}/tts/generate", self.base_url))
             .json(&body)
             .send()
             .await?
+            .error_for_status()?
             .bytes()
             .await
     }
+
+    /// Switch the tts service to the weight set with the given name.
+    pub async fn set_weights(&self, name: &str) -> Result<(), reqwest::Error> {
+        self.client
+            .post(format!("{}/tts/weights", self.base_url))
+            .json(&SetWeightsRequest {
+                name: name.to_string(),
+            })
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
 }
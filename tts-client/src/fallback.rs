@@ -0,0 +1,37 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+const SAMPLE_RATE: u32 = 22050;
+
+/// Generate a short mono 16-bit WAV beep, used when no fallback clip is configured.
+pub fn beep() -> Bytes {
+    let frequency = 880.0f32;
+    let samples = SAMPLE_RATE / 2; // 0.5s
+    let data_len = samples * 2;
+
+    let mut buf = BytesMut::with_capacity(44 + data_len as usize);
+    // RIFF header
+    buf.put_slice(b"RIFF");
+    buf.put_u32_le(36 + data_len);
+    buf.put_slice(b"WAVE");
+    // fmt chunk: PCM, mono, 16 bits
+    buf.put_slice(b"fmt ");
+    buf.put_u32_le(16);
+    buf.put_u16_le(1);
+    buf.put_u16_le(1);
+    buf.put_u32_le(SAMPLE_RATE);
+    buf.put_u32_le(SAMPLE_RATE * 2);
+    buf.put_u16_le(2);
+    buf.put_u16_le(16);
+    // data chunk
+    buf.put_slice(b"data");
+    buf.put_u32_le(data_len);
+    for i in 0..samples {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        // fade out to avoid a click at the end
+        let envelope = 1.0 - i as f32 / samples as f32;
+        let sample = (t * frequency * std::f32::consts::TAU).sin() * envelope * 0.3;
+        buf.put_i16_le((sample * i16::MAX as f32) as i16);
+    }
+
+    buf.freeze()
+}
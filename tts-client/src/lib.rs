@@ -1,3 +1,5 @@
+pub mod api;
 mod client;
+pub mod fallback;
 
 pub use client::TtsClient;
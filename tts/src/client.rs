@@ -22,6 +22,8 @@ impl TtsClient {
         text_lang: &str,
         ref_audio_path: &Path,
         ref_audio_text: &str,
+        ref_audio_lang: &str,
+        speed_factor: f32,
     ) -> Result<Bytes, reqwest::Error> {
         let payload = json!({
             "text": text,
@@ -29,7 +31,7 @@ impl TtsClient {
             "ref_audio_path": ref_audio_path.to_string_lossy(),
             "aux_ref_audio_paths": [],
             "prompt_text": ref_audio_text,
-            "prompt_lang": "ja",
+            "prompt_lang": ref_audio_lang,
             "top_k": 15,
             "top_p": 1,
             "temperature": 1,
@@ -37,7 +39,7 @@ impl TtsClient {
             "batch_size": 1,
             "batch_threshold": 0.75,
             "split_bucket": true,
-            "speed_factor": 1.0,
+            "speed_factor": speed_factor,
             "streaming_mode": false,
             "seed": -1,
             "parallel_infer": true,
@@ -58,4 +60,26 @@ impl TtsClient {
 
         Ok(res)
     }
+
+    pub async fn set_gpt_weights(&self, weights_path: &str) -> Result<(), reqwest::Error> {
+        self.client
+            .get(format!("{}/set_gpt_weights", self.base_url))
+            .query(&[("weights_path", weights_path)])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    pub async fn set_sovits_weights(&self, weights_path: &str) -> Result<(), reqwest::Error> {
+        self.client
+            .get(format!("{}/set_sovits_weights", self.base_url))
+            .query(&[("weights_path", weights_path)])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
 }
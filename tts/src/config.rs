@@ -1,17 +1,39 @@
-use std::{env, fs, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+};
+
+use config::{ServicesConfig, TtsServiceConfig};
 
 pub struct AppConfig {
-    pub ref_audio: RefAudioConfig,
+    pub voices: VoicesConfig,
+    pub weights: WeightsConfig,
     pub servlet: ServletConfig,
     pub tts: TtsConfig,
 }
 
 impl AppConfig {
     pub fn from_env() -> Result<Self, anyhow::Error> {
+        let env = TtsServiceConfig::from_env()?;
+        let voices = VoicesConfig::new(&env)?;
+        let weights = WeightsConfig::new(&env)?;
+        // switching back from a voice's own weights needs to know what to switch to
+        if weights.base.is_none()
+            && let Some((name, _)) = voices
+                .voices
+                .iter()
+                .find(|(_, voice)| voice.gpt_weights.is_some() || voice.sovits_weights.is_some())
+        {
+            anyhow::bail!(
+                "Voice {name} loads its own weights, set TTS_BASE_WEIGHTS to the weight set of the other voices"
+            );
+        }
         Ok(Self {
-            ref_audio: RefAudioConfig::from_env()?,
-            servlet: ServletConfig::from_env()?,
-            tts: TtsConfig::from_env()?,
+            voices,
+            weights,
+            servlet: ServletConfig::new(&ServicesConfig::from_env()?),
+            tts: TtsConfig::new(&env),
         })
     }
 }
@@ -21,10 +43,10 @@ pub struct TtsConfig {
 }
 
 impl TtsConfig {
-    pub fn from_env() -> Result<Self, anyhow::Error> {
-        Ok(Self {
-            base_url: env::var("GPTSOVITS_API_BASE_URL")?,
-        })
+    pub fn new(env: &TtsServiceConfig) -> Self {
+        Self {
+            base_url: env.gptsovits_base_url.clone(),
+        }
     }
 }
 
@@ -33,23 +55,105 @@ pub struct ServletConfig {
 }
 
 impl ServletConfig {
-    pub fn from_env() -> Result<Self, anyhow::Error> {
-        Ok(Self {
-            address: env::var("TTS_ADDRESS").unwrap_or_else(|_| "127.0.0.1:20888".to_string()),
-        })
+    pub fn new(services: &ServicesConfig) -> Self {
+        Self {
+            address: services.tts_address.clone(),
+        }
     }
 }
 
-pub struct RefAudioConfig {
-    pub path: PathBuf,
-    pub text: String,
+/// Engine-specific settings of a single voice.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct VoiceConfig {
+    pub ref_audio: PathBuf,
+    pub ref_text: String,
+    #[serde(default = "default_prompt_lang")]
+    pub prompt_lang: String,
+    /// GPT weights to load before synthesizing with this voice
+    #[serde(default)]
+    pub gpt_weights: Option<String>,
+    /// SoVITS weights to load before synthesizing with this voice
+    #[serde(default)]
+    pub sovits_weights: Option<String>,
 }
 
-impl RefAudioConfig {
-    pub fn from_env() -> Result<Self, anyhow::Error> {
+fn default_prompt_lang() -> String {
+    "ja".to_string()
+}
+
+pub struct VoicesConfig {
+    pub default_voice: String,
+    pub voices: HashMap<String, VoiceConfig>,
+}
+
+impl VoicesConfig {
+    pub fn new(env: &TtsServiceConfig) -> Result<Self, anyhow::Error> {
+        let mut voices: HashMap<String, VoiceConfig> = HashMap::new();
+
+        // the voice configured by TTS_REF_AUDIO/TTS_REF_TEXT is always available
+        voices.insert(
+            "default".to_string(),
+            VoiceConfig {
+                ref_audio: fs::canonicalize(&env.ref_audio)?,
+                ref_text: env.ref_text.clone(),
+                prompt_lang: default_prompt_lang(),
+                gpt_weights: None,
+                sovits_weights: None,
+            },
+        );
+
+        // extra voices: a json object mapping voice names to voice configs
+        if let Some(path) = &env.voices {
+            let extra: HashMap<String, VoiceConfig> =
+                serde_json::from_reader(fs::File::open(fs::canonicalize(path)?)?)?;
+            for (name, mut voice) in extra {
+                // GPT-SoVITS resolves paths relative to its own working directory
+                voice.ref_audio = fs::canonicalize(&voice.ref_audio)?;
+                voices.insert(name, voice);
+            }
+        }
+
+        let default_voice = env.default_voice.clone();
+        if !voices.contains_key(&default_voice) {
+            anyhow::bail!("Default voice {default_voice} is not configured");
+        }
+
         Ok(Self {
-            path: fs::canonicalize(env::var("TTS_REF_AUDIO").unwrap_or_else(|_| "./resources/ref_audio.ogg".to_string()))?,
-            text: env::var("TTS_REF_TEXT").unwrap_or_else(|_| "ふむ、おぬしが我輩のご主人か?".to_string())
+            default_voice,
+            voices,
         })
     }
+
+    pub fn get(&self, name: Option<&str>) -> Option<&VoiceConfig> {
+        self.voices.get(name.unwrap_or(&self.default_voice))
+    }
+}
+
+pub use tts_client::api::WeightSet;
+
+pub struct WeightsConfig {
+    pub weight_sets: BTreeMap<String, WeightSet>,
+    /// Loaded for the voices without their own weights
+    pub base: Option<WeightSet>,
+}
+
+impl WeightsConfig {
+    pub fn new(env: &TtsServiceConfig) -> Result<Self, anyhow::Error> {
+        // a json object mapping weight set names to weight paths
+        let weight_sets = match &env.weights {
+            Some(path) => serde_json::from_reader(fs::File::open(fs::canonicalize(path)?)?)?,
+            None => BTreeMap::new(),
+        };
+        let base = match &env.base_weights {
+            Some(name) => Some(
+                weight_sets
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Base weight set {name} is not configured"))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self { weight_sets, base })
+    }
 }
@@ -1 +1,2 @@
 pub mod tts;
+pub mod weights;
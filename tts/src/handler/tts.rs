@@ -1,38 +1,65 @@
 use actix_web::{Responder, ResponseError, http::StatusCode, web};
+use http_common::error::check_text;
+use tokio::sync::Mutex;
+use tts_client::api::GenerateRequest;
 
-use crate::{TtsClient, config::RefAudioConfig};
+use crate::{TtsClient, config::VoicesConfig, weights::LoadedWeights};
 
-#[derive(serde::Deserialize, Debug)]
-pub struct GenerateTtsModel {
-    text: String,
-}
+/// Longer lines take too long to synthesize, split them instead.
+const MAX_TEXT_CHARS: usize = 1000;
 
 #[derive(thiserror::Error, Debug)]
 pub enum TtsError {
     #[error("Failed to send request {0}")]
     Request(#[from] reqwest::Error),
+    #[error("Unknown voice {0}")]
+    UnknownVoice(String),
 }
 
 impl ResponseError for TtsError {
     fn status_code(&self) -> actix_web::http::StatusCode {
         match self {
             TtsError::Request(_error) => StatusCode::INTERNAL_SERVER_ERROR,
+            TtsError::UnknownVoice(_) => StatusCode::BAD_REQUEST,
         }
     }
 }
 
-#[tracing::instrument(skip(tts_client, ref_audio_config))]
+#[tracing::instrument(skip(tts_client, voices_config, loaded_weights))]
 pub async fn generate_tts(
-    body: web::Json<GenerateTtsModel>,
+    body: web::Json<GenerateRequest>,
     tts_client: web::Data<TtsClient>,
-    ref_audio_config: web::Data<RefAudioConfig>,
-) -> Result<impl Responder, TtsError> {
-    // TODO: replace with another eror type
+    voices_config: web::Data<VoicesConfig>,
+    loaded_weights: web::Data<Mutex<LoadedWeights>>,
+) -> actix_web::Result<impl Responder> {
+    check_text("text", &body.text, MAX_TEXT_CHARS)?;
     let text = body.text.as_ref();
+    let voice = voices_config
+        .get(body.voice.as_deref())
+        .ok_or_else(|| TtsError::UnknownVoice(body.voice.clone().unwrap_or_default()))?;
+
+    // hold the lock while synthesizing, so other requests can't swap the weights in between
+    let mut loaded_weights = loaded_weights.lock().await;
+    loaded_weights
+        .ensure(
+            &tts_client,
+            voice.gpt_weights.as_deref(),
+            voice.sovits_weights.as_deref(),
+        )
+        .await
+        .map_err(TtsError::from)?;
 
     let voice_bytes = tts_client
-        .generate_tts(text, "ja", &ref_audio_config.path, &ref_audio_config.text)
-        .await?;
+        .generate_tts(
+            text,
+            "ja",
+            &voice.ref_audio,
+            &voice.ref_text,
+            &voice.prompt_lang,
+            body.speed.unwrap_or(1.0),
+        )
+        .await
+        .map_err(TtsError::from)?;
 
     Ok(voice_bytes)
 }
@@ -0,0 +1,51 @@
+use actix_web::{HttpResponse, Responder, ResponseError, http::StatusCode, web};
+use tokio::sync::Mutex;
+use tts_client::api::{self, SetWeightsRequest, WeightsResponse};
+
+use crate::{TtsClient, config::WeightsConfig, weights::LoadedWeights};
+
+#[derive(thiserror::Error, Debug)]
+pub enum WeightsError {
+    #[error("Failed to switch weights {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Unknown weight set {0}")]
+    UnknownWeightSet(String),
+}
+
+impl ResponseError for WeightsError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            WeightsError::Request(_error) => StatusCode::BAD_GATEWAY,
+            WeightsError::UnknownWeightSet(_) => StatusCode::NOT_FOUND,
+        }
+    }
+}
+
+pub async fn list_weights(
+    weights_config: web::Data<WeightsConfig>,
+    loaded_weights: web::Data<Mutex<LoadedWeights>>,
+) -> impl Responder {
+    let loaded = api::LoadedWeights::from(&*loaded_weights.lock().await);
+    HttpResponse::Ok().json(WeightsResponse {
+        available: weights_config.weight_sets.clone(),
+        loaded,
+    })
+}
+
+#[tracing::instrument(skip(tts_client, weights_config, loaded_weights))]
+pub async fn set_weights(
+    body: web::Json<SetWeightsRequest>,
+    tts_client: web::Data<TtsClient>,
+    weights_config: web::Data<WeightsConfig>,
+    loaded_weights: web::Data<Mutex<LoadedWeights>>,
+) -> Result<impl Responder, WeightsError> {
+    let weight_set = weights_config
+        .weight_sets
+        .get(&body.name)
+        .ok_or_else(|| WeightsError::UnknownWeightSet(body.name.clone()))?;
+
+    let mut loaded_weights = loaded_weights.lock().await;
+    loaded_weights.select(&tts_client, weight_set).await?;
+
+    Ok(HttpResponse::Ok().json(api::LoadedWeights::from(&*loaded_weights)))
+}
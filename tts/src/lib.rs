@@ -1,8 +1,10 @@
 mod client;
 pub mod config;
 mod handler;
+mod openapi;
 mod scope;
 pub mod startup;
 pub mod telemetry;
+pub mod weights;
 
 pub use client::TtsClient;
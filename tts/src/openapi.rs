@@ -0,0 +1,51 @@
+use http_common::openapi::{ApiDoc, Operation};
+use tts_client::api::{GenerateRequest, LoadedWeights, SetWeightsRequest, WeightsResponse};
+
+/// The routes of [`crate::scope::tts::tts_scope`], served at `/openapi.json`.
+pub fn api_doc() -> ApiDoc {
+    ApiDoc::new("tts", env!("CARGO_PKG_VERSION"))
+        .operation(
+            Operation::post("/tts/generate", "Synthesize a line")
+                .body::<GenerateRequest>()
+                .content(200, "The synthesized speech", "audio/wav")
+                .response(400, "Blank or too long text, or an unknown voice")
+                .response(413, "Body too large")
+                .response(500, "GPT-SoVITS failed"),
+        )
+        .operation(
+            Operation::get(
+                "/tts/weights",
+                "List the weight sets and the loaded weights",
+            )
+            .json::<WeightsResponse>(200, "The configured and loaded weights"),
+        )
+        .operation(
+            Operation::post("/tts/weights", "Switch to a weight set")
+                .body::<SetWeightsRequest>()
+                .json::<LoadedWeights>(200, "The weights loaded now")
+                .response(404, "Unknown weight set")
+                .response(502, "GPT-SoVITS failed to switch"),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use http_common::testing::route_mismatches;
+
+    use crate::{openapi::api_doc, startup::configure_server};
+
+    /// Every route of the scopes, kept by hand so that a new one can't go undocumented
+    const ROUTES: [(&str, &str); 3] = [
+        ("post", "/tts/generate"),
+        ("get", "/tts/weights"),
+        ("post", "/tts/weights"),
+    ];
+
+    #[actix_web::test]
+    async fn documented_routes_exist() {
+        assert_eq!(
+            route_mismatches(configure_server, &ROUTES, &api_doc()).await,
+            [] as [String; 0]
+        );
+    }
+}
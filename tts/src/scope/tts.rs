@@ -5,4 +5,6 @@ use crate::handler;
 pub fn tts_scope() -> Scope {
     web::scope("tts")
         .route("generate", web::post().to(handler::tts::generate_tts))
+        .route("weights", web::get().to(handler::weights::list_weights))
+        .route("weights", web::post().to(handler::weights::set_weights))
 }
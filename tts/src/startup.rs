@@ -3,31 +3,44 @@ use std::net::TcpListener;
 use actix_web::{
     App, HttpServer,
     dev::Server,
-    middleware::NormalizePath,
+    middleware::{NormalizePath, from_fn},
     web::{self, ServiceConfig},
 };
+use http_common::error::json_errors;
+use tokio::sync::Mutex;
 use tracing_actix_web::TracingLogger;
 
-use crate::{TtsClient, config::AppConfig, scope::tts::tts_scope};
+use crate::{TtsClient, config::AppConfig, openapi, scope::tts::tts_scope, weights::LoadedWeights};
 
-fn configure_server(config: &mut ServiceConfig) {
+/// Bodies are a line of text, anything larger is refused before parsing.
+const MAX_BODY: usize = 16 * 1024;
+
+pub(crate) fn configure_server(config: &mut ServiceConfig) {
     config.service(tts_scope());
+    http_common::openapi::configure(config, &openapi::api_doc());
 }
 
 pub fn create_server(listener: TcpListener, config: AppConfig) -> anyhow::Result<Server> {
     let tts_client = web::Data::new(TtsClient::new(config.tts.base_url));
 
-    let ref_audio_config = web::Data::new(config.ref_audio);
+    let loaded_weights =
+        web::Data::new(Mutex::new(LoadedWeights::new(config.weights.base.clone())));
+    let voices_config = web::Data::new(config.voices);
+    let weights_config = web::Data::new(config.weights);
 
     let server = HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(json_errors))
             .wrap(TracingLogger::default())
             .wrap(NormalizePath::new(
                 actix_web::middleware::TrailingSlash::MergeOnly,
             ))
             .configure(configure_server)
+            .app_data(web::JsonConfig::default().limit(MAX_BODY))
             .app_data(tts_client.clone())
-            .app_data(ref_audio_config.clone())
+            .app_data(voices_config.clone())
+            .app_data(weights_config.clone())
+            .app_data(loaded_weights.clone())
     });
 
     Ok(server.listen(listener)?.run())
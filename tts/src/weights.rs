@@ -0,0 +1,78 @@
+use tts_client::api::{self, WeightSet};
+
+use crate::TtsClient;
+
+/// Tracks the weights currently loaded into GPT-SoVITS, so switching only
+/// happens when a request actually needs different weights.
+#[derive(Default, Debug, Clone)]
+pub struct LoadedWeights {
+    gpt: Option<String>,
+    sovits: Option<String>,
+    /// Loaded for voices without their own weights, `None` keeps what GPT-SoVITS started with
+    base: Option<WeightSet>,
+}
+
+impl From<&LoadedWeights> for api::LoadedWeights {
+    fn from(weights: &LoadedWeights) -> Self {
+        Self {
+            gpt: weights.gpt.clone(),
+            sovits: weights.sovits.clone(),
+        }
+    }
+}
+
+impl LoadedWeights {
+    pub fn new(base: Option<WeightSet>) -> Self {
+        Self {
+            base,
+            ..Self::default()
+        }
+    }
+
+    /// Load the weights of a voice, the base weights where it has none.
+    pub async fn ensure(
+        &mut self,
+        tts_client: &TtsClient,
+        gpt_weights: Option<&str>,
+        sovits_weights: Option<&str>,
+    ) -> Result<(), reqwest::Error> {
+        let base = self.base.clone();
+        let gpt_weights = gpt_weights.or(base.as_ref().map(|base| base.gpt_weights.as_str()));
+        let sovits_weights =
+            sovits_weights.or(base.as_ref().map(|base| base.sovits_weights.as_str()));
+
+        if let Some(path) = gpt_weights
+            && self.gpt.as_deref() != Some(path)
+        {
+            tracing::info!("Switching GPT weights to {path}");
+            tts_client.set_gpt_weights(path).await?;
+            self.gpt = Some(path.to_string());
+        }
+
+        if let Some(path) = sovits_weights
+            && self.sovits.as_deref() != Some(path)
+        {
+            tracing::info!("Switching SoVITS weights to {path}");
+            tts_client.set_sovits_weights(path).await?;
+            self.sovits = Some(path.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Load a weight set and keep it for the voices without their own weights.
+    pub async fn select(
+        &mut self,
+        tts_client: &TtsClient,
+        weight_set: &WeightSet,
+    ) -> Result<(), reqwest::Error> {
+        self.ensure(
+            tts_client,
+            Some(&weight_set.gpt_weights),
+            Some(&weight_set.sovits_weights),
+        )
+        .await?;
+        self.base = Some(weight_set.clone());
+        Ok(())
+    }
+}
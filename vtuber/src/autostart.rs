@@ -0,0 +1,189 @@
+use std::{env, path::Path};
+
+use anyhow::Context;
+
+/// Name of the login entry on Linux and Windows.
+#[cfg(any(not(target_os = "macos"), test))]
+const NAME: &str = "Murasame";
+#[cfg(any(target_os = "macos", test))]
+const LABEL: &str = "io.github.cubewhy.murasame";
+
+/// Launch the app at login from the current directory, where its `.env` is.
+pub fn enable() -> anyhow::Result<()> {
+    let exe = env::current_exe().context("Failed to find the executable")?;
+    let dir = env::current_dir().context("Failed to find the working directory")?;
+    platform::enable(&exe, &dir)
+}
+
+/// Stop launching the app at login, nothing happens if it wasn't enabled.
+pub fn disable() -> anyhow::Result<()> {
+    platform::disable()
+}
+
+/// Quoted for a desktop entry's `Exec` key or a Windows command line.
+#[cfg(any(not(target_os = "macos"), test))]
+fn quote(arg: &Path) -> String {
+    let arg = arg.to_string_lossy();
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') && cfg!(not(windows)) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn desktop_entry(exe: &Path, dir: &Path) -> String {
+    format!(
+        "[Desktop Entry]\nType=Application\nName={NAME}\nExec={} --working-dir {}\nX-GNOME-Autostart-enabled=true\n",
+        quote(exe),
+        quote(dir)
+    )
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn launch_agent(exe: &Path, dir: &Path) -> String {
+    let escape = |path: &Path| {
+        path.to_string_lossy()
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+        <string>--working-dir</string>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+        escape(exe),
+        escape(dir)
+    )
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use std::{fs, io, path::Path};
+
+    use anyhow::Context;
+
+    use crate::autostart::desktop_entry;
+
+    fn entry_path() -> anyhow::Result<std::path::PathBuf> {
+        let config = dirs::config_dir().context("No config directory")?;
+        Ok(config.join("autostart").join("murasame.desktop"))
+    }
+
+    pub fn enable(exe: &Path, dir: &Path) -> anyhow::Result<()> {
+        let path = entry_path()?;
+        fs::create_dir_all(path.parent().expect("has a parent"))?;
+        fs::write(&path, desktop_entry(exe, dir))?;
+        log::info!("Wrote {}", path.display());
+        Ok(())
+    }
+
+    pub fn disable() -> anyhow::Result<()> {
+        match fs::remove_file(entry_path()?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::{fs, io, path::Path};
+
+    use anyhow::Context;
+
+    use crate::autostart::{LABEL, launch_agent};
+
+    fn agent_path() -> anyhow::Result<std::path::PathBuf> {
+        let home = dirs::home_dir().context("No home directory")?;
+        Ok(home
+            .join("Library/LaunchAgents")
+            .join(format!("{LABEL}.plist")))
+    }
+
+    pub fn enable(exe: &Path, dir: &Path) -> anyhow::Result<()> {
+        let path = agent_path()?;
+        fs::create_dir_all(path.parent().expect("has a parent"))?;
+        fs::write(&path, launch_agent(exe, dir))?;
+        log::info!("Wrote {}", path.display());
+        Ok(())
+    }
+
+    pub fn disable() -> anyhow::Result<()> {
+        match fs::remove_file(agent_path()?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::{path::Path, process::Command};
+
+    use crate::autostart::{NAME, quote};
+
+    const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+    fn reg(args: &[&str]) -> anyhow::Result<()> {
+        let status = Command::new("reg").args(args).status()?;
+        anyhow::ensure!(status.success(), "reg exited with {status}");
+        Ok(())
+    }
+
+    pub fn enable(exe: &Path, dir: &Path) -> anyhow::Result<()> {
+        let command = format!("{} --working-dir {}", quote(exe), quote(dir));
+        reg(&[
+            "add", RUN_KEY, "/v", NAME, "/t", "REG_SZ", "/d", &command, "/f",
+        ])
+    }
+
+    pub fn disable() -> anyhow::Result<()> {
+        // fails when the value is missing
+        let _ = reg(&["delete", RUN_KEY, "/v", NAME, "/f"]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::autostart::{desktop_entry, launch_agent};
+
+    #[test]
+    fn write_login_entries() {
+        let exe = Path::new("/opt/murasame/vtuber");
+        let dir = Path::new("/home/me/My $pet");
+
+        let entry = desktop_entry(exe, dir);
+        if cfg!(not(windows)) {
+            assert!(
+                entry.contains(r#"Exec="/opt/murasame/vtuber" --working-dir "/home/me/My \$pet""#)
+            );
+        }
+
+        let agent = launch_agent(exe, Path::new("/Users/me/R&D"));
+        assert!(agent.contains("<string>/Users/me/R&amp;D</string>"));
+        assert!(agent.contains("<string>io.github.cubewhy.murasame</string>"));
+    }
+}
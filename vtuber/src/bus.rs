@@ -1,27 +1,276 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Instant, SystemTime},
+};
+
 use bytes::Bytes;
 use tokio::sync::{broadcast, mpsc};
 
-#[derive(Debug, Clone)]
+use crate::{
+    companion::PomodoroView, config::LiveConfig, latency::Trace, metrics::Metrics, poll::PollView,
+    soundboard::SoundCommand,
+};
+
+/// Serializable for [`crate::replay`] recordings.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum InEvent {
     Comment(CommentEvent),
+    Gift(GiftEvent),
+    Subscription(SubscriptionEvent),
+    /// Transcribed speech of the streamer, see [`crate::stt`], or what they typed in the window
+    HostSpeech {
+        speaker: String,
+        text: String,
+    },
+    Control(ControlCommand),
+    /// The desktop user clicked a hit area of a character
+    Touch {
+        /// Name of the touched character
+        character: String,
+        prompt: String,
+    },
+    Notification(NotificationEvent),
 }
 
 #[derive(Debug, Clone)]
 pub enum UiEvent {
     NewComment(CommentEvent),
+    CommentRejected {
+        comment: CommentEvent,
+        reason: String,
+    },
     AiThinking,
     AiReply {
+        /// Index into [`crate::config::AppConfig::characters`]
+        character: usize,
         text: String,
         layers: Vec<String>,
         voice: Bytes,
+        /// Priority of the answered comment
+        priority: Priority,
+        /// Stages the answered comment went through so far, with its id
+        trace: Trace,
     },
     Error(String),
+    Control(ControlCommand),
+    /// The config file changed and the safe settings were applied
+    ConfigReloaded(Arc<LiveConfig>),
+    /// The conversation of this character continues with its reloaded system prompt
+    SystemPromptReloaded {
+        character: usize,
+    },
+    /// A viewer asked for a sound effect or music
+    Sound(SoundCommand),
+    /// A poll started, got a vote or closed
+    Poll(PollView),
+    /// The pomodoro started, moved to the next phase or stopped
+    Pomodoro(Option<PomodoroView>),
+    /// The voice of a line started or stopped playing
+    Speaking {
+        character: usize,
+        speaking: bool,
+        at: SystemTime,
+    },
 }
 
-#[derive(Debug, Clone)]
+/// Operator commands, e.g. from global hotkeys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlCommand {
+    /// Stop the line that is currently spoken
+    Skip,
+    /// Drop the current answer and answer the same comment again
+    Regenerate,
+    /// Answer a recent comment again, by its id, sent from the chat panel
+    Requeue(u64),
+    ToggleMute,
+    /// Hold the voice where it is, the next line waits as well
+    TogglePause,
+    /// Only show the base layer until toggled again
+    ToggleNeutral,
+    /// Put the window into a corner of the next monitor
+    NextMonitor,
+    /// Save the shown frame as a png, see [`crate::capture::Recorder`]
+    Screenshot,
+    /// Save the last seconds as a GIF
+    Clip,
+    /// Bring the window to the front, sent when the app is started a second time
+    Activate,
+    /// Let the character read out and react to the selected text or the clipboard
+    ReadSelection,
+    /// Start a pomodoro, or stop the running one, see [`crate::companion::Companion`]
+    TogglePomodoro,
+    /// Record the microphone until [`ControlCommand::StopListening`], sent while the push-to-talk
+    /// hotkey is held or after the wake word
+    StartListening,
+    StopListening,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CommentEvent {
+    /// Unique within this process, a new one is assigned when deserializing
+    #[serde(skip_deserializing, default = "next_comment_id")]
+    pub id: u64,
     pub user: String,
     pub text: String,
+    /// Name of the comment source, e.g. "http" or "twitch"
+    pub source: String,
+    pub priority: Priority,
+    pub kind: CommentKind,
+    /// What the viewer wrote if `text` is a translation, shown instead of it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<String>,
+    /// The cleaned up name shown and given to the AI, see [`CommentEvent::name`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// When the comment reached the app, for latency metrics
+    #[serde(skip, default = "Instant::now")]
+    pub received_at: Instant,
+}
+
+/// What the viewer did, gifts and subscriptions are turned into comments for the AI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentKind {
+    #[default]
+    Chat,
+    Gift,
+    Subscription,
+    /// Said by the streamer instead of written in chat
+    Host,
+    /// The desktop user touched a character, named as the user
+    Touch,
+    /// Something happened on the computer, named as the user, see [`NotificationEvent`]
+    Notification,
+    /// Said by the character as it is instead of asking the AI
+    Announcement,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct GiftEvent {
+    pub user: String,
+    pub amount: f64,
+    #[serde(default)]
+    pub currency: String,
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Set by the source, written to recordings but never read
+    #[serde(skip_deserializing)]
+    #[schemars(skip)]
+    pub source: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SubscriptionEvent {
+    pub user: String,
+    #[serde(default)]
+    pub tier: String,
+    /// Consecutive months, 1 for new subscribers
+    #[serde(default = "default_months")]
+    pub months: u32,
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Set by the source, written to recordings but never read
+    #[serde(skip_deserializing)]
+    #[schemars(skip)]
+    pub source: String,
+}
+
+fn default_months() -> u32 {
+    1
+}
+
+/// Something the characters tell the desktop user about, e.g. a low battery or a reminder.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct NotificationEvent {
+    /// What happened, e.g. `battery_low`, picks the line in
+    /// [`crate::reaction::Reactions::notification_lines`]
+    #[serde(default = "default_notification_event")]
+    pub event: String,
+    pub title: String,
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Set by the source, written to recordings but never read
+    #[serde(skip_deserializing)]
+    #[schemars(skip)]
+    pub source: String,
+}
+
+fn default_notification_event() -> String {
+    "notification".to_string()
+}
+
+static NEXT_COMMENT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_comment_id() -> u64 {
+    NEXT_COMMENT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+impl CommentEvent {
+    pub fn new(
+        user: impl Into<String>,
+        text: impl Into<String>,
+        source: impl Into<String>,
+        priority: Priority,
+    ) -> Self {
+        Self {
+            id: next_comment_id(),
+            user: user.into(),
+            text: text.into(),
+            source: source.into(),
+            priority,
+            kind: CommentKind::Chat,
+            original: None,
+            display_name: None,
+            received_at: Instant::now(),
+        }
+    }
+
+    /// The display name if the comment passed the intake, the username otherwise.
+    pub fn name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.user)
+    }
+
+    /// A copy to be answered again, counted as a new comment.
+    pub fn requeued(&self) -> Self {
+        Self {
+            id: next_comment_id(),
+            received_at: Instant::now(),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_kind(mut self, kind: CommentKind) -> Self {
+        self.kind = kind;
+        self
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Deserialize,
+    serde::Serialize,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    #[default]
+    Normal,
+    /// The comment mentions the character
+    Mention,
+    /// Paid messages and operator injected comments
+    Superchat,
 }
 
 pub struct Bus {
@@ -45,5 +294,9 @@ impl Bus {
 }
 
 pub struct FrontendHandle {
+    pub in_tx: mpsc::Sender<InEvent>,
+    /// For the player to tell when the characters speak
+    pub ui_tx: broadcast::Sender<UiEvent>,
     pub ui_rx: broadcast::Receiver<UiEvent>,
+    pub metrics: Arc<Metrics>,
 }
@@ -0,0 +1,229 @@
+use std::{
+    borrow::Borrow,
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use image::{
+    Delay, Frame, ImageResult, RgbaImage,
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::FilterType,
+};
+
+use crate::config::{CaptureConfig, ClipConfig};
+
+/// Shorter frame delays are slowed down by most GIF viewers.
+const MIN_DELAY: Duration = Duration::from_millis(20);
+
+/// Place the characters' frames side by side, top aligned, like in the headless stream.
+pub fn compose<I: Borrow<RgbaImage>>(images: &[Option<I>]) -> RgbaImage {
+    let images: Vec<&RgbaImage> = images.iter().flatten().map(Borrow::borrow).collect();
+    let width = images.iter().map(|image| image.width()).sum();
+    let height = images.iter().map(|image| image.height()).max().unwrap_or(0);
+    let mut frame = RgbaImage::new(width, height);
+    let mut x = 0;
+    for image in images {
+        image::imageops::replace(&mut frame, image, x as i64, 0);
+        x += image.width();
+    }
+    frame
+}
+
+/// Keeps the shown frame for screenshots and, when clips are on, scaled down copies of the
+/// frames of the last seconds for saving a GIF of them.
+///
+/// Frames only change when something is rendered, so every frame is kept with the time it
+/// was first shown instead of at a fixed rate.
+pub struct Recorder {
+    dir: PathBuf,
+    clip: Option<ClipConfig>,
+    /// In full size
+    shown: Option<RgbaImage>,
+    /// The oldest frame may have been shown before the clip starts and is still shown at its start
+    frames: VecDeque<(Instant, RgbaImage)>,
+    /// Taken by `frames`
+    bytes: usize,
+}
+
+impl Recorder {
+    pub fn new(config: &CaptureConfig) -> Self {
+        Self {
+            dir: config.dir.clone(),
+            clip: config.clip.clone(),
+            shown: None,
+            frames: VecDeque::new(),
+            bytes: 0,
+        }
+    }
+
+    /// Remember a frame shown from `now` on, forgetting the ones before the clip.
+    pub fn record(&mut self, frame: RgbaImage, now: Instant) {
+        if let Some(clip) = &self.clip {
+            let small = scale_down(&frame, clip.width);
+            self.bytes += small.as_raw().len();
+            self.frames.push_back((now, small));
+            while self.frames.len() > 1
+                && (self.bytes > clip.max_bytes || self.frames[1].0 + clip.length <= now)
+            {
+                if let Some((_, frame)) = self.frames.pop_front() {
+                    self.bytes -= frame.as_raw().len();
+                }
+            }
+        }
+        self.shown = Some(frame);
+    }
+
+    /// Save the frame shown now as a png in the background.
+    pub fn screenshot(&self) {
+        let Some(frame) = &self.shown else {
+            log::warn!("Nothing was shown yet to take a screenshot of");
+            return;
+        };
+        let frame = frame.clone();
+        save_in_background(&self.dir, "png", move |path| frame.save(path));
+    }
+
+    /// Save the frames of the last seconds as a looping GIF in the background, fully
+    /// transparent pixels stay transparent.
+    pub fn save_clip(&self, now: Instant) {
+        let Some(clip) = &self.clip else {
+            log::warn!("Clips are off, set VTUBER_CAPTURE_CLIP to record them");
+            return;
+        };
+        let frames = clip_frames(&self.frames, clip.length, now);
+        if frames.is_empty() {
+            log::warn!("Nothing was shown yet to save a clip of");
+            return;
+        }
+        save_in_background(&self.dir, "gif", move |path| save_gif(path, frames));
+    }
+}
+
+/// Keeps the aspect ratio, smaller frames are kept as they are.
+fn scale_down(frame: &RgbaImage, width: u32) -> RgbaImage {
+    if frame.width() <= width {
+        return frame.clone();
+    }
+    let height = (frame.height() as u64 * width as u64 / frame.width() as u64).max(1) as u32;
+    image::imageops::resize(frame, width, height, FilterType::Triangle)
+}
+
+/// The frames shown in the `length` before `now`, each with how long it was shown.
+fn clip_frames(
+    frames: &VecDeque<(Instant, RgbaImage)>,
+    length: Duration,
+    now: Instant,
+) -> Vec<(RgbaImage, Duration)> {
+    let start = now.checked_sub(length);
+    let ends = frames
+        .iter()
+        .skip(1)
+        .map(|(shown_at, _)| *shown_at)
+        .chain([now]);
+    frames
+        .iter()
+        .zip(ends)
+        .filter_map(|((shown_at, frame), end)| {
+            let from = start.map_or(*shown_at, |start| (*shown_at).max(start));
+            (end > from).then(|| (frame.clone(), (end - from).max(MIN_DELAY)))
+        })
+        .collect()
+}
+
+fn save_gif(path: &Path, frames: Vec<(RgbaImage, Duration)>) -> ImageResult<()> {
+    let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+    encoder.set_repeat(Repeat::Infinite)?;
+    for (frame, shown) in frames {
+        encoder.encode_frame(Frame::from_parts(
+            frame,
+            0,
+            0,
+            Delay::from_saturating_duration(shown),
+        ))?;
+    }
+    Ok(())
+}
+
+/// A new file in `dir` named after the current time.
+fn capture_path(dir: &Path, extension: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let name = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+    Ok(dir.join(format!("{name}.{extension}")))
+}
+
+/// Encoding takes a while, don't hold up the window.
+fn save_in_background(
+    dir: &Path,
+    extension: &str,
+    save: impl FnOnce(&Path) -> ImageResult<()> + Send + 'static,
+) {
+    let path = match capture_path(dir, extension) {
+        Ok(path) => path,
+        Err(e) => {
+            log::error!("Failed to create the capture folder {}: {e}", dir.display());
+            return;
+        }
+    };
+    std::thread::Builder::new()
+        .name("capture".to_string())
+        .spawn(move || match save(&path) {
+            Ok(()) => log::info!("Saved capture {}", path.display()),
+            Err(e) => log::error!("Failed to save capture {}: {e}", path.display()),
+        })
+        .expect("failed to spawn the capture thread");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        time::{Duration, Instant},
+    };
+
+    use image::{Rgba, RgbaImage};
+
+    use crate::capture::{clip_frames, compose, scale_down};
+
+    #[test]
+    fn clip_the_last_seconds() {
+        let start = Instant::now();
+        let secs = Duration::from_secs;
+        let frame = |value| RgbaImage::from_pixel(1, 1, Rgba([value, 0, 0, 255]));
+        let frames = VecDeque::from([
+            (start, frame(0)),
+            (start + secs(4), frame(1)),
+            (start + secs(8), frame(2)),
+        ]);
+
+        let clip = clip_frames(&frames, secs(5), start + secs(10));
+        let shown: Vec<_> = clip
+            .iter()
+            .map(|(frame, shown)| (frame.get_pixel(0, 0)[0], *shown))
+            .collect();
+        // the first frame was only shown before the clip, the second one partly
+        assert_eq!(shown, [(1, secs(3)), (2, secs(2))]);
+    }
+
+    #[test]
+    fn scale_down_to_the_width() {
+        let frame = RgbaImage::new(1000, 500);
+        assert_eq!(scale_down(&frame, 480).dimensions(), (480, 240));
+        assert_eq!(scale_down(&frame, 2000).dimensions(), (1000, 500));
+    }
+
+    #[test]
+    fn compose_side_by_side() {
+        let frame = compose(&[
+            Some(RgbaImage::new(2, 3)),
+            None,
+            Some(RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 255]))),
+        ]);
+        assert_eq!(frame.dimensions(), (3, 3));
+        assert_eq!(frame.get_pixel(2, 0)[0], 255);
+        // transparent below the shorter character
+        assert_eq!(frame.get_pixel(2, 1)[3], 0);
+    }
+}
@@ -0,0 +1,141 @@
+use eframe::egui;
+
+use crate::theme::{self, PanelColors};
+
+/// Sent messages kept for recalling with the arrow keys.
+const MAX_HISTORY: usize = 50;
+
+/// Messages sent before, browsed like a shell history.
+#[derive(Default)]
+struct History {
+    entries: Vec<String>,
+    /// The recalled entry while browsing
+    cursor: Option<usize>,
+}
+
+impl History {
+    fn push(&mut self, message: String) {
+        if self.entries.last() != Some(&message) {
+            self.entries.push(message);
+        }
+        if self.entries.len() > MAX_HISTORY {
+            self.entries.remove(0);
+        }
+        self.cursor = None;
+    }
+
+    /// One entry back, stays at the oldest.
+    fn older(&mut self) -> Option<&str> {
+        let cursor = match self.cursor {
+            Some(cursor) => cursor.saturating_sub(1),
+            None => self.entries.len().checked_sub(1)?,
+        };
+        self.cursor = Some(cursor);
+        Some(&self.entries[cursor])
+    }
+
+    /// One entry forward, past the newest one is an empty input.
+    fn newer(&mut self) -> Option<&str> {
+        let cursor = self.cursor? + 1;
+        if cursor < self.entries.len() {
+            self.cursor = Some(cursor);
+            Some(&self.entries[cursor])
+        } else {
+            self.cursor = None;
+            Some("")
+        }
+    }
+}
+
+/// A text field to talk to the characters from the desktop, F4 toggles it.
+pub struct ChatInput {
+    open: bool,
+    text: String,
+    history: History,
+}
+
+impl ChatInput {
+    pub fn new(open: bool) -> Self {
+        Self {
+            open,
+            text: String::new(),
+            history: History::default(),
+        }
+    }
+
+    /// Draw the input at the bottom of the window, returns a message once sent with enter.
+    pub fn show(&mut self, ctx: &egui::Context, colors: &PanelColors) -> Option<String> {
+        if ctx.input(|i| i.key_pressed(egui::Key::F4)) {
+            self.open = !self.open;
+        }
+        if !self.open {
+            return None;
+        }
+
+        let mut sent = None;
+        egui::TopBottomPanel::bottom("chat_input")
+            .frame(
+                egui::Frame::default()
+                    .fill(theme::color(colors.background))
+                    .inner_margin(4.0),
+            )
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.text)
+                        .hint_text("Say something, ↑ for earlier messages")
+                        .desired_width(f32::INFINITY),
+                );
+                if response.has_focus() {
+                    let recalled = ui.input(|i| {
+                        if i.key_pressed(egui::Key::ArrowUp) {
+                            Some(true)
+                        } else if i.key_pressed(egui::Key::ArrowDown) {
+                            Some(false)
+                        } else {
+                            None
+                        }
+                    });
+                    let entry = match recalled {
+                        Some(true) => self.history.older(),
+                        Some(false) => self.history.newer(),
+                        None => None,
+                    };
+                    if let Some(entry) = entry {
+                        self.text = entry.to_string();
+                    }
+                }
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    let message = self.text.trim().to_string();
+                    self.text.clear();
+                    if !message.is_empty() {
+                        self.history.push(message.clone());
+                        sent = Some(message);
+                    }
+                    // keep typing
+                    response.request_focus();
+                }
+            });
+        sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chat_input::History;
+
+    #[test]
+    fn recall_history() {
+        let mut history = History::default();
+        assert_eq!(history.older(), None);
+        history.push("hello".to_string());
+        history.push("how are you".to_string());
+        history.push("how are you".to_string());
+
+        assert_eq!(history.older(), Some("how are you"));
+        assert_eq!(history.older(), Some("hello"));
+        assert_eq!(history.older(), Some("hello"));
+        assert_eq!(history.newer(), Some("how are you"));
+        assert_eq!(history.newer(), Some(""));
+        assert_eq!(history.newer(), None);
+    }
+}
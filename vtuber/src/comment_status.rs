@@ -0,0 +1,149 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use crate::bus::Priority;
+
+/// Comments older than this many are forgotten.
+const MAX_TRACKED: usize = 1000;
+
+/// What became of a comment sent to the HTTP or WebSocket API, see `/comments/status/{id}`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CommentStatus {
+    /// Sent to the pipeline, not moderated yet
+    Received,
+    /// Waiting for an answer
+    Queued {
+        /// 1 for the comment answered next
+        position: usize,
+    },
+    Answering,
+    Answered,
+    /// Skipped by the queue, e.g. rate limited or pushed out by newer comments
+    Dropped {
+        reason: String,
+    },
+    /// Rejected by moderation or the safety filter, the reason is only logged since anyone may
+    /// ask for any id
+    Filtered,
+}
+
+#[derive(Default)]
+struct Tracked {
+    statuses: HashMap<u64, CommentStatus>,
+    /// Oldest first, for forgetting them
+    order: VecDeque<u64>,
+    /// The queue as last seen by the pipeline, in answer order
+    waiting: Vec<(u64, Priority)>,
+}
+
+/// Follows the comments of API clients from the intake to the answer, by comment id.
+#[derive(Default)]
+pub struct CommentStatuses {
+    tracked: Mutex<Tracked>,
+}
+
+impl CommentStatuses {
+    /// Start following a comment, only these are updated by [`CommentStatuses::set`].
+    pub fn track(&self, id: u64) {
+        let mut tracked = self.tracked.lock().unwrap();
+        tracked.statuses.insert(id, CommentStatus::Received);
+        tracked.order.push_back(id);
+        while tracked.order.len() > MAX_TRACKED {
+            if let Some(oldest) = tracked.order.pop_front() {
+                tracked.statuses.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn set(&self, id: u64, status: CommentStatus) {
+        if let Some(tracked) = self.tracked.lock().unwrap().statuses.get_mut(&id) {
+            *tracked = status;
+        }
+    }
+
+    /// The comment made it into the queue, its position is looked up when asked for.
+    pub fn queued(&self, id: u64) {
+        self.set(id, CommentStatus::Queued { position: 0 });
+    }
+
+    /// Remember the order of the queue, see [`crate::queue::CommentQueue::waiting`].
+    pub fn set_waiting(&self, waiting: Vec<(u64, Priority)>) {
+        self.tracked.lock().unwrap().waiting = waiting;
+    }
+
+    /// Where a new comment of this priority would be queued, before moderation and the queue
+    /// had their say.
+    pub fn estimate_position(&self, priority: Priority) -> usize {
+        let tracked = self.tracked.lock().unwrap();
+        tracked
+            .waiting
+            .iter()
+            .filter(|(_, waiting)| *waiting >= priority)
+            .count()
+            + 1
+    }
+
+    pub fn get(&self, id: u64) -> Option<CommentStatus> {
+        let tracked = self.tracked.lock().unwrap();
+        let status = tracked.statuses.get(&id)?.clone();
+        Some(match status {
+            CommentStatus::Queued { .. } => {
+                match tracked
+                    .waiting
+                    .iter()
+                    .position(|(waiting, _)| *waiting == id)
+                {
+                    Some(index) => CommentStatus::Queued {
+                        position: index + 1,
+                    },
+                    // the queue drops the oldest comments when it is full
+                    None => CommentStatus::Dropped {
+                        reason: "overflow".to_string(),
+                    },
+                }
+            }
+            status => status,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::Priority,
+        comment_status::{CommentStatus, CommentStatuses},
+    };
+
+    #[test]
+    fn follow_a_comment() {
+        let statuses = CommentStatuses::default();
+        statuses.set_waiting(vec![(1, Priority::Superchat), (2, Priority::Normal)]);
+        assert_eq!(statuses.estimate_position(Priority::Normal), 3);
+        assert_eq!(statuses.estimate_position(Priority::Mention), 2);
+
+        statuses.track(3);
+        assert_eq!(statuses.get(3), Some(CommentStatus::Received));
+        // untracked comments are left alone
+        statuses.set(2, CommentStatus::Answered);
+        assert_eq!(statuses.get(2), None);
+
+        statuses.queued(3);
+        statuses.set_waiting(vec![(1, Priority::Superchat), (3, Priority::Mention)]);
+        assert_eq!(statuses.get(3), Some(CommentStatus::Queued { position: 2 }));
+
+        statuses.set_waiting(vec![(1, Priority::Superchat)]);
+        assert!(matches!(
+            statuses.get(3),
+            Some(CommentStatus::Dropped { .. })
+        ));
+
+        // cut off by a shutdown and put back in front
+        statuses.set(3, CommentStatus::Answering);
+        statuses.queued(3);
+        statuses.set_waiting(vec![(3, Priority::Mention), (1, Priority::Superchat)]);
+        assert_eq!(statuses.get(3), Some(CommentStatus::Queued { position: 1 }));
+    }
+}
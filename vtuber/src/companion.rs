@@ -0,0 +1,424 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Notify;
+
+use crate::config::PomodoroConfig;
+
+/// Source of the comments announcing pomodoro phases and reminders.
+pub const COMPANION_SOURCE: &str = "companion";
+
+#[derive(thiserror::Error, Debug)]
+pub enum CompanionError {
+    #[error("A pomodoro is already running")]
+    Running,
+    #[error("No pomodoro is running")]
+    NotRunning,
+    #[error("No such reminder")]
+    UnknownReminder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Work => "work",
+            Phase::ShortBreak => "short break",
+            Phase::LongBreak => "long break",
+        }
+    }
+}
+
+/// Snapshot of the running pomodoro for the frontends.
+#[derive(Debug, Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct PomodoroView {
+    pub phase: Phase,
+    /// Work phases finished since the pomodoro was started
+    pub completed: u32,
+    #[serde(skip)]
+    pub ends_at: Instant,
+    pub remaining_secs: u64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct ReminderView {
+    pub id: u64,
+    pub text: String,
+    pub remaining_secs: u64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct CompanionView {
+    pub pomodoro: Option<PomodoroView>,
+    /// Soonest first
+    pub reminders: Vec<ReminderView>,
+}
+
+/// Something the character tells the desktop user about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Announcement {
+    /// A phase of the pomodoro started
+    Phase {
+        phase: Phase,
+        length: Duration,
+        completed: u32,
+    },
+    Reminder(String),
+}
+
+impl Announcement {
+    /// Asks the AI to announce it in character, with an expression to match.
+    pub fn prompt(&self) -> String {
+        match self {
+            Announcement::Phase {
+                phase: Phase::Work,
+                length,
+                ..
+            } => format!(
+                "【番茄钟】现在开始专注 {} 分钟, 请用认真的表情鼓励我专心工作。",
+                minutes(*length)
+            ),
+            Announcement::Phase {
+                phase: Phase::ShortBreak,
+                length,
+                ..
+            } => format!(
+                "【番茄钟】专注时间结束了, 休息 {} 分钟, 请用轻松的表情提醒我站起来活动一下。",
+                minutes(*length)
+            ),
+            Announcement::Phase {
+                phase: Phase::LongBreak,
+                length,
+                completed,
+            } => format!(
+                "【番茄钟】已经完成了 {completed} 个番茄钟, 休息 {} 分钟, 请用开心的表情夸奖我并让我好好休息。",
+                minutes(*length)
+            ),
+            Announcement::Reminder(text) => {
+                format!("【提醒】时间到了, 请用合适的表情提醒我: {text}")
+            }
+        }
+    }
+}
+
+fn minutes(length: Duration) -> u64 {
+    (length.as_secs() + 30) / 60
+}
+
+struct Pomodoro {
+    phase: Phase,
+    completed: u32,
+    ends_at: Instant,
+}
+
+impl Pomodoro {
+    fn view(&self, now: Instant) -> PomodoroView {
+        PomodoroView {
+            phase: self.phase,
+            completed: self.completed,
+            ends_at: self.ends_at,
+            remaining_secs: self.ends_at.saturating_duration_since(now).as_secs(),
+        }
+    }
+}
+
+struct Reminder {
+    id: u64,
+    text: String,
+    due: Instant,
+}
+
+#[derive(Default)]
+struct State {
+    pomodoro: Option<Pomodoro>,
+    reminders: Vec<Reminder>,
+    next_id: u64,
+    /// Announced right away, e.g. the first work phase
+    pending: Vec<Announcement>,
+}
+
+/// Pomodoro cycles and reminders for the desktop user, started from the window, a hotkey or the
+/// API and announced by the character.
+#[derive(Default)]
+pub struct Companion {
+    config: PomodoroConfig,
+    state: Mutex<State>,
+    /// Wakes the intake to check the deadlines again
+    changed: Notify,
+}
+
+impl Companion {
+    pub fn new(config: PomodoroConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::default(),
+            changed: Notify::new(),
+        }
+    }
+
+    fn length(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Work => self.config.work,
+            Phase::ShortBreak => self.config.short_break,
+            Phase::LongBreak => self.config.long_break,
+        }
+    }
+
+    pub fn start_pomodoro(&self, now: Instant) -> Result<PomodoroView, CompanionError> {
+        let mut state = self.state.lock().unwrap();
+        if state.pomodoro.is_some() {
+            return Err(CompanionError::Running);
+        }
+
+        let length = self.length(Phase::Work);
+        let pomodoro = Pomodoro {
+            phase: Phase::Work,
+            completed: 0,
+            ends_at: now + length,
+        };
+        let view = pomodoro.view(now);
+        state.pomodoro = Some(pomodoro);
+        state.pending.push(Announcement::Phase {
+            phase: Phase::Work,
+            length,
+            completed: 0,
+        });
+        self.changed.notify_one();
+        Ok(view)
+    }
+
+    pub fn stop_pomodoro(&self) -> Result<(), CompanionError> {
+        self.state
+            .lock()
+            .unwrap()
+            .pomodoro
+            .take()
+            .ok_or(CompanionError::NotRunning)?;
+        self.changed.notify_one();
+        Ok(())
+    }
+
+    /// Start the pomodoro, or stop it if it is running. Returns the started one.
+    pub fn toggle_pomodoro(&self, now: Instant) -> Option<PomodoroView> {
+        match self.stop_pomodoro() {
+            Ok(()) => None,
+            Err(_) => self.start_pomodoro(now).ok(),
+        }
+    }
+
+    pub fn pomodoro(&self, now: Instant) -> Option<PomodoroView> {
+        self.state
+            .lock()
+            .unwrap()
+            .pomodoro
+            .as_ref()
+            .map(|pomodoro| pomodoro.view(now))
+    }
+
+    pub fn add_reminder(&self, text: String, after: Duration, now: Instant) -> ReminderView {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let reminder = Reminder {
+            id: state.next_id,
+            text,
+            due: now + after,
+        };
+        let view = ReminderView {
+            id: reminder.id,
+            text: reminder.text.clone(),
+            remaining_secs: after.as_secs(),
+        };
+        state.reminders.push(reminder);
+        state.reminders.sort_by_key(|reminder| reminder.due);
+        self.changed.notify_one();
+        view
+    }
+
+    pub fn cancel_reminder(&self, id: u64) -> Result<(), CompanionError> {
+        let mut state = self.state.lock().unwrap();
+        let index = state
+            .reminders
+            .iter()
+            .position(|reminder| reminder.id == id)
+            .ok_or(CompanionError::UnknownReminder)?;
+        state.reminders.remove(index);
+        self.changed.notify_one();
+        Ok(())
+    }
+
+    pub fn view(&self, now: Instant) -> CompanionView {
+        let state = self.state.lock().unwrap();
+        CompanionView {
+            pomodoro: state.pomodoro.as_ref().map(|pomodoro| pomodoro.view(now)),
+            reminders: state
+                .reminders
+                .iter()
+                .map(|reminder| ReminderView {
+                    id: reminder.id,
+                    text: reminder.text.clone(),
+                    remaining_secs: reminder.due.saturating_duration_since(now).as_secs(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Move the pomodoro on and take the reminders that are due, in the order to announce them.
+    pub fn take_due(&self, now: Instant) -> Vec<Announcement> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        let mut due = std::mem::take(&mut state.pending);
+        if let Some(pomodoro) = &mut state.pomodoro
+            && pomodoro.ends_at <= now
+        {
+            let phase = match pomodoro.phase {
+                Phase::Work => {
+                    pomodoro.completed += 1;
+                    if pomodoro.completed % self.config.long_break_every.max(1) == 0 {
+                        Phase::LongBreak
+                    } else {
+                        Phase::ShortBreak
+                    }
+                }
+                Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+            };
+            let length = self.length(phase);
+            // counted from now, a computer waking from sleep doesn't catch up on missed phases
+            pomodoro.phase = phase;
+            pomodoro.ends_at = now + length;
+            due.push(Announcement::Phase {
+                phase,
+                length,
+                completed: pomodoro.completed,
+            });
+        }
+        let ready = state
+            .reminders
+            .iter()
+            .take_while(|reminder| reminder.due <= now)
+            .count();
+        due.extend(
+            state
+                .reminders
+                .drain(..ready)
+                .map(|reminder| Announcement::Reminder(reminder.text)),
+        );
+        due
+    }
+
+    /// Resolves when something may be due or the pomodoro or reminders changed.
+    pub async fn wait(&self) {
+        let next = {
+            let state = self.state.lock().unwrap();
+            if !state.pending.is_empty() {
+                return;
+            }
+            state
+                .pomodoro
+                .iter()
+                .map(|pomodoro| pomodoro.ends_at)
+                .chain(state.reminders.first().map(|reminder| reminder.due))
+                .min()
+        };
+        match next {
+            Some(next) => tokio::select! {
+                _ = tokio::time::sleep_until(next.into()) => {}
+                _ = self.changed.notified() => {}
+            },
+            None => self.changed.notified().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        companion::{Announcement, Companion, CompanionError, Phase},
+        config::PomodoroConfig,
+    };
+
+    fn companion() -> Companion {
+        Companion::new(PomodoroConfig {
+            work: Duration::from_secs(25 * 60),
+            short_break: Duration::from_secs(5 * 60),
+            long_break: Duration::from_secs(15 * 60),
+            long_break_every: 2,
+        })
+    }
+
+    #[test]
+    fn cycle_through_the_phases() {
+        let companion = companion();
+        let start = Instant::now();
+        companion.start_pomodoro(start).unwrap();
+        assert!(matches!(
+            companion.start_pomodoro(start),
+            Err(CompanionError::Running)
+        ));
+        assert_eq!(
+            companion.take_due(start),
+            vec![Announcement::Phase {
+                phase: Phase::Work,
+                length: Duration::from_secs(25 * 60),
+                completed: 0
+            }]
+        );
+        assert!(companion.take_due(start).is_empty());
+
+        let mut now = start;
+        let mut phases = Vec::new();
+        for _ in 0..4 {
+            now = companion.pomodoro(now).unwrap().ends_at;
+            for announcement in companion.take_due(now) {
+                if let Announcement::Phase { phase, .. } = announcement {
+                    phases.push(phase);
+                }
+            }
+        }
+        assert_eq!(
+            phases,
+            [
+                Phase::ShortBreak,
+                Phase::Work,
+                Phase::LongBreak,
+                Phase::Work
+            ]
+        );
+        assert_eq!(companion.pomodoro(now).unwrap().completed, 2);
+
+        assert!(companion.toggle_pomodoro(now).is_none());
+        assert!(companion.pomodoro(now).is_none());
+    }
+
+    #[test]
+    fn remind_in_order() {
+        let companion = companion();
+        let now = Instant::now();
+        companion.add_reminder("drink water".to_string(), Duration::from_secs(60), now);
+        let stretch = companion.add_reminder("stretch".to_string(), Duration::from_secs(30), now);
+        let call = companion.add_reminder("call mom".to_string(), Duration::from_secs(90), now);
+        companion.cancel_reminder(call.id).unwrap();
+        assert!(companion.cancel_reminder(call.id).is_err());
+        assert_eq!(companion.view(now).reminders[0].id, stretch.id);
+
+        assert!(companion.take_due(now).is_empty());
+        assert_eq!(
+            companion.take_due(now + Duration::from_secs(60)),
+            vec![
+                Announcement::Reminder("stretch".to_string()),
+                Announcement::Reminder("drink water".to_string())
+            ]
+        );
+        assert!(companion.view(now).reminders.is_empty());
+    }
+}
@@ -1,103 +1,1472 @@
 use std::{
+    collections::{BTreeMap, HashMap},
     fs::{self, File},
     io::Read,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
 };
 
 use ai::Dataset;
+use bytes::Bytes;
+use config::{
+    ServicesConfig, VtuberAiConfig, VtuberCharacterConfig, VtuberRenderConfig,
+    WindowPlacementConfig, get_list,
+};
+use global_hotkey::hotkey::HotKey;
 use layer_composer::Model;
 
-use crate::utils::get_env;
+use crate::{
+    bus::{ControlCommand, Priority},
+    effects::VoiceEffect,
+    instance,
+    obs::ObsRule,
+    plugin::command::CommandConfig,
+    reaction::Reactions,
+    safety::SafetyAction,
+    scaling::FitMode,
+    soundboard::SoundboardConfig,
+    source::scheduler::ScheduleEntry,
+    subtitle::SubtitleFormat,
+    subtitle_style::SubtitleStyle,
+    theme::Theme,
+    utils::{get_env, get_secret, read_list},
+};
 
 pub struct AppConfig {
     pub tts: TtsConfig,
-    pub ai: AiConfig,
-    pub render: RenderConfig,
+    pub ai: VtuberAiConfig,
+    /// Looking up the time, weather and computer while answering
+    pub tools: Option<ToolsConfig>,
+    pub audio: AudioConfig,
+    /// The main character from the env first, then the ones from `VTUBER_CHARACTERS`
+    pub characters: Vec<CharacterConfig>,
+    /// How often the characters answer each other after a comment
+    pub dialogue_turns: usize,
     pub server: ServerConfig,
+    pub subtitle: Option<SubtitleConfig>,
+    /// The line being said is written here as plain text, for OBS text sources and screen readers
+    pub caption_file: Option<PathBuf>,
+    pub twitch: Option<TwitchConfig>,
+    pub moderation: ModerationConfig,
+    /// Checking what the characters say before it is spoken, see [`crate::safety`]
+    pub safety: Option<SafetyConfig>,
+    pub queue: QueueConfig,
+    pub obs: Option<ObsConfig>,
+    pub storage: Option<StorageConfig>,
+    pub hotkeys: Option<HotkeyConfig>,
+    pub headless: Option<HeadlessConfig>,
+    pub viewers: Option<ViewersConfig>,
+    pub schedule: Vec<ScheduleEntry>,
+    /// External programs hooked into the pipeline
+    pub plugins: Vec<CommandConfig>,
+    pub reactions: Reactions,
+    pub preempt: PreemptConfig,
+    /// Reply lines are saved here until spoken and resumed after a restart
+    pub reply_journal: Option<PathBuf>,
+    pub pacing: PacingConfig,
+    pub poll: PollConfig,
+    pub names: NameConfig,
+    pub stt: Option<SttConfig>,
+    pub soundboard: Option<SoundboardConfig>,
+    pub translation: Option<TranslationConfig>,
+    /// Following the topic of the chat, steering it back to the planned ones
+    pub topics: Option<TopicsConfig>,
+    /// Show pipeline metrics in the window, toggled with F3
+    pub debug_overlay: bool,
+    pub idle: IdleConfig,
+    pub window: WindowConfig,
+    pub gui: GuiConfig,
+    pub touch: TouchConfig,
+    /// Walking along the bottom of the screen, the window stays where it is if unset
+    pub walk: Option<WalkConfig>,
+    pub capture: CaptureConfig,
+    pub read_aloud: ReadAloudConfig,
+    pub pomodoro: PomodoroConfig,
+    /// Telling the desktop user when the battery runs low, only on Linux
+    pub battery: Option<BatteryConfig>,
+    /// Every incoming event is written here, see [`crate::replay`]
+    pub event_recording: Option<PathBuf>,
+    /// Comment log or event recording replayed with the mock LLM, see [`AppConfig::simulate`]
+    pub simulation: Option<PathBuf>,
+    /// Only one app listens here, starting another one raises its window instead
+    pub instance_address: Option<SocketAddr>,
 }
 
 impl AppConfig {
     pub fn from_env() -> anyhow::Result<Self> {
+        let ai = VtuberAiConfig::from_env()?;
+        let services = ServicesConfig::from_env()?;
         Ok(Self {
-            tts: TtsConfig::from_env()?,
-            ai: AiConfig::from_env()?,
-            render: RenderConfig::from_env()?,
-            server: ServerConfig::from_env()?,
+            tts: TtsConfig::new(&services)?,
+            audio: AudioConfig::from_env()?,
+            translation: TranslationConfig::from_env(&ai),
+            topics: TopicsConfig::from_env(&ai)?,
+            ai,
+            tools: ToolsConfig::from_env()?,
+            characters: CharacterConfig::load_all()?,
+            dialogue_turns: match get_env("VTUBER_CHARACTERS_DIALOGUE_TURNS") {
+                Ok(value) => value.parse()?,
+                Err(_) => 0,
+            },
+            server: ServerConfig::new(&services)?,
+            subtitle: SubtitleConfig::from_env(),
+            caption_file: get_env("VTUBER_CAPTION_FILE").ok().map(PathBuf::from),
+            twitch: TwitchConfig::from_env()?,
+            moderation: ModerationConfig::from_env()?,
+            safety: SafetyConfig::from_env()?,
+            queue: QueueConfig::from_env()?,
+            obs: ObsConfig::from_env()?,
+            storage: StorageConfig::from_env(),
+            hotkeys: HotkeyConfig::from_env()?,
+            headless: HeadlessConfig::from_env()?,
+            viewers: ViewersConfig::from_env(),
+            plugins: match get_env("VTUBER_PLUGINS") {
+                Ok(path) => serde_json::from_reader(File::open(fs::canonicalize(path)?)?)?,
+                Err(_) => Vec::new(),
+            },
+            instance_address: match get_env("VTUBER_INSTANCE_ADDRESS") {
+                Ok(addr) if addr == "off" => None,
+                Ok(addr) => Some(addr.parse()?),
+                Err(_) => Some(instance::DEFAULT_ADDRESS),
+            },
+            schedule: match get_env("VTUBER_SCHEDULE") {
+                Ok(path) => serde_json::from_reader(File::open(fs::canonicalize(path)?)?)?,
+                Err(_) => Vec::new(),
+            },
+            reactions: match get_env("VTUBER_REACTIONS") {
+                Ok(path) => serde_json::from_reader(File::open(fs::canonicalize(path)?)?)?,
+                Err(_) => Reactions::default(),
+            },
+            preempt: PreemptConfig::from_env()?,
+            reply_journal: get_env("VTUBER_REPLY_JOURNAL").ok().map(PathBuf::from),
+            pacing: PacingConfig::from_env()?,
+            poll: PollConfig::from_env()?,
+            names: NameConfig::from_env()?,
+            stt: SttConfig::from_env()?,
+            soundboard: match get_env("VTUBER_SOUNDBOARD") {
+                Ok(path) => Some(serde_json::from_reader(File::open(fs::canonicalize(
+                    path,
+                )?)?)?),
+                Err(_) => None,
+            },
+            debug_overlay: match get_env("VTUBER_DEBUG_OVERLAY") {
+                Ok(value) => value.parse()?,
+                Err(_) => false,
+            },
+            idle: IdleConfig::from_env()?,
+            window: WindowConfig::from_env()?,
+            gui: GuiConfig::from_env()?,
+            touch: TouchConfig::from_env()?,
+            walk: WalkConfig::from_env()?,
+            capture: CaptureConfig::from_env()?,
+            read_aloud: ReadAloudConfig::from_env()?,
+            pomodoro: PomodoroConfig::from_env()?,
+            battery: BatteryConfig::from_env()?,
+            event_recording: get_env("VTUBER_RECORD_EVENTS").ok().map(PathBuf::from),
+            simulation: None,
         })
     }
+
+    /// Replay a comment log instead of going live: answers come from the mock LLM and nothing
+    /// else calls a paid API. Live inputs are turned off and the GUI is shown.
+    pub fn simulate(&mut self, log: PathBuf) {
+        self.simulation = Some(log);
+        // the replayed events would be recorded once more
+        self.event_recording = None;
+        self.twitch = None;
+        self.stt = None;
+        self.battery = None;
+        self.translation = None;
+        self.topics = None;
+        self.moderation.classifier_model = None;
+        if let Some(safety) = &mut self.safety {
+            safety.classifier_model = None;
+        }
+        self.headless = None;
+    }
 }
 
+#[derive(Clone)]
 pub struct ServerConfig {
     pub addr: String,
+    /// Origins of browser widgets allowed to call the server, `*` for any
+    pub cors_origins: Vec<String>,
+    /// Reverse proxies whose `X-Forwarded-For` is believed
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Prefix of every route, e.g. `/vtuber` behind a proxy passing the path on as is
+    pub base_path: String,
+    /// Needed for the operator routes, which are disabled without it
+    pub token: Option<String>,
+    /// Comments each client may send a minute, by the address behind the trusted proxies
+    pub rate_limit: Option<u32>,
 }
 
 impl ServerConfig {
-    pub fn from_env() -> anyhow::Result<Self> {
+    pub fn new(services: &ServicesConfig) -> anyhow::Result<Self> {
+        let trusted_proxies = get_list("VTUBER_SERVER_TRUSTED_PROXIES")
+            .iter()
+            .map(|addr| addr.parse())
+            .collect::<Result<_, _>>()?;
+        let base_path = get_env("VTUBER_SERVER_BASE_PATH").unwrap_or_default();
         Ok(Self {
-            addr: get_env("VTUBER_SERVER_ADDRESS")?,
+            addr: services.vtuber_address.clone(),
+            cors_origins: get_list("VTUBER_SERVER_CORS_ORIGINS"),
+            trusted_proxies,
+            base_path: normalize_base_path(&base_path),
+            token: get_secret("VTUBER_SERVER_TOKEN").ok(),
+            rate_limit: match get_env("VTUBER_SERVER_RATE_LIMIT").as_deref() {
+                Ok("off") => None,
+                Ok(value) => Some(value.parse()?),
+                Err(_) => Some(30),
+            },
         })
     }
 }
 
+/// Where logs and crash reports are written, `VTUBER_DATA_DIR` or the platform's data directory.
+pub fn data_dir() -> PathBuf {
+    match get_env("VTUBER_DATA_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => dirs::data_local_dir()
+            .map(|dir| dir.join("murasame"))
+            .unwrap_or_else(|| PathBuf::from("data")),
+    }
+}
+
+/// `vtuber/` and `/vtuber` both become `/vtuber`, `/` none.
+fn normalize_base_path(path: &str) -> String {
+    let path = path.trim().trim_matches('/');
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!("/{path}")
+    }
+}
+
 pub struct TtsConfig {
     pub base_url: String,
+    /// Speech rate, 1.0 is the natural speed
+    pub speed: Option<f32>,
+    /// Played instead of the voice when synthesis fails
+    pub fallback_audio: Bytes,
+    /// Synthesized voices are kept here and reused for the same line
+    pub cache: Option<PathBuf>,
 }
 
 impl TtsConfig {
-    pub fn from_env() -> anyhow::Result<Self> {
+    pub fn new(services: &ServicesConfig) -> anyhow::Result<Self> {
+        let fallback_audio = match get_env("VTUBER_TTS_FALLBACK_AUDIO") {
+            Ok(path) => Bytes::from(fs::read(fs::canonicalize(path)?)?),
+            Err(_) => tts_client::fallback::beep(),
+        };
+
         Ok(Self {
-            base_url: get_env("VTUBER_TTS_API_BASE_URL")?,
+            base_url: services.tts_url.clone(),
+            speed: tts_speed(&env_var)?,
+            fallback_audio,
+            cache: get_env("VTUBER_TTS_CACHE").ok().map(PathBuf::from),
         })
     }
 }
 
-pub struct AiConfig {
-    pub model: String,
-    pub api_key: String,
-    pub thinking: bool,
+fn tts_speed(var: Vars) -> anyhow::Result<Option<f32>> {
+    match var("VTUBER_TTS_SPEED") {
+        Some(value) => Ok(Some(value.parse()?)),
+        None => Ok(None),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ToolsConfig {
+    /// Weather API, `{location}` is substituted and the answer is handed to the AI
+    pub weather_url: String,
+    /// Looked up when the AI asks for no place, empty makes it name one
+    pub weather_location: String,
+}
+
+impl ToolsConfig {
+    /// The characters only get tools when `VTUBER_AI_TOOLS` is true, each message takes another
+    /// request then.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let enabled = match get_env("VTUBER_AI_TOOLS") {
+            Ok(value) => value.parse()?,
+            Err(_) => false,
+        };
+        if !enabled {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            weather_url: get_env("VTUBER_WEATHER_URL")
+                .unwrap_or_else(|_| "https://wttr.in/{location}?format=%C+%t+%h+%w".to_string()),
+            weather_location: get_env("VTUBER_WEATHER_LOCATION").unwrap_or_default(),
+        }))
+    }
+}
+
+/// A character on stream with its own persona, voice and model.
+#[derive(Clone, Debug)]
+pub struct CharacterConfig {
+    pub name: String,
+    pub user_title: Option<String>,
     pub dataset: Dataset,
     pub system_instruction_template: String,
+    pub voice: Option<String>,
+    pub render: RenderConfig,
+}
 
-    pub character_name: String,
-    pub user_title: Option<String>,
+/// Entry of the `VTUBER_CHARACTERS` file, paths are relative to the working directory.
+#[derive(serde::Deserialize)]
+struct CharacterEntry {
+    name: String,
+    #[serde(default)]
+    user_title: Option<String>,
+    dataset: PathBuf,
+    /// The main character's template if unset
+    #[serde(default)]
+    system_instruction_template: Option<PathBuf>,
+    #[serde(default)]
+    voice: Option<String>,
+    model: PathBuf,
+    base_layer: String,
+    #[serde(default)]
+    mouth_layers: Vec<String>,
+    #[serde(default)]
+    blink_layers: Vec<String>,
+    #[serde(default)]
+    default_layers: Vec<String>,
+    #[serde(default)]
+    rest_layers: Vec<String>,
+    #[serde(default)]
+    listening_layers: Vec<String>,
+    #[serde(default)]
+    walk_left_layers: Vec<String>,
+    #[serde(default)]
+    walk_right_layers: Vec<String>,
 }
 
-impl AiConfig {
+impl CharacterConfig {
+    /// The main character, configured by `VTUBER_AI_*`, `VTUBER_TTS_VOICE` and `VTUBER_RENDER_*`.
     pub fn from_env() -> anyhow::Result<Self> {
-        let dataset_path = fs::canonicalize(get_env("VTUBER_AI_DATASET")?)?;
+        let env = VtuberCharacterConfig::from_env()?;
+        let dataset_path = fs::canonicalize(&env.dataset)?;
         let dataset = Dataset::from_reader(&mut File::open(dataset_path)?, false)?;
 
-        let system_instruction_template_path =
-            fs::canonicalize(get_env("VTUBER_AI_SYSTEM_INSTRUCTION_TEMPLATE")?)?;
-        let mut system_instruction_template = String::new();
-        // read system instruction template
-        File::open(&system_instruction_template_path)?
-            .read_to_string(&mut system_instruction_template)?;
-
         Ok(Self {
-            model: get_env("VTUBER_AI_MODEL")?,
-            thinking: get_env("VTUBER_AI_THINKING")?.parse()?,
-            api_key: get_env("GEMINI_API_KEY")?,
-            character_name: get_env("VTUBER_AI_CHARACTER_NAME")?,
-            user_title: get_env("VTUBER_AI_USER_TITLE").ok(),
+            name: env.name,
+            user_title: env.user_title,
             dataset,
-            system_instruction_template,
+            system_instruction_template: fs::read_to_string(fs::canonicalize(
+                &env.system_instruction_template,
+            )?)?,
+            voice: env.voice,
+            render: RenderConfig::from_env()?,
         })
     }
+
+    /// The main character followed by the ones listed in `VTUBER_CHARACTERS`.
+    pub fn load_all() -> anyhow::Result<Vec<Self>> {
+        let main = Self::from_env()?;
+        let entries = character_entries(&env_var)?;
+
+        let mut characters = Vec::with_capacity(1 + entries.len());
+        for entry in entries {
+            let dataset = Dataset::from_reader(&mut File::open(&entry.dataset)?, false)?;
+            let system_instruction_template = match &entry.system_instruction_template {
+                Some(path) => fs::read_to_string(path)?,
+                None => main.system_instruction_template.clone(),
+            };
+            characters.push(Self {
+                name: entry.name,
+                user_title: entry.user_title,
+                dataset,
+                system_instruction_template,
+                voice: entry.voice,
+                render: RenderConfig {
+                    model: Model::from_reader(File::open(&entry.model)?)?,
+                    base_layer: entry.base_layer,
+                    mouth_layers: entry.mouth_layers,
+                    blink_layers: entry.blink_layers,
+                    default_layers: entry.default_layers,
+                    rest_layers: entry.rest_layers,
+                    listening_layers: entry.listening_layers,
+                    walk_left_layers: entry.walk_left_layers,
+                    walk_right_layers: entry.walk_right_layers,
+                },
+            });
+        }
+        characters.insert(0, main);
+        Ok(characters)
+    }
+}
+
+/// Where the settings that can change while running are read from, the environment at startup
+/// and the reloaded `.env` file later, see [`crate::reload`].
+pub type Vars<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// The variable from the environment, for [`Vars`].
+fn env_var(name: &str) -> Option<String> {
+    get_env(name).ok()
+}
+
+fn required_var(var: Vars, name: &str) -> anyhow::Result<String> {
+    var(name).ok_or_else(|| config::ConfigError::Missing(name.to_string()).into())
+}
+
+fn character_entries(var: Vars) -> anyhow::Result<Vec<CharacterEntry>> {
+    Ok(match var("VTUBER_CHARACTERS") {
+        Some(path) => serde_json::from_reader(File::open(fs::canonicalize(path)?)?)?,
+        None => Vec::new(),
+    })
+}
+
+/// The template files of the characters listed in `VTUBER_CHARACTERS` that have their own.
+pub fn character_template_paths(var: Vars) -> Vec<PathBuf> {
+    character_entries(var)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|entry| entry.system_instruction_template)
+        .collect()
+}
+
+fn read_system_instruction_template(var: Vars) -> anyhow::Result<String> {
+    let system_instruction_template_path =
+        fs::canonicalize(required_var(var, "VTUBER_AI_SYSTEM_INSTRUCTION_TEMPLATE")?)?;
+    let mut system_instruction_template = String::new();
+    File::open(&system_instruction_template_path)?
+        .read_to_string(&mut system_instruction_template)?;
+    Ok(system_instruction_template)
+}
+
+/// The templates of the main character and of the ones in `VTUBER_CHARACTERS`.
+fn read_system_instruction_templates(var: Vars) -> anyhow::Result<Vec<String>> {
+    let main = read_system_instruction_template(var)?;
+    let mut templates = vec![main.clone()];
+    for entry in character_entries(var)? {
+        templates.push(match &entry.system_instruction_template {
+            Some(path) => fs::read_to_string(path)?,
+            None => main.clone(),
+        });
+    }
+    Ok(templates)
 }
 
 #[derive(Clone, Debug)]
 pub struct RenderConfig {
     pub model: Model,
     pub base_layer: String,
+    /// Switched while speaking by the loudness of the voice, from closed to open
+    pub mouth_layers: Vec<String>,
+    /// Shown one after another for a blink, e.g. half closed then closed eyes
+    pub blink_layers: Vec<String>,
+    /// The expression shown before the first reply and whenever nothing is left to say
+    pub default_layers: Vec<String>,
+    /// The expression settled into after a while of silence, see [`IdleConfig::rest_after`],
+    /// the default layers if empty
+    pub rest_layers: Vec<String>,
+    /// Shown while the microphone is recorded for push-to-talk or after the wake word
+    pub listening_layers: Vec<String>,
+    /// Shown while the window walks to the left or right, see [`WalkConfig`]
+    pub walk_left_layers: Vec<String>,
+    pub walk_right_layers: Vec<String>,
 }
 
 impl RenderConfig {
     pub fn from_env() -> anyhow::Result<Self> {
-        let model_path = fs::canonicalize(get_env("VTUBER_RENDER_MODEL")?)?;
-        let model = Model::from_reader(File::open(model_path)?)?;
+        let env = VtuberRenderConfig::from_env()?;
+        let model = Model::from_reader(File::open(fs::canonicalize(&env.model)?)?)?;
         Ok(Self {
             model,
-            base_layer: get_env("VTUBER_RENDER_BASE_LAYER")?,
+            base_layer: env.base_layer,
+            mouth_layers: env.mouth_layers,
+            blink_layers: env.blink_layers,
+            default_layers: env.default_layers,
+            rest_layers: env.rest_layers,
+            listening_layers: env.listening_layers,
+            walk_left_layers: env.walk_left_layers,
+            walk_right_layers: env.walk_right_layers,
+        })
+    }
+    /// The base layer followed by the given layers, in render order.
+    pub fn with_base_layer(&self, layers: &[String]) -> Vec<String> {
+        let mut layers_to_render = Vec::with_capacity(1 + layers.len());
+        layers_to_render.push(self.base_layer.clone());
+        layers_to_render.extend_from_slice(layers);
+        layers_to_render
+    }
+}
+
+pub struct SubtitleConfig {
+    pub path: PathBuf,
+    pub format: SubtitleFormat,
+}
+
+impl SubtitleConfig {
+    /// Subtitles are only written when `VTUBER_SUBTITLE_FILE` is set.
+    pub fn from_env() -> Option<Self> {
+        let path = PathBuf::from(get_env("VTUBER_SUBTITLE_FILE").ok()?);
+        let format = SubtitleFormat::from_path(&path);
+        Some(Self { path, format })
+    }
+}
+
+#[derive(Clone)]
+pub struct TwitchConfig {
+    pub channel: String,
+    pub nick: String,
+    pub oauth_token: Option<String>,
+}
+
+impl TwitchConfig {
+    /// Twitch chat is only read when `VTUBER_TWITCH_CHANNEL` is set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(channel) = get_env("VTUBER_TWITCH_CHANNEL") else {
+            return Ok(None);
+        };
+        let oauth_token = get_secret("VTUBER_TWITCH_OAUTH_TOKEN").ok();
+        let nick = match oauth_token {
+            Some(_) => get_env("VTUBER_TWITCH_NICK")?,
+            None => String::new(),
+        };
+
+        Ok(Some(Self {
+            channel,
+            nick,
+            oauth_token,
+        }))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ModerationConfig {
+    pub blocklist: Vec<String>,
+    pub patterns: Vec<String>,
+    pub banned_users: Vec<String>,
+    /// LLM used to classify comments, disabled if unset
+    pub classifier_model: Option<String>,
+    pub log: Option<PathBuf>,
+}
+
+impl ModerationConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::from_vars(&env_var)
+    }
+
+    pub fn from_vars(var: Vars) -> anyhow::Result<Self> {
+        let list = |name: &str| -> anyhow::Result<Vec<String>> {
+            match var(name) {
+                Some(path) => read_list(path),
+                None => Ok(Vec::new()),
+            }
+        };
+
+        Ok(Self {
+            blocklist: list("VTUBER_MODERATION_BLOCKLIST")?,
+            patterns: list("VTUBER_MODERATION_PATTERNS")?,
+            banned_users: list("VTUBER_MODERATION_BANNED_USERS")?,
+            classifier_model: var("VTUBER_MODERATION_MODEL"),
+            log: var("VTUBER_MODERATION_LOG").map(PathBuf::from),
         })
     }
 }
+
+#[derive(Clone, Debug)]
+pub struct SafetyConfig {
+    pub blocklist: Vec<String>,
+    pub patterns: Vec<String>,
+    /// LLM used to classify responses, disabled if unset
+    pub classifier_model: Option<String>,
+    pub action: SafetyAction,
+}
+
+impl SafetyConfig {
+    /// Responses are only checked when a blocklist, patterns or a model is set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let blocklist = get_env("VTUBER_SAFETY_BLOCKLIST").ok();
+        let patterns = get_env("VTUBER_SAFETY_PATTERNS").ok();
+        let classifier_model = get_env("VTUBER_SAFETY_MODEL").ok();
+        if blocklist.is_none() && patterns.is_none() && classifier_model.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            blocklist: blocklist.map(read_list).transpose()?.unwrap_or_default(),
+            patterns: patterns.map(read_list).transpose()?.unwrap_or_default(),
+            classifier_model,
+            action: match get_env("VTUBER_SAFETY_ACTION") {
+                Ok(value) => serde_json::from_value(serde_json::Value::String(value))?,
+                Err(_) => SafetyAction::default(),
+            },
+        }))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct QueueConfig {
+    /// Comments waiting for an answer, the oldest ones are dropped first
+    pub max_depth: usize,
+    /// Minimum time between two answered comments of the same user
+    pub user_interval: Duration,
+    /// Identical comments within this window are ignored
+    pub dedupe_window: Duration,
+    /// Chance that an ordinary comment is answered, mentions and paid ones always are
+    pub answer_probability: f32,
+    /// Comments containing one of these are always answered, like the ones naming a character
+    pub triggers: Vec<String>,
+    /// Ordinary comments are ignored for this long after one was let through
+    pub reply_interval: Duration,
+    /// Unanswered comments are saved here on shutdown and answered after the next start
+    pub state_file: Option<PathBuf>,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 20,
+            user_interval: Duration::from_secs(10),
+            dedupe_window: Duration::from_secs(60),
+            answer_probability: 1.0,
+            triggers: Vec::new(),
+            reply_interval: Duration::ZERO,
+            state_file: None,
+        }
+    }
+}
+
+/// Seconds read from `name`, the negative and overflowing ones are refused rather than panicking
+/// in `Duration::from_secs_f32`.
+fn parse_secs(name: &str, value: &str) -> anyhow::Result<Duration> {
+    Duration::try_from_secs_f32(value.parse()?)
+        .map_err(|_| anyhow::anyhow!("{name} must be zero or more seconds, got {value}"))
+}
+
+impl QueueConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+        let secs = |name: &str, default: Duration| -> anyhow::Result<Duration> {
+            match get_env(name) {
+                Ok(value) => parse_secs(name, &value),
+                Err(_) => Ok(default),
+            }
+        };
+
+        let answer_probability = match get_env("VTUBER_QUEUE_ANSWER_PROBABILITY") {
+            Ok(value) => value.parse()?,
+            Err(_) => default.answer_probability,
+        };
+        anyhow::ensure!(
+            (0.0..=1.0).contains(&answer_probability),
+            "VTUBER_QUEUE_ANSWER_PROBABILITY must be between 0 and 1"
+        );
+
+        Ok(Self {
+            max_depth: match get_env("VTUBER_QUEUE_MAX_DEPTH") {
+                Ok(value) => value.parse()?,
+                Err(_) => default.max_depth,
+            },
+            user_interval: secs("VTUBER_QUEUE_USER_INTERVAL", default.user_interval)?,
+            dedupe_window: secs("VTUBER_QUEUE_DEDUPE_WINDOW", default.dedupe_window)?,
+            answer_probability,
+            triggers: get_list("VTUBER_QUEUE_TRIGGERS"),
+            reply_interval: secs("VTUBER_QUEUE_REPLY_INTERVAL", default.reply_interval)?,
+            state_file: get_env("VTUBER_QUEUE_STATE_FILE").ok().map(PathBuf::from),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct ObsConfig {
+    pub address: String,
+    pub password: Option<String>,
+    pub rules: Vec<ObsRule>,
+}
+
+impl ObsConfig {
+    /// OBS is only controlled when `VTUBER_OBS_ADDRESS` is set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(address) = get_env("VTUBER_OBS_ADDRESS") else {
+            return Ok(None);
+        };
+        let rules = match get_env("VTUBER_OBS_RULES") {
+            Ok(path) => serde_json::from_reader(File::open(fs::canonicalize(path)?)?)?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Some(Self {
+            address,
+            password: get_secret("VTUBER_OBS_PASSWORD").ok(),
+            rules,
+        }))
+    }
+}
+
+pub struct StorageConfig {
+    pub database: PathBuf,
+    /// The session report is written here when the stream ends
+    pub report_dir: Option<PathBuf>,
+}
+
+impl StorageConfig {
+    /// Transcripts are only recorded when `VTUBER_STORAGE_DATABASE` is set.
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            database: PathBuf::from(get_env("VTUBER_STORAGE_DATABASE").ok()?),
+            report_dir: get_env("VTUBER_STORAGE_REPORT_DIR").ok().map(PathBuf::from),
+        })
+    }
+}
+
+pub struct HotkeyConfig {
+    pub bindings: Vec<(HotKey, ControlCommand)>,
+}
+
+impl HotkeyConfig {
+    /// Hotkeys are only registered when at least one binding is set, e.g. `ctrl+alt+KeyS`.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let mut bindings = Vec::new();
+        for (name, command) in [
+            ("VTUBER_HOTKEY_SKIP", ControlCommand::Skip),
+            ("VTUBER_HOTKEY_REGENERATE", ControlCommand::Regenerate),
+            ("VTUBER_HOTKEY_MUTE", ControlCommand::ToggleMute),
+            ("VTUBER_HOTKEY_PAUSE", ControlCommand::TogglePause),
+            ("VTUBER_HOTKEY_NEUTRAL", ControlCommand::ToggleNeutral),
+            ("VTUBER_HOTKEY_NEXT_MONITOR", ControlCommand::NextMonitor),
+            ("VTUBER_HOTKEY_SCREENSHOT", ControlCommand::Screenshot),
+            ("VTUBER_HOTKEY_CLIP", ControlCommand::Clip),
+            (
+                "VTUBER_HOTKEY_READ_SELECTION",
+                ControlCommand::ReadSelection,
+            ),
+            ("VTUBER_HOTKEY_POMODORO", ControlCommand::TogglePomodoro),
+            ("VTUBER_HOTKEY_TALK", ControlCommand::StartListening),
+        ] {
+            if let Ok(binding) = get_env(name) {
+                let hotkey = binding
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("Bad hotkey in {name}: {e}"))?;
+                bindings.push((hotkey, command));
+            }
+        }
+
+        Ok((!bindings.is_empty()).then_some(Self { bindings }))
+    }
+}
+
+pub struct HeadlessConfig {
+    /// Program receiving raw RGBA frames on stdin, `{width}`, `{height}` and `{fps}` are substituted
+    pub command: Vec<String>,
+    pub fps: u32,
+}
+
+impl HeadlessConfig {
+    /// The window is replaced by the frame sink when `VTUBER_HEADLESS_COMMAND` is set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(command) = get_env("VTUBER_HEADLESS_COMMAND") else {
+            return Ok(None);
+        };
+        // quoted like a shell command, so arguments can contain spaces
+        let command = shlex::split(&command)
+            .ok_or_else(|| anyhow::anyhow!("VTUBER_HEADLESS_COMMAND has an unclosed quote"))?;
+        if command.is_empty() {
+            anyhow::bail!("VTUBER_HEADLESS_COMMAND is empty");
+        }
+
+        Ok(Some(Self {
+            command,
+            fps: match get_env("VTUBER_HEADLESS_FPS") {
+                Ok(value) => value.parse()?,
+                Err(_) => 30,
+            },
+        }))
+    }
+}
+
+pub struct ViewersConfig {
+    pub path: PathBuf,
+}
+
+impl ViewersConfig {
+    /// Viewer profiles are only kept when `VTUBER_VIEWERS_FILE` is set.
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            path: PathBuf::from(get_env("VTUBER_VIEWERS_FILE").ok()?),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PreemptConfig {
+    /// Replies of this priority or higher jump the playback queue, never if unset
+    pub priority: Option<Priority>,
+    /// Fade the interrupted line out instead of finishing the sentence
+    pub fade_out: Option<Duration>,
+}
+
+impl PreemptConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let priority = match get_env("VTUBER_PREEMPT_PRIORITY") {
+            Ok(value) if value == "off" => None,
+            Ok(value) => Some(serde_json::from_value(serde_json::Value::String(value))?),
+            Err(_) => None,
+        };
+        let fade_out = match get_env("VTUBER_PREEMPT_FADE_OUT") {
+            Ok(value) => Some(parse_secs("VTUBER_PREEMPT_FADE_OUT", &value)?),
+            Err(_) => None,
+        };
+
+        Ok(Self { priority, fade_out })
+    }
+}
+
+const DEFAULT_TERSE_PROMPT: &str = "【简短】现在评论很多, 请只用一句话简短地回答。";
+
+/// Limits on how much the character says per comment.
+#[derive(Clone, Debug)]
+pub struct PacingConfig {
+    /// Lines of a reply beyond this are dropped
+    pub max_sentences: Option<usize>,
+    /// Speaking time per comment, the first line is always spoken
+    pub max_speech: Option<Duration>,
+    /// Answer briefly while at least this many comments are waiting
+    pub terse_queue_length: Option<usize>,
+    /// Appended to the prompt in terse mode
+    pub terse_prompt: String,
+}
+
+impl PacingConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            max_sentences: match get_env("VTUBER_PACING_MAX_SENTENCES") {
+                Ok(value) => Some(value.parse()?),
+                Err(_) => None,
+            },
+            max_speech: match get_env("VTUBER_PACING_MAX_SECONDS") {
+                Ok(value) => Some(parse_secs("VTUBER_PACING_MAX_SECONDS", &value)?),
+                Err(_) => None,
+            },
+            terse_queue_length: match get_env("VTUBER_PACING_TERSE_QUEUE_LENGTH") {
+                Ok(value) => Some(value.parse()?),
+                Err(_) => None,
+            },
+            terse_prompt: get_env("VTUBER_PACING_TERSE_PROMPT")
+                .unwrap_or_else(|_| DEFAULT_TERSE_PROMPT.to_string()),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GuiConfig {
+    pub subtitle: SubtitleStyle,
+    /// How many recent comments are kept
+    pub comment_count: usize,
+    /// Show the recent comments next to the characters at startup, F2 toggles it
+    pub chat_panel: bool,
+    pub fit: FitMode,
+    /// Size of the characters relative to the fitted size
+    pub zoom: f32,
+    /// Frame rate while nothing but breathing moves, the window redraws right away on events
+    pub idle_fps: u32,
+    /// How long a new expression fades in over the last one, zero cuts right away
+    pub crossfade: Duration,
+    /// Show the text field for talking to the characters at startup, F4 toggles it
+    pub text_input: bool,
+    /// Who the characters hear typing
+    pub user_name: String,
+    /// Show the transcript of the spoken lines at startup, F5 toggles it
+    pub transcript: bool,
+    /// A preset or a theme file, see [`Theme::load`]
+    pub theme_name: String,
+    pub theme: Theme,
+}
+
+impl GuiConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut subtitle: SubtitleStyle = match get_env("VTUBER_GUI_SUBTITLE_STYLE") {
+            Ok(path) => serde_json::from_reader(File::open(fs::canonicalize(path)?)?)?,
+            Err(_) => SubtitleStyle::default(),
+        };
+        let theme_name = get_env("VTUBER_GUI_THEME").ok();
+        let theme = match &theme_name {
+            Some(name) => {
+                let theme = Theme::load(name)?;
+                theme.apply(&mut subtitle);
+                theme
+            }
+            None => Theme::default(),
+        };
+        // set by the settings window, so they win over the style file
+        if let Ok(value) = get_env("VTUBER_GUI_FONT_SIZE") {
+            subtitle.font_size = value.parse()?;
+        }
+        if let Ok(value) = get_env("VTUBER_GUI_TEXT_COLOR") {
+            subtitle.color = parse_color(&value)?;
+        }
+        if let Ok(value) = get_env("VTUBER_GUI_OVERLAY_OPACITY") {
+            subtitle.background.opacity = value.parse()?;
+        }
+
+        Ok(Self {
+            subtitle,
+            comment_count: match get_env("VTUBER_GUI_COMMENT_COUNT") {
+                Ok(value) => value.parse()?,
+                Err(_) => 50,
+            },
+            chat_panel: match get_env("VTUBER_GUI_CHAT_PANEL") {
+                Ok(value) => value.parse()?,
+                Err(_) => false,
+            },
+            fit: match get_env("VTUBER_GUI_FIT") {
+                Ok(value) => serde_json::from_value(serde_json::Value::String(value))?,
+                Err(_) => FitMode::default(),
+            },
+            zoom: match get_env("VTUBER_GUI_ZOOM") {
+                Ok(value) => value.parse()?,
+                Err(_) => 1.0,
+            },
+            idle_fps: match get_env("VTUBER_GUI_IDLE_FPS") {
+                Ok(value) => value.parse()?,
+                Err(_) => 15,
+            },
+            crossfade: match get_env("VTUBER_GUI_CROSSFADE") {
+                Ok(value) => parse_secs("VTUBER_GUI_CROSSFADE", &value)?,
+                Err(_) => Duration::from_millis(150),
+            },
+            text_input: match get_env("VTUBER_GUI_TEXT_INPUT") {
+                Ok(value) => value.parse()?,
+                Err(_) => true,
+            },
+            user_name: get_env("VTUBER_GUI_USER_NAME").unwrap_or_else(|_| "host".to_string()),
+            transcript: match get_env("VTUBER_GUI_TRANSCRIPT") {
+                Ok(value) => value.parse()?,
+                Err(_) => false,
+            },
+            theme_name: theme_name.unwrap_or_else(|| "dark".to_string()),
+            theme,
+        })
+    }
+}
+
+/// Parse a `#rrggbb` color.
+fn parse_color(value: &str) -> anyhow::Result<[u8; 3]> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if hex.len() != 6 || !hex.is_ascii() {
+        anyhow::bail!("Bad color {value}, expected #rrggbb");
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    pub always_on_top: bool,
+    /// Show the title bar and borders
+    pub decorations: bool,
+    /// The screen it opens on, snapping to the screen edges and the saved position
+    pub placement: WindowPlacementConfig,
+}
+
+impl WindowConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            always_on_top: match get_env("VTUBER_WINDOW_ALWAYS_ON_TOP") {
+                Ok(value) => value.parse()?,
+                Err(_) => true,
+            },
+            decorations: match get_env("VTUBER_WINDOW_DECORATIONS") {
+                Ok(value) => value.parse()?,
+                Err(_) => false,
+            },
+            placement: WindowPlacementConfig::from_env("VTUBER")?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IdleConfig {
+    /// Average time between blinks
+    pub blink_interval: Duration,
+    /// How much the characters grow and shrink while breathing, 0 to keep them still
+    pub breathing: f32,
+    /// How far the characters sway to the side, as a part of their width
+    pub sway: f32,
+    /// How far the characters turn towards the mouse cursor, 0 to look ahead
+    pub follow: f32,
+    /// How long the expression of a line stays after it was spoken
+    pub expression_hold: Duration,
+    /// Silence after which the characters settle into their rest layers, never if unset
+    pub rest_after: Option<Duration>,
+}
+
+impl IdleConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            blink_interval: match get_env("VTUBER_IDLE_BLINK_INTERVAL") {
+                Ok(value) => parse_secs("VTUBER_IDLE_BLINK_INTERVAL", &value)?,
+                Err(_) => Duration::from_secs(4),
+            },
+            breathing: match get_env("VTUBER_IDLE_BREATHING") {
+                Ok(value) => value.parse()?,
+                Err(_) => 0.006,
+            },
+            sway: match get_env("VTUBER_IDLE_SWAY") {
+                Ok(value) => value.parse()?,
+                Err(_) => 0.004,
+            },
+            follow: match get_env("VTUBER_IDLE_FOLLOW") {
+                Ok(value) => value.parse()?,
+                Err(_) => 0.0,
+            },
+            expression_hold: match get_env("VTUBER_IDLE_EXPRESSION_HOLD") {
+                Ok(value) => parse_secs("VTUBER_IDLE_EXPRESSION_HOLD", &value)?,
+                Err(_) => Duration::ZERO,
+            },
+            rest_after: match get_env("VTUBER_IDLE_REST_AFTER") {
+                Ok(value) => Some(parse_secs("VTUBER_IDLE_REST_AFTER", &value)?),
+                Err(_) => None,
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AudioConfig {
+    /// Output device name, the default output if unset
+    pub device: Option<String>,
+    pub voice_volume: f32,
+    /// Effects the AI may put on a line, by name, see [`crate::effects`]
+    pub effects: BTreeMap<String, VoiceEffect>,
+}
+
+impl AudioConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            device: get_env("VTUBER_AUDIO_DEVICE")
+                .ok()
+                .filter(|name| !name.is_empty()),
+            voice_volume: match get_env("VTUBER_AUDIO_VOICE_VOLUME") {
+                Ok(value) => value.parse()?,
+                Err(_) => 1.0,
+            },
+            effects: match get_env("VTUBER_AUDIO_EFFECTS") {
+                Ok(path) => serde_json::from_reader(File::open(fs::canonicalize(path)?)?)?,
+                Err(_) => BTreeMap::new(),
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NameConfig {
+    /// Longer names are cut, in characters
+    pub max_length: usize,
+    /// Shown for names that are empty after cleaning or hit the moderation rules
+    pub fallback: String,
+    /// Display names by lowercase username, used as they are
+    pub overrides: HashMap<String, String>,
+}
+
+impl NameConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            max_length: match get_env("VTUBER_NAMES_MAX_LENGTH") {
+                Ok(value) => value.parse()?,
+                Err(_) => 20,
+            },
+            fallback: get_env("VTUBER_NAMES_FALLBACK").unwrap_or_else(|_| "观众".to_string()),
+            overrides: match get_env("VTUBER_NAMES_OVERRIDES") {
+                Ok(path) => {
+                    let overrides: HashMap<String, String> =
+                        serde_json::from_reader(File::open(fs::canonicalize(path)?)?)?;
+                    overrides
+                        .into_iter()
+                        .map(|(user, name)| (user.to_lowercase(), name))
+                        .collect()
+                }
+                Err(_) => HashMap::new(),
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WalkConfig {
+    /// Average time standing between walks
+    pub interval: Duration,
+    /// Pixels per second
+    pub speed: f32,
+}
+
+impl WalkConfig {
+    /// The window only walks when `VTUBER_WALK` is true.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let walk = match get_env("VTUBER_WALK") {
+            Ok(value) => value.parse()?,
+            Err(_) => false,
+        };
+        if !walk {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            interval: match get_env("VTUBER_WALK_INTERVAL") {
+                Ok(value) => parse_secs("VTUBER_WALK_INTERVAL", &value)?,
+                Err(_) => Duration::from_secs(120),
+            },
+            speed: match get_env("VTUBER_WALK_SPEED") {
+                Ok(value) => value.parse()?,
+                Err(_) => 40.0,
+            },
+        }))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TouchConfig {
+    /// An area only reacts again after this long
+    pub cooldown: Duration,
+    /// Let the character answer touches of areas with a prompt
+    pub ai: bool,
+}
+
+impl TouchConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            cooldown: match get_env("VTUBER_TOUCH_COOLDOWN") {
+                Ok(value) => parse_secs("VTUBER_TOUCH_COOLDOWN", &value)?,
+                Err(_) => Duration::from_secs(10),
+            },
+            ai: match get_env("VTUBER_TOUCH_AI") {
+                Ok(value) => value.parse()?,
+                Err(_) => true,
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CaptureConfig {
+    /// Screenshots and clips are saved here
+    pub dir: PathBuf,
+    /// Keeping the frames for clips, `None` only allows screenshots
+    pub clip: Option<ClipConfig>,
+}
+
+impl CaptureConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            dir: match get_env("VTUBER_CAPTURE_DIR") {
+                Ok(path) => PathBuf::from(path),
+                Err(_) => PathBuf::from("./captures"),
+            },
+            clip: ClipConfig::from_env()?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ClipConfig {
+    /// How far back a clip goes
+    pub length: Duration,
+    /// Frames are scaled down to this width for the clip
+    pub width: u32,
+    /// Older frames are forgotten once the kept ones take more memory, a clip is shorter then
+    pub max_bytes: usize,
+}
+
+impl ClipConfig {
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(length) = get_env("VTUBER_CAPTURE_CLIP") else {
+            return Ok(None);
+        };
+        let length = parse_secs("VTUBER_CAPTURE_CLIP", &length)?;
+        anyhow::ensure!(
+            !length.is_zero(),
+            "VTUBER_CAPTURE_CLIP must be longer than 0 seconds"
+        );
+        Ok(Some(Self {
+            length,
+            width: match get_env("VTUBER_CAPTURE_CLIP_WIDTH") {
+                Ok(value) => value.parse()?,
+                Err(_) => 480,
+            },
+            max_bytes: match get_env("VTUBER_CAPTURE_CLIP_MEMORY") {
+                Ok(value) => value.parse::<usize>()? * 1024 * 1024,
+                Err(_) => 64 * 1024 * 1024,
+            },
+        }))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReadAloudConfig {
+    /// Asks the character about the selected text, `{text}` is substituted
+    pub prompt: String,
+    /// Longer selections are cut off
+    pub max_chars: usize,
+}
+
+impl ReadAloudConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            prompt: get_env("VTUBER_READ_ALOUD_PROMPT").unwrap_or_else(|_| {
+                "【朗读】我选中了下面这段文字, 请用你的语气念给我听, 太长的话就概括一下, 再说说你的看法:\n{text}"
+                    .to_string()
+            }),
+            max_chars: match get_env("VTUBER_READ_ALOUD_MAX_CHARS") {
+                Ok(value) => value.parse()?,
+                Err(_) => 1000,
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PomodoroConfig {
+    pub work: Duration,
+    pub short_break: Duration,
+    pub long_break: Duration,
+    /// Every this many work phases the break is a long one
+    pub long_break_every: u32,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work: Duration::from_secs(25 * 60),
+            short_break: Duration::from_secs(5 * 60),
+            long_break: Duration::from_secs(15 * 60),
+            long_break_every: 4,
+        }
+    }
+}
+
+impl PomodoroConfig {
+    /// Lengths are given in minutes.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+        let minutes = |name: &str, default: Duration| -> anyhow::Result<Duration> {
+            Ok(match get_env(name) {
+                Ok(value) => {
+                    Duration::try_from_secs_f32(value.parse::<f32>()? * 60.0).map_err(|_| {
+                        anyhow::anyhow!("{name} must be zero or more minutes, got {value}")
+                    })?
+                }
+                Err(_) => default,
+            })
+        };
+        Ok(Self {
+            work: minutes("VTUBER_POMODORO_WORK", default.work)?,
+            short_break: minutes("VTUBER_POMODORO_SHORT_BREAK", default.short_break)?,
+            long_break: minutes("VTUBER_POMODORO_LONG_BREAK", default.long_break)?,
+            long_break_every: match get_env("VTUBER_POMODORO_LONG_BREAK_EVERY") {
+                Ok(value) => value.parse()?,
+                Err(_) => default.long_break_every,
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BatteryConfig {
+    /// Percent left to warn at
+    pub low: u8,
+    /// How often the battery level is read
+    pub interval: Duration,
+}
+
+impl BatteryConfig {
+    /// The battery is only watched when `VTUBER_BATTERY_LOW` is set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(low) = get_env("VTUBER_BATTERY_LOW") else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            low: low.parse()?,
+            interval: match get_env("VTUBER_BATTERY_INTERVAL") {
+                Ok(value) => parse_secs("VTUBER_BATTERY_INTERVAL", &value)?,
+                Err(_) => Duration::from_secs(60),
+            },
+        }))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PollConfig {
+    /// How long polls started by the AI run, the operator picks per poll
+    pub duration: Duration,
+    /// Let the AI start polls on its own
+    pub ai: bool,
+}
+
+impl PollConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            duration: match get_env("VTUBER_POLL_DURATION") {
+                Ok(value) => parse_secs("VTUBER_POLL_DURATION", &value)?,
+                Err(_) => Duration::from_secs(60),
+            },
+            ai: match get_env("VTUBER_POLL_AI") {
+                Ok(value) => value.parse()?,
+                Err(_) => false,
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SttConfig {
+    /// Transcription endpoint taking a multipart `file`, e.g. whisper.cpp's `/inference` or an
+    /// OpenAI compatible `/v1/audio/transcriptions`
+    pub url: String,
+    pub api_key: Option<String>,
+    pub model: Option<String>,
+    pub language: Option<String>,
+    /// Input device name, the default microphone if unset
+    pub device: Option<String>,
+    /// Who the character hears talking
+    pub host_name: String,
+    /// RMS level above which the microphone counts as speech
+    pub threshold: f32,
+    /// Silence that ends an utterance
+    pub pause: Duration,
+    pub listen: Listen,
+}
+
+/// When the microphone is transcribed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Listen {
+    /// Everything the host says
+    Always,
+    /// While the push-to-talk hotkey is held
+    PushToTalk,
+    /// What is said after the wake word, or right after it was said alone
+    WakeWord(String),
+}
+
+impl Listen {
+    fn from_env() -> anyhow::Result<Self> {
+        Ok(match get_env("VTUBER_STT_MODE").as_deref() {
+            Err(_) | Ok("always") => Listen::Always,
+            Ok("push_to_talk") => Listen::PushToTalk,
+            Ok("wake_word") => Listen::WakeWord(get_env("VTUBER_STT_WAKE_WORD").map_err(|_| {
+                anyhow::anyhow!("VTUBER_STT_WAKE_WORD is needed to listen for a wake word")
+            })?),
+            Ok(mode) => anyhow::bail!("Unknown VTUBER_STT_MODE {mode}"),
+        })
+    }
+}
+
+impl SttConfig {
+    /// The microphone is only transcribed when `VTUBER_STT_URL` is set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(url) = get_env("VTUBER_STT_URL") else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            url,
+            api_key: get_secret("VTUBER_STT_API_KEY").ok(),
+            model: get_env("VTUBER_STT_MODEL").ok(),
+            language: get_env("VTUBER_STT_LANGUAGE").ok(),
+            device: get_env("VTUBER_STT_DEVICE").ok(),
+            host_name: get_env("VTUBER_STT_HOST_NAME").unwrap_or_else(|_| "host".to_string()),
+            threshold: match get_env("VTUBER_STT_THRESHOLD") {
+                Ok(value) => value.parse()?,
+                Err(_) => 0.02,
+            },
+            pause: match get_env("VTUBER_STT_PAUSE") {
+                Ok(value) => parse_secs("VTUBER_STT_PAUSE", &value)?,
+                Err(_) => Duration::from_millis(800),
+            },
+            listen: Listen::from_env()?,
+        }))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TranslationConfig {
+    /// The character's working language, e.g. "简体中文"
+    pub language: String,
+    pub model: String,
+}
+
+impl TranslationConfig {
+    /// Comments are only translated when `VTUBER_TRANSLATION_LANGUAGE` is set.
+    pub fn from_env(ai: &VtuberAiConfig) -> Option<Self> {
+        let language = get_env("VTUBER_TRANSLATION_LANGUAGE").ok()?;
+        Some(Self {
+            language,
+            model: get_env("VTUBER_TRANSLATION_MODEL").unwrap_or_else(|_| ai.model.clone()),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TopicsConfig {
+    /// Topics planned for the stream, in order
+    pub plan: Vec<String>,
+    /// The topic is summarized after this many answers
+    pub every: usize,
+    pub model: String,
+}
+
+impl TopicsConfig {
+    /// Topics are only tracked when `VTUBER_TOPICS_EVERY` or `VTUBER_TOPICS_PLAN` is set.
+    pub fn from_env(ai: &VtuberAiConfig) -> anyhow::Result<Option<Self>> {
+        let every = get_env("VTUBER_TOPICS_EVERY").ok();
+        let plan = get_env("VTUBER_TOPICS_PLAN").ok();
+        if every.is_none() && plan.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            plan: match plan {
+                Some(path) => read_list(path)?,
+                None => Vec::new(),
+            },
+            every: match every {
+                Some(value) => value.parse()?,
+                None => 5,
+            },
+            model: get_env("VTUBER_TOPICS_MODEL").unwrap_or_else(|_| ai.model.clone()),
+        }))
+    }
+}
+
+/// The settings applied on the fly when the config changes, see [`crate::reload`].
+///
+/// The template and base layer belong to the main character.
+#[derive(Clone, Debug)]
+pub struct LiveConfig {
+    /// One per character, in the order of [`AppConfig::characters`]
+    pub system_instruction_templates: Vec<String>,
+    pub base_layer: String,
+    pub tts_speed: Option<f32>,
+    pub moderation: ModerationConfig,
+}
+
+impl LiveConfig {
+    pub fn from_vars(var: Vars) -> anyhow::Result<Self> {
+        Ok(Self {
+            system_instruction_templates: read_system_instruction_templates(var)?,
+            base_layer: required_var(var, "VTUBER_RENDER_BASE_LAYER")?,
+            tts_speed: tts_speed(var)?,
+            moderation: ModerationConfig::from_vars(var)?,
+        })
+    }
+
+    /// The live settings as they were at startup.
+    pub fn from_app_config(config: &AppConfig) -> Self {
+        Self {
+            system_instruction_templates: config
+                .characters
+                .iter()
+                .map(|character| character.system_instruction_template.clone())
+                .collect(),
+            base_layer: config.characters[0].render.base_layer.clone(),
+            tts_speed: config.tts.speed,
+            moderation: config.moderation.clone(),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    fmt::Write as _,
+    fs,
+    panic::{self, PanicHookInfo},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use tokio::sync::broadcast;
+
+use crate::{bus::UiEvent, handler::events::OverlayEvent};
+
+/// Bus events kept for the next crash report.
+const RECENT_EVENTS: usize = 50;
+/// Environment variables that make up the configuration.
+const CONFIG_PREFIXES: &[&str] = &["VTUBER_", "TTS_", "GPTSOVITS_", "GEMINI_", "FRONTEND_"];
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Write a report into `dir` for every panic, after the default hook printed it.
+pub fn install(dir: PathBuf) {
+    let config = config_summary(std::env::vars());
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        // the panic may have happened while recording
+        let events: Vec<String> = match RECENT.try_lock() {
+            Ok(recent) => recent.iter().cloned().collect(),
+            Err(_) => Vec::new(),
+        };
+        let report = report(info, &Backtrace::force_capture(), &events, &config);
+        match write_report(&dir, &report) {
+            Ok(path) => log::error!("Wrote a crash report to {}", path.display()),
+            Err(e) => log::error!("Failed to write a crash report: {e}"),
+        }
+    }));
+}
+
+/// Remember the last events on the bus for the crash reports.
+pub fn spawn_event_recorder(mut ui_rx: broadcast::Receiver<UiEvent>) {
+    tokio::spawn(async move {
+        loop {
+            let event = match ui_rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            let Ok(line) = serde_json::to_string(&OverlayEvent::from_ui_event(&event, false))
+            else {
+                continue;
+            };
+            let mut recent = RECENT.lock().unwrap();
+            if recent.len() == RECENT_EVENTS {
+                recent.pop_front();
+            }
+            recent.push_back(format!(
+                "{} {line}",
+                chrono::Local::now().format("%H:%M:%S%.3f")
+            ));
+        }
+    });
+}
+
+fn write_report(dir: &Path, report: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let name = chrono::Local::now().format("crash-%Y%m%d-%H%M%S%.3f.txt");
+    let path = dir.join(name.to_string());
+    // secrets from the keyring can turn up in the panic message or the other variables
+    fs::write(&path, config::redact(report).as_bytes())?;
+    Ok(path)
+}
+
+/// The configuration as `NAME=value` lines, secrets redacted.
+fn config_summary(vars: impl Iterator<Item = (String, String)>) -> Vec<String> {
+    let mut summary: Vec<String> = vars
+        .filter(|(name, _)| {
+            CONFIG_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        })
+        .map(|(name, value)| {
+            if config::SECRETS.contains(&name.as_str()) {
+                format!("{name}=<redacted>")
+            } else {
+                format!("{name}={value}")
+            }
+        })
+        .collect();
+    summary.sort();
+    summary
+}
+
+fn report(
+    info: &PanicHookInfo<'_>,
+    backtrace: &Backtrace,
+    events: &[String],
+    config: &[String],
+) -> String {
+    let thread = std::thread::current();
+    let mut report = format!(
+        "vtuber {} crashed at {}\n\nthread '{}' panicked",
+        env!("CARGO_PKG_VERSION"),
+        chrono::Local::now().to_rfc3339(),
+        thread.name().unwrap_or("<unnamed>"),
+    );
+    if let Some(location) = info.location() {
+        let _ = write!(report, " at {location}");
+    }
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<no message>");
+    let _ = write!(report, ":\n{message}\n\nBacktrace:\n{backtrace}\n");
+
+    let _ = writeln!(report, "\nLast {} events:", events.len());
+    for event in events {
+        let _ = writeln!(report, "{event}");
+    }
+    let _ = writeln!(report, "\nConfiguration:");
+    for line in config {
+        let _ = writeln!(report, "{line}");
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crash::config_summary;
+
+    #[test]
+    fn redact_secrets() {
+        let vars = [
+            ("VTUBER_TWITCH_OAUTH_TOKEN", "oauth:abc"),
+            ("PATH", "/usr/bin"),
+            ("VTUBER_AI_MODEL", "gemini-2.5-flash"),
+            ("GEMINI_API_KEY", "secret"),
+            ("VTUBER_HOTKEY_SKIP", "F9"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        assert_eq!(
+            config_summary(vars.into_iter()),
+            [
+                "GEMINI_API_KEY=<redacted>",
+                "VTUBER_AI_MODEL=gemini-2.5-flash",
+                "VTUBER_HOTKEY_SKIP=F9",
+                "VTUBER_TWITCH_OAUTH_TOKEN=<redacted>",
+            ]
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+
+/// Fades a character from its last frame into the next one when the expression changes,
+/// blinks and the moving mouth are cut as they are.
+pub struct CrossFade {
+    duration: Duration,
+    /// Layers of the last expression, without the blink and the mouth
+    layers: Vec<String>,
+    /// The mouth layers, left out of the expression
+    mouth_layers: Vec<String>,
+    /// The next frame shows a new expression
+    pending: bool,
+    started_at: Option<Instant>,
+}
+
+impl CrossFade {
+    pub fn new(duration: Duration, layers: Vec<String>, mouth_layers: Vec<String>) -> Self {
+        Self {
+            duration,
+            layers,
+            mouth_layers,
+            pending: false,
+            started_at: None,
+        }
+    }
+
+    /// Note the layers rendered next, the frame fades in if they differ from the last ones.
+    pub fn expression(&mut self, layers: &[String]) {
+        let expression = layers
+            .iter()
+            .filter(|layer| !self.mouth_layers.contains(layer));
+        if !self.layers.iter().eq(expression.clone()) {
+            self.layers = expression.cloned().collect();
+            self.pending = !self.duration.is_zero();
+        }
+    }
+
+    /// Start fading if the new frame shows a new expression, returns whether it does.
+    pub fn start(&mut self, now: Instant) -> bool {
+        if !std::mem::take(&mut self.pending) {
+            return false;
+        }
+        self.started_at = Some(now);
+        true
+    }
+
+    /// How far the new frame has faded in, nothing once it is done.
+    pub fn progress(&mut self, now: Instant) -> Option<f32> {
+        let started_at = self.started_at?;
+        let progress =
+            now.saturating_duration_since(started_at).as_secs_f32() / self.duration.as_secs_f32();
+        if progress >= 1.0 {
+            self.started_at = None;
+            return None;
+        }
+        Some(progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::crossfade::CrossFade;
+
+    fn layers(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn fade_new_expressions() {
+        let now = Instant::now();
+        let mut fade = CrossFade::new(
+            Duration::from_millis(200),
+            layers(&["smile"]),
+            layers(&["mouth_open"]),
+        );
+
+        // same expression, e.g. a blink
+        fade.expression(&layers(&["smile"]));
+        assert!(!fade.start(now));
+        assert_eq!(fade.progress(now), None);
+        // the mouth moves while speaking
+        fade.expression(&layers(&["smile", "mouth_open"]));
+        assert!(!fade.start(now));
+
+        fade.expression(&layers(&["angry"]));
+        assert!(fade.start(now));
+        assert!(!fade.start(now));
+        assert_eq!(fade.progress(now), Some(0.0));
+        assert_eq!(fade.progress(now + Duration::from_millis(100)), Some(0.5));
+        assert_eq!(fade.progress(now + Duration::from_millis(200)), None);
+        assert_eq!(fade.progress(now + Duration::from_millis(100)), None);
+    }
+
+    #[test]
+    fn cut_without_duration() {
+        let mut fade = CrossFade::new(Duration::ZERO, layers(&["smile"]), Vec::new());
+        fade.expression(&layers(&["angry"]));
+        assert!(!fade.start(Instant::now()));
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufReader, Cursor},
+};
+
+use bytes::Bytes;
+use rodio::{Decoder, Source};
+
+use crate::stt::encode_wav;
+
+/// Delays of the comb filters making up the reverb, in seconds, apart so they don't ring together.
+const REVERB_DELAYS: [f32; 4] = [0.0297, 0.0371, 0.0411, 0.0437];
+const REVERB_FEEDBACK: f32 = 0.8;
+/// The reverb and echo ring out for at most this long after the voice.
+const MAX_TAIL: f32 = 3.0;
+/// Echoes quieter than this are cut off.
+const SILENCE: f32 = 0.01;
+
+/// Repeats of the voice, each quieter than the last.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Echo {
+    /// Seconds between two repeats
+    pub delay: f32,
+    /// Volume of a repeat relative to the previous one, below 1
+    pub decay: f32,
+}
+
+/// A way of delivering a line, picked by the AI by name, see [`ai::AIResponse::effect`].
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct VoiceEffect {
+    /// Tells the AI when to use the effect
+    #[serde(default)]
+    pub description: String,
+    /// How much of a room is heard, from 0 to 1
+    #[serde(default)]
+    pub reverb: Option<f32>,
+    #[serde(default)]
+    pub echo: Option<Echo>,
+    /// Played this much faster, raising the pitch above 1 and lowering it below
+    #[serde(default)]
+    pub pitch: Option<f32>,
+}
+
+/// Tell the AI which effects it may pick, nothing if none are configured.
+pub fn effects_prompt(effects: &BTreeMap<String, VoiceEffect>) -> Option<String> {
+    if effects.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = effects
+        .iter()
+        .map(|(name, effect)| format!("{name}: {}", effect.description))
+        .collect();
+    Some(format!(
+        "【声音效果】在需要戏剧性或搞笑效果时, 可以在 effect 字段填写以下效果之一, 平时请留空:\n{}",
+        lines.join("\n")
+    ))
+}
+
+/// Apply the effect to an encoded voice, returns a mono WAV.
+pub fn apply(voice: &Bytes, effect: &VoiceEffect) -> anyhow::Result<Bytes> {
+    let decoder = Decoder::new(BufReader::new(Cursor::new(voice.clone())))?;
+    let channels = decoder.channels() as usize;
+    let sample_rate = decoder.sample_rate();
+    let samples: Vec<f32> = decoder.collect();
+    let mono: Vec<f32> = samples
+        .chunks(channels.max(1))
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+
+    Ok(encode_wav(&process(mono, sample_rate, effect), sample_rate))
+}
+
+/// Pitch first, then echo and reverb, so the repeats sound like the shifted voice.
+fn process(samples: Vec<f32>, sample_rate: u32, effect: &VoiceEffect) -> Vec<f32> {
+    let mut samples = match effect.pitch {
+        Some(pitch) if pitch > 0.0 && pitch != 1.0 => resample(&samples, pitch),
+        _ => samples,
+    };
+    if let Some(echo) = &effect.echo {
+        samples = add_echo(samples, sample_rate, echo);
+    }
+    if let Some(amount) = effect.reverb {
+        samples = add_reverb(samples, sample_rate, amount.clamp(0.0, 1.0));
+    }
+
+    // the repeats add up, keep the loudest one from clipping
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if peak > 1.0 {
+        samples.iter_mut().for_each(|s| *s /= peak);
+    }
+    samples
+}
+
+/// Play faster or slower by linear interpolation, like a tape.
+fn resample(samples: &[f32], speed: f32) -> Vec<f32> {
+    let len = (samples.len() as f32 / speed) as usize;
+    (0..len)
+        .map(|i| {
+            let position = i as f32 * speed;
+            let index = position as usize;
+            let fraction = position - index as f32;
+            let current = samples.get(index).copied().unwrap_or_default();
+            let next = samples.get(index + 1).copied().unwrap_or(current);
+            current + (next - current) * fraction
+        })
+        .collect()
+}
+
+fn add_echo(mut samples: Vec<f32>, sample_rate: u32, echo: &Echo) -> Vec<f32> {
+    let delay = (echo.delay * sample_rate as f32) as usize;
+    let decay = echo.decay.clamp(0.0, 0.95);
+    if delay == 0 || decay == 0.0 {
+        return samples;
+    }
+
+    // until the repeats fade out
+    let repeats = (SILENCE.ln() / decay.ln()).ceil();
+    let tail = (echo.delay * repeats).min(MAX_TAIL);
+    samples.resize(samples.len() + (tail * sample_rate as f32) as usize, 0.0);
+    for i in delay..samples.len() {
+        samples[i] += samples[i - delay] * decay;
+    }
+    samples
+}
+
+fn add_reverb(mut samples: Vec<f32>, sample_rate: u32, amount: f32) -> Vec<f32> {
+    if amount == 0.0 {
+        return samples;
+    }
+
+    let tail = (REVERB_DELAYS[3] * SILENCE.ln() / REVERB_FEEDBACK.ln()).min(MAX_TAIL);
+    samples.resize(samples.len() + (tail * sample_rate as f32) as usize, 0.0);
+    let mut wet = vec![0.0; samples.len()];
+    for delay in REVERB_DELAYS {
+        let delay = ((delay * sample_rate as f32) as usize).max(1);
+        let mut comb = vec![0.0; samples.len()];
+        for i in 0..samples.len() {
+            let feedback = if i >= delay { comb[i - delay] } else { 0.0 };
+            comb[i] = samples[i] + feedback * REVERB_FEEDBACK;
+            wet[i] += comb[i] / REVERB_DELAYS.len() as f32;
+        }
+    }
+
+    samples
+        .iter()
+        .zip(wet)
+        .map(|(dry, wet)| dry * (1.0 - amount) + wet * amount)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::effects::{Echo, VoiceEffect, process, resample};
+
+    #[test]
+    fn shift_pitch() {
+        let samples: Vec<f32> = (0..100).map(|i| i as f32).collect();
+        let faster = resample(&samples, 2.0);
+        assert_eq!(faster.len(), 50);
+        assert_eq!(faster[10], 20.0);
+        assert_eq!(resample(&samples, 0.5).len(), 200);
+    }
+
+    #[test]
+    fn echo_repeats_the_voice() {
+        let effect = VoiceEffect {
+            echo: Some(Echo {
+                delay: 0.01,
+                decay: 0.5,
+            }),
+            ..Default::default()
+        };
+        let mut voice = vec![0.0; 100];
+        voice[0] = 1.0;
+        let echoed = process(voice, 1000, &effect);
+
+        assert!(echoed.len() > 100);
+        assert_eq!((echoed[0], echoed[10], echoed[20]), (1.0, 0.5, 0.25));
+        assert_eq!(echoed[5], 0.0);
+    }
+
+    #[test]
+    fn keep_reverb_from_clipping() {
+        let effect = VoiceEffect {
+            reverb: Some(1.0),
+            ..Default::default()
+        };
+        let reverberated = process(vec![0.9; 1000], 8000, &effect);
+        assert!(reverberated.len() > 1000);
+        assert!(reverberated.iter().all(|s| s.abs() <= 1.0));
+    }
+}
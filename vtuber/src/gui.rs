@@ -1,52 +1,102 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap},
     fs::File,
     io::Read,
-    sync::{Arc, mpsc},
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
-use bytes::Bytes;
+use desktop_window::WindowKeeper;
 use eframe::egui::{self, Color32, FontData, FontDefinitions, FontFamily, Image};
 use font_kit::{
     family_name::FamilyName, handle::Handle, properties::Properties, source::SystemSource,
 };
-use rodio::{OutputStream, OutputStreamBuilder, Source};
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
 
 use crate::{
-    bus::UiEvent,
-    config::{AppConfig, RenderConfig},
+    bus::{CommentEvent, ControlCommand, FrontendHandle, InEvent, UiEvent},
+    capture::{self, Recorder},
+    chat_input::ChatInput,
+    companion::PomodoroView,
+    config::AppConfig,
+    crossfade::CrossFade,
+    idle::{Blinker, CursorFollow, breathing_scale, sway_offset},
+    metrics::Metrics,
+    player::{Line, Player},
+    poll::PollView,
+    render::{Frame, RenderWorker},
+    scaling::{FitMode, display_pixels, fit_size, shrunk_size},
+    settings::{Settings, SettingsWindow},
+    shutdown::Shutdown,
+    subtitle_style::SUBTITLE_FONT,
+    textures::Textures,
+    theme,
+    toast::Toasts,
+    touch::Touches,
+    transcript::Transcript,
+    walk::{Direction, Walker},
 };
 
+/// How long the results of a closed poll stay on screen.
+const POLL_RESULT_SHOWN: Duration = Duration::from_secs(15);
+/// The thinking dots are hidden after this in case no reply comes.
+const THINKING_SHOWN: Duration = Duration::from_secs(30);
+
 pub fn run_gui(
-    ui_rx: broadcast::Receiver<UiEvent>,
+    frontend: FrontendHandle,
     app_config: &AppConfig,
+    shutdown: Shutdown,
 ) -> Result<(), eframe::Error> {
+    let window = &app_config.window;
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_transparent(true)
+        .with_decorations(window.decorations)
+        .with_inner_size([320.0 * app_config.characters.len() as f32, 240.0]);
+    if window.always_on_top {
+        viewport = viewport.with_window_level(egui::WindowLevel::AlwaysOnTop);
+    }
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_transparent(true)
-            .with_inner_size([320.0, 240.0]),
+        viewport,
         ..Default::default()
     };
 
-    eframe::run_native(
+    desktop_window::run_native(
         "Vtuber App",
         options,
-        Box::new(|_cc| Ok(Box::new(VtuberApp::new(ui_rx, app_config)))),
+        window.placement.clone(),
+        move |cc, keeper| {
+            spawn_repaint_waker(frontend.ui_rx.resubscribe(), cc.egui_ctx.clone());
+            Ok(Box::new(VtuberApp::new(
+                &cc.egui_ctx,
+                frontend,
+                app_config,
+                shutdown,
+                keeper,
+            )?))
+        },
     )
 }
 
+/// A comment listed in the chat panel.
+pub struct RecentComment {
+    pub comment: CommentEvent,
+    pub time: chrono::DateTime<chrono::Local>,
+}
+
 #[derive(Default)]
 pub struct AppState {
-    pub recent_comments: Vec<(String, String)>,
-    pub current_line: Option<(String, Vec<String>, Bytes)>,
+    pub recent_comments: Vec<RecentComment>,
 }
 
 impl AppState {
-    pub fn push_comment(&mut self, user: String, text: String) {
-        self.recent_comments.push((user, text));
-        if self.recent_comments.len() > 50 {
-            self.recent_comments.remove(0);
+    pub fn push_comment(&mut self, comment: CommentEvent, max_comments: usize) {
+        self.recent_comments.push(RecentComment {
+            comment,
+            time: chrono::Local::now(),
+        });
+        if self.recent_comments.len() > max_comments {
+            let excess = self.recent_comments.len() - max_comments;
+            self.recent_comments.drain(..excess);
         }
     }
 }
@@ -56,206 +106,593 @@ pub struct VtuberApp {
 
     state: AppState,
 
-    character_name: String,
+    character_names: Vec<String>,
 
     ui_rx: broadcast::Receiver<UiEvent>,
+    /// Comments clicked in the chat panel are sent back here
+    in_tx: mpsc::Sender<InEvent>,
+
+    /// One per character, side by side
+    textures: Textures<usize>,
+    /// The frames faded out when the expression changes
+    previous_textures: Textures<usize>,
+    fades: Vec<CrossFade>,
+
+    renderer: RenderWorker,
+    /// Last layers of every character, without the blink
+    expressions: Vec<Vec<String>>,
+    blinkers: Vec<Blinker>,
+    /// How much the characters breathe, see [`breathing_scale`]
+    breathing: f32,
+    /// How far the characters sway, see [`sway_offset`]
+    sway: f32,
+    /// One per character
+    follows: Vec<CursorFollow>,
+    started_at: Instant,
+
+    player: Player,
 
-    composite_tex: Option<egui::TextureHandle>,
+    shutdown: Shutdown,
 
-    img_rx: mpsc::Receiver<egui::ColorImage>,
-    img_tx: mpsc::Sender<egui::ColorImage>,
+    metrics: Arc<Metrics>,
+    /// Toggled with F3
+    show_debug_overlay: bool,
+    /// Toggled with F2
+    show_chat_panel: bool,
+    /// Raise the window on the next frame
+    activate: bool,
 
-    audio_stream: OutputStream,
-    pending: VecDeque<(String, Vec<String>, Bytes)>,
-    is_playing: bool,
-    finished_rx: mpsc::Receiver<()>,
-    finished_tx: mpsc::Sender<()>,
+    /// The running poll, or the last one while its results are shown
+    poll: Option<PollView>,
+    /// The running pomodoro, offered in the right click menu of the characters since the app has
+    /// no tray icon
+    pomodoro: Option<PomodoroView>,
+    /// When the AI started on a comment, until its reply comes
+    thinking_since: Option<Instant>,
+    toasts: Toasts,
 
-    render_config: RenderConfig,
+    window: WindowKeeper,
+
+    settings: Settings,
+    settings_window: SettingsWindow,
+
+    walker: Option<Walker>,
+    /// Set while walking
+    walking: Option<Direction>,
+    /// Per character, shown while walking left and right
+    walk_layers: Vec<(Vec<String>, Vec<String>)>,
+    /// Set while the microphone is recorded for the characters
+    listening: bool,
+    /// Per character, shown while listening
+    listening_layers: Vec<Vec<String>>,
+
+    chat_input: ChatInput,
+    transcript: Transcript,
+    /// Who the characters hear typing in the chat input
+    user_name: String,
+
+    touches: Touches,
+    /// Answer touches with the AI, see [`crate::config::TouchConfig::ai`]
+    touch_ai: bool,
+
+    /// The last frame of every character, composited for captures and faded from
+    frames: Vec<Option<Frame>>,
+    recorder: Recorder,
 }
 
 impl VtuberApp {
-    pub fn new(ui_rx: broadcast::Receiver<UiEvent>, app_config: &AppConfig) -> Self {
-        let (img_tx, img_rx) = mpsc::channel::<egui::ColorImage>();
-        let audio_stream = OutputStreamBuilder::open_default_stream().unwrap();
-        let (finished_tx, finished_rx) = mpsc::channel();
-
-        Self {
+    pub fn new(
+        ctx: &egui::Context,
+        frontend: FrontendHandle,
+        app_config: &AppConfig,
+        shutdown: Shutdown,
+        window: WindowKeeper,
+    ) -> anyhow::Result<Self> {
+        let FrontendHandle {
+            in_tx,
+            ui_tx,
+            ui_rx,
+            metrics,
+        } = frontend;
+        Ok(Self {
             need_init: true,
             state: AppState::default(),
-            character_name: app_config.ai.character_name.to_owned(),
-            composite_tex: None,
+            character_names: app_config
+                .characters
+                .iter()
+                .map(|character| character.name.to_owned())
+                .collect(),
+            textures: Textures::new("composited"),
+            previous_textures: Textures::new("previous"),
+            fades: app_config
+                .characters
+                .iter()
+                .map(|character| {
+                    CrossFade::new(
+                        app_config.gui.crossfade,
+                        character.render.default_layers.clone(),
+                        character.render.mouth_layers.clone(),
+                    )
+                })
+                .collect(),
             ui_rx,
-            img_rx,
-            img_tx,
-            audio_stream,
-
-            pending: VecDeque::new(),
-            is_playing: false,
-            finished_rx,
-            finished_tx,
+            in_tx,
+            renderer: RenderWorker::spawn(
+                app_config
+                    .characters
+                    .iter()
+                    .map(|character| character.render.to_owned())
+                    .collect(),
+                {
+                    let ctx = ctx.clone();
+                    move || ctx.request_repaint()
+                },
+            ),
+            expressions: app_config
+                .characters
+                .iter()
+                .map(|character| character.render.default_layers.clone())
+                .collect(),
+            blinkers: app_config
+                .characters
+                .iter()
+                .map(|character| {
+                    Blinker::new(
+                        character.render.blink_layers.clone(),
+                        &app_config.idle,
+                        Instant::now(),
+                    )
+                })
+                .collect(),
+            breathing: app_config.idle.breathing,
+            sway: app_config.idle.sway,
+            follows: app_config
+                .characters
+                .iter()
+                .map(|_| CursorFollow::new(app_config.idle.follow))
+                .collect(),
+            started_at: Instant::now(),
+            player: Player::new(app_config, metrics.clone(), ui_tx)?,
 
-            render_config: app_config.render.to_owned(),
-        }
+            shutdown,
+            metrics,
+            show_debug_overlay: app_config.debug_overlay,
+            show_chat_panel: app_config.gui.chat_panel,
+            activate: false,
+            poll: None,
+            pomodoro: None,
+            thinking_since: None,
+            toasts: Toasts::default(),
+            window,
+            settings: Settings::from_config(app_config),
+            settings_window: SettingsWindow::new(app_config),
+            touches: Touches::new(
+                app_config
+                    .characters
+                    .iter()
+                    .map(|character| character.render.model.manifest().hit_areas.clone())
+                    .collect(),
+                &app_config.touch,
+            ),
+            touch_ai: app_config.touch.ai,
+            walker: app_config
+                .walk
+                .as_ref()
+                .map(|walk_config| Walker::new(walk_config, Instant::now())),
+            walking: None,
+            walk_layers: app_config
+                .characters
+                .iter()
+                .map(|character| {
+                    (
+                        character.render.walk_left_layers.clone(),
+                        character.render.walk_right_layers.clone(),
+                    )
+                })
+                .collect(),
+            listening: false,
+            listening_layers: app_config
+                .characters
+                .iter()
+                .map(|character| character.render.listening_layers.clone())
+                .collect(),
+            chat_input: ChatInput::new(app_config.gui.text_input),
+            transcript: Transcript::new(app_config.gui.transcript),
+            user_name: app_config.gui.user_name.clone(),
+            frames: vec![None; app_config.characters.len()],
+            recorder: Recorder::new(&app_config.capture),
+        })
     }
 
     fn drain_pending_image(&mut self, ctx: &egui::Context) {
-        while let Ok(ci) = self.img_rx.try_recv() {
-            let tex = ctx.load_texture("composited", ci, egui::TextureOptions::LINEAR);
-            self.composite_tex = Some(tex);
+        // only the newest frame of every character is uploaded
+        let mut changed = BTreeSet::new();
+        // the frames shown until now, to fade from
+        let mut replaced = BTreeMap::new();
+        while let Some((character, image)) = self.renderer.try_frame() {
+            if let Some(previous) = self.frames[character].replace(image) {
+                replaced.entry(character).or_insert(previous);
+            }
+            changed.insert(character);
+        }
+        for &character in &changed {
+            self.player.frame_shown(character);
+        }
+        let now = Instant::now();
+        for (character, previous) in replaced {
+            if self.fades[character].start(now) {
+                let options = self.settings.fit.texture_options();
+                self.previous_textures
+                    .set(ctx, character, previous.texture, options);
+            }
+        }
+        for &character in &changed {
+            if let Some(frame) = &self.frames[character] {
+                let options = self.settings.fit.texture_options();
+                self.textures
+                    .set(ctx, character, frame.texture.clone(), options);
+            }
+        }
+        if !changed.is_empty() {
+            let images: Vec<_> = self
+                .frames
+                .iter()
+                .map(|frame| frame.as_ref().map(|frame| &frame.image))
+                .collect();
+            self.recorder
+                .record(capture::compose(&images), Instant::now());
         }
     }
 
-    fn start_next_if_any(&mut self, ctx: &egui::Context) {
-        if let Some((text, reply_layers, voice)) = self.pending.pop_front() {
-            self.is_playing = true;
+    /// Re-render the speaking character, the others keep their last expression.
+    fn render_current(&mut self) {
+        if let Some(line) = self.player.current() {
+            let character = line.character;
+            self.expressions[character] = self.player.shown_layers().to_vec();
+            self.render_character(character);
+        }
+    }
 
-            self.state.current_line = Some((text.clone(), reply_layers.clone(), voice.clone()));
+    /// Render the character's last expression, or its reaction to a touch, blinking if it is.
+    fn render_character(&mut self, character: usize) {
+        let listening = &self.listening_layers[character];
+        let mut layers = match self.touches.reaction(character) {
+            Some(reaction) => reaction.to_vec(),
+            None if self.listening && !listening.is_empty() => listening.clone(),
+            None => self.expressions[character].clone(),
+        };
+        let (left, right) = &self.walk_layers[character];
+        match self.walking {
+            Some(Direction::Left) => layers.extend_from_slice(left),
+            Some(Direction::Right) => layers.extend_from_slice(right),
+            None => {}
+        }
+        self.fades[character].expression(&layers);
+        layers.extend(self.blinkers[character].layer().cloned());
+        self.renderer.render(character, &layers);
+    }
 
-            let mut model = self.render_config.model.clone();
-            let mut layers_to_render = Vec::with_capacity(1 + reply_layers.len());
-            layers_to_render.push(self.render_config.base_layer.clone());
-            layers_to_render.extend(reply_layers.clone());
+    /// Move the window along the bottom of the screen when it is time to walk.
+    fn walk(&mut self, ctx: &egui::Context, now: Instant) {
+        let Some(walker) = &mut self.walker else {
+            return;
+        };
+        let (outer, held) = ctx.input(|i| (i.viewport().outer_rect, i.pointer.any_down()));
+        // don't walk away from the user
+        if held && walker.is_walking() {
+            walker.stop(now);
+        }
+        let screen = outer.and_then(|outer| self.window.monitor(outer));
+        let walking = match (outer, screen) {
+            (Some(outer), Some(screen)) if !held => {
+                walker
+                    .update(now, outer, screen)
+                    .map(|(position, direction)| {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(position));
+                        direction
+                    })
+            }
+            _ => None,
+        };
 
-            let tx_img = self.img_tx.clone();
-            std::thread::spawn(move || {
-                let image = model
-                    .render(&layers_to_render)
-                    .expect("image render failed");
-                let color_image = rgba_image_to_color_image(&image.into());
-                let _ = tx_img.send(color_image);
-            });
+        if walking != self.walking {
+            self.walking = walking;
+            for character in 0..self.renderer.characters() {
+                self.render_character(character);
+            }
+        }
+    }
 
-            let voice_bytes_for_len = voice.clone();
-            let voice_bytes_for_play = voice.clone();
+    /// React to a click on the character, `point` is in pixels of its image.
+    fn touch(&mut self, character: usize, point: egui::Vec2) {
+        let Some(area) = self
+            .touches
+            .touch(character, point.x, point.y, Instant::now())
+        else {
+            return;
+        };
+        if self.touch_ai
+            && let Some(prompt) = &area.prompt
+        {
+            let touch = InEvent::Touch {
+                character: self.character_names[character].clone(),
+                prompt: prompt.clone(),
+            };
+            if let Err(e) = self.in_tx.try_send(touch) {
+                log::error!("Failed to send touch: {e}");
+            }
+        }
+        self.render_character(character);
+    }
 
-            let finished_tx = self.finished_tx.clone();
-            let mix_handle = self.audio_stream.mixer().clone();
+    fn draw_debug_overlay(&self, ctx: &egui::Context) {
+        let colors = &self.settings.theme.panel;
+        egui::Area::new(egui::Id::new("debug_overlay"))
+            .fixed_pos(egui::pos2(4.0, 4.0))
+            .show(ctx, |ui| {
+                egui::Frame::default()
+                    .fill(theme::color(colors.background))
+                    .inner_margin(6.0)
+                    .show(ui, |ui| {
+                        // may show on stream, like the toasts
+                        for line in self.metrics.overlay_lines() {
+                            ui.label(
+                                egui::RichText::new(config::redact(&line))
+                                    .monospace()
+                                    .size(11.0)
+                                    .color(theme::color(colors.text)),
+                            );
+                        }
+                    });
+            });
+    }
 
-            std::thread::spawn(move || {
-                let total = {
-                    let r = std::io::BufReader::new(std::io::Cursor::new(voice_bytes_for_len));
-                    rodio::Decoder::new(r).ok().and_then(|s| s.total_duration())
-                };
+    /// List the recent comments, the one being answered is highlighted. Returns the id of the
+    /// comment that was clicked to be answered again.
+    fn draw_chat_panel(&self, ctx: &egui::Context) -> Option<u64> {
+        let colors = &self.settings.theme.panel;
+        let answering = self
+            .player
+            .current()
+            .and_then(|line| line.trace.as_ref())
+            .map(|trace| trace.id);
+        let mut requeued = None;
+        egui::SidePanel::right("chat_panel")
+            .default_width(240.0)
+            .frame(
+                egui::Frame::default()
+                    .fill(theme::color(colors.background))
+                    .inner_margin(6.0),
+            )
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for recent in &self.state.recent_comments {
+                            let comment = &recent.comment;
+                            let text = format!(
+                                "{} {}: {}",
+                                recent.time.format("%H:%M:%S"),
+                                comment.name(),
+                                comment.original.as_deref().unwrap_or(&comment.text)
+                            );
+                            let fill = if answering == Some(comment.id) {
+                                theme::color(colors.highlight)
+                            } else {
+                                Color32::TRANSPARENT
+                            };
+                            let response = egui::Frame::default()
+                                .fill(fill)
+                                .corner_radius(4.0)
+                                .inner_margin(2.0)
+                                .show(ui, |ui| {
+                                    ui.add(
+                                        egui::Label::new(
+                                            egui::RichText::new(text)
+                                                .color(theme::color(colors.text)),
+                                        )
+                                        .wrap()
+                                        .sense(egui::Sense::click()),
+                                    )
+                                })
+                                .inner
+                                .on_hover_text("Click to answer again");
+                            if response.clicked() {
+                                requeued = Some(comment.id);
+                            }
+                        }
+                    });
+            });
+        requeued
+    }
 
-                {
-                    let r = std::io::BufReader::new(std::io::Cursor::new(voice_bytes_for_play));
-                    if let Ok(source) = rodio::Decoder::new(r) {
-                        mix_handle.add(source);
-                    }
-                }
+    /// Bouncing dots over the characters while the AI works on a reply.
+    fn draw_thinking(&self, ctx: &egui::Context, since: Instant) {
+        const DOTS: usize = 3;
 
-                if let Some(d) = total {
-                    std::thread::sleep(d);
-                } else {
-                    std::thread::sleep(std::time::Duration::from_secs(3));
+        let colors = &self.settings.theme.thinking;
+        let elapsed = since.elapsed().as_secs_f32();
+        egui::Area::new(egui::Id::new("thinking"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 12.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                let (rect, _) =
+                    ui.allocate_exact_size(egui::vec2(56.0, 26.0), egui::Sense::hover());
+                let painter = ui.painter();
+                painter.rect_filled(rect, 13.0, theme::color(colors.background));
+                for dot in 0..DOTS {
+                    // one after another, a third of a second apart
+                    let phase = (elapsed * 3.0 - dot as f32) * std::f32::consts::PI / 1.5;
+                    let lift = phase.sin().max(0.0) * 4.0;
+                    let center = egui::pos2(
+                        rect.center().x + (dot as f32 - 1.0) * 13.0,
+                        rect.center().y - lift,
+                    );
+                    painter.circle_filled(center, 3.5, theme::color(colors.dots));
                 }
-
-                let _ = finished_tx.send(());
             });
-
-            ctx.request_repaint();
-        }
-
-        // free memory
-        self.pending.shrink_to_fit();
     }
 
-    fn draw_overlay_lines(
-        &self,
-        ui: &mut egui::Ui,
-        area: egui::Rect,
-        lines: &[&str],
-        font_id: egui::FontId,
-        text_color: egui::Color32,
-        padding: egui::Vec2,
-        corner_radius: f32,
-        bg_color: egui::Color32,
-        max_width_override: Option<f32>,
-    ) {
-        let painter = ui.painter_at(area);
-
-        let max_width =
-            max_width_override.unwrap_or_else(|| (area.width() - 2.0 * padding.x).max(0.0));
-
-        let mut galleys: Vec<Arc<egui::Galley>> = Vec::with_capacity(lines.len());
-        let mut total_h = 0.0f32;
-        let mut max_w = 0.0f32;
-
-        ui.fonts(|f| {
-            for &line in lines {
-                if line.is_empty() {
-                    let galley =
-                        f.layout(" ".to_owned(), font_id.clone(), Color32::WHITE, max_width);
-                    max_w = max_w.max(galley.size().x);
-                    total_h += galley.size().y;
-                    galleys.push(galley);
-                    continue;
-                }
-                let galley = f.layout(line.to_owned(), font_id.clone(), Color32::WHITE, max_width);
-                max_w = max_w.max(galley.size().x);
-                total_h += galley.size().y;
-                galleys.push(galley);
-            }
-        });
-
-        let text_origin = egui::pos2(
-            area.left() + padding.x,
-            (area.bottom() * 3.0 / 4.0) - padding.y - total_h,
-        ); // TODO: pass this variable by parameters
-
-        let bg_rect = egui::Rect::from_min_size(
-            egui::pos2(text_origin.x - padding.x, text_origin.y - padding.y),
-            egui::vec2(max_w + padding.x * 2.0, total_h + padding.y * 2.0),
-        );
-        painter.rect(
-            bg_rect,
-            corner_radius,
-            bg_color,
-            egui::Stroke::NONE,
-            egui::StrokeKind::Inside,
-        );
-
-        let mut cursor = text_origin;
-        for galley in galleys {
-            painter.galley(cursor, galley.clone(), text_color);
-            cursor.y += galley.size().y;
-        }
+    fn draw_poll(&self, ctx: &egui::Context, poll: &PollView) {
+        let colors = &self.settings.theme.panel;
+        let total = poll.total_votes();
+        egui::Area::new(egui::Id::new("poll"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-4.0, 4.0))
+            .show(ctx, |ui| {
+                egui::Frame::default()
+                    .fill(theme::color(colors.background))
+                    .corner_radius(8.0)
+                    .inner_margin(8.0)
+                    .show(ui, |ui| {
+                        ui.set_max_width(220.0);
+                        ui.label(
+                            egui::RichText::new(&poll.question)
+                                .strong()
+                                .color(theme::color(colors.text)),
+                        );
+                        for (number, option) in poll.options.iter().enumerate() {
+                            let share = if total == 0 {
+                                0.0
+                            } else {
+                                option.votes as f32 / total as f32
+                            };
+                            ui.add(egui::ProgressBar::new(share).desired_height(14.0).text(
+                                format!("{}. {} ({})", number + 1, option.text, option.votes),
+                            ));
+                        }
+                        let status = if poll.closed {
+                            format!("Closed, {total} votes")
+                        } else {
+                            let remaining = poll.ends_at.saturating_duration_since(Instant::now());
+                            format!("{} s left, vote with the number", remaining.as_secs())
+                        };
+                        ui.label(
+                            egui::RichText::new(status)
+                                .size(11.0)
+                                .color(theme::color(colors.muted)),
+                        );
+                    });
+            });
     }
 
-    fn poll_events(&mut self, ctx: &egui::Context) {
+    fn poll_events(&mut self) {
         loop {
             match self.ui_rx.try_recv() {
                 Ok(UiEvent::NewComment(e)) => {
-                    self.state.push_comment(e.user, e.text);
+                    self.state.push_comment(e, self.settings.comment_count);
                 }
 
                 Ok(UiEvent::AiReply {
+                    character,
                     text,
                     layers: reply_layers,
                     voice,
+                    priority,
+                    trace,
                 }) => {
-                    self.pending.push_back((text, reply_layers, voice));
+                    self.thinking_since = None;
+                    self.player.enqueue(Line {
+                        character,
+                        text,
+                        layers: reply_layers,
+                        voice,
+                        priority,
+                        trace: Some(trace),
+                        journal_seq: None,
+                    });
+                }
+
+                Ok(UiEvent::CommentRejected { comment, reason }) => {
+                    log::debug!("Hide rejected comment from {}: {reason}", comment.user);
                 }
 
-                Ok(_) => { /* TODO: display errors */ }
+                Ok(UiEvent::AiThinking) => self.thinking_since = Some(Instant::now()),
+
+                Ok(UiEvent::Error(err)) => {
+                    log::error!("Pipeline error: {err}");
+                    self.thinking_since = None;
+                    self.toasts.push(err, Instant::now());
+                }
+
+                Ok(UiEvent::Control(command)) => {
+                    match command {
+                        ControlCommand::NextMonitor => self.window.throw_to_next_monitor(),
+                        ControlCommand::Screenshot => self.recorder.screenshot(),
+                        ControlCommand::Clip => self.recorder.save_clip(Instant::now()),
+                        ControlCommand::Activate => self.activate = true,
+                        ControlCommand::StartListening | ControlCommand::StopListening => {
+                            self.listening = command == ControlCommand::StartListening;
+                            for character in 0..self.frames.len() {
+                                self.render_character(character);
+                            }
+                        }
+                        _ => {}
+                    }
+                    if self.player.handle_control(command) {
+                        self.render_current();
+                    }
+                }
+
+                Ok(UiEvent::SystemPromptReloaded { character }) => {
+                    let name = &self.character_names[character];
+                    self.toasts.push(
+                        format!("Reloaded the system prompt of {name}"),
+                        Instant::now(),
+                    );
+                }
+
+                Ok(UiEvent::Sound(command)) => self.player.handle_sound(&command),
+
+                Ok(UiEvent::Poll(poll)) => self.poll = Some(poll),
+
+                Ok(UiEvent::Pomodoro(pomodoro)) => self.pomodoro = pomodoro,
+
+                // sent by the player
+                Ok(UiEvent::Speaking { .. }) => {}
+
+                Ok(UiEvent::ConfigReloaded(live)) => {
+                    // only the main character is reloaded
+                    if self.renderer.set_base_layer(0, &live.base_layer) {
+                        let layers = match self.player.current() {
+                            Some(line) if line.character == 0 => self.player.shown_layers(),
+                            _ => self.player.default_layers(0),
+                        };
+                        self.expressions[0] = layers.to_vec();
+                        self.render_character(0);
+                    }
+                }
 
                 Err(broadcast::error::TryRecvError::Empty) => break,
                 Err(_) => break,
             }
         }
 
-        if !self.is_playing {
-            self.start_next_if_any(ctx);
+        if self.player.poll() {
+            self.render_current();
         }
-
-        while self.finished_rx.try_recv().is_ok() {
-            self.is_playing = false;
-            self.start_next_if_any(ctx);
+        if let Some(notice) = self.player.take_audio_notice() {
+            self.toasts.push(notice, Instant::now());
         }
     }
 }
 
+/// Redraw the window whenever the pipeline sends an event, it is not redrawn continuously.
+fn spawn_repaint_waker(mut ui_rx: broadcast::Receiver<UiEvent>, ctx: egui::Context) {
+    std::thread::Builder::new()
+        .name("repaint".to_string())
+        .spawn(move || {
+            // lagging behind still means there was something new
+            while !matches!(
+                ui_rx.blocking_recv(),
+                Err(broadcast::error::RecvError::Closed)
+            ) {
+                ctx.request_repaint();
+            }
+        })
+        .expect("failed to spawn the repaint thread");
+}
+
 /// Attempt to load a system font by any of the given `family_names`, returning the first match.
 fn load_font_family(family_names: &[&str]) -> Option<Vec<u8>> {
     let system_source = SystemSource::new();
@@ -283,7 +720,11 @@ fn load_font_family(family_names: &[&str]) -> Option<Vec<u8>> {
     None
 }
 
-pub fn load_system_fonts(mut fonts: FontDefinitions) -> FontDefinitions {
+/// Load fallback fonts for more languages, and the subtitle font on top of them.
+pub fn load_system_fonts(
+    mut fonts: FontDefinitions,
+    subtitle_font: Option<&str>,
+) -> FontDefinitions {
     let mut fontdb = HashMap::new();
 
     fontdb.insert(
@@ -329,61 +770,366 @@ pub fn load_system_fonts(mut fonts: FontDefinitions) -> FontDefinitions {
                 .push(region.to_owned());
         }
     }
+
+    let mut subtitle_families = fonts.families[&FontFamily::Proportional].clone();
+    if let Some(name) = subtitle_font {
+        match load_font_family(&[name]) {
+            Some(font_data) => {
+                fonts.font_data.insert(
+                    SUBTITLE_FONT.to_owned(),
+                    FontData::from_owned(font_data).into(),
+                );
+                subtitle_families.insert(0, SUBTITLE_FONT.to_owned());
+            }
+            None => log::warn!("Subtitle font {name} not found, using the default one"),
+        }
+    }
+    fonts
+        .families
+        .insert(FontFamily::Name(SUBTITLE_FONT.into()), subtitle_families);
     fonts
 }
 
 impl eframe::App for VtuberApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        self.poll_events(ctx);
+        self.poll_events();
         self.drain_pending_image(ctx);
 
+        // let the current sentence finish before closing
+        if self.shutdown.is_triggered() && self.player.wind_down() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+
+        if std::mem::take(&mut self.activate) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        }
+
         if self.need_init {
-            ctx.set_fonts(load_system_fonts(FontDefinitions::empty()));
+            ctx.set_fonts(load_system_fonts(
+                FontDefinitions::empty(),
+                self.settings.subtitle.font_family.as_deref(),
+            ));
+            ctx.set_visuals(self.settings.theme.visuals());
+            for character in 0..self.renderer.characters() {
+                self.render_character(character);
+            }
             self.need_init = false;
         }
 
+        let now = Instant::now();
+        for character in 0..self.blinkers.len() {
+            if self.blinkers[character].update(now) {
+                self.render_character(character);
+            }
+        }
+        for character in self.touches.expire(now) {
+            self.render_character(character);
+        }
+        self.walk(ctx, now);
+
+        if ctx.input(|i| i.key_pressed(egui::Key::F2)) {
+            self.show_chat_panel = !self.show_chat_panel;
+        }
+        if self.show_chat_panel
+            && let Some(id) = self.draw_chat_panel(ctx)
+            && let Err(e) = self
+                .in_tx
+                .try_send(InEvent::Control(ControlCommand::Requeue(id)))
+        {
+            log::error!("Failed to requeue comment: {e}");
+        }
+
+        if let Some(text) = self.chat_input.show(ctx, &self.settings.theme.panel) {
+            let message = InEvent::HostSpeech {
+                speaker: self.user_name.clone(),
+                text,
+            };
+            if let Err(e) = self.in_tx.try_send(message) {
+                log::error!("Failed to send message: {e}");
+            }
+        }
+
+        let mut fading = false;
+        let mut crossfading = false;
+        let mut following = false;
+        let mut touched = None;
+        let mut toggle_pomodoro = false;
+        let mut display_sizes = Vec::new();
         egui::CentralPanel::default()
             .frame(egui::Frame::default().fill(Color32::TRANSPARENT))
             .show(ctx, |ui| {
-                if let Some(tex) = &self.composite_tex {
-                    // Render the image
-                    let _image_response =
-                        ui.add(Image::new(tex).fit_to_exact_size(ui.available_size_before_wrap()));
+                if !self.textures.is_empty() {
+                    // Render the characters side by side
+                    ui.columns(self.frames.len(), |columns| {
+                        let elapsed = self.started_at.elapsed();
+                        let now = Instant::now();
+                        let (cursor, dt) = ctx.input(|i| (i.pointer.hover_pos(), i.stable_dt));
+                        for (character, column) in columns.iter_mut().enumerate() {
+                            if let Some(tex) = self.textures.get(&character) {
+                                let available = column.available_rect_before_wrap();
+                                // whole multiples of the pixels need all of them
+                                let display = (self.settings.fit != FitMode::Integer).then(|| {
+                                    display_pixels(
+                                        available.size(),
+                                        ctx.pixels_per_point(),
+                                        self.settings.zoom,
+                                    )
+                                });
+                                display_sizes.push((character, display));
+                                let fitted = fit_size(
+                                    tex.size_vec2(),
+                                    available.size(),
+                                    ctx.pixels_per_point(),
+                                    self.settings.fit,
+                                    self.settings.zoom,
+                                );
+                                let scale = breathing_scale(
+                                    elapsed,
+                                    character as f32 * 0.37,
+                                    self.breathing,
+                                );
+                                // breathe from the feet up
+                                let size = fitted * scale;
+                                let rect = egui::Rect::from_min_size(
+                                    egui::pos2(
+                                        available.center().x - size.x / 2.0,
+                                        available.bottom() - size.y,
+                                    ),
+                                    size,
+                                );
+                                // sway and turn to the cursor, moving the picture as a whole
+                                let follow = &mut self.follows[character];
+                                following |= follow.update(
+                                    cursor.map(|cursor| (cursor - rect.center()) / (size / 2.0)),
+                                    dt,
+                                );
+                                let sway = sway_offset(elapsed, character as f32 * 0.53, self.sway);
+                                let rect = rect.translate(
+                                    egui::vec2(sway * size.x, 0.0) + follow.offset(size),
+                                );
+                                let lean = follow.lean();
+                                let feet = egui::vec2(0.5, 1.0);
+                                // the new expression fades in over the last one, which stays
+                                // opaque so the character doesn't turn see-through halfway
+                                let progress = self.fades[character].progress(now);
+                                let mut tint = Color32::WHITE;
+                                if let Some(progress) = progress
+                                    && let Some(previous) = self.previous_textures.get(&character)
+                                {
+                                    crossfading = true;
+                                    Image::new(previous)
+                                        .rotate(lean, feet)
+                                        .paint_at(column, rect);
+                                    tint = Color32::WHITE.gamma_multiply(progress);
+                                }
+                                let response = column.put(
+                                    rect,
+                                    Image::new(tex)
+                                        .fit_to_exact_size(size)
+                                        .tint(tint)
+                                        .rotate(lean, feet)
+                                        .sense(egui::Sense::click_and_drag()),
+                                );
+                                if response.clicked()
+                                    && let Some(pointer) = response.interact_pointer_pos()
+                                {
+                                    // in pixels of the rendered image, before it was shrunk
+                                    let full_size = self.frames[character].as_ref().map_or(
+                                        tex.size_vec2(),
+                                        |frame| {
+                                            egui::vec2(
+                                                frame.full_size[0] as f32,
+                                                frame.full_size[1] as f32,
+                                            )
+                                        },
+                                    );
+                                    let point = (pointer - rect.min) / rect.size() * full_size;
+                                    touched = Some((character, point));
+                                }
+                                response.context_menu(|ui| {
+                                    let label = match &self.pomodoro {
+                                        Some(pomodoro) => format!(
+                                            "Stop pomodoro ({}, {} min left)",
+                                            pomodoro.phase.name(),
+                                            pomodoro
+                                                .ends_at
+                                                .saturating_duration_since(now)
+                                                .as_secs()
+                                                .div_ceil(60)
+                                        ),
+                                        None => "Start pomodoro".to_string(),
+                                    };
+                                    if ui.button(label).clicked() {
+                                        toggle_pomodoro = true;
+                                        ui.close();
+                                    }
+                                });
+                                // grab the character to move the window, ctrl+scroll to resize it
+                                if response.drag_started_by(egui::PointerButton::Primary) {
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::StartDrag);
+                                }
+                                let zoom = ctx.input(|i| i.zoom_delta());
+                                if response.hovered()
+                                    && zoom != 1.0
+                                    && let Some(inner) = ctx.input(|i| i.viewport().inner_rect)
+                                {
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(
+                                        inner.size() * zoom,
+                                    ));
+                                }
+                            }
+                        }
+                    });
 
                     // Render text
-                    if let Some((line, _, _)) = &self.state.current_line {
-                        let lines: [&str; 2] = [&format!("【{}】", self.character_name), line];
-
-                        let area = ui.clip_rect();
-
-                        self.draw_overlay_lines(
-                            ui,
-                            area,
-                            &lines,
-                            egui::FontId::proportional(26.0),
-                            Color32::WHITE,
-                            egui::vec2(12.0, 10.0),
-                            10.0,
-                            Color32::from_black_alpha(160),
-                            None,
+                    if let Some(line) = self.player.current()
+                        && let Some((started_at, finished_at)) = self.player.current_timing()
+                    {
+                        let style = &self.settings.subtitle;
+                        let theme = &self.settings.theme;
+                        let name = theme.name_tag(&self.character_names[line.character]);
+                        let lines = [
+                            (name.as_str(), name.as_str(), theme.name_tag.color),
+                            (
+                                line.text.as_str(),
+                                style.reveal.revealed(&line.text, self.player.progress()),
+                                None,
+                            ),
+                        ];
+                        let opacity = style.opacity(
+                            started_at.elapsed(),
+                            finished_at.map(|finished_at| finished_at.elapsed()),
                         );
+                        style.draw(ui, ui.clip_rect(), &lines, opacity);
+                        fading = opacity > 0.0 && opacity < 1.0;
                     }
                 } else {
-                    // TODO: render default image
-                    ui.label("(wait for response...)");
+                    // the first frames are on their way
+                    ui.centered_and_justified(|ui| ui.spinner());
                 }
             });
 
-        ctx.request_repaint();
+        if let Some((character, point)) = touched {
+            self.touch(character, point);
+        }
+        if toggle_pomodoro
+            && let Err(e) = self
+                .in_tx
+                .try_send(InEvent::Control(ControlCommand::TogglePomodoro))
+        {
+            log::error!("Failed to toggle the pomodoro: {e}");
+        }
+        // frames are rendered at the size they are shown, again when it changes
+        for (character, display) in display_sizes {
+            let wanted = |frame: &Frame| {
+                display
+                    .and_then(|display| shrunk_size(frame.full_size, display))
+                    .unwrap_or(frame.full_size)
+            };
+            if self.renderer.set_display_size(character, display)
+                && self.frames[character].as_ref().is_some_and(|frame| {
+                    wanted(frame) != <[u32; 2]>::from(frame.image.dimensions())
+                })
+            {
+                self.render_character(character);
+            }
+        }
+
+        if ctx.input(|i| i.key_pressed(egui::Key::F3)) {
+            self.show_debug_overlay = !self.show_debug_overlay;
+        }
+        if self.show_debug_overlay {
+            self.draw_debug_overlay(ctx);
+        }
+
+        if self
+            .poll
+            .as_ref()
+            .is_some_and(|poll| poll.closed && poll.ends_at.elapsed() > POLL_RESULT_SHOWN)
+        {
+            self.poll = None;
+        }
+        if let Some(poll) = &self.poll {
+            self.draw_poll(ctx, poll);
+        }
+
+        if self
+            .thinking_since
+            .is_some_and(|since| since.elapsed() > THINKING_SHOWN)
+        {
+            self.thinking_since = None;
+        }
+        if let Some(since) = self.thinking_since {
+            self.draw_thinking(ctx, since);
+        }
+        self.toasts.show(ctx, &self.settings.theme.toast);
+
+        if let Some(line) = self.player.current()
+            && let Some((started_at, _)) = self.player.current_timing()
+        {
+            self.transcript.follow(
+                &self.character_names[line.character],
+                &line.text,
+                started_at,
+            );
+        }
+        self.transcript.show(ctx);
+
+        self.window.update(ctx);
+
+        let volume = self.settings.voice_volume;
+        let device = self.settings.audio_device.clone();
+        let dark_windows = self.settings.theme.dark_windows;
+        self.settings_window
+            .show(ctx, &mut self.settings, self.player.output_level());
+        if self.settings.theme.dark_windows != dark_windows {
+            ctx.set_visuals(self.settings.theme.visuals());
+        }
+        if self.settings.voice_volume != volume {
+            self.player.set_volume(self.settings.voice_volume);
+        }
+        if self.settings.audio_device != device {
+            match self
+                .player
+                .set_device(self.settings.audio_device.as_deref())
+            {
+                Ok(()) => log::info!(
+                    "Playing on {}",
+                    self.settings
+                        .audio_device
+                        .as_deref()
+                        .unwrap_or("the default output")
+                ),
+                Err(e) => {
+                    self.toasts.push(
+                        format!("Failed to switch the output device: {e}"),
+                        Instant::now(),
+                    );
+                    self.settings.audio_device = device;
+                }
+            }
+        }
+
+        // redraw right away while something moves, breathing and the rest go at the idle rate
+        if self.player.is_busy()
+            || fading
+            || crossfading
+            || following
+            || self.thinking_since.is_some()
+            || self.blinkers.iter().any(Blinker::is_blinking)
+            || self.walking.is_some()
+        {
+            ctx.request_repaint();
+        } else {
+            ctx.request_repaint_after(Duration::from_secs_f32(
+                1.0 / self.settings.idle_fps.max(1) as f32,
+            ));
+        }
     }
 
     fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
         [0.0, 0.0, 0.0, 0.0]
     }
 }
-
-fn rgba_image_to_color_image(img: &image::RgbaImage) -> egui::ColorImage {
-    let (w, h) = img.dimensions();
-    let raw = img.as_raw();
-    egui::ColorImage::from_rgba_unmultiplied([w as usize, h as usize], raw)
-}
@@ -1 +1,10 @@
+pub mod capture;
 pub mod comments;
+pub mod companion;
+pub mod events;
+pub mod health;
+pub mod metrics;
+pub mod notify;
+pub mod polls;
+pub mod sessions;
+pub mod viewers;
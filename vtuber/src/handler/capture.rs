@@ -0,0 +1,39 @@
+use actix_web::{Responder, http::StatusCode, web};
+use http_common::error::ApiError;
+
+use crate::{
+    bus::{ControlCommand, InEvent},
+    server::EventSender,
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct CaptureQuery {
+    /// Save the last seconds as a GIF instead of a screenshot
+    #[serde(default)]
+    clip: bool,
+}
+
+/// Save what the window shows into the capture folder.
+pub async fn capture(
+    query: web::Query<CaptureQuery>,
+    sender: web::Data<EventSender>,
+) -> Result<impl Responder, ApiError> {
+    let command = if query.clip {
+        ControlCommand::Clip
+    } else {
+        ControlCommand::Screenshot
+    };
+    sender
+        .0
+        .send(InEvent::Control(command))
+        .await
+        .map_err(|_| {
+            ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "pipeline_stopped",
+                "Comment pipeline is not running",
+            )
+        })?;
+
+    Ok("ok")
+}
@@ -1,29 +1,235 @@
-use actix_web::{Responder, web};
+use std::time::Instant;
+
+use actix_web::{HttpRequest, HttpResponse, Responder, http::StatusCode, web};
+use actix_ws::Message;
+use http_common::{
+    error::{ApiError, check_text},
+    forwarded::{ClientAddr, ClientLimiter},
+};
+use tokio::sync::mpsc;
 
 use crate::{
-    bus::{CommentEvent, InEvent},
-    server::EventSender,
+    bus::{CommentEvent, GiftEvent, InEvent, Priority, SubscriptionEvent},
+    comment_status::{CommentStatus, CommentStatuses},
+    server::{CommentSenders, EventSender},
+    source::http::{HTTP_SOURCE, WEBSOCKET_SOURCE},
 };
 
-#[derive(serde::Deserialize)]
+/// Longer names are likely not a real username.
+const MAX_USER_CHARS: usize = 100;
+/// Longer comments are spam more often than not, and slow to answer.
+const MAX_TEXT_CHARS: usize = 500;
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
 pub struct AddCommentModel {
     user: String,
     text: String,
 }
 
+impl AddCommentModel {
+    fn validate(&self) -> Result<(), ApiError> {
+        check_text("user", &self.user, MAX_USER_CHARS)?;
+        check_text("text", &self.text, MAX_TEXT_CHARS)
+    }
+
+    /// Clients can't raise their own priority, superchats only come from the platforms.
+    fn into_comment(self, source: &str) -> CommentEvent {
+        CommentEvent::new(self.user, self.text, source, Priority::Normal)
+    }
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct AddCommentResponse {
+    /// For asking what became of the comment at `/comments/status/{id}`
+    id: u64,
+    /// Where the comment would be queued right now, 1 for answered next. Clients sending many
+    /// comments should slow down while this grows.
+    position: usize,
+}
+
+fn pipeline_stopped() -> ApiError {
+    ApiError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "pipeline_stopped",
+        "Comment pipeline is not running",
+    )
+}
+
+/// Follow the comment and send it to its source, returns its id.
+async fn send_comment(
+    comment: CommentEvent,
+    sender: &mpsc::Sender<CommentEvent>,
+    comments: &CommentStatuses,
+) -> Result<u64, ApiError> {
+    let id = comment.id;
+    comments.track(id);
+    sender.send(comment).await.map_err(|_| {
+        comments.set(
+            id,
+            CommentStatus::Dropped {
+                reason: "pipeline stopped".to_string(),
+            },
+        );
+        pipeline_stopped()
+    })?;
+    Ok(id)
+}
+
 pub async fn add_comment(
     payload: web::Json<AddCommentModel>,
+    client: ClientAddr,
+    limiter: Option<web::Data<ClientLimiter>>,
+    senders: web::Data<CommentSenders>,
+    comments: web::Data<CommentStatuses>,
+) -> Result<impl Responder, ApiError> {
+    payload.validate()?;
+    if let Some(limiter) = &limiter {
+        limiter.check(client, Instant::now())?;
+    }
+    let position = comments.estimate_position(Priority::Normal);
+    let id = send_comment(
+        payload.into_inner().into_comment(HTTP_SOURCE),
+        &senders.http,
+        &comments,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(AddCommentResponse { id, position }))
+}
+
+pub async fn get_comment_status(
+    path: web::Path<u64>,
+    comments: web::Data<CommentStatuses>,
+) -> Result<impl Responder, ApiError> {
+    let id = path.into_inner();
+    let status = comments.get(id).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            "unknown_comment",
+            format!("No comment {id} was sent recently"),
+        )
+    })?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+pub async fn add_gift(
+    payload: web::Json<GiftEvent>,
     sender: web::Data<EventSender>,
-) -> impl Responder {
-    // TODO: nsfw filter
-    let sender = &sender.0;
+) -> Result<impl Responder, ApiError> {
+    let mut gift = payload.into_inner();
+    gift.source = "http".to_string();
     sender
-        .send(InEvent::Comment(CommentEvent {
-            user: payload.user.to_owned(),
-            text: payload.text.to_owned(),
-        }))
+        .0
+        .send(InEvent::Gift(gift))
         .await
-        .unwrap(); // TODO: add error handling
+        .map_err(|_| pipeline_stopped())?;
+
+    Ok("ok")
+}
+
+pub async fn add_subscription(
+    payload: web::Json<SubscriptionEvent>,
+    sender: web::Data<EventSender>,
+) -> Result<impl Responder, ApiError> {
+    let mut sub = payload.into_inner();
+    sub.source = "http".to_string();
+    sender
+        .0
+        .send(InEvent::Subscription(sub))
+        .await
+        .map_err(|_| pipeline_stopped())?;
+
+    Ok("ok")
+}
+
+#[derive(serde::Deserialize)]
+pub struct WsCommentModel {
+    /// Echoed back in the acknowledgement
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    comment: AddCommentModel,
+}
+
+#[derive(serde::Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WsAck {
+    Ok {
+        id: Option<serde_json::Value>,
+        /// See `/comments/status/{id}`
+        comment_id: u64,
+    },
+    Error {
+        id: Option<serde_json::Value>,
+        message: String,
+    },
+}
+
+/// Accept comments over a long-lived WebSocket, one json message per comment.
+pub async fn comments_ws(
+    req: HttpRequest,
+    body: web::Payload,
+    client: ClientAddr,
+    limiter: Option<web::Data<ClientLimiter>>,
+    senders: web::Data<CommentSenders>,
+    comments: web::Data<CommentStatuses>,
+) -> actix_web::Result<HttpResponse> {
+    let (response, mut session, mut msg_stream) = actix_ws::handle(&req, body)?;
+
+    actix_web::rt::spawn(async move {
+        while let Some(Ok(msg)) = msg_stream.recv().await {
+            let ack = match msg {
+                Message::Text(text) => match serde_json::from_str::<WsCommentModel>(&text) {
+                    Ok(model) if let Err(e) = model.comment.validate() => WsAck::Error {
+                        id: model.id,
+                        message: e.to_string(),
+                    },
+                    Ok(model)
+                        if let Some(limiter) = &limiter
+                            && let Err(e) = limiter.check(client, Instant::now()) =>
+                    {
+                        WsAck::Error {
+                            id: model.id,
+                            message: e.to_string(),
+                        }
+                    }
+                    Ok(model) => {
+                        let comment = model.comment.into_comment(WEBSOCKET_SOURCE);
+                        match send_comment(comment, &senders.websocket, &comments).await {
+                            Ok(comment_id) => WsAck::Ok {
+                                id: model.id,
+                                comment_id,
+                            },
+                            Err(e) => WsAck::Error {
+                                id: model.id,
+                                message: e.to_string(),
+                            },
+                        }
+                    }
+                    Err(e) => WsAck::Error {
+                        id: None,
+                        message: format!("Bad comment: {e}"),
+                    },
+                },
+                Message::Ping(bytes) => {
+                    if session.pong(&bytes).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                Message::Close(reason) => {
+                    let _ = session.close(reason).await;
+                    return;
+                }
+                _ => continue,
+            };
+
+            let ack = serde_json::to_string(&ack).expect("ack is serializable");
+            if session.text(ack).await.is_err() {
+                return;
+            }
+        }
+    });
 
-    "ok" // TODO: response with json
+    Ok(response)
 }
@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use actix_web::{HttpResponse, Responder, http::StatusCode, web};
+use http_common::error::{ApiError, check_text};
+
+use crate::companion::{Companion, CompanionError};
+
+/// Reminders are read out, longer ones drag on.
+const MAX_REMINDER_CHARS: usize = 200;
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct AddReminderModel {
+    /// What to remind of, the character words it
+    text: String,
+    /// Seconds from now
+    after: f64,
+}
+
+impl From<CompanionError> for ApiError {
+    fn from(error: CompanionError) -> Self {
+        let (status, code) = match error {
+            CompanionError::Running => (StatusCode::CONFLICT, "pomodoro_running"),
+            CompanionError::NotRunning => (StatusCode::NOT_FOUND, "pomodoro_not_running"),
+            CompanionError::UnknownReminder => (StatusCode::NOT_FOUND, "unknown_reminder"),
+        };
+        ApiError::new(status, code, error.to_string())
+    }
+}
+
+pub async fn get_companion(companion: web::Data<Companion>) -> impl Responder {
+    HttpResponse::Ok().json(companion.view(Instant::now()))
+}
+
+pub async fn start_pomodoro(companion: web::Data<Companion>) -> Result<impl Responder, ApiError> {
+    let pomodoro = companion.start_pomodoro(Instant::now())?;
+    log::info!("Operator started a pomodoro");
+    Ok(HttpResponse::Created().json(pomodoro))
+}
+
+pub async fn stop_pomodoro(companion: web::Data<Companion>) -> Result<impl Responder, ApiError> {
+    companion.stop_pomodoro()?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn add_reminder(
+    body: web::Json<AddReminderModel>,
+    companion: web::Data<Companion>,
+) -> Result<impl Responder, ApiError> {
+    let body = body.into_inner();
+    check_text("text", &body.text, MAX_REMINDER_CHARS)?;
+    let after = Duration::try_from_secs_f64(body.after)
+        .map_err(|_| ApiError::invalid("after is not a valid number of seconds"))?;
+    let reminder = companion.add_reminder(body.text, after, Instant::now());
+    Ok(HttpResponse::Created().json(reminder))
+}
+
+pub async fn cancel_reminder(
+    path: web::Path<u64>,
+    companion: web::Data<Companion>,
+) -> Result<impl Responder, ApiError> {
+    companion.cancel_reminder(path.into_inner())?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,161 @@
+use std::time::UNIX_EPOCH;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_ws::Message;
+use base64::Engine;
+use tokio::sync::broadcast;
+
+use crate::{
+    bus::{CommentEvent, ControlCommand, Priority, UiEvent},
+    companion::PomodoroView,
+    poll::PollView,
+    server::UiEventSender,
+    soundboard::SoundCommand,
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct EventsQuery {
+    /// Embed the synthesized voice as base64, off by default to keep overlays light
+    #[serde(default)]
+    audio: bool,
+}
+
+/// JSON view of a [`UiEvent`] for overlays and companion apps.
+#[derive(serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OverlayEvent<'a> {
+    NewComment {
+        comment: &'a CommentEvent,
+    },
+    CommentRejected {
+        comment: &'a CommentEvent,
+        reason: &'a str,
+    },
+    Thinking,
+    Reply {
+        character: usize,
+        text: &'a str,
+        layers: &'a [String],
+        priority: Priority,
+        audio_size: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        audio: Option<String>,
+    },
+    Error {
+        message: &'a str,
+    },
+    Control {
+        command: ControlCommand,
+    },
+    ConfigReloaded,
+    SystemPromptReloaded {
+        character: usize,
+    },
+    Sound {
+        command: &'a SoundCommand,
+    },
+    Poll {
+        poll: &'a PollView,
+    },
+    /// `None` once the pomodoro stopped
+    Pomodoro {
+        pomodoro: &'a Option<PomodoroView>,
+    },
+    Speaking {
+        character: usize,
+        speaking: bool,
+        /// Unix time in milliseconds the voice started or stopped playing
+        timestamp: u64,
+    },
+}
+
+impl<'a> OverlayEvent<'a> {
+    pub fn from_ui_event(event: &'a UiEvent, with_audio: bool) -> Self {
+        match event {
+            UiEvent::NewComment(comment) => Self::NewComment { comment },
+            UiEvent::CommentRejected { comment, reason } => {
+                Self::CommentRejected { comment, reason }
+            }
+            UiEvent::AiThinking => Self::Thinking,
+            UiEvent::AiReply {
+                character,
+                text,
+                layers,
+                voice,
+                priority,
+                ..
+            } => Self::Reply {
+                character: *character,
+                text,
+                layers,
+                priority: *priority,
+                audio_size: voice.len(),
+                audio: with_audio.then(|| base64::engine::general_purpose::STANDARD.encode(voice)),
+            },
+            UiEvent::Error(message) => Self::Error { message },
+            UiEvent::Control(command) => Self::Control { command: *command },
+            UiEvent::ConfigReloaded(_) => Self::ConfigReloaded,
+            UiEvent::SystemPromptReloaded { character } => Self::SystemPromptReloaded {
+                character: *character,
+            },
+            UiEvent::Sound(command) => Self::Sound { command },
+            UiEvent::Poll(poll) => Self::Poll { poll },
+            UiEvent::Pomodoro(pomodoro) => Self::Pomodoro { pomodoro },
+            UiEvent::Speaking {
+                character,
+                speaking,
+                at,
+            } => Self::Speaking {
+                character: *character,
+                speaking: *speaking,
+                timestamp: at
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |since| since.as_millis() as u64),
+            },
+        }
+    }
+}
+
+/// Broadcast every [`UiEvent`] as json to the connected client.
+pub async fn events_ws(
+    req: HttpRequest,
+    body: web::Payload,
+    query: web::Query<EventsQuery>,
+    ui_sender: web::Data<UiEventSender>,
+) -> actix_web::Result<HttpResponse> {
+    let (response, mut session, mut msg_stream) = actix_ws::handle(&req, body)?;
+    let mut ui_rx = ui_sender.0.subscribe();
+    let with_audio = query.audio;
+
+    actix_web::rt::spawn(async move {
+        loop {
+            tokio::select! {
+                event = ui_rx.recv() => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            log::warn!("Overlay client lagged behind, skipped {skipped} events");
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    let json = serde_json::to_string(&OverlayEvent::from_ui_event(&event, with_audio))
+                        .expect("overlay events are serializable");
+                    if session.text(json).await.is_err() {
+                        return;
+                    }
+                }
+                msg = msg_stream.recv() => {
+                    match msg {
+                        Some(Ok(Message::Ping(bytes))) if session.pong(&bytes).await.is_err() => return,
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
@@ -0,0 +1,30 @@
+use actix_web::{HttpResponse, Responder, web};
+
+use crate::metrics::Metrics;
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct HealthModel {
+    /// "ok", or "degraded" while a part runs with less than configured
+    status: &'static str,
+    audio: AudioHealthModel,
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct AudioHealthModel {
+    /// Whether the voice plays on an output device
+    available: bool,
+    /// Why it plays silently
+    error: Option<String>,
+}
+
+/// What the app runs without, the app is up whenever this answers.
+pub async fn get_health(metrics: web::Data<Metrics>) -> impl Responder {
+    let error = metrics.audio.error();
+    HttpResponse::Ok().json(HealthModel {
+        status: if error.is_none() { "ok" } else { "degraded" },
+        audio: AudioHealthModel {
+            available: error.is_none(),
+            error,
+        },
+    })
+}
@@ -0,0 +1,10 @@
+use actix_web::{HttpResponse, Responder, web};
+
+use crate::metrics::Metrics;
+
+/// Pipeline counters and latencies in the Prometheus text format.
+pub async fn get_metrics(metrics: web::Data<Metrics>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.render())
+}
@@ -0,0 +1,38 @@
+use actix_web::{Responder, http::StatusCode, web};
+use http_common::error::{ApiError, check_text};
+
+use crate::{
+    bus::{InEvent, NotificationEvent},
+    server::EventSender,
+};
+
+/// Longer notifications are cut short by the desktop as well.
+const MAX_TEXT_CHARS: usize = 500;
+
+/// Let the characters tell about something that happened, e.g. a calendar reminder.
+pub async fn notify(
+    payload: web::Json<NotificationEvent>,
+    sender: web::Data<EventSender>,
+) -> Result<impl Responder, ApiError> {
+    let mut notification = payload.into_inner();
+    check_text("title", &notification.title, MAX_TEXT_CHARS)?;
+    if let Some(message) = &notification.message
+        && !message.trim().is_empty()
+    {
+        check_text("message", message, MAX_TEXT_CHARS)?;
+    }
+    notification.source = "http".to_string();
+    sender
+        .0
+        .send(InEvent::Notification(notification))
+        .await
+        .map_err(|_| {
+            ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "pipeline_stopped",
+                "Comment pipeline is not running",
+            )
+        })?;
+
+    Ok("ok")
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use actix_web::{HttpResponse, Responder, ResponseError, http::StatusCode, web};
+
+use crate::{
+    bus::UiEvent,
+    poll::{PollError, Polls},
+    server::UiEventSender,
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct StartPollModel {
+    question: String,
+    options: Vec<String>,
+    /// Seconds, 60 if unset
+    #[serde(default = "default_duration")]
+    duration: f64,
+}
+
+fn default_duration() -> f64 {
+    60.0
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PollsError {
+    #[error(transparent)]
+    Poll(#[from] PollError),
+    #[error("Invalid duration")]
+    InvalidDuration,
+}
+
+impl ResponseError for PollsError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            PollsError::Poll(PollError::Running) => StatusCode::CONFLICT,
+            PollsError::Poll(PollError::TooFewOptions) => StatusCode::BAD_REQUEST,
+            PollsError::Poll(PollError::NotRunning) => StatusCode::NOT_FOUND,
+            PollsError::InvalidDuration => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+pub async fn get_poll(polls: web::Data<Polls>) -> Result<impl Responder, PollsError> {
+    let poll = polls.view().ok_or(PollError::NotRunning)?;
+    Ok(HttpResponse::Ok().json(poll))
+}
+
+pub async fn start_poll(
+    body: web::Json<StartPollModel>,
+    polls: web::Data<Polls>,
+    ui_sender: web::Data<UiEventSender>,
+) -> Result<impl Responder, PollsError> {
+    let body = body.into_inner();
+    let duration =
+        Duration::try_from_secs_f64(body.duration).map_err(|_| PollsError::InvalidDuration)?;
+    let poll = polls.start(body.question, body.options, duration)?;
+    log::info!("Operator started poll {}", poll.question);
+    let _ = ui_sender.0.send(UiEvent::Poll(poll.clone()));
+    Ok(HttpResponse::Created().json(poll))
+}
+
+/// End the poll early, the results are announced like for a poll that ran out.
+pub async fn end_poll(polls: web::Data<Polls>) -> Result<impl Responder, PollsError> {
+    polls.finish_now()?;
+    Ok(HttpResponse::Accepted().finish())
+}
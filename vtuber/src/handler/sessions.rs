@@ -0,0 +1,103 @@
+use actix_web::{HttpResponse, Responder, ResponseError, http::StatusCode, web};
+
+use crate::{report::session_report, storage::Storage};
+
+#[derive(serde::Deserialize, Default, Clone, Copy, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptFormat {
+    #[default]
+    Json,
+    Markdown,
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct TranscriptQuery {
+    #[serde(default)]
+    format: TranscriptFormat,
+}
+
+#[derive(serde::Deserialize, Default, Clone, Copy, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Markdown,
+    Html,
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct ReportQuery {
+    #[serde(default)]
+    format: ReportFormat,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SessionsError {
+    #[error("Transcript storage is disabled")]
+    Disabled,
+    #[error("Unknown session {0}")]
+    UnknownSession(i64),
+    #[error("Failed to query transcripts: {0}")]
+    Database(#[from] rusqlite::Error),
+}
+
+impl ResponseError for SessionsError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            SessionsError::Disabled => StatusCode::SERVICE_UNAVAILABLE,
+            SessionsError::UnknownSession(_) => StatusCode::NOT_FOUND,
+            SessionsError::Database(_error) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+pub async fn list_sessions(
+    storage: Option<web::Data<Storage>>,
+) -> Result<impl Responder, SessionsError> {
+    let storage = storage.ok_or(SessionsError::Disabled)?;
+    let sessions = storage.blocking(Storage::sessions).await?;
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+pub async fn get_transcript(
+    path: web::Path<i64>,
+    query: web::Query<TranscriptQuery>,
+    storage: Option<web::Data<Storage>>,
+) -> Result<impl Responder, SessionsError> {
+    let storage = storage.ok_or(SessionsError::Disabled)?;
+    let session_id = path.into_inner();
+    let transcript = storage
+        .blocking(move |storage| storage.transcript(session_id))
+        .await?
+        .ok_or(SessionsError::UnknownSession(session_id))?;
+
+    Ok(match query.format {
+        TranscriptFormat::Json => HttpResponse::Ok().json(transcript),
+        TranscriptFormat::Markdown => HttpResponse::Ok()
+            .content_type("text/markdown; charset=utf-8")
+            .body(transcript.to_markdown()),
+    })
+}
+
+pub async fn get_report(
+    path: web::Path<i64>,
+    query: web::Query<ReportQuery>,
+    storage: Option<web::Data<Storage>>,
+) -> Result<impl Responder, SessionsError> {
+    let storage = storage.ok_or(SessionsError::Disabled)?;
+    let session_id = path.into_inner();
+    let report = storage
+        .blocking(move |storage| session_report(storage, session_id))
+        .await?
+        .ok_or(SessionsError::UnknownSession(session_id))?;
+
+    Ok(match query.format {
+        ReportFormat::Json => HttpResponse::Ok().json(report),
+        ReportFormat::Markdown => HttpResponse::Ok()
+            .content_type("text/markdown; charset=utf-8")
+            .body(report.to_markdown()),
+        ReportFormat::Html => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(report.to_html()),
+    })
+}
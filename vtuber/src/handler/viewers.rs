@@ -0,0 +1,111 @@
+use actix_web::{HttpResponse, Responder, ResponseError, http::StatusCode, web};
+use http_common::error::{ApiError, check_text};
+
+use crate::viewers::ViewerRegistry;
+
+/// Nicknames and notes go into the prompt, keep them short.
+const MAX_NICKNAME_CHARS: usize = 50;
+const MAX_NOTE_CHARS: usize = 200;
+const MAX_NOTES: usize = 20;
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct UpdateViewerModel {
+    /// An empty nickname removes it
+    #[serde(default)]
+    nickname: Option<String>,
+    /// Replaces all notes
+    #[serde(default)]
+    notes: Option<Vec<String>>,
+    #[serde(default)]
+    add_note: Option<String>,
+}
+
+impl UpdateViewerModel {
+    fn validate(&self) -> Result<(), ApiError> {
+        if let Some(nickname) = &self.nickname
+            && !nickname.is_empty()
+        {
+            check_text("nickname", nickname, MAX_NICKNAME_CHARS)?;
+        }
+        if let Some(notes) = &self.notes {
+            if notes.len() > MAX_NOTES {
+                return Err(ApiError::invalid(format!(
+                    "{} notes given, at most {MAX_NOTES} are allowed",
+                    notes.len()
+                )));
+            }
+            for note in notes {
+                check_text("note", note, MAX_NOTE_CHARS)?;
+            }
+        }
+        if let Some(note) = &self.add_note {
+            check_text("add_note", note, MAX_NOTE_CHARS)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ViewersError {
+    #[error("Viewer profiles are disabled")]
+    Disabled,
+    #[error("Unknown viewer {0}")]
+    UnknownViewer(String),
+    #[error("Failed to save viewer profiles: {0}")]
+    Save(#[from] std::io::Error),
+}
+
+impl ResponseError for ViewersError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ViewersError::Disabled => StatusCode::SERVICE_UNAVAILABLE,
+            ViewersError::UnknownViewer(_) => StatusCode::NOT_FOUND,
+            ViewersError::Save(_error) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+pub async fn list_viewers(
+    viewers: Option<web::Data<ViewerRegistry>>,
+) -> Result<impl Responder, ViewersError> {
+    let viewers = viewers.ok_or(ViewersError::Disabled)?;
+    Ok(HttpResponse::Ok().json(viewers.list()))
+}
+
+pub async fn get_viewer(
+    path: web::Path<String>,
+    viewers: Option<web::Data<ViewerRegistry>>,
+) -> Result<impl Responder, ViewersError> {
+    let viewers = viewers.ok_or(ViewersError::Disabled)?;
+    let name = path.into_inner();
+    let profile = viewers
+        .get(&name)
+        .ok_or(ViewersError::UnknownViewer(name))?;
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+pub async fn update_viewer(
+    path: web::Path<String>,
+    body: web::Json<UpdateViewerModel>,
+    viewers: Option<web::Data<ViewerRegistry>>,
+) -> actix_web::Result<impl Responder> {
+    body.validate()?;
+    let viewers = viewers.ok_or(ViewersError::Disabled)?;
+    let name = path.into_inner();
+    let body = body.into_inner();
+    let profile = viewers
+        .update(&name, |profile| {
+            if let Some(nickname) = body.nickname {
+                profile.nickname = (!nickname.is_empty()).then_some(nickname);
+            }
+            if let Some(notes) = body.notes {
+                profile.notes = notes;
+            }
+            if let Some(note) = body.add_note {
+                profile.notes.push(note);
+            }
+        })
+        .map_err(ViewersError::from)?
+        .ok_or(ViewersError::UnknownViewer(name))?;
+    Ok(HttpResponse::Ok().json(profile))
+}
@@ -0,0 +1,181 @@
+use std::{
+    io::Write,
+    process::{Child, Command, Stdio},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use image::{RgbaImage, imageops::FilterType};
+use tokio::sync::broadcast;
+
+use crate::{
+    bus::{ControlCommand, UiEvent},
+    capture::Recorder,
+    config::{AppConfig, HeadlessConfig, RenderConfig},
+    metrics::Metrics,
+    player::{Line, Player},
+    render::{Frame, RenderWorker},
+    shutdown::Shutdown,
+};
+
+/// Run without a window, streaming composited frames into an external program.
+///
+/// Frames are raw RGBA at a fixed rate, e.g. piped into ffmpeg to feed a v4l2loopback
+/// virtual camera or an NDI stream. Audio is played on the default output like in the GUI.
+/// Multiple characters are placed side by side in one frame.
+pub fn run_headless(
+    mut ui_rx: broadcast::Receiver<UiEvent>,
+    ui_tx: broadcast::Sender<UiEvent>,
+    app_config: &AppConfig,
+    config: &HeadlessConfig,
+    shutdown: Shutdown,
+    metrics: Arc<Metrics>,
+) -> anyhow::Result<()> {
+    let mut render_configs: Vec<RenderConfig> = app_config
+        .characters
+        .iter()
+        .map(|character| character.render.clone())
+        .collect();
+    let mut player = Player::new(app_config, metrics, ui_tx)?;
+
+    // each character gets a slot as large as its base image
+    let mut images = Vec::with_capacity(render_configs.len());
+    for render_config in &mut render_configs {
+        let layers = render_config.with_base_layer(&render_config.default_layers);
+        let image = render_config.model.render(&layers)?.into_rgba8();
+        images.push(image);
+    }
+    let width = images.iter().map(|image| image.width()).sum();
+    let height = images.iter().map(|image| image.height()).max().unwrap_or(1);
+    let mut slots = Vec::with_capacity(images.len());
+    let mut frame = RgbaImage::new(width, height);
+    let mut x = 0;
+    for image in &images {
+        image::imageops::replace(&mut frame, image, x as i64, 0);
+        slots.push((x, image.dimensions()));
+        x += image.width();
+    }
+
+    let mut sink = spawn_frame_sink(config, width, height)?;
+    let mut stdin = sink.stdin.take().expect("stdin is piped");
+    log::info!("Streaming {width}x{height} frames at {} fps", config.fps);
+
+    let mut renderer = RenderWorker::spawn(render_configs, || {});
+    let mut recorder = Recorder::new(&app_config.capture);
+    recorder.record(frame.clone(), Instant::now());
+
+    let interval = Duration::from_secs_f64(1.0 / config.fps.max(1) as f64);
+    let mut next_frame = Instant::now();
+    loop {
+        // let the current sentence finish, then close the stream so the sink can flush
+        if shutdown.is_triggered() && player.wind_down() {
+            drop(stdin);
+            sink.wait()?;
+            return Ok(());
+        }
+
+        loop {
+            match ui_rx.try_recv() {
+                Ok(UiEvent::AiReply {
+                    character,
+                    text,
+                    layers,
+                    voice,
+                    priority,
+                    trace,
+                }) => player.enqueue(Line {
+                    character,
+                    text,
+                    layers,
+                    voice,
+                    priority,
+                    trace: Some(trace),
+                    journal_seq: None,
+                }),
+                Ok(UiEvent::Control(command)) => {
+                    match command {
+                        ControlCommand::Screenshot => recorder.screenshot(),
+                        ControlCommand::Clip => recorder.save_clip(Instant::now()),
+                        _ => {}
+                    }
+                    if player.handle_control(command)
+                        && let Some(line) = player.current()
+                    {
+                        renderer.render(line.character, player.shown_layers());
+                    }
+                }
+                Ok(UiEvent::ConfigReloaded(live)) => {
+                    // only the main character is reloaded
+                    if renderer.set_base_layer(0, &live.base_layer) {
+                        let layers = match player.current() {
+                            Some(line) if line.character == 0 => player.shown_layers(),
+                            _ => player.default_layers(0),
+                        };
+                        renderer.render(0, layers);
+                    }
+                }
+                Ok(UiEvent::Sound(command)) => player.handle_sound(&command),
+                Ok(UiEvent::Error(err)) => log::error!("Pipeline error: {err}"),
+                Ok(_) => {}
+                Err(broadcast::error::TryRecvError::Empty) => break,
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(broadcast::error::TryRecvError::Closed) => return Ok(()),
+            }
+        }
+
+        if player.poll()
+            && let Some(line) = player.current()
+        {
+            renderer.render(line.character, player.shown_layers());
+        }
+
+        let mut changed = false;
+        while let Some((character, Frame { image, .. })) = renderer.try_frame() {
+            changed = true;
+            player.frame_shown(character);
+            let (x, (slot_width, slot_height)) = slots[character];
+            let image = if image.dimensions() == (slot_width, slot_height) {
+                image
+            } else {
+                // the stream size is fixed once the sink is started
+                image::imageops::resize(&image, slot_width, slot_height, FilterType::Triangle)
+            };
+            image::imageops::replace(&mut frame, &image, x as i64, 0);
+        }
+        if changed {
+            recorder.record(frame.clone(), Instant::now());
+        }
+
+        if let Err(e) = stdin.write_all(frame.as_raw()) {
+            let status = sink.wait()?;
+            anyhow::bail!("Frame sink exited ({status}): {e}");
+        }
+
+        next_frame += interval;
+        let now = Instant::now();
+        if next_frame > now {
+            std::thread::sleep(next_frame - now);
+        } else {
+            // fell behind, don't try to catch up with a burst of frames
+            next_frame = now;
+        }
+    }
+}
+
+fn spawn_frame_sink(config: &HeadlessConfig, width: u32, height: u32) -> anyhow::Result<Child> {
+    let args: Vec<String> = config
+        .command
+        .iter()
+        .map(|arg| {
+            arg.replace("{width}", &width.to_string())
+                .replace("{height}", &height.to_string())
+                .replace("{fps}", &config.fps.to_string())
+        })
+        .collect();
+    log::info!("Starting frame sink: {}", args.join(" "));
+
+    Ok(Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(Stdio::piped())
+        .spawn()?)
+}
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use tokio::sync::mpsc;
+
+use crate::{
+    bus::{ControlCommand, InEvent},
+    config::HotkeyConfig,
+};
+
+/// Registered global hotkeys, unregistered when dropped.
+///
+/// Must be created on the main thread. On Windows and macOS the events only arrive while the
+/// main thread runs the event loop of the GUI, so they don't work in headless mode; X11 has a
+/// thread of its own.
+pub struct Hotkeys {
+    _manager: GlobalHotKeyManager,
+}
+
+impl Hotkeys {
+    pub fn register(config: &HotkeyConfig, in_tx: mpsc::Sender<InEvent>) -> anyhow::Result<Self> {
+        let manager = GlobalHotKeyManager::new()?;
+        let mut commands = HashMap::new();
+        for (hotkey, command) in &config.bindings {
+            manager.register(*hotkey)?;
+            commands.insert(hotkey.id(), *command);
+            log::info!("Bound {command:?} to {}", hotkey.into_string());
+        }
+
+        std::thread::spawn(move || {
+            let receiver = GlobalHotKeyEvent::receiver();
+            while let Ok(event) = receiver.recv() {
+                let command = match (event.state(), commands.get(&event.id())) {
+                    (HotKeyState::Pressed, Some(command)) => *command,
+                    // push-to-talk is held down
+                    (HotKeyState::Released, Some(ControlCommand::StartListening)) => {
+                        ControlCommand::StopListening
+                    }
+                    _ => continue,
+                };
+                if in_tx.blocking_send(InEvent::Control(command)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self { _manager: manager })
+    }
+}
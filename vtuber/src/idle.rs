@@ -0,0 +1,200 @@
+use std::{
+    f32::consts::TAU,
+    time::{Duration, Instant},
+};
+
+use eframe::egui::Vec2;
+
+use crate::config::IdleConfig;
+
+/// How long every layer of a blink is shown.
+const BLINK_STEP: Duration = Duration::from_millis(60);
+/// One breath in and out.
+const BREATH_PERIOD: Duration = Duration::from_millis(4500);
+/// One sway to the side and back.
+const SWAY_PERIOD: Duration = Duration::from_millis(7300);
+/// How quickly the characters turn to the cursor, per second.
+const FOLLOW_SPEED: f32 = 6.0;
+
+/// Plays a character's blink layers now and then.
+pub struct Blinker {
+    layers: Vec<String>,
+    /// Average time between blinks
+    interval: Duration,
+    next_blink: Instant,
+    /// When the running blink started
+    started_at: Option<Instant>,
+    /// The shown layer of the running blink
+    step: Option<usize>,
+}
+
+impl Blinker {
+    pub fn new(layers: Vec<String>, config: &IdleConfig, now: Instant) -> Self {
+        let mut blinker = Self {
+            layers,
+            interval: config.blink_interval,
+            next_blink: now,
+            started_at: None,
+            step: None,
+        };
+        blinker.schedule(now);
+        blinker
+    }
+
+    /// Advance the blink, returns true if the shown layer changed.
+    pub fn update(&mut self, now: Instant) -> bool {
+        if self.layers.is_empty() {
+            return false;
+        }
+
+        if self.started_at.is_none() && now >= self.next_blink {
+            self.started_at = Some(now);
+        }
+        let step = self
+            .started_at
+            .map(|started_at| {
+                (now.duration_since(started_at).as_millis() / BLINK_STEP.as_millis()) as usize
+            })
+            .filter(|&step| step < self.layers.len());
+        if step.is_none() && self.started_at.take().is_some() {
+            self.schedule(now);
+        }
+
+        let changed = step != self.step;
+        self.step = step;
+        changed
+    }
+
+    pub fn is_blinking(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    /// The blink layer to show on top of everything else.
+    pub fn layer(&self) -> Option<&String> {
+        self.step.map(|step| &self.layers[step])
+    }
+
+    fn schedule(&mut self, now: Instant) {
+        // anywhere from half to one and a half times the interval, so blinks don't look mechanical
+        self.next_blink = now + self.interval.mul_f32(0.5 + fastrand::f32());
+    }
+}
+
+/// Scale of the character while breathing, `amplitude` is the largest change in size.
+///
+/// Every character gets a `phase` so they don't breathe in unison.
+pub fn breathing_scale(elapsed: Duration, phase: f32, amplitude: f32) -> f32 {
+    let t = elapsed.as_secs_f32() / BREATH_PERIOD.as_secs_f32() + phase;
+    1.0 + amplitude * (t * TAU).sin()
+}
+
+/// How far the character sways to the side, `amplitude` is the largest part of its width.
+pub fn sway_offset(elapsed: Duration, phase: f32, amplitude: f32) -> f32 {
+    let t = elapsed.as_secs_f32() / SWAY_PERIOD.as_secs_f32() + phase;
+    amplitude * (t * TAU).sin()
+}
+
+/// Turns a character towards the mouse cursor, easing so it doesn't jump along with it.
+pub struct CursorFollow {
+    /// The largest shift, as a part of the character's size
+    intensity: f32,
+    /// Where the character looks, -1 to 1 on both axes
+    direction: Vec2,
+}
+
+impl CursorFollow {
+    pub fn new(intensity: f32) -> Self {
+        Self {
+            intensity,
+            direction: Vec2::ZERO,
+        }
+    }
+
+    /// Ease towards the cursor, given relative to the character's center in halves of its
+    /// size, or back to the front when the cursor is away. Returns whether it still moves.
+    pub fn update(&mut self, cursor: Option<Vec2>, dt: f32) -> bool {
+        if self.intensity == 0.0 {
+            return false;
+        }
+        let target = cursor
+            .map(|cursor| cursor.clamp(Vec2::splat(-1.0), Vec2::splat(1.0)))
+            .unwrap_or(Vec2::ZERO);
+        self.direction += (target - self.direction) * (1.0 - (-FOLLOW_SPEED * dt).exp());
+        (target - self.direction).length() > 0.001
+    }
+
+    /// How far the character is moved towards the cursor.
+    pub fn offset(&self, size: Vec2) -> Vec2 {
+        self.direction * size * self.intensity
+    }
+
+    /// How far the character leans towards the cursor, in radians around its feet.
+    pub fn lean(&self) -> f32 {
+        self.direction.x * self.intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use eframe::egui::{Vec2, vec2};
+
+    use crate::{
+        config::IdleConfig,
+        idle::{Blinker, CursorFollow},
+    };
+
+    #[test]
+    fn blink_through_layers() {
+        let config = IdleConfig {
+            blink_interval: Duration::from_secs(4),
+            breathing: 0.0,
+            sway: 0.0,
+            follow: 0.0,
+            expression_hold: Duration::ZERO,
+            rest_after: None,
+        };
+        let start = Instant::now();
+        let layers = vec!["half.png".to_string(), "closed.png".to_string()];
+        let mut blinker = Blinker::new(layers, &config, start);
+        assert!(!blinker.update(start));
+        assert!(blinker.layer().is_none());
+
+        // the first blink comes within one and a half intervals
+        let blink = start + Duration::from_secs(6);
+        assert!(blinker.update(blink));
+        assert_eq!(blinker.layer().unwrap(), "half.png");
+        assert!(!blinker.update(blink + Duration::from_millis(30)));
+        assert!(blinker.update(blink + Duration::from_millis(70)));
+        assert_eq!(blinker.layer().unwrap(), "closed.png");
+        assert!(blinker.update(blink + Duration::from_millis(130)));
+        assert!(blinker.layer().is_none());
+    }
+
+    #[test]
+    fn follow_the_cursor() {
+        let mut follow = CursorFollow::new(0.02);
+        let size = vec2(400.0, 800.0);
+        // far to the right and level with the center
+        let mut moving = true;
+        for _ in 0..120 {
+            moving = follow.update(Some(vec2(5.0, 0.0)), 1.0 / 60.0);
+        }
+        assert!(!moving);
+        let offset = follow.offset(size);
+        assert!((offset.x - 8.0).abs() < 0.01 && offset.y.abs() < 0.01);
+        assert!(follow.lean() > 0.0);
+
+        // back to the front once the cursor left
+        assert!(follow.update(None, 1.0 / 60.0));
+        for _ in 0..120 {
+            follow.update(None, 1.0 / 60.0);
+        }
+        assert!(follow.offset(size).length() < 0.01);
+
+        let mut still = CursorFollow::new(0.0);
+        assert!(!still.update(Some(vec2(1.0, 1.0)), 1.0 / 60.0));
+        assert_eq!(still.offset(size), Vec2::ZERO);
+    }
+}
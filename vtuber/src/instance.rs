@@ -0,0 +1,101 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream},
+    time::Duration,
+};
+
+use tokio::sync::mpsc;
+
+use crate::bus::{ControlCommand, InEvent};
+
+/// Where the running instance listens unless `VTUBER_INSTANCE_ADDRESS` says otherwise.
+pub const DEFAULT_ADDRESS: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 47811));
+
+/// Sent by a second start, tells the listener apart from other programs on the port.
+const ACTIVATE: &str = "murasame activate";
+/// Answered by the listener, tells the second start that the app really runs.
+const ACTIVATED: &str = "murasame activated";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Become the only running instance by listening on `addr`, or ask the instance already
+/// listening there to raise its window and return `None`. Fails if another program has the
+/// port.
+pub fn acquire(addr: SocketAddr) -> anyhow::Result<Option<TcpListener>> {
+    match TcpListener::bind(addr) {
+        Ok(listener) => Ok(Some(listener)),
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+            stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+            writeln!(stream, "{ACTIVATE}")?;
+            let mut line = String::new();
+            let answered = BufReader::new(stream).read_line(&mut line).is_ok();
+            if !answered || line.trim() != ACTIVATED {
+                anyhow::bail!(
+                    "{addr} is taken by another program, set VTUBER_INSTANCE_ADDRESS to a free port or \"off\""
+                );
+            }
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Turn every later start of the app into an [`ControlCommand::Activate`].
+pub fn spawn_activation_listener(listener: TcpListener, in_tx: mpsc::Sender<InEvent>) {
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let _ = stream.set_read_timeout(Some(CONNECT_TIMEOUT));
+            let mut line = String::new();
+            if BufReader::new(&stream).read_line(&mut line).is_err() || line.trim() != ACTIVATE {
+                continue;
+            }
+            log::info!("The app was started again, raising the window");
+            if in_tx
+                .blocking_send(InEvent::Control(ControlCommand::Activate))
+                .is_err()
+            {
+                break;
+            }
+            let _ = writeln!(&stream, "{ACTIVATED}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+
+    use tokio::sync::mpsc;
+
+    use crate::{
+        bus::{ControlCommand, InEvent},
+        instance::{acquire, spawn_activation_listener},
+    };
+
+    #[test]
+    fn forward_second_start() {
+        let listener = acquire(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .unwrap()
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (in_tx, mut in_rx) = mpsc::channel(1);
+        spawn_activation_listener(listener, in_tx);
+
+        assert!(acquire(addr).unwrap().is_none());
+        assert!(matches!(
+            in_rx.blocking_recv(),
+            Some(InEvent::Control(ControlCommand::Activate))
+        ));
+    }
+
+    #[test]
+    fn refuse_other_programs() {
+        // accepts but never answers
+        let other = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).unwrap();
+        let addr = other.local_addr().unwrap();
+        assert!(acquire(addr).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use std::{fs, io, path::Path, sync::mpsc, thread::JoinHandle};
+
+use bytes::Bytes;
+
+use crate::{bus::Priority, player::Line};
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Entry {
+    character: usize,
+    text: String,
+    layers: Vec<String>,
+    priority: Priority,
+}
+
+/// A change to the journal, done on its thread.
+enum Write {
+    Append {
+        seq: u64,
+        entry: Entry,
+        voice: Bytes,
+    },
+    Remove(u64),
+}
+
+/// Reply lines waiting to be spoken, kept on disk until they were played so a crash or restart
+/// doesn't lose answers that were already paid for.
+///
+/// Every line is a `<seq>.wav` with its voice and a `<seq>.json` written after it. The files are
+/// written on a thread of their own to not hold up the window, dropping the journal waits for
+/// them.
+pub struct ReplyJournal {
+    writes: Option<mpsc::Sender<Write>>,
+    worker: Option<JoinHandle<()>>,
+    next_seq: u64,
+}
+
+impl ReplyJournal {
+    /// Open the journal, returning the lines left over from the last run in order. Lines that
+    /// can't be resumed are removed.
+    pub fn open(dir: &Path) -> io::Result<(Self, Vec<Line>)> {
+        fs::create_dir_all(dir)?;
+
+        let mut seqs = Vec::new();
+        let mut voices = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let seq = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok());
+            match (
+                seq,
+                path.extension().and_then(|extension| extension.to_str()),
+            ) {
+                (Some(seq), Some("json")) => seqs.push(seq),
+                (Some(seq), Some("wav")) => voices.push(seq),
+                _ => {}
+            }
+        }
+        seqs.sort_unstable();
+
+        let mut lines = Vec::with_capacity(seqs.len());
+        for &seq in &seqs {
+            match read_line(dir, seq) {
+                Ok(mut line) => {
+                    line.journal_seq = Some(seq);
+                    lines.push(line);
+                }
+                Err(e) => {
+                    log::error!("Dropped unreadable reply {seq}: {e}");
+                    remove(dir, seq);
+                }
+            }
+        }
+        // the app stopped between writing the voice and the metadata
+        for seq in voices {
+            if seqs.binary_search(&seq).is_err() {
+                remove(dir, seq);
+            }
+        }
+
+        let next_seq = seqs.last().map_or(0, |seq| seq + 1);
+        let (writes, write_rx) = mpsc::channel();
+        let worker = std::thread::Builder::new()
+            .name("reply journal".to_string())
+            .spawn({
+                let dir = dir.to_path_buf();
+                move || run_worker(&dir, write_rx)
+            })?;
+        let journal = Self {
+            writes: Some(writes),
+            worker: Some(worker),
+            next_seq,
+        };
+        Ok((journal, lines))
+    }
+
+    /// Save a line before it is queued, returns its sequence number.
+    pub fn append(&mut self, line: &Line) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        let entry = Entry {
+            character: line.character,
+            text: line.text.clone(),
+            layers: line.layers.clone(),
+            priority: line.priority,
+        };
+        self.send(Write::Append {
+            seq,
+            entry,
+            voice: line.voice.clone(),
+        });
+        seq
+    }
+
+    /// Forget a line that was spoken or dropped.
+    pub fn remove(&self, seq: u64) {
+        self.send(Write::Remove(seq));
+    }
+
+    fn send(&self, write: Write) {
+        if let Some(writes) = &self.writes
+            && writes.send(write).is_err()
+        {
+            log::error!("The reply journal stopped, lines are not saved anymore");
+        }
+    }
+}
+
+impl Drop for ReplyJournal {
+    fn drop(&mut self) {
+        // the worker stops once it wrote everything sent before
+        self.writes = None;
+        if let Some(worker) = self.worker.take()
+            && worker.join().is_err()
+        {
+            log::error!("The reply journal thread panicked");
+        }
+    }
+}
+
+fn run_worker(dir: &Path, writes: mpsc::Receiver<Write>) {
+    for write in writes {
+        match write {
+            Write::Append { seq, entry, voice } => {
+                if let Err(e) = append(dir, seq, &entry, &voice) {
+                    log::error!("Failed to save reply line: {e}");
+                }
+            }
+            Write::Remove(seq) => remove(dir, seq),
+        }
+    }
+}
+
+fn append(dir: &Path, seq: u64, entry: &Entry, voice: &[u8]) -> io::Result<()> {
+    fs::write(dir.join(format!("{seq}.wav")), voice)?;
+    // the metadata marks the line as complete
+    fs::write(dir.join(format!("{seq}.json")), serde_json::to_vec(entry)?)
+}
+
+fn remove(dir: &Path, seq: u64) {
+    for extension in ["json", "wav"] {
+        let path = dir.join(format!("{seq}.{extension}"));
+        if let Err(e) = fs::remove_file(&path)
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            log::error!("Failed to remove {}: {e}", path.display());
+        }
+    }
+}
+
+fn read_line(dir: &Path, seq: u64) -> anyhow::Result<Line> {
+    let entry: Entry = serde_json::from_slice(&fs::read(dir.join(format!("{seq}.json")))?)?;
+    let voice = Bytes::from(fs::read(dir.join(format!("{seq}.wav")))?);
+    Ok(Line {
+        character: entry.character,
+        text: entry.text,
+        layers: entry.layers,
+        voice,
+        priority: entry.priority,
+        trace: None,
+        journal_seq: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{bus::Priority, journal::ReplyJournal, player::Line};
+
+    fn line(text: &str) -> Line {
+        Line {
+            character: 0,
+            text: text.to_string(),
+            layers: vec!["smile.png".to_string()],
+            voice: Bytes::from_static(b"RIFF"),
+            priority: Priority::Mention,
+            trace: None,
+            journal_seq: None,
+        }
+    }
+
+    #[test]
+    fn restore_unplayed_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut journal, restored) = ReplyJournal::open(dir.path()).unwrap();
+        assert!(restored.is_empty());
+
+        let first = journal.append(&line("first"));
+        journal.append(&line("second"));
+        journal.append(&line("third"));
+        journal.remove(first);
+        // waits for the writes
+        drop(journal);
+
+        // left behind by a crash and a broken file
+        std::fs::write(dir.path().join("7.wav"), b"RIFF").unwrap();
+        std::fs::write(dir.path().join("8.json"), b"{").unwrap();
+        let (mut journal, restored) = ReplyJournal::open(dir.path()).unwrap();
+        let texts: Vec<_> = restored.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, ["second", "third"]);
+        assert_eq!(restored[0].priority, Priority::Mention);
+        assert_eq!(restored[0].voice, Bytes::from_static(b"RIFF"));
+        assert!(!dir.path().join("7.wav").exists());
+        assert!(!dir.path().join("8.json").exists());
+        // numbers are not reused
+        assert_eq!(journal.append(&line("fourth")), 9);
+        drop(journal);
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Stages a comment goes through until its answer is shown, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Waiting in the comment queue
+    Queue,
+    /// Until the LLM answered
+    Llm,
+    /// Until a line's voice was synthesized
+    Tts,
+    /// Until the frontend started speaking the line, including the lines before it
+    Playback,
+    /// Until the frame showing the line's expression was ready
+    Render,
+}
+
+impl Stage {
+    fn name(self) -> &'static str {
+        match self {
+            Stage::Queue => "queue",
+            Stage::Llm => "llm",
+            Stage::Tts => "tts",
+            Stage::Playback => "playback",
+            Stage::Render => "render",
+        }
+    }
+}
+
+/// When a comment finished every stage on its way to being answered, carried along with the
+/// bus events so each stage adds its own. Every line of an answer gets its own copy once the
+/// answer is split up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    /// Id of the answered comment, correlates the log lines of all stages
+    pub id: u64,
+    pub received_at: Instant,
+    /// End of every stage passed so far
+    marks: Vec<(Stage, Instant)>,
+}
+
+impl Trace {
+    pub fn new(id: u64, received_at: Instant) -> Self {
+        Self {
+            id,
+            received_at,
+            marks: Vec::new(),
+        }
+    }
+
+    /// Note that `stage` ended at `at`.
+    pub fn mark(&mut self, stage: Stage, at: Instant) {
+        self.marks.push((stage, at));
+    }
+
+    pub fn marked(&self, stage: Stage) -> Option<Instant> {
+        self.marks
+            .iter()
+            .find(|(marked, _)| *marked == stage)
+            .map(|(_, at)| *at)
+    }
+
+    /// How long each stage took, from the end of the one before.
+    pub fn stages(&self) -> Vec<(Stage, Duration)> {
+        let mut start = self.received_at;
+        self.marks
+            .iter()
+            .map(|&(stage, at)| {
+                let duration = at.saturating_duration_since(start);
+                start = at;
+                (stage, duration)
+            })
+            .collect()
+    }
+
+    /// From receiving the comment until the last stage passed.
+    pub fn total(&self) -> Duration {
+        self.marks.last().map_or(Duration::ZERO, |(_, at)| {
+            at.saturating_duration_since(self.received_at)
+        })
+    }
+}
+
+/// One line for the logs, e.g. `comment 12: queue 0.10s, llm 1.20s, ... = 2.42s`.
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "comment {}:", self.id)?;
+        for (i, (stage, duration)) in self.stages().into_iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(
+                f,
+                "{separator}{} {:.2}s",
+                stage.name(),
+                duration.as_secs_f64()
+            )?;
+        }
+        write!(f, " = {:.2}s", self.total().as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::latency::{Stage, Trace};
+
+    #[test]
+    fn time_stages() {
+        let received = Instant::now();
+        let mut trace = Trace::new(12, received);
+        assert_eq!(trace.total(), Duration::ZERO);
+
+        trace.mark(Stage::Queue, received + Duration::from_millis(100));
+        trace.mark(Stage::Llm, received + Duration::from_millis(1300));
+        trace.mark(Stage::Tts, received + Duration::from_millis(2100));
+        assert_eq!(
+            trace.stages(),
+            [
+                (Stage::Queue, Duration::from_millis(100)),
+                (Stage::Llm, Duration::from_millis(1200)),
+                (Stage::Tts, Duration::from_millis(800)),
+            ]
+        );
+        assert_eq!(trace.total(), Duration::from_millis(2100));
+        assert_eq!(
+            trace.marked(Stage::Llm),
+            Some(received + Duration::from_millis(1300))
+        );
+        assert!(trace.marked(Stage::Render).is_none());
+        assert_eq!(
+            trace.to_string(),
+            "comment 12: queue 0.10s, llm 1.20s, tts 0.80s = 2.10s"
+        );
+    }
+}
@@ -1,11 +1,71 @@
+pub(crate) mod autostart;
 pub(crate) mod bus;
+pub(crate) mod capture;
+pub(crate) mod chat_input;
+pub(crate) mod comment_status;
+pub(crate) mod companion;
 pub mod config;
+pub(crate) mod crash;
+pub(crate) mod crossfade;
+pub(crate) mod effects;
 pub(crate) mod handler;
+pub(crate) mod hotkey;
+pub(crate) mod idle;
+pub(crate) mod instance;
+pub(crate) mod journal;
+pub(crate) mod latency;
+pub(crate) mod lipsync;
+pub(crate) mod logging;
+pub(crate) mod meter;
+pub(crate) mod metrics;
+pub(crate) mod moderation;
+pub(crate) mod names;
+pub(crate) mod notification;
+pub(crate) mod obs;
+pub(crate) mod output;
+pub(crate) mod pipeline;
+pub(crate) mod player;
+pub(crate) mod plugin;
+pub(crate) mod poll;
+pub(crate) mod queue;
+pub(crate) mod reaction;
+pub(crate) mod read_aloud;
+pub(crate) mod reload;
+pub(crate) mod render;
+pub(crate) mod replay;
+pub(crate) mod report;
+pub(crate) mod safety;
+pub(crate) mod scaling;
 pub(crate) mod scope;
+pub(crate) mod settings;
+pub(crate) mod soundboard;
+pub(crate) mod source;
+pub(crate) mod storage;
+pub(crate) mod stt;
+pub(crate) mod subtitle;
+pub(crate) mod subtitle_style;
+pub(crate) mod supervisor;
+pub(crate) mod textures;
+pub(crate) mod theme;
+pub(crate) mod toast;
+pub(crate) mod tools;
+pub(crate) mod topics;
+pub(crate) mod touch;
+pub(crate) mod transcript;
+pub(crate) mod translation;
+pub(crate) mod tts_cache;
 pub(crate) mod utils;
+pub(crate) mod viewers;
+pub(crate) mod walk;
 
 mod gui;
+mod headless;
+mod openapi;
 mod server;
+mod shutdown;
 mod startup;
 
+pub use autostart::{disable as disable_autostart, enable as enable_autostart};
+pub use crash::install as install_crash_reporter;
+pub use logging::init as init_logging;
 pub use startup::run;
@@ -0,0 +1,84 @@
+use std::{
+    io::{BufReader, Cursor},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use rodio::{Decoder, Source};
+
+/// Length of one step of the envelope, the mouth moves at most this often.
+const FRAME: Duration = Duration::from_millis(60);
+/// Frames quieter than this share of the loudest one keep the mouth closed.
+const SILENCE: f32 = 0.15;
+
+/// Loudness of a voice over time, scaled so the loudest frame is 1.
+pub struct Envelope {
+    levels: Vec<f32>,
+}
+
+impl Envelope {
+    pub fn analyze(voice: &Bytes) -> Option<Self> {
+        let source = Decoder::new(BufReader::new(Cursor::new(voice.clone()))).ok()?;
+        let window = source.sample_rate() as f32 * source.channels() as f32 * FRAME.as_secs_f32();
+        Some(Self::from_samples(source, (window as usize).max(1)))
+    }
+
+    /// RMS of every `window` samples.
+    fn from_samples(samples: impl Iterator<Item = f32>, window: usize) -> Self {
+        let mut levels = Vec::new();
+        let (mut sum, mut count) = (0.0, 0);
+        for sample in samples {
+            sum += sample * sample;
+            count += 1;
+            if count == window {
+                levels.push((sum / count as f32).sqrt());
+                (sum, count) = (0.0, 0);
+            }
+        }
+        if count > 0 {
+            levels.push((sum / count as f32).sqrt());
+        }
+
+        let peak = levels.iter().copied().fold(0.0, f32::max);
+        if peak > 0.0 {
+            for level in &mut levels {
+                *level /= peak;
+            }
+        }
+        Self { levels }
+    }
+
+    /// Which of `count` mouth layers, ordered from closed to open, fits the voice at `position`.
+    pub fn mouth_at(&self, position: Duration, count: usize) -> usize {
+        let frame = (position.as_secs_f32() / FRAME.as_secs_f32()) as usize;
+        mouth_for_level(self.levels.get(frame).copied().unwrap_or(0.0), count)
+    }
+}
+
+fn mouth_for_level(level: f32, count: usize) -> usize {
+    if count <= 1 || level < SILENCE {
+        return 0;
+    }
+    let open = (level - SILENCE) / (1.0 - SILENCE);
+    1 + ((open * (count - 1) as f32) as usize).min(count - 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::lipsync::Envelope;
+
+    #[test]
+    fn mouth_follows_loudness() {
+        // silence, a quiet frame, a loud frame and silence again
+        let samples = [0.0, 0.0, 0.3, -0.3, 1.0, -1.0, 0.0, 0.0];
+        let envelope = Envelope::from_samples(samples.into_iter(), 2);
+        let frame = |index: u64| Duration::from_millis(60 * index + 10);
+
+        let mouths: Vec<_> = (0..5).map(|i| envelope.mouth_at(frame(i), 3)).collect();
+        assert_eq!(mouths, [0, 1, 2, 0, 0]);
+        // a single layer is never opened
+        assert_eq!(envelope.mouth_at(frame(2), 1), 0);
+    }
+}
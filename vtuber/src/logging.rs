@@ -0,0 +1,131 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use env_logger::{Env, Target, WriteStyle};
+
+/// A log file is continued in a new one once it grew this large.
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+/// Older log files are deleted, counting the one being written.
+const KEPT_LOGS: usize = 5;
+
+/// Log to stderr and to `vtuber.log` in `dir`, only to stderr if the file can't be written.
+pub fn init(dir: &Path) {
+    let mut builder = env_logger::Builder::from_env(Env::default().default_filter_or("info"));
+    let opened = RotatingLog::open(dir, MAX_LOG_SIZE, KEPT_LOGS).map(|file| {
+        builder
+            .target(Target::Pipe(Box::new(Tee(file))))
+            .write_style(WriteStyle::Never);
+    });
+    builder.init();
+    match opened {
+        Ok(()) => log::info!("Logging to {}", log_path(dir, 0).display()),
+        Err(e) => log::warn!("Failed to open a log file in {}: {e}", dir.display()),
+    }
+}
+
+/// `vtuber.log` is written to, `vtuber.1.log` is the one before and so on.
+fn log_path(dir: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => dir.join("vtuber.log"),
+        _ => dir.join(format!("vtuber.{index}.log")),
+    }
+}
+
+/// A log file that is rotated on start and whenever it grew too large, keeping the last few.
+struct RotatingLog {
+    dir: PathBuf,
+    file: File,
+    written: u64,
+    max_size: u64,
+    kept: usize,
+}
+
+impl RotatingLog {
+    /// Every run starts with a fresh file.
+    fn open(dir: &Path, max_size: u64, kept: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        rotate(dir, kept)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file: File::create(log_path(dir, 0))?,
+            written: 0,
+            max_size,
+            kept,
+        })
+    }
+}
+
+/// Move every log file one index up, dropping the oldest.
+fn rotate(dir: &Path, kept: usize) -> io::Result<()> {
+    for index in (0..kept.saturating_sub(1)).rev() {
+        let from = log_path(dir, index);
+        if from.exists() {
+            fs::rename(from, log_path(dir, index + 1))?;
+        }
+    }
+    Ok(())
+}
+
+impl Write for RotatingLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_size {
+            self.file.flush()?;
+            rotate(&self.dir, self.kept)?;
+            self.file = File::create(log_path(&self.dir, 0))?;
+            self.written = 0;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Writes the log to stderr as well, with the secrets redacted.
+struct Tee(RotatingLog);
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        let redacted = config::redact(&text);
+        let _ = io::stderr().write_all(redacted.as_bytes());
+        self.0.write_all(redacted.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = io::stderr().flush();
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use crate::logging::{RotatingLog, log_path};
+
+    #[test]
+    fn rotate_log_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(log_path(dir, 0), "last run\n").unwrap();
+
+        let mut log = RotatingLog::open(dir, 10, 3).unwrap();
+        assert_eq!(fs::read_to_string(log_path(dir, 1)).unwrap(), "last run\n");
+        log.write_all(b"first\n").unwrap();
+        log.write_all(b"second\n").unwrap();
+        log.write_all(b"third\n").unwrap();
+
+        assert_eq!(fs::read_to_string(log_path(dir, 0)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(log_path(dir, 1)).unwrap(), "second\n");
+        assert_eq!(fs::read_to_string(log_path(dir, 2)).unwrap(), "first\n");
+        assert!(!log_path(dir, 3).exists());
+    }
+}
@@ -1,12 +1,43 @@
-use env_logger::Env;
-use vtuber::run;
+use std::path::PathBuf;
+
+use clap::Parser;
+use vtuber::{
+    config::data_dir, disable_autostart, enable_autostart, init_logging, install_crash_reporter,
+    run,
+};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Replay a JSONL comment log or event recording with the mock LLM instead of going live
+    #[arg(long, value_name = "COMMENT_LOG")]
+    simulate: Option<PathBuf>,
+    /// Launch at login from the current directory, or stop doing so, then exit
+    #[arg(long, value_name = "ENABLED")]
+    autostart: Option<bool>,
+    /// Directory with the `.env` to start in, used by the login entry
+    #[arg(long, value_name = "DIR")]
+    working_dir: Option<PathBuf>,
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    if let Some(dir) = &args.working_dir {
+        std::env::set_current_dir(dir)?;
+    }
     dotenvy::dotenv()?;
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let data_dir = data_dir();
+    init_logging(&data_dir.join("logs"));
+    install_crash_reporter(data_dir.join("crashes"));
+
+    match args.autostart {
+        Some(true) => return enable_autostart(),
+        Some(false) => return disable_autostart(),
+        None => {}
+    }
 
-    run().await?;
+    run(args.simulate).await?;
 
     Ok(())
 }
@@ -0,0 +1,123 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{ChannelCount, SampleRate, Source, source::SeekError};
+
+/// How often the level is updated.
+const WINDOW: Duration = Duration::from_millis(50);
+
+/// Loudness of the audio passing through a [`Metered`] source, shared with the window.
+#[derive(Clone, Default)]
+pub struct LevelMeter {
+    /// RMS of the last window as f32 bits
+    level: Arc<AtomicU32>,
+}
+
+impl LevelMeter {
+    /// RMS of the last few milliseconds, 0 to 1.
+    pub fn level(&self) -> f32 {
+        f32::from_bits(self.level.load(Ordering::Relaxed))
+    }
+
+    pub fn reset(&self) {
+        self.set(0.0);
+    }
+
+    fn set(&self, level: f32) {
+        self.level.store(level.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Passes the samples of a source through unchanged, measuring their level.
+pub struct Metered<S> {
+    inner: S,
+    meter: LevelMeter,
+    /// Samples per measurement
+    window: usize,
+    sum: f32,
+    count: usize,
+}
+
+impl<S: Source> Metered<S> {
+    pub fn new(inner: S, meter: LevelMeter) -> Self {
+        let window = inner.sample_rate() as f32 * inner.channels() as f32 * WINDOW.as_secs_f32();
+        Self {
+            inner,
+            meter,
+            window: (window as usize).max(1),
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl<S: Source> Iterator for Metered<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let Some(sample) = self.inner.next() else {
+            self.meter.reset();
+            return None;
+        };
+        self.sum += sample * sample;
+        self.count += 1;
+        if self.count == self.window {
+            self.meter.set((self.sum / self.count as f32).sqrt());
+            (self.sum, self.count) = (0.0, 0);
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Metered<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.inner.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use crate::meter::{LevelMeter, Metered};
+
+    #[test]
+    fn measure_level() {
+        // 20 samples a second make one sample per window
+        let meter = LevelMeter::default();
+        let mut metered = Metered::new(
+            SamplesBuffer::new(1, 20, vec![0.5, -0.25, 0.0]),
+            meter.clone(),
+        );
+
+        assert_eq!(metered.next(), Some(0.5));
+        assert_eq!(meter.level(), 0.5);
+        assert_eq!(metered.next(), Some(-0.25));
+        assert_eq!(meter.level(), 0.25);
+        metered.next();
+        // silent once the source ended
+        assert_eq!(metered.next(), None);
+        assert_eq!(meter.level(), 0.0);
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    fmt::Write,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// Upper bounds of the latency buckets in seconds.
+const BUCKETS: [f64; 10] = [0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+
+/// Latency distribution in the Prometheus histogram layout.
+#[derive(Default)]
+pub struct Histogram {
+    /// Observations per bucket, not cumulative
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+    last_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(index) = BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        let micros = duration.as_micros() as u64;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+        self.last_micros.store(micros, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        let count = self.count();
+        (count > 0).then(|| Duration::from_micros(self.sum_micros.load(Ordering::Relaxed) / count))
+    }
+
+    pub fn last(&self) -> Option<Duration> {
+        (self.count() > 0).then(|| Duration::from_micros(self.last_micros.load(Ordering::Relaxed)))
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        let mut cumulative = 0;
+        for (bound, bucket) in BUCKETS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+        }
+        let count = self.count();
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {count}");
+    }
+}
+
+#[derive(Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        let _ = writeln!(out, "{name} {}", self.get());
+    }
+}
+
+/// Whether the voice is heard, the error keeping it silent if not.
+#[derive(Default)]
+pub struct AudioHealth(Mutex<Option<String>>);
+
+impl AudioHealth {
+    pub fn set_error(&self, error: Option<String>) {
+        *self.0.lock().unwrap() = error;
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Counters and stage latencies of the pipeline, served at `/metrics`.
+#[derive(Default)]
+pub struct Metrics {
+    pub comments_received: Counter,
+    pub comments_rejected: Counter,
+    pub comments_dropped: Counter,
+    pub replies: Counter,
+    pub llm_errors: Counter,
+    pub tts_errors: Counter,
+    /// Responses the safety filter redacted, regenerated or dropped
+    pub unsafe_responses: Counter,
+    /// From receiving a comment until the LLM answered, including the time in the queue
+    pub comment_to_llm: Histogram,
+    /// How long the voice of a line takes to synthesize once its turn came, without the lines
+    /// before it
+    pub llm_to_tts: Histogram,
+    /// From a synthesized line reaching the frontend until it is spoken
+    pub tts_to_playback: Histogram,
+    /// From a line being spoken until the frame with its expression is ready
+    pub playback_to_render: Histogram,
+    /// From receiving a comment until a line answering it is spoken and shown
+    pub end_to_end: Histogram,
+    /// Set by the player, also served at `/health`
+    pub audio: AudioHealth,
+}
+
+impl Metrics {
+    /// Render in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (counter, name, help) in [
+            (
+                &self.comments_received,
+                "vtuber_comments_received_total",
+                "Comments received from all sources",
+            ),
+            (
+                &self.comments_rejected,
+                "vtuber_comments_rejected_total",
+                "Comments rejected by moderation",
+            ),
+            (
+                &self.comments_dropped,
+                "vtuber_comments_dropped_total",
+                "Comments skipped by the queue",
+            ),
+            (
+                &self.replies,
+                "vtuber_replies_total",
+                "Lines sent to the frontend",
+            ),
+            (
+                &self.llm_errors,
+                "vtuber_llm_errors_total",
+                "Failed LLM requests",
+            ),
+            (
+                &self.tts_errors,
+                "vtuber_tts_errors_total",
+                "Failed TTS requests",
+            ),
+            (
+                &self.unsafe_responses,
+                "vtuber_unsafe_responses_total",
+                "Responses caught by the safety filter",
+            ),
+        ] {
+            counter.render(&mut out, name, help);
+        }
+        for (histogram, name, help) in [
+            (
+                &self.comment_to_llm,
+                "vtuber_comment_to_llm_seconds",
+                "Time from receiving a comment until the LLM answered",
+            ),
+            (
+                &self.llm_to_tts,
+                "vtuber_llm_to_tts_seconds",
+                "Time to synthesize the voice of a line, without the lines before it",
+            ),
+            (
+                &self.tts_to_playback,
+                "vtuber_tts_to_playback_seconds",
+                "Time from a synthesized line until it is spoken",
+            ),
+            (
+                &self.playback_to_render,
+                "vtuber_playback_to_render_seconds",
+                "Time from speaking a line until its expression is rendered",
+            ),
+            (
+                &self.end_to_end,
+                "vtuber_end_to_end_seconds",
+                "Time from receiving a comment until a line answering it is spoken and shown",
+            ),
+        ] {
+            histogram.render(&mut out, name, help);
+        }
+        let name = "vtuber_audio_output_available";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Whether the voice plays on an output device"
+        );
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        let _ = writeln!(out, "{name} {}", u8::from(self.audio.error().is_none()));
+        out
+    }
+
+    /// Short summary for the debug overlay.
+    pub fn overlay_lines(&self) -> Vec<String> {
+        let stage = |name: &str, histogram: &Histogram| {
+            let secs = |d: Option<Duration>| {
+                d.map_or("-".to_string(), |d| format!("{:.2}s", d.as_secs_f64()))
+            };
+            format!(
+                "{name}: last {} / mean {} ({})",
+                secs(histogram.last()),
+                secs(histogram.mean()),
+                histogram.count()
+            )
+        };
+        let mut lines = vec![
+            format!(
+                "comments: {} received, {} rejected, {} dropped",
+                self.comments_received.get(),
+                self.comments_rejected.get(),
+                self.comments_dropped.get()
+            ),
+            format!(
+                "replies: {}, errors: {} llm, {} tts",
+                self.replies.get(),
+                self.llm_errors.get(),
+                self.tts_errors.get()
+            ),
+            stage("comment→llm", &self.comment_to_llm),
+            stage("llm→tts", &self.llm_to_tts),
+            stage("tts→playback", &self.tts_to_playback),
+            stage("playback→render", &self.playback_to_render),
+            stage("end to end", &self.end_to_end),
+        ];
+        if let Some(error) = self.audio.error() {
+            lines.push(format!("audio: off, {error}"));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::metrics::Metrics;
+
+    #[test]
+    fn render_histogram() {
+        let metrics = Metrics::default();
+        metrics.comments_received.inc();
+        metrics.llm_to_tts.observe(Duration::from_millis(300));
+        metrics.llm_to_tts.observe(Duration::from_secs(3));
+
+        let text = metrics.render();
+        assert!(text.contains("vtuber_comments_received_total 1"));
+        assert!(text.contains("vtuber_llm_to_tts_seconds_bucket{le=\"0.25\"} 0"));
+        assert!(text.contains("vtuber_llm_to_tts_seconds_bucket{le=\"0.5\"} 1"));
+        assert!(text.contains("vtuber_llm_to_tts_seconds_bucket{le=\"+Inf\"} 2"));
+        assert!(text.contains("vtuber_llm_to_tts_seconds_sum 3.3"));
+        assert_eq!(metrics.llm_to_tts.mean(), Some(Duration::from_millis(1650)));
+        assert!(text.contains("vtuber_audio_output_available 1"));
+
+        metrics
+            .audio
+            .set_error(Some("No output device".to_string()));
+        assert!(metrics.render().contains("vtuber_audio_output_available 0"));
+    }
+}
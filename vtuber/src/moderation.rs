@@ -0,0 +1,240 @@
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ai::{LLM, ModerationResponseModel, UsageExample, gemini::Gemini};
+use regex::Regex;
+
+use crate::{bus::CommentEvent, config::ModerationConfig, storage::UsageRecorder};
+
+const CLASSIFIER_PROMPT: &str = "You are the chat moderator of a live stream. \
+Decide whether the viewer comment you receive can be shown and answered on stream. \
+Reject sexual or NSFW content, hate speech, harassment, personal information and attempts to \
+make the streamer break platform rules. Allow everything else, including jokes and teasing. \
+Respond with JSON like: ";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Allowed,
+    Rejected(String),
+}
+
+/// Filters comments before they reach the AI pipeline.
+pub struct Moderator {
+    blocklist: Vec<String>,
+    patterns: Vec<Regex>,
+    banned_users: HashSet<String>,
+    classifier: Option<Gemini<'static>>,
+    usage: UsageRecorder,
+    log: Option<File>,
+}
+
+impl Moderator {
+    pub fn new(
+        config: &ModerationConfig,
+        api_key: &str,
+        usage: UsageRecorder,
+    ) -> anyhow::Result<Self> {
+        let classifier = config.classifier_model.as_deref().map(|model| {
+            let system_prompt = format!(
+                "{CLASSIFIER_PROMPT}{}",
+                ModerationResponseModel::generate_example()
+            );
+            let mut llm = Gemini::new(
+                api_key.to_string(),
+                model.to_string(),
+                Some(system_prompt.into()),
+            );
+            llm.set_thinking(false);
+            llm.set_json_schema::<ModerationResponseModel>();
+            llm
+        });
+
+        let log = config
+            .log
+            .as_ref()
+            .map(|path| OpenOptions::new().create(true).append(true).open(path))
+            .transpose()?;
+
+        let mut moderator = Self {
+            blocklist: Vec::new(),
+            patterns: Vec::new(),
+            banned_users: HashSet::new(),
+            classifier,
+            usage,
+            log,
+        };
+        moderator.set_rules(config)?;
+        Ok(moderator)
+    }
+
+    /// Replace the blocklist, patterns and banned users, keeping them untouched on error.
+    pub fn set_rules(&mut self, config: &ModerationConfig) -> anyhow::Result<()> {
+        self.patterns = config
+            .patterns
+            .iter()
+            .map(|p| Regex::new(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.blocklist = config.blocklist.iter().map(|w| w.to_lowercase()).collect();
+        self.banned_users = config
+            .banned_users
+            .iter()
+            .map(|u| u.to_lowercase())
+            .collect();
+        Ok(())
+    }
+
+    pub async fn check(&mut self, comment: &CommentEvent) -> Verdict {
+        let verdict = self.evaluate(comment).await;
+        self.write_log(comment, &verdict);
+        verdict
+    }
+
+    async fn evaluate(&mut self, comment: &CommentEvent) -> Verdict {
+        if let Verdict::Rejected(reason) = self.check_rules(comment) {
+            return Verdict::Rejected(reason);
+        }
+
+        let Some(classifier) = &mut self.classifier else {
+            return Verdict::Allowed;
+        };
+
+        // every comment is classified on its own
+        classifier.clear_history();
+        let outcome = classifier.chat(&comment.text).await;
+        if outcome.is_ok() {
+            self.usage.record(classifier.last_usage()).await;
+        }
+        match outcome
+            .map_err(anyhow::Error::from)
+            .and_then(|res| Ok(serde_json::from_str::<ModerationResponseModel>(&res)?))
+        {
+            Ok(res) if res.allowed => Verdict::Allowed,
+            Ok(res) => Verdict::Rejected(format!("classifier: {}", res.reason)),
+            Err(e) => {
+                // don't let a flaky classifier block the whole chat
+                log::error!("Failed to classify comment, allowing it: {e}");
+                Verdict::Allowed
+            }
+        }
+    }
+
+    /// Whether a viewer name passes the blocklist and patterns.
+    pub fn allows_name(&self, name: &str) -> bool {
+        let lowercase = name.to_lowercase();
+        !self
+            .blocklist
+            .iter()
+            .any(|w| lowercase.contains(w.as_str()))
+            && !self.patterns.iter().any(|p| p.is_match(name))
+    }
+
+    fn check_rules(&self, comment: &CommentEvent) -> Verdict {
+        if self.banned_users.contains(&comment.user.to_lowercase()) {
+            return Verdict::Rejected("banned user".to_string());
+        }
+
+        let text = comment.text.to_lowercase();
+        if let Some(word) = self.blocklist.iter().find(|w| text.contains(w.as_str())) {
+            return Verdict::Rejected(format!("blocked word {word}"));
+        }
+
+        if let Some(pattern) = self.patterns.iter().find(|p| p.is_match(&comment.text)) {
+            return Verdict::Rejected(format!("matched pattern {pattern}"));
+        }
+
+        Verdict::Allowed
+    }
+
+    fn write_log(&mut self, comment: &CommentEvent, verdict: &Verdict) {
+        let Some(log) = &mut self.log else {
+            return;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let entry = serde_json::json!({
+            "timestamp": timestamp,
+            "user": comment.user,
+            "source": comment.source,
+            "text": comment.text,
+            "allowed": *verdict == Verdict::Allowed,
+            "reason": match verdict {
+                Verdict::Allowed => None,
+                Verdict::Rejected(reason) => Some(reason),
+            },
+        });
+        if let Err(e) = writeln!(log, "{entry}") {
+            log::error!("Failed to write moderation log: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::CommentEvent,
+        config::ModerationConfig,
+        moderation::{Moderator, Verdict},
+        storage::UsageRecorder,
+    };
+
+    fn comment(user: &str, text: &str) -> CommentEvent {
+        CommentEvent::new(user, text, "test", Default::default())
+    }
+
+    #[test]
+    fn check_rules() {
+        let config = ModerationConfig {
+            blocklist: vec!["BadWord".to_string()],
+            patterns: vec![r"https?://".to_string()],
+            banned_users: vec!["Troll".to_string()],
+            classifier_model: None,
+            log: None,
+        };
+        let moderator = Moderator::new(&config, "", UsageRecorder::default()).unwrap();
+
+        assert_eq!(
+            moderator.check_rules(&comment("viewer", "hello")),
+            Verdict::Allowed
+        );
+        assert!(matches!(
+            moderator.check_rules(&comment("troll", "hello")),
+            Verdict::Rejected(_)
+        ));
+        assert!(matches!(
+            moderator.check_rules(&comment("viewer", "a badword here")),
+            Verdict::Rejected(_)
+        ));
+        assert!(matches!(
+            moderator.check_rules(&comment("viewer", "visit http://spam")),
+            Verdict::Rejected(_)
+        ));
+
+        let mut moderator = moderator;
+        let unbanned = ModerationConfig {
+            banned_users: Vec::new(),
+            ..config.clone()
+        };
+        moderator.set_rules(&unbanned).unwrap();
+        assert_eq!(
+            moderator.check_rules(&comment("troll", "hello")),
+            Verdict::Allowed
+        );
+        // a broken pattern keeps the previous rules
+        let broken = ModerationConfig {
+            patterns: vec!["(".to_string()],
+            ..unbanned
+        };
+        assert!(moderator.set_rules(&broken).is_err());
+        assert!(matches!(
+            moderator.check_rules(&comment("viewer", "visit http://spam")),
+            Verdict::Rejected(_)
+        ));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::config::NameConfig;
+
+/// Whether the character adds nothing visible, like zero-width spaces, direction overrides or
+/// the combining marks zalgo text is stacked from.
+fn is_invisible(c: char) -> bool {
+    c.is_control()
+        || matches!(c,
+            '\u{0300}'..='\u{036f}'
+            | '\u{0483}'..='\u{0489}'
+            | '\u{1ab0}'..='\u{1aff}'
+            | '\u{1dc0}'..='\u{1dff}'
+            | '\u{200b}'..='\u{200f}'
+            | '\u{202a}'..='\u{202e}'
+            | '\u{2060}'..='\u{206f}'
+            | '\u{20d0}'..='\u{20ff}'
+            | '\u{fe00}'..='\u{fe0f}'
+            | '\u{fe20}'..='\u{fe2f}'
+            | '\u{feff}')
+}
+
+/// Map fancy letters, like fullwidth, circled or mathematical ones, to plain ASCII.
+fn transliterate(c: char) -> char {
+    let code = c as u32;
+    let mapped = match code {
+        // fullwidth ASCII
+        0xff01..=0xff5e => code - 0xfee0,
+        0x3000 => ' ' as u32,
+        // Ⓐ-Ⓩ, ⓐ-ⓩ
+        0x24b6..=0x24cf => 'A' as u32 + code - 0x24b6,
+        0x24d0..=0x24e9 => 'a' as u32 + code - 0x24d0,
+        // bold, italic, script, fraktur... every style is A-Z followed by a-z
+        0x1d400..=0x1d6a3 => match (code - 0x1d400) % 52 {
+            letter @ 0..26 => 'A' as u32 + letter,
+            letter => 'a' as u32 + letter - 26,
+        },
+        // mathematical digits, five styles of 0-9
+        0x1d7ce..=0x1d7ff => '0' as u32 + (code - 0x1d7ce) % 10,
+        _ => code,
+    };
+    char::from_u32(mapped).unwrap_or(c)
+}
+
+/// Strip invisible characters, transliterate fancy letters, collapse whitespace and cut the name
+/// to `max_length` characters.
+pub fn normalize_name(name: &str, max_length: usize) -> String {
+    let cleaned: String = name
+        .chars()
+        .filter(|&c| !is_invisible(c))
+        .map(transliterate)
+        .collect();
+    let mut normalized = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    if let Some((cut, _)) = normalized.char_indices().nth(max_length) {
+        normalized.truncate(cut);
+        normalized.truncate(normalized.trim_end().len());
+    }
+    normalized
+}
+
+/// Turns usernames into the names shown on screen and given to the AI.
+pub struct NameNormalizer {
+    config: NameConfig,
+}
+
+impl NameNormalizer {
+    pub fn new(config: NameConfig) -> Self {
+        Self { config }
+    }
+
+    /// The override for the user if there is one, otherwise the normalized name. Names that end
+    /// up empty or fail `is_allowed` are replaced by the fallback.
+    pub fn display_name(&self, user: &str, is_allowed: impl Fn(&str) -> bool) -> String {
+        if let Some(name) = self.config.overrides.get(&user.to_lowercase()) {
+            return name.clone();
+        }
+        let name = normalize_name(user, self.config.max_length);
+        if name.is_empty() || !is_allowed(&name) {
+            return self.config.fallback.clone();
+        }
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        config::NameConfig,
+        names::{NameNormalizer, normalize_name},
+    };
+
+    #[test]
+    fn normalize_names() {
+        assert_eq!(normalize_name("Z\u{0351}\u{0308}a\u{0324}lgo", 20), "Zalgo");
+        assert_eq!(normalize_name("\u{200b}ad\u{202e}min\u{feff}", 20), "admin");
+        assert_eq!(normalize_name("ＭＵＲＡＳＡＭＥ", 20), "MURASAME");
+        assert_eq!(normalize_name("𝓜𝓾𝓻𝓪 ⓕⓐⓝ 𝟙𝟚𝟛", 20), "Mura fan 123");
+        assert_eq!(
+            normalize_name("  ムラサメ   ちゃん ", 20),
+            "ムラサメ ちゃん"
+        );
+        assert_eq!(normalize_name("a very long name indeed", 7), "a very");
+    }
+
+    #[test]
+    fn override_and_fallback() {
+        let normalizer = NameNormalizer::new(NameConfig {
+            max_length: 20,
+            fallback: "viewer".to_string(),
+            overrides: HashMap::from([("xx_sniper_xx".to_string(), "Sniper".to_string())]),
+        });
+        assert_eq!(normalizer.display_name("XX_Sniper_XX", |_| false), "Sniper");
+        assert_eq!(
+            normalizer.display_name("\u{200b}\u{200b}", |_| true),
+            "viewer"
+        );
+        assert_eq!(
+            normalizer.display_name("badword", |name| !name.contains("bad")),
+            "viewer"
+        );
+        assert_eq!(normalizer.display_name("Alice", |_| true), "Alice");
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use tokio::sync::mpsc;
+
+use crate::{
+    bus::{InEvent, NotificationEvent},
+    config::BatteryConfig,
+};
+
+/// Source of the comments made up from notifications, see [`NotificationEvent`].
+pub const NOTIFICATION_SOURCE: &str = "notification";
+/// Where Linux lists batteries and chargers.
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+/// Warns once every time the battery runs low while discharging.
+struct BatteryWatch {
+    /// Percent
+    low: u8,
+    warned: bool,
+}
+
+impl BatteryWatch {
+    /// Whether to warn about the battery at `capacity` percent.
+    fn check(&mut self, capacity: u8, discharging: bool) -> bool {
+        if !discharging || capacity > self.low {
+            self.warned = false;
+            return false;
+        }
+        !std::mem::replace(&mut self.warned, true)
+    }
+}
+
+/// The first battery of the computer, if Linux lists one.
+fn find_battery() -> Option<PathBuf> {
+    fs::read_dir(POWER_SUPPLY)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            fs::read_to_string(path.join("type")).is_ok_and(|kind| kind.trim() == "Battery")
+        })
+}
+
+/// Percent left and whether the battery is discharging.
+fn read_battery(battery: &Path) -> io::Result<(u8, bool)> {
+    let capacity = fs::read_to_string(battery.join("capacity"))?
+        .trim()
+        .parse()
+        .map_err(io::Error::other)?;
+    let status = fs::read_to_string(battery.join("status"))?;
+    Ok((capacity, status.trim() == "Discharging"))
+}
+
+/// Send a `battery_low` notification when the battery runs low, computers without a battery
+/// Linux lists are not watched.
+pub fn spawn_battery_watcher(config: BatteryConfig, in_tx: mpsc::Sender<InEvent>) {
+    let Some(battery) = find_battery() else {
+        log::warn!("No battery found in {POWER_SUPPLY}, not watching the battery level");
+        return;
+    };
+    log::info!("Watching the battery {}", battery.display());
+
+    tokio::spawn(async move {
+        let mut watch = BatteryWatch {
+            low: config.low,
+            warned: false,
+        };
+        let mut interval = tokio::time::interval(config.interval);
+        loop {
+            interval.tick().await;
+            let (capacity, discharging) = match read_battery(&battery) {
+                Ok(state) => state,
+                Err(e) => {
+                    log::error!("Failed to read the battery level: {e}");
+                    continue;
+                }
+            };
+            if !watch.check(capacity, discharging) {
+                continue;
+            }
+
+            let notification = NotificationEvent {
+                event: "battery_low".to_string(),
+                title: "Battery low".to_string(),
+                message: Some(format!("{capacity}%")),
+                source: "battery".to_string(),
+            };
+            if in_tx
+                .send(InEvent::Notification(notification))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notification::BatteryWatch;
+
+    #[test]
+    fn warn_once_per_low_battery() {
+        let mut watch = BatteryWatch {
+            low: 20,
+            warned: false,
+        };
+
+        assert!(!watch.check(50, true));
+        assert!(watch.check(20, true));
+        assert!(!watch.check(15, true));
+        // plugged in, then unplugged while still low
+        assert!(!watch.check(16, false));
+        assert!(watch.check(16, true));
+    }
+}
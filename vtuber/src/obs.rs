@@ -0,0 +1,345 @@
+use std::time::Duration;
+
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value as JsonValue, json};
+use sha2::{Digest, Sha256};
+use tokio::{net::TcpStream, sync::broadcast};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream,
+    tungstenite::{self, Message},
+};
+
+use crate::{bus::UiEvent, config::ObsConfig};
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// When a rule fires, matched against [`UiEvent`]s.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct RuleTrigger {
+    pub event: TriggerEvent,
+    /// Only match replies showing this layer
+    #[serde(default)]
+    pub layer: Option<String>,
+    /// Only match events whose text contains this string
+    #[serde(default)]
+    pub text_contains: Option<String>,
+    /// Only match replies and speech of this character, by index into the characters
+    #[serde(default)]
+    pub character: Option<usize>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerEvent {
+    Comment,
+    Thinking,
+    Reply,
+    Error,
+    /// The voice of a line started playing
+    SpeakingStarted,
+    SpeakingStopped,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)] // named after the obs-websocket requests
+pub enum ObsAction {
+    SetScene {
+        scene: String,
+    },
+    SetSourceVisible {
+        scene: String,
+        source: String,
+        visible: bool,
+    },
+    SetFilterEnabled {
+        source: String,
+        filter: String,
+        enabled: bool,
+    },
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ObsRule {
+    pub on: RuleTrigger,
+    pub actions: Vec<ObsAction>,
+}
+
+impl RuleTrigger {
+    pub fn matches(&self, event: &UiEvent) -> bool {
+        let (kind, text, layers, character): (TriggerEvent, Option<&str>, &[String], _) =
+            match event {
+                UiEvent::NewComment(comment) => {
+                    (TriggerEvent::Comment, Some(&comment.text), &[], None)
+                }
+                UiEvent::AiThinking => (TriggerEvent::Thinking, None, &[], None),
+                UiEvent::AiReply {
+                    text,
+                    layers,
+                    character,
+                    ..
+                } => (TriggerEvent::Reply, Some(text), layers, Some(*character)),
+                UiEvent::Error(message) => (TriggerEvent::Error, Some(message), &[], None),
+                UiEvent::Speaking {
+                    character,
+                    speaking,
+                    ..
+                } => {
+                    let kind = if *speaking {
+                        TriggerEvent::SpeakingStarted
+                    } else {
+                        TriggerEvent::SpeakingStopped
+                    };
+                    (kind, None, &[], Some(*character))
+                }
+                _ => return false,
+            };
+
+        kind == self.event
+            && self
+                .character
+                .is_none_or(|wanted| character == Some(wanted))
+            && self
+                .layer
+                .as_ref()
+                .is_none_or(|layer| layers.contains(layer))
+            && self
+                .text_contains
+                .as_ref()
+                .is_none_or(|needle| text.is_some_and(|t| t.contains(needle.as_str())))
+    }
+}
+
+/// Drive OBS from UI events according to the configured rules, reconnecting with backoff.
+pub async fn run_obs_bridge(config: ObsConfig, mut ui_rx: broadcast::Receiver<UiEvent>) {
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        match ObsClient::connect(&config.address, config.password.as_deref()).await {
+            Ok(mut client) => {
+                log::info!("Connected to OBS at {}", config.address);
+                backoff = MIN_BACKOFF;
+                match client.apply_rules(&config.rules, &mut ui_rx).await {
+                    Ok(()) => return,
+                    Err(e) => log::error!("OBS connection lost: {e}"),
+                }
+            }
+            Err(e) => log::error!("Failed to connect to OBS: {e}"),
+        }
+
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// A minimal obs-websocket v5 client.
+pub struct ObsClient {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_request_id: u64,
+}
+
+impl ObsClient {
+    pub async fn connect(address: &str, password: Option<&str>) -> anyhow::Result<Self> {
+        let (socket, _) = tokio_tungstenite::connect_async(address).await?;
+        let mut client = Self {
+            socket,
+            next_request_id: 0,
+        };
+
+        // Hello
+        let hello = client.recv_op(0).await?;
+        let mut identify = json!({ "rpcVersion": 1, "eventSubscriptions": 0 });
+        if let Some(auth) = hello.get("authentication") {
+            let password = password.ok_or_else(|| anyhow::anyhow!("OBS requires a password"))?;
+            let challenge = auth["challenge"].as_str().unwrap_or_default();
+            let salt = auth["salt"].as_str().unwrap_or_default();
+            identify["authentication"] = auth_string(password, salt, challenge).into();
+        }
+
+        // Identify -> Identified
+        client.send_op(1, identify).await?;
+        client.recv_op(2).await?;
+
+        Ok(client)
+    }
+
+    async fn apply_rules(
+        &mut self,
+        rules: &[ObsRule],
+        ui_rx: &mut broadcast::Receiver<UiEvent>,
+    ) -> anyhow::Result<()> {
+        loop {
+            let event = match ui_rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            };
+
+            for rule in rules.iter().filter(|rule| rule.on.matches(&event)) {
+                for action in &rule.actions {
+                    if let Err(e) = self.run_action(action).await {
+                        // reconnect on socket errors, keep going if OBS only rejected the request
+                        if e.is::<tungstenite::Error>() {
+                            return Err(e);
+                        }
+                        log::error!("OBS action {action:?} failed: {e}");
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn run_action(&mut self, action: &ObsAction) -> anyhow::Result<()> {
+        match action {
+            ObsAction::SetScene { scene } => {
+                self.request("SetCurrentProgramScene", json!({ "sceneName": scene }))
+                    .await?;
+            }
+            ObsAction::SetSourceVisible {
+                scene,
+                source,
+                visible,
+            } => {
+                let item = self
+                    .request(
+                        "GetSceneItemId",
+                        json!({ "sceneName": scene, "sourceName": source }),
+                    )
+                    .await?;
+                self.request(
+                    "SetSceneItemEnabled",
+                    json!({
+                        "sceneName": scene,
+                        "sceneItemId": item["sceneItemId"],
+                        "sceneItemEnabled": visible,
+                    }),
+                )
+                .await?;
+            }
+            ObsAction::SetFilterEnabled {
+                source,
+                filter,
+                enabled,
+            } => {
+                self.request(
+                    "SetSourceFilterEnabled",
+                    json!({
+                        "sourceName": source,
+                        "filterName": filter,
+                        "filterEnabled": enabled,
+                    }),
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send a request and wait for its response data.
+    pub async fn request(
+        &mut self,
+        request_type: &str,
+        request_data: JsonValue,
+    ) -> anyhow::Result<JsonValue> {
+        self.next_request_id += 1;
+        let request_id = self.next_request_id.to_string();
+        self.send_op(
+            6,
+            json!({
+                "requestType": request_type,
+                "requestId": request_id,
+                "requestData": request_data,
+            }),
+        )
+        .await?;
+
+        loop {
+            let response = self.recv_op(7).await?;
+            if response["requestId"] != request_id.as_str() {
+                continue;
+            }
+
+            let status = &response["requestStatus"];
+            if status["result"].as_bool() != Some(true) {
+                anyhow::bail!(
+                    "{request_type} failed with code {}: {}",
+                    status["code"],
+                    status["comment"].as_str().unwrap_or_default()
+                );
+            }
+            return Ok(response["responseData"].clone());
+        }
+    }
+
+    async fn send_op(&mut self, op: u8, d: JsonValue) -> anyhow::Result<()> {
+        let message = json!({ "op": op, "d": d }).to_string();
+        self.socket.send(Message::text(message)).await?;
+        Ok(())
+    }
+
+    /// Wait for the next message with the given opcode, skipping everything else.
+    async fn recv_op(&mut self, op: u8) -> anyhow::Result<JsonValue> {
+        while let Some(message) = self.socket.next().await {
+            let Message::Text(text) = message? else {
+                continue;
+            };
+            let mut value: JsonValue = serde_json::from_str(&text)?;
+            if value["op"] == op {
+                return Ok(value["d"].take());
+            }
+        }
+        Err(tungstenite::Error::ConnectionClosed.into())
+    }
+}
+
+fn auth_string(password: &str, salt: &str, challenge: &str) -> String {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let secret = engine.encode(Sha256::digest(format!("{password}{salt}")));
+    engine.encode(Sha256::digest(format!("{secret}{challenge}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{bus::UiEvent, latency::Trace, obs::ObsRule};
+
+    #[test]
+    fn match_rules() {
+        let rule: ObsRule = serde_json::from_str(
+            r#"{"on": {"event": "reply", "layer": "angry.png"}, "actions": [{"type": "set_scene", "scene": "Zoom"}]}"#,
+        )
+        .unwrap();
+        let reply = |layer: &str| UiEvent::AiReply {
+            text: "hmph".to_string(),
+            layers: vec![layer.to_string()],
+            voice: Bytes::new(),
+            character: 0,
+            priority: Default::default(),
+            trace: Trace::new(1, std::time::Instant::now()),
+        };
+
+        assert!(rule.on.matches(&reply("angry.png")));
+        assert!(!rule.on.matches(&reply("smile.png")));
+        assert!(!rule.on.matches(&UiEvent::AiThinking));
+    }
+
+    #[test]
+    fn match_speaking() {
+        let rule: ObsRule = serde_json::from_str(
+            r#"{"on": {"event": "speaking_started", "character": 1}, "actions": [{"type": "set_filter_enabled", "source": "Murasame", "filter": "Glow", "enabled": true}]}"#,
+        )
+        .unwrap();
+        let speaking = |character: usize, speaking: bool| UiEvent::Speaking {
+            character,
+            speaking,
+            at: std::time::SystemTime::now(),
+        };
+
+        assert!(rule.on.matches(&speaking(1, true)));
+        assert!(!rule.on.matches(&speaking(0, true)));
+        assert!(!rule.on.matches(&speaking(1, false)));
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+
+use http_common::openapi::{ApiDoc, Operation};
+
+use crate::{
+    bus::{GiftEvent, NotificationEvent, SubscriptionEvent},
+    comment_status::CommentStatus,
+    companion::{CompanionView, PomodoroView, ReminderView},
+    handler::{
+        capture::CaptureQuery,
+        comments::{AddCommentModel, AddCommentResponse},
+        companion::AddReminderModel,
+        events::EventsQuery,
+        health::HealthModel,
+        polls::StartPollModel,
+        sessions::{ReportQuery, TranscriptQuery},
+        viewers::UpdateViewerModel,
+    },
+    poll::PollView,
+    report::SessionReport,
+    storage::{SessionSummary, Transcript},
+    viewers::ViewerProfile,
+};
+
+/// The routes of [`crate::server`], served at `/openapi.json`.
+pub fn api_doc() -> ApiDoc {
+    ApiDoc::new("vtuber", env!("CARGO_PKG_VERSION"))
+        .operation(
+            Operation::post("/capture", "Save a screenshot or clip of the window")
+                .query::<CaptureQuery>()
+                .content(200, "Queued", "text/plain")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(503, "The comment pipeline is not running"),
+        )
+        .operation(
+            Operation::post(
+                "/comments/add",
+                "Add a comment for the characters to answer",
+            )
+            .body::<AddCommentModel>()
+            .json::<AddCommentResponse>(
+                200,
+                "Sent to the pipeline, moderation and the queue follow",
+            )
+            .response(400, "Blank or too long user or text")
+            .response(413, "Body too large")
+            .response(429, "Too many comments from the client this minute")
+            .response(503, "The comment pipeline is not running"),
+        )
+        .operation(
+            Operation::post("/comments/gift", "Add a gift")
+                .body::<GiftEvent>()
+                .content(200, "Queued", "text/plain")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(503, "The comment pipeline is not running"),
+        )
+        .operation(
+            Operation::post("/comments/subscription", "Add a subscription")
+                .body::<SubscriptionEvent>()
+                .content(200, "Queued", "text/plain")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(503, "The comment pipeline is not running"),
+        )
+        .operation(
+            Operation::get(
+                "/comments/ws",
+                "WebSocket taking one json comment per message, each acknowledged",
+            )
+            .response(101, "Switching to the WebSocket"),
+        )
+        .operation(
+            Operation::get(
+                "/comments/status/{id}",
+                "What became of a comment sent to the API",
+            )
+            .path_parameter("id", "integer")
+            .json::<CommentStatus>(
+                200,
+                "Received, queued, answering, answered, dropped or filtered",
+            )
+            .response(404, "Unknown or forgotten comment"),
+        )
+        .operation(
+            Operation::get(
+                "/companion",
+                "The running pomodoro and the pending reminders",
+            )
+            .json::<CompanionView>(200, "The pomodoro and reminders")
+            .response(401, "Missing or wrong operator token")
+            .response(403, "No operator token is configured"),
+        )
+        .operation(
+            Operation::post("/companion/pomodoro", "Start a pomodoro")
+                .json::<PomodoroView>(201, "The started pomodoro, the character announces it")
+                .response(409, "A pomodoro is running already")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured"),
+        )
+        .operation(
+            Operation::delete("/companion/pomodoro", "Stop the running pomodoro")
+                .response(204, "Stopped")
+                .response(404, "No pomodoro is running")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured"),
+        )
+        .operation(
+            Operation::post(
+                "/companion/reminders",
+                "Let the character remind of something later",
+            )
+            .body::<AddReminderModel>()
+            .json::<ReminderView>(201, "The reminder")
+            .response(400, "Blank or too long text or an invalid delay")
+            .response(401, "Missing or wrong operator token")
+            .response(403, "No operator token is configured"),
+        )
+        .operation(
+            Operation::delete("/companion/reminders/{id}", "Cancel a reminder")
+                .response(204, "Cancelled")
+                .response(404, "No such reminder")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured"),
+        )
+        .operation(
+            Operation::get("/events/ws", "WebSocket streaming what the characters do")
+                .query::<EventsQuery>()
+                .response(101, "Switching to the WebSocket"),
+        )
+        .operation(
+            Operation::get("/health", "What the app runs without")
+                .json::<HealthModel>(200, "The app is up"),
+        )
+        .operation(Operation::get("/metrics", "Pipeline metrics").content(
+            200,
+            "Counters and latencies in the Prometheus text format",
+            "text/plain",
+        ))
+        .operation(
+            Operation::post("/notify", "Let the characters tell about something")
+                .body::<NotificationEvent>()
+                .content(200, "Queued, moderation follows", "text/plain")
+                .response(400, "Blank or too long title or message")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(503, "The comment pipeline is not running"),
+        )
+        .operation(
+            Operation::get("/polls", "The running poll")
+                .json::<PollView>(200, "The poll and its votes")
+                .response(404, "No poll is running"),
+        )
+        .operation(
+            Operation::post("/polls", "Start a poll")
+                .body::<StartPollModel>()
+                .json::<PollView>(201, "The started poll")
+                .response(400, "Too few options or an invalid duration")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(409, "A poll is running already"),
+        )
+        .operation(
+            Operation::delete("/polls", "End the running poll early")
+                .response(202, "The results are being announced")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(404, "No poll is running"),
+        )
+        .operation(
+            Operation::get("/sessions", "List the stored sessions")
+                .json::<Vec<SessionSummary>>(200, "The sessions")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(503, "Transcript storage is disabled"),
+        )
+        .operation(
+            Operation::get("/sessions/{id}/report", "Statistics of a session")
+                .path_parameter("id", "integer")
+                .query::<ReportQuery>()
+                .json::<SessionReport>(200, "The report, or markdown or HTML if asked for")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(404, "Unknown session")
+                .response(503, "Transcript storage is disabled"),
+        )
+        .operation(
+            Operation::get("/sessions/{id}/transcript", "The transcript of a session")
+                .path_parameter("id", "integer")
+                .query::<TranscriptQuery>()
+                .json::<Transcript>(200, "The transcript, or markdown if asked for")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(404, "Unknown session")
+                .response(503, "Transcript storage is disabled"),
+        )
+        .operation(
+            Operation::get("/viewers", "List the viewer profiles")
+                .json::<BTreeMap<String, ViewerProfile>>(200, "Profiles keyed by username")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(503, "Viewer profiles are disabled"),
+        )
+        .operation(
+            Operation::get("/viewers/{name}", "A viewer's profile")
+                .path_parameter("name", "string")
+                .json::<ViewerProfile>(200, "The profile")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(404, "Unknown viewer")
+                .response(503, "Viewer profiles are disabled"),
+        )
+        .operation(
+            Operation::patch("/viewers/{name}", "Edit a viewer's nickname and notes")
+                .path_parameter("name", "string")
+                .body::<UpdateViewerModel>()
+                .json::<ViewerProfile>(200, "The updated profile")
+                .response(400, "Too long nickname or notes, or too many notes")
+                .response(401, "Missing or wrong operator token")
+                .response(403, "No operator token is configured")
+                .response(404, "Unknown viewer")
+                .response(503, "Viewer profiles are disabled"),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use http_common::testing::route_mismatches;
+
+    use crate::{openapi::api_doc, server::config_server};
+
+    /// Every route of the scopes, kept by hand so that a new one can't go undocumented
+    const ROUTES: [(&str, &str); 24] = [
+        ("post", "/capture"),
+        ("post", "/comments/add"),
+        ("post", "/comments/gift"),
+        ("post", "/comments/subscription"),
+        ("get", "/comments/ws"),
+        ("get", "/comments/status/{id}"),
+        ("get", "/companion"),
+        ("post", "/companion/pomodoro"),
+        ("delete", "/companion/pomodoro"),
+        ("post", "/companion/reminders"),
+        ("delete", "/companion/reminders/{id}"),
+        ("get", "/events/ws"),
+        ("get", "/health"),
+        ("get", "/metrics"),
+        ("post", "/notify"),
+        ("get", "/polls"),
+        ("post", "/polls"),
+        ("delete", "/polls"),
+        ("get", "/sessions"),
+        ("get", "/sessions/{id}/report"),
+        ("get", "/sessions/{id}/transcript"),
+        ("get", "/viewers"),
+        ("get", "/viewers/{name}"),
+        ("patch", "/viewers/{name}"),
+    ];
+
+    #[actix_web::test]
+    async fn documented_routes_exist() {
+        assert_eq!(
+            route_mismatches(config_server, &ROUTES, &api_doc()).await,
+            [] as [String; 0]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use rodio::{
+    OutputStream, OutputStreamBuilder,
+    cpal::{
+        self, StreamError,
+        traits::{DeviceTrait, HostTrait},
+    },
+    mixer::{self, Mixer},
+};
+
+/// The silent output plays this often, like the buffer of a device.
+const SILENT_CHUNK: Duration = Duration::from_millis(20);
+const SILENT_CHANNELS: u16 = 2;
+const SILENT_SAMPLE_RATE: u32 = 44100;
+
+/// Where the voice and the sounds are played, nothing while no device can be opened.
+pub enum Output {
+    Device {
+        stream: OutputStream,
+        /// Set once the device went away, e.g. unplugged
+        lost: Arc<AtomicBool>,
+    },
+    Silent(SilentOutput),
+}
+
+impl Output {
+    /// Open the output device with the given name, or the default one.
+    pub fn open(name: Option<&str>) -> anyhow::Result<Self> {
+        let lost = Arc::new(AtomicBool::new(false));
+        let on_error = {
+            let lost = lost.clone();
+            move |e: StreamError| match e {
+                StreamError::DeviceNotAvailable => lost.store(true, Ordering::Relaxed),
+                e => log::error!("Audio output error: {e}"),
+            }
+        };
+
+        let builder = match name {
+            Some(name) => {
+                let devices: Vec<_> = cpal::default_host().output_devices()?.collect();
+                let Some(device) = devices
+                    .iter()
+                    .find(|device| device.name().is_ok_and(|n| n == name))
+                else {
+                    let names: Vec<_> = devices.iter().filter_map(|d| d.name().ok()).collect();
+                    return Err(anyhow!(
+                        "Output device {name} not found, available: {}",
+                        names.join(", ")
+                    ));
+                };
+                log::info!("Playing on {name}");
+                OutputStreamBuilder::from_device(device.clone())?
+            }
+            None => OutputStreamBuilder::from_default_device()?,
+        };
+        let mut stream = builder
+            .with_error_callback(on_error)
+            .open_stream_or_fallback()?;
+        // replaced streams are dropped on purpose
+        stream.log_on_drop(false);
+        Ok(Self::Device { stream, lost })
+    }
+
+    pub fn silent() -> Self {
+        Self::Silent(SilentOutput::start())
+    }
+
+    pub fn mixer(&self) -> &Mixer {
+        match self {
+            Output::Device { stream, .. } => stream.mixer(),
+            Output::Silent(silent) => &silent.mixer,
+        }
+    }
+
+    pub fn is_silent(&self) -> bool {
+        matches!(self, Output::Silent(_))
+    }
+
+    /// Whether the device went away and nothing is played anymore.
+    pub fn is_lost(&self) -> bool {
+        match self {
+            Output::Device { lost, .. } => lost.load(Ordering::Relaxed),
+            Output::Silent(_) => false,
+        }
+    }
+}
+
+/// Plays into nothing at the pace of a device, so lines take as long as they would be heard.
+pub struct SilentOutput {
+    mixer: Mixer,
+    stopped: Arc<AtomicBool>,
+}
+
+impl SilentOutput {
+    fn start() -> Self {
+        let (mixer, mut source) = mixer::mixer(SILENT_CHANNELS, SILENT_SAMPLE_RATE);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let samples = (SILENT_SAMPLE_RATE as f32 * SILENT_CHUNK.as_secs_f32()) as usize
+            * SILENT_CHANNELS as usize;
+        thread::Builder::new()
+            .name("silent-output".to_string())
+            .spawn({
+                let stopped = stopped.clone();
+                move || {
+                    let mut next = Instant::now();
+                    while !stopped.load(Ordering::Relaxed) {
+                        // the mixer never runs dry, it plays silence between sounds
+                        source.by_ref().take(samples).for_each(drop);
+                        next += SILENT_CHUNK;
+                        thread::sleep(next.saturating_duration_since(Instant::now()));
+                    }
+                }
+            })
+            .expect("Failed to spawn the silent output");
+        Self { mixer, stopped }
+    }
+}
+
+impl Drop for SilentOutput {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
+
+    use rodio::{
+        Sink, Source,
+        source::{EmptyCallback, SineWave},
+    };
+
+    use crate::output::Output;
+
+    #[test]
+    fn play_silently_in_real_time() {
+        let output = Output::silent();
+        assert!(output.is_silent() && !output.is_lost());
+
+        let sink = Sink::connect_new(output.mixer());
+        let (tx, rx) = mpsc::channel();
+        sink.append(SineWave::new(440.0).take_duration(Duration::from_millis(200)));
+        sink.append(EmptyCallback::new(Box::new(move || {
+            let _ = tx.send(());
+        })));
+
+        let started_at = Instant::now();
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(started_at.elapsed() >= Duration::from_millis(150));
+    }
+}
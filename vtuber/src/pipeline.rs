@@ -0,0 +1,1434 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, VecDeque},
+    fs::{self, File},
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ai::{
+    AIResponse, SystemPromptRenderer,
+    gemini::{Gemini, Usage},
+    mock::MockLLM,
+};
+use bytes::Bytes;
+use tokio::{
+    sync::{Notify, broadcast, mpsc, watch},
+    task::JoinHandle,
+};
+use tts_client::TtsClient;
+
+use crate::{
+    bus::{CommentEvent, CommentKind, ControlCommand, InEvent, Priority, UiEvent},
+    comment_status::{CommentStatus, CommentStatuses},
+    companion::{COMPANION_SOURCE, Companion},
+    config::{AppConfig, CharacterConfig, LiveConfig, QueueConfig},
+    effects::{self, VoiceEffect, effects_prompt},
+    latency::{Stage, Trace},
+    metrics::Metrics,
+    moderation::{Moderator, Verdict},
+    names::NameNormalizer,
+    notification::NOTIFICATION_SOURCE,
+    plugin::{CommentAction, PluginRegistry},
+    poll::{POLL_SOURCE, Polls},
+    queue::{CommentQueue, PushOutcome},
+    reaction::{gift_comment, notification_comment, subscription_comment},
+    read_aloud::{READ_ALOUD_SOURCE, read_aloud_prompt, read_selection},
+    replay::{EventRecorder, REPLAY_SOURCE},
+    safety::{Review, SafetyAction, SafetyFilter},
+    shutdown::Shutdown,
+    soundboard::parse_command,
+    source::{scheduler::SCHEDULER_SOURCE, simulation::SIMULATION_SOURCE},
+    storage::{ResponseRecord, Storage, UsageRecorder},
+    stt::HOST_SOURCE,
+    supervisor::Supervisor,
+    tools,
+    topics::TopicTracker,
+    touch::TOUCH_SOURCE,
+    translation::{Translator, needs_translation},
+    tts_cache::TtsCache,
+    utils::audio_duration,
+    viewers::{ViewerRegistry, unix_now},
+};
+
+/// Source of the lines the characters say to each other.
+const DIALOGUE_SOURCE: &str = "dialogue";
+/// Unsafe responses are dropped after asking again this often
+const MAX_REGENERATIONS: usize = 2;
+/// Accepted comments kept for answering again, as many as the chat panel can show
+const RECENT_COMMENTS: usize = 200;
+const REGENERATE_PROMPT: &str =
+    "【重新回答】你刚才的回答不适合在直播中说, 请换一种安全的说法重新回答。";
+
+/// Sources of the comments made up by the app or said by the host, and of the ones replayed
+/// from a log, which would count the viewers twice.
+pub const APP_SOURCES: [&str; 10] = [
+    SCHEDULER_SOURCE,
+    DIALOGUE_SOURCE,
+    HOST_SOURCE,
+    POLL_SOURCE,
+    TOUCH_SOURCE,
+    NOTIFICATION_SOURCE,
+    READ_ALOUD_SOURCE,
+    COMPANION_SOURCE,
+    SIMULATION_SOURCE,
+    REPLAY_SOURCE,
+];
+
+/// Whether a comment was written by a viewer rather than made up by the app or said by the host.
+fn from_viewer(comment_event: &CommentEvent) -> bool {
+    !APP_SOURCES.contains(&comment_event.source.as_str())
+}
+
+fn render_system_prompt(
+    character: &CharacterConfig,
+    template: &str,
+    effects: &BTreeMap<String, VoiceEffect>,
+) -> anyhow::Result<String> {
+    let user_title = character.user_title.to_owned().unwrap_or_else(|| {
+        character
+            .user_title
+            .to_owned()
+            .unwrap_or_else(|| "<unknown>".to_string())
+    });
+    let system_prompt_renderer =
+        SystemPromptRenderer::new(&character.name, &user_title, &character.dataset);
+    let system_prompt = system_prompt_renderer.format_with_template(
+        template,
+        Some(
+            character
+                .render
+                .model
+                .layer_descriptions()
+                .iter()
+                .map(|(k, v)| (*k, v.description.to_owned()))
+                .collect(),
+        ),
+    )?;
+    Ok(match effects_prompt(effects) {
+        Some(effects) => format!("{system_prompt}\n\n{effects}"),
+        None => system_prompt,
+    })
+}
+
+/// The LLM answering as a character, the mock one when simulating.
+enum CharacterLlm {
+    Gemini(Gemini<'static>),
+    Mock(MockLLM),
+}
+
+impl CharacterLlm {
+    fn set_system_prompt(&mut self, system_prompt: String) {
+        if let Self::Gemini(llm) = self {
+            llm.set_system_prompt(Some(Cow::Owned(system_prompt)));
+        }
+    }
+
+    async fn chat(
+        &mut self,
+        prompt: &str,
+        model: Arc<layer_composer::Model>,
+    ) -> anyhow::Result<Vec<AIResponse>> {
+        match self {
+            Self::Gemini(llm) => ai::chat(prompt, llm, Some(model)).await,
+            Self::Mock(llm) => ai::chat(prompt, llm, Some(model)).await,
+        }
+    }
+
+    /// Tokens of the last answer, the mock LLM uses none.
+    fn last_usage(&self) -> Option<Usage> {
+        match self {
+            Self::Gemini(llm) => llm.last_usage(),
+            Self::Mock(_) => None,
+        }
+    }
+}
+
+fn init_llm(
+    config: &AppConfig,
+    character: &CharacterConfig,
+    system_prompt: String,
+) -> CharacterLlm {
+    if config.simulation.is_some() {
+        let layers = character
+            .render
+            .model
+            .layer_descriptions()
+            .into_keys()
+            .collect();
+        return CharacterLlm::Mock(MockLLM::new(layers));
+    }
+
+    let mut llm = Gemini::new(
+        config.ai.api_key.clone(),
+        config.ai.model.clone(),
+        Some(Cow::Owned(system_prompt)),
+    );
+    llm.set_thinking(config.ai.thinking);
+    llm.set_json_schema::<Vec<ai::AIResponseModel>>();
+    if let Some(tools_config) = &config.tools {
+        llm.set_tools(tools::builtin_tools(tools_config));
+    }
+    CharacterLlm::Gemini(llm)
+}
+
+/// Apply reloaded settings between two answers, the conversations are kept.
+fn apply_live_config(
+    previous: &LiveConfig,
+    live: &LiveConfig,
+    speakers: &mut [Speaker],
+    tts_client: &mut TtsClient,
+    config: &AppConfig,
+    ui_tx: &broadcast::Sender<UiEvent>,
+) {
+    let changed = changed_templates(
+        &previous.system_instruction_templates,
+        &live.system_instruction_templates,
+    );
+    for speaker in speakers
+        .iter_mut()
+        .filter(|speaker| changed.contains(&speaker.index))
+    {
+        let character = &config.characters[speaker.index];
+        let template = &live.system_instruction_templates[speaker.index];
+        match render_system_prompt(character, template, &config.audio.effects) {
+            Ok(system_prompt) => {
+                log::info!("Reloaded the system prompt of {}", character.name);
+                speaker.system_prompt = system_prompt;
+                speaker.apply_system_prompt();
+                let _ = ui_tx.send(UiEvent::SystemPromptReloaded {
+                    character: speaker.index,
+                });
+            }
+            Err(e) => log::error!(
+                "Failed to render the reloaded system prompt of {}: {e}",
+                character.name
+            ),
+        }
+    }
+    tts_client.set_speed(live.tts_speed);
+}
+
+/// Characters whose template differs, the ones added to the characters file need a restart.
+fn changed_templates(previous: &[String], current: &[String]) -> Vec<usize> {
+    previous
+        .iter()
+        .zip(current)
+        .enumerate()
+        .filter(|(_, (previous, current))| previous != current)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// One character's side of the conversation.
+struct Speaker {
+    /// Index into [`AppConfig::characters`]
+    index: usize,
+    llm: CharacterLlm,
+    model: Arc<layer_composer::Model>,
+    /// Rendered from the template, without the topic
+    system_prompt: String,
+    /// Where the conversation is, see [`TopicTracker`]
+    topic: Option<String>,
+}
+
+impl Speaker {
+    /// Tell the character the topic of the conversation in the system prompt, so it isn't
+    /// repeated in the history with every comment.
+    fn set_topic(&mut self, topic: Option<String>) {
+        if self.topic != topic {
+            self.topic = topic;
+            self.apply_system_prompt();
+        }
+    }
+
+    fn apply_system_prompt(&mut self) {
+        let system_prompt = match &self.topic {
+            Some(topic) => format!("{}\n\n{topic}", self.system_prompt),
+            None => self.system_prompt.clone(),
+        };
+        self.llm.set_system_prompt(system_prompt);
+    }
+}
+
+/// The character a comment is meant for: the one mentioned first, otherwise the next in turn.
+fn pick_character(text: &str, names: &[String], next: &mut usize) -> usize {
+    let text = text.to_lowercase();
+    let mentioned = names
+        .iter()
+        .enumerate()
+        .filter_map(|(index, name)| Some((text.find(&name.to_lowercase())?, index)))
+        .min();
+    if let Some((_, index)) = mentioned {
+        return index;
+    }
+
+    let index = *next % names.len();
+    *next = (index + 1) % names.len();
+    index
+}
+
+/// Subsystems the pipeline reports to, all but the metrics are optional.
+#[derive(Clone, Default)]
+pub struct PipelineServices {
+    pub storage: Option<Arc<Storage>>,
+    /// Tokens of the characters and of the classifiers, translations and summaries
+    pub usage: UsageRecorder,
+    pub viewers: Option<Arc<ViewerRegistry>>,
+    pub metrics: Arc<Metrics>,
+    pub polls: Arc<Polls>,
+    pub plugins: PluginRegistry,
+    /// What became of the comments sent over the API
+    pub comments: Arc<CommentStatuses>,
+    pub companion: Arc<Companion>,
+}
+
+/// Comments accepted by moderation, waiting for the AI worker.
+struct SharedQueue {
+    queue: Mutex<CommentQueue>,
+    notify: Notify,
+    /// The comment answered last, for regenerating, only changed while holding `queue`
+    last_answered: Mutex<Option<CommentEvent>>,
+    /// The comments accepted last, for answering again from the chat panel
+    recent: Mutex<VecDeque<CommentEvent>>,
+}
+
+/// Start answering comments, the returned task ends after the queue was saved on shutdown.
+///
+/// The intake and the worker are restarted by the supervisor when they fail.
+pub fn spawn_ai_pipeline(
+    in_rx: mpsc::Receiver<InEvent>,
+    ui_tx: broadcast::Sender<UiEvent>,
+    live_rx: watch::Receiver<Arc<LiveConfig>>,
+    services: PipelineServices,
+    app_config: Arc<AppConfig>,
+    shutdown: Shutdown,
+    supervisor: &Supervisor,
+) -> anyhow::Result<JoinHandle<()>> {
+    let mut comment_queue = CommentQueue::new(
+        app_config.queue.clone(),
+        app_config
+            .characters
+            .iter()
+            .map(|character| character.name.clone())
+            .collect(),
+    );
+    if let Some(path) = &app_config.queue.state_file {
+        restore_queue(path, &mut comment_queue, &services);
+    }
+    let queue = Arc::new(SharedQueue {
+        queue: Mutex::new(comment_queue),
+        notify: Notify::new(),
+        last_answered: Mutex::new(None),
+        recent: Mutex::new(VecDeque::with_capacity(RECENT_COMMENTS)),
+    });
+
+    // survives restarts of the intake, a panic releases the lock
+    let intake = Arc::new(tokio::sync::Mutex::new(Intake {
+        in_rx,
+        moderator: Moderator::new(
+            &app_config.moderation,
+            &app_config.ai.api_key,
+            services.usage.clone(),
+        )?,
+        translator: app_config
+            .translation
+            .as_ref()
+            .map(|config| Translator::new(config, &app_config.ai.api_key, services.usage.clone()))
+            .map(|translator| Arc::new(tokio::sync::Mutex::new(translator))),
+        names: NameNormalizer::new(app_config.names.clone()),
+        recorder: app_config
+            .event_recording
+            .as_deref()
+            .map(EventRecorder::create)
+            .transpose()?,
+    }));
+    supervisor.supervise("Comment intake", {
+        let (ui_tx, live_rx, queue) = (ui_tx.clone(), live_rx.clone(), queue.clone());
+        let (services, app_config, shutdown) =
+            (services.clone(), app_config.clone(), shutdown.clone());
+        move || {
+            run_intake(
+                intake.clone(),
+                ui_tx.clone(),
+                live_rx.clone(),
+                queue.clone(),
+                services.clone(),
+                app_config.clone(),
+                shutdown.clone(),
+            )
+        }
+    });
+
+    // built up front so a bad config fails the startup, restarts build their own
+    let mut speakers = Some(build_speakers(&app_config)?);
+    let worker = supervisor.supervise("AI worker", {
+        let queue = queue.clone();
+        let app_config = app_config.clone();
+        move || {
+            run_ai_worker(
+                speakers.take(),
+                ui_tx.clone(),
+                live_rx.clone(),
+                queue.clone(),
+                services.clone(),
+                app_config.clone(),
+                shutdown.clone(),
+            )
+        }
+    });
+
+    Ok(tokio::spawn(async move {
+        let _ = worker.await;
+        save_queue(&queue, &app_config.queue);
+    }))
+}
+
+/// Queue the comments left unanswered by the last run.
+fn restore_queue(path: &Path, comment_queue: &mut CommentQueue, services: &PipelineServices) {
+    let comments: Vec<CommentEvent> = match File::open(path) {
+        Ok(file) => match serde_json::from_reader(file) {
+            Ok(comments) => comments,
+            Err(e) => {
+                log::error!("Failed to read saved comments from {}: {e}", path.display());
+                return;
+            }
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+        Err(e) => {
+            log::error!("Failed to open {}: {e}", path.display());
+            return;
+        }
+    };
+
+    log::info!("Restored {} unanswered comments", comments.len());
+    for comment in comments.into_iter().rev() {
+        if let Some(storage) = &services.storage
+            && let Err(e) = storage.record_comment(&comment)
+        {
+            log::error!("Failed to record comment: {e}");
+        }
+        comment_queue.requeue(comment);
+    }
+    if let Err(e) = fs::remove_file(path) {
+        log::error!("Failed to remove {}: {e}", path.display());
+    }
+}
+
+/// Save the unanswered comments, or report them as dropped without a state file.
+fn save_queue(queue: &SharedQueue, config: &QueueConfig) {
+    let comments = queue.queue.lock().unwrap().drain();
+    if comments.is_empty() {
+        return;
+    }
+
+    let Some(path) = &config.state_file else {
+        log::warn!("Dropped {} unanswered comments", comments.len());
+        return;
+    };
+    let saved = File::create(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| Ok(serde_json::to_writer(file, &comments)?));
+    match saved {
+        Ok(()) => log::info!(
+            "Saved {} unanswered comments to {}",
+            comments.len(),
+            path.display()
+        ),
+        Err(e) => log::error!("Failed to save unanswered comments: {e}"),
+    }
+}
+
+/// State of the intake kept across restarts.
+struct Intake {
+    in_rx: mpsc::Receiver<InEvent>,
+    moderator: Moderator,
+    /// Shared by the translations running next to the intake, one at a time in arrival order
+    translator: Option<Arc<tokio::sync::Mutex<Translator>>>,
+    names: NameNormalizer,
+    recorder: Option<EventRecorder>,
+}
+
+/// Moderate incoming events and put them into the queue.
+async fn run_intake(
+    intake: Arc<tokio::sync::Mutex<Intake>>,
+    ui_tx: broadcast::Sender<UiEvent>,
+    mut live_rx: watch::Receiver<Arc<LiveConfig>>,
+    queue: Arc<SharedQueue>,
+    services: PipelineServices,
+    app_config: Arc<AppConfig>,
+    shutdown: Shutdown,
+) -> anyhow::Result<()> {
+    let mut intake = intake.lock().await;
+    let Intake {
+        in_rx,
+        moderator,
+        translator,
+        names,
+        recorder,
+    } = &mut *intake;
+    loop {
+        let evt = tokio::select! {
+            // nothing new is queued while shutting down
+            _ = shutdown.triggered() => break,
+            evt = in_rx.recv() => evt,
+            _ = services.polls.wait() => {
+                if let Some(result) = services.polls.close_due() {
+                    log::info!("Poll {} closed with {} votes", result.question, result.total_votes());
+                    let _ = ui_tx.send(UiEvent::Poll(result.clone()));
+                    let comment_event = CommentEvent::new(
+                        "poll",
+                        result.result_prompt(),
+                        POLL_SOURCE,
+                        Priority::Superchat,
+                    );
+                    accept_comment(comment_event, &ui_tx, &queue, &services).await;
+                }
+                continue;
+            }
+            _ = services.companion.wait() => {
+                let now = Instant::now();
+                for announcement in services.companion.take_due(now) {
+                    log::info!("Announcing {announcement:?}");
+                    let comment_event = CommentEvent::new(
+                        &app_config.gui.user_name,
+                        announcement.prompt(),
+                        COMPANION_SOURCE,
+                        // due now, but not paid for
+                        Priority::Mention,
+                    )
+                    .with_kind(CommentKind::Notification);
+                    accept_comment(comment_event, &ui_tx, &queue, &services).await;
+                }
+                let _ = ui_tx.send(UiEvent::Pomodoro(services.companion.pomodoro(now)));
+                continue;
+            }
+        };
+        let Some(evt) = evt else {
+            break;
+        };
+        if let Some(recorder) = recorder {
+            recorder.record(&evt);
+        }
+
+        if live_rx.has_changed().unwrap_or(false) {
+            let live = live_rx.borrow_and_update().clone();
+            if let Err(e) = moderator.set_rules(&live.moderation) {
+                log::error!("Failed to apply reloaded moderation rules: {e}");
+            }
+        }
+
+        if !matches!(evt, InEvent::Control(_)) {
+            services.metrics.comments_received.inc();
+        }
+
+        match evt {
+            InEvent::Comment(mut comment_event) => {
+                log::info!(
+                    "Received comment from user {} via {}: {}",
+                    comment_event.user,
+                    comment_event.source,
+                    comment_event.text
+                );
+                if let Verdict::Rejected(reason) = moderator.check(&comment_event).await {
+                    log::info!("Rejected comment from {}: {reason}", comment_event.user);
+                    services.metrics.comments_rejected.inc();
+                    services
+                        .comments
+                        .set(comment_event.id, CommentStatus::Filtered);
+                    let _ = ui_tx.send(UiEvent::CommentRejected {
+                        comment: comment_event,
+                        reason,
+                    });
+                    continue;
+                }
+
+                comment_event.display_name = Some(
+                    names.display_name(&comment_event.user, |name| moderator.allows_name(name)),
+                );
+
+                if let CommentAction::Drop(reason) =
+                    services.plugins.comment(&mut comment_event).await
+                {
+                    services.metrics.comments_rejected.inc();
+                    services
+                        .comments
+                        .set(comment_event.id, CommentStatus::Filtered);
+                    let _ = ui_tx.send(UiEvent::CommentRejected {
+                        comment: comment_event,
+                        reason,
+                    });
+                    continue;
+                }
+
+                // soundboard commands are for the frontend, not the AI
+                if let Some(soundboard) = &app_config.soundboard
+                    && let Some(command) = parse_command(&comment_event.text)
+                {
+                    services.comments.set(
+                        comment_event.id,
+                        CommentStatus::Dropped {
+                            reason: "sound request".to_string(),
+                        },
+                    );
+                    if soundboard.knows(&command) {
+                        let _ = ui_tx.send(UiEvent::Sound(command));
+                    } else {
+                        log::info!("Ignored unknown sound request {command:?}");
+                    }
+                    continue;
+                }
+
+                // votes are tallied, not answered
+                if let Some(poll) = services
+                    .polls
+                    .vote(&comment_event.user, &comment_event.text)
+                {
+                    services.comments.set(
+                        comment_event.id,
+                        CommentStatus::Dropped {
+                            reason: "vote".to_string(),
+                        },
+                    );
+                    let _ = ui_tx.send(UiEvent::Poll(poll));
+                    continue;
+                }
+
+                if let Some(translator) = translator
+                    && needs_translation(&comment_event.text)
+                {
+                    // the next events don't wait for the translation
+                    let translator = translator.clone();
+                    let (ui_tx, queue, services) = (ui_tx.clone(), queue.clone(), services.clone());
+                    tokio::spawn(async move {
+                        let translated =
+                            translator.lock().await.translate(&comment_event.text).await;
+                        match translated {
+                            Ok(translation) => {
+                                log::info!("Translated comment: {translation}");
+                                comment_event.original =
+                                    Some(std::mem::replace(&mut comment_event.text, translation));
+                            }
+                            Err(e) => {
+                                log::error!("Failed to translate comment, keeping it as is: {e}")
+                            }
+                        }
+                        accept_comment(comment_event, &ui_tx, &queue, &services).await;
+                    });
+                    continue;
+                }
+
+                accept_comment(comment_event, &ui_tx, &queue, &services).await;
+            }
+            InEvent::Gift(mut gift) => {
+                log::info!(
+                    "Received gift of {} {} from {} via {}",
+                    gift.amount,
+                    gift.currency,
+                    gift.user,
+                    gift.source
+                );
+                gift.message =
+                    moderate_message(moderator, &gift.user, gift.message, &gift.source).await;
+                let name = names.display_name(&gift.user, |name| moderator.allows_name(name));
+                let comment_event = gift_comment(&gift, &name, &app_config.reactions.gift);
+                accept_comment(comment_event, &ui_tx, &queue, &services).await;
+            }
+            InEvent::Subscription(mut sub) => {
+                log::info!(
+                    "Received subscription ({}, {} months) from {} via {}",
+                    sub.tier,
+                    sub.months,
+                    sub.user,
+                    sub.source
+                );
+                sub.message =
+                    moderate_message(moderator, &sub.user, sub.message, &sub.source).await;
+                let name = names.display_name(&sub.user, |name| moderator.allows_name(name));
+                let comment_event =
+                    subscription_comment(&sub, &name, &app_config.reactions.subscription);
+                accept_comment(comment_event, &ui_tx, &queue, &services).await;
+            }
+            InEvent::HostSpeech { speaker, text } => {
+                log::info!("Heard {speaker}: {text}");
+                // the host is trusted and goes ahead of the chat
+                let comment_event =
+                    CommentEvent::new(speaker, text, HOST_SOURCE, Priority::Superchat)
+                        .with_kind(CommentKind::Host);
+                accept_comment(comment_event, &ui_tx, &queue, &services).await;
+            }
+            InEvent::Touch { character, prompt } => {
+                log::info!("{character} was touched: {prompt}");
+                let comment_event =
+                    CommentEvent::new(character, prompt, TOUCH_SOURCE, Priority::Mention)
+                        .with_kind(CommentKind::Touch);
+                accept_comment(comment_event, &ui_tx, &queue, &services).await;
+            }
+            InEvent::Notification(mut notification) => {
+                log::info!(
+                    "Received notification {} via {}: {}",
+                    notification.event,
+                    notification.source,
+                    notification.title
+                );
+                // e.g. a calendar invite, anyone may have written it
+                let title = std::mem::take(&mut notification.title);
+                let Some(title) = moderate_message(
+                    moderator,
+                    &notification.event,
+                    Some(title),
+                    &notification.source,
+                )
+                .await
+                else {
+                    continue;
+                };
+                notification.title = title;
+                notification.message = moderate_message(
+                    moderator,
+                    &notification.event,
+                    notification.message,
+                    &notification.source,
+                )
+                .await;
+                let comment_event = notification_comment(&notification, &app_config.reactions);
+                accept_comment(comment_event, &ui_tx, &queue, &services).await;
+            }
+            InEvent::Control(command) => {
+                log::info!("Operator command {command:?}");
+                if command == ControlCommand::Regenerate {
+                    // the worker can't pick the next comment in between
+                    let mut comment_queue = queue.queue.lock().unwrap();
+                    if let Some(comment) = queue.last_answered.lock().unwrap().take() {
+                        services.comments.queued(comment.id);
+                        comment_queue.requeue(comment);
+                        services.comments.set_waiting(comment_queue.waiting());
+                        queue.notify.notify_one();
+                    }
+                }
+                if let ControlCommand::Requeue(id) = command {
+                    let comment = queue
+                        .recent
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .find(|comment| comment.id == id)
+                        .map(CommentEvent::requeued);
+                    match comment {
+                        Some(comment) => {
+                            log::info!("Requeued comment from {}: {}", comment.user, comment.text);
+                            let mut comment_queue = queue.queue.lock().unwrap();
+                            services.comments.queued(comment.id);
+                            comment_queue.requeue(comment);
+                            services.comments.set_waiting(comment_queue.waiting());
+                            queue.notify.notify_one();
+                        }
+                        None => log::warn!("Comment {id} is too old to answer again"),
+                    }
+                }
+                if command == ControlCommand::TogglePomodoro {
+                    services.companion.toggle_pomodoro(Instant::now());
+                }
+                if command == ControlCommand::ReadSelection {
+                    // the clipboard may wait for the app owning the selection
+                    let selection = tokio::task::spawn_blocking(read_selection)
+                        .await
+                        .map_err(anyhow::Error::from)
+                        .and_then(|result| result);
+                    match selection {
+                        Ok(text) => match read_aloud_prompt(&text, &app_config.read_aloud) {
+                            Some(prompt) => {
+                                let comment_event = CommentEvent::new(
+                                    &app_config.gui.user_name,
+                                    prompt,
+                                    READ_ALOUD_SOURCE,
+                                    Priority::Superchat,
+                                );
+                                accept_comment(comment_event, &ui_tx, &queue, &services).await;
+                            }
+                            None => {
+                                let _ = ui_tx
+                                    .send(UiEvent::Error("Nothing selected to read".to_string()));
+                            }
+                        },
+                        Err(e) => {
+                            log::error!("Failed to read the selection: {e}");
+                            let _ = ui_tx
+                                .send(UiEvent::Error(format!("Failed to read the selection: {e}")));
+                        }
+                    }
+                }
+                let _ = ui_tx.send(UiEvent::Control(command));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Announce, record and queue a comment that passed moderation.
+async fn accept_comment(
+    comment_event: CommentEvent,
+    ui_tx: &broadcast::Sender<UiEvent>,
+    queue: &SharedQueue,
+    services: &PipelineServices,
+) {
+    let _ = ui_tx.send(UiEvent::NewComment(comment_event.clone()));
+    {
+        let mut recent = queue.recent.lock().unwrap();
+        if recent.len() == RECENT_COMMENTS {
+            recent.pop_front();
+        }
+        recent.push_back(comment_event.clone());
+    }
+    if let Some(storage) = &services.storage {
+        let comment = comment_event.clone();
+        if let Err(e) = storage
+            .blocking(move |storage| storage.record_comment(&comment))
+            .await
+        {
+            log::error!("Failed to record comment: {e}");
+        }
+    }
+    if let Some(viewers) = &services.viewers
+        && from_viewer(&comment_event)
+    {
+        viewers.record_message(&comment_event.user, unix_now());
+    }
+
+    let mut comment_queue = queue.queue.lock().unwrap();
+    let (id, user) = (comment_event.id, comment_event.user.clone());
+    match comment_queue.push(comment_event, Instant::now()) {
+        PushOutcome::Queued => {
+            services.comments.queued(id);
+            services.comments.set_waiting(comment_queue.waiting());
+            queue.notify.notify_one();
+        }
+        outcome => {
+            let reason = match outcome {
+                PushOutcome::RateLimited => "rate limited",
+                PushOutcome::Duplicate => "duplicate",
+                _ => "ignored",
+            };
+            services.comments.set(
+                id,
+                CommentStatus::Dropped {
+                    reason: reason.to_string(),
+                },
+            );
+            services.metrics.comments_dropped.inc();
+            let stats = comment_queue.stats();
+            log::info!(
+                "Skipped comment from {user} ({outcome:?}), dropped so far: {} rate limited, {} duplicates, {} ignored, {} overflow",
+                stats.dropped_rate_limited,
+                stats.dropped_duplicate,
+                stats.dropped_ignored,
+                stats.dropped_overflow
+            );
+        }
+    }
+}
+
+/// Drop the message attached to a gift, subscription or notification if moderation rejects it,
+/// the thanks are still given.
+async fn moderate_message(
+    moderator: &mut Moderator,
+    user: &str,
+    message: Option<String>,
+    source: &str,
+) -> Option<String> {
+    let message = message?;
+    let comment = CommentEvent::new(user, message, source, Priority::Superchat);
+    match moderator.check(&comment).await {
+        Verdict::Allowed => Some(comment.text),
+        Verdict::Rejected(reason) => {
+            log::info!("Dropped message from {user}: {reason}");
+            None
+        }
+    }
+}
+
+fn build_speakers(app_config: &AppConfig) -> anyhow::Result<Vec<Speaker>> {
+    app_config
+        .characters
+        .iter()
+        .enumerate()
+        .map(|(index, character)| {
+            let system_prompt = render_system_prompt(
+                character,
+                &character.system_instruction_template,
+                &app_config.audio.effects,
+            )?;
+            Ok(Speaker {
+                index,
+                llm: init_llm(app_config, character, system_prompt.clone()),
+                model: Arc::new(character.render.model.clone()),
+                system_prompt,
+                topic: None,
+            })
+        })
+        .collect()
+}
+
+/// Answer queued comments one at a time, a restart without `speakers` starts new conversations.
+async fn run_ai_worker(
+    speakers: Option<Vec<Speaker>>,
+    ui_tx: broadcast::Sender<UiEvent>,
+    mut live_rx: watch::Receiver<Arc<LiveConfig>>,
+    queue: Arc<SharedQueue>,
+    services: PipelineServices,
+    app_config: Arc<AppConfig>,
+    shutdown: Shutdown,
+) -> anyhow::Result<()> {
+    let mut speakers = match speakers {
+        Some(speakers) => speakers,
+        None => build_speakers(&app_config)?,
+    };
+    let names: Vec<String> = app_config
+        .characters
+        .iter()
+        .map(|character| character.name.clone())
+        .collect();
+    let mut next_character = 0;
+    let mut tts_client = TtsClient::new(app_config.tts.base_url.as_str());
+    tts_client.set_speed(app_config.tts.speed);
+    let tts_cache = app_config
+        .tts
+        .cache
+        .as_deref()
+        .map(TtsCache::open)
+        .transpose()?;
+    let mut terse = false;
+    let mut topics = app_config
+        .topics
+        .as_ref()
+        .map(|config| TopicTracker::new(config, &app_config.ai.api_key, services.usage.clone()));
+    let safety = app_config
+        .safety
+        .as_ref()
+        .map(|config| SafetyFilter::new(config, &app_config.ai.api_key, services.usage.clone()))
+        .transpose()?
+        .map(tokio::sync::Mutex::new);
+    // what the speakers were built with
+    let mut live = Arc::new(LiveConfig::from_app_config(&app_config));
+    while !shutdown.is_triggered() {
+        let reloaded = live_rx.borrow_and_update().clone();
+        if !Arc::ptr_eq(&reloaded, &live) {
+            apply_live_config(
+                &live,
+                &reloaded,
+                &mut speakers,
+                &mut tts_client,
+                &app_config,
+                &ui_tx,
+            );
+            live = reloaded;
+        }
+
+        let (next, waiting) = {
+            let mut comment_queue = queue.queue.lock().unwrap();
+            let next = comment_queue.pop();
+            if let Some(comment) = &next {
+                services.comments.set(comment.id, CommentStatus::Answering);
+                services.comments.set_waiting(comment_queue.waiting());
+                *queue.last_answered.lock().unwrap() = Some(comment.clone());
+            }
+            (next, comment_queue.len())
+        };
+        let Some(comment_event) = next else {
+            tokio::select! {
+                _ = queue.notify.notified() => {}
+                _ = shutdown.triggered() => {}
+                // applied right away, not only with the next comment
+                _ = live_rx.changed() => {}
+            }
+            continue;
+        };
+
+        let busy = app_config
+            .pacing
+            .terse_queue_length
+            .is_some_and(|length| waiting >= length);
+        if busy != terse {
+            terse = busy;
+            log::info!(
+                "{} terse mode with {waiting} comments waiting",
+                if terse { "Entering" } else { "Leaving" }
+            );
+        }
+
+        // touches are answered by the touched character
+        let addressed = match comment_event.kind {
+            CommentKind::Touch => &comment_event.user,
+            _ => &comment_event.text,
+        };
+        let first = pick_character(addressed, &names, &mut next_character);
+        let context = AnswerContext {
+            terse,
+            tts_client: &tts_client,
+            tts_cache: tts_cache.as_ref(),
+            ui_tx: &ui_tx,
+            services: &services,
+            app_config: &app_config,
+            topic: topics.as_ref().and_then(TopicTracker::prompt_context),
+            safety: safety.as_ref(),
+        };
+        let said = tokio::select! {
+            said = converse(&comment_event, &mut speakers, first, &context) => said,
+            _ = shutdown.triggered() => {
+                // the answer is cut off, try again after the restart
+                let mut comment_queue = queue.queue.lock().unwrap();
+                services.comments.queued(comment_event.id);
+                comment_queue.requeue(comment_event);
+                services.comments.set_waiting(comment_queue.waiting());
+                continue;
+            }
+        };
+        let status = match &said {
+            Ok(said) if said.is_empty() => CommentStatus::Dropped {
+                reason: "no answer".to_string(),
+            },
+            Ok(_) => CommentStatus::Answered,
+            Err(Unsafe) => CommentStatus::Filtered,
+        };
+        services.comments.set(comment_event.id, status);
+        let said = said.unwrap_or_default();
+
+        // the topic follows the conversation, not the events
+        if let Some(topics) = &mut topics
+            && matches!(comment_event.kind, CommentKind::Chat | CommentKind::Host)
+            && !said.is_empty()
+        {
+            topics.record(
+                comment_event.name(),
+                &comment_event.text,
+                &names[first],
+                &said.join(" "),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// What answering a comment needs besides the speaker.
+struct AnswerContext<'a> {
+    /// Many comments are waiting, keep it short
+    terse: bool,
+    tts_client: &'a TtsClient,
+    tts_cache: Option<&'a TtsCache>,
+    ui_tx: &'a broadcast::Sender<UiEvent>,
+    services: &'a PipelineServices,
+    app_config: &'a AppConfig,
+    /// Where the conversation is, see [`TopicTracker`]
+    topic: Option<String>,
+    safety: Option<&'a tokio::sync::Mutex<SafetyFilter>>,
+}
+
+/// The answer didn't pass the safety filter, see [`review_responses`].
+#[derive(Debug)]
+struct Unsafe;
+
+/// Answer a comment, then let the characters reply to each other for the configured turns.
+///
+/// Returns what the first character said to the comment.
+async fn converse(
+    comment_event: &CommentEvent,
+    speakers: &mut [Speaker],
+    first: usize,
+    context: &AnswerContext<'_>,
+) -> Result<Vec<String>, Unsafe> {
+    let AnswerContext {
+        services,
+        app_config,
+        ..
+    } = context;
+    let mut index = first;
+    let answer = answer_comment(comment_event, &mut speakers[index], context).await;
+    // the characters don't chat among themselves while the queue is long
+    if speakers.len() < 2 || context.terse {
+        return answer;
+    }
+
+    let mut said = answer.as_ref().cloned().unwrap_or_default();
+    for _ in 0..app_config.dialogue_turns {
+        if said.is_empty() {
+            break;
+        }
+        let line = CommentEvent::new(
+            &app_config.characters[index].name,
+            said.join("\n"),
+            DIALOGUE_SOURCE,
+            comment_event.priority,
+        );
+        if let Some(storage) = &services.storage {
+            let line = line.clone();
+            if let Err(e) = storage
+                .blocking(move |storage| storage.record_comment(&line))
+                .await
+            {
+                log::error!("Failed to record comment: {e}");
+            }
+        }
+
+        index = (index + 1) % speakers.len();
+        said = answer_comment(&line, &mut speakers[index], context)
+            .await
+            .unwrap_or_default();
+    }
+    answer
+}
+
+/// Answer as the given character, returns what was said.
+async fn answer_comment(
+    comment_event: &CommentEvent,
+    speaker: &mut Speaker,
+    context: &AnswerContext<'_>,
+) -> Result<Vec<String>, Unsafe> {
+    let AnswerContext {
+        terse,
+        ui_tx,
+        services,
+        app_config,
+        ref topic,
+        ..
+    } = *context;
+    let pacing = &app_config.pacing;
+    let _ = ui_tx.send(UiEvent::AiThinking);
+    let character = &app_config.characters[speaker.index];
+    let mut trace = Trace::new(comment_event.id, comment_event.received_at);
+    trace.mark(Stage::Queue, Instant::now());
+
+    let mut prompt = match &services.viewers {
+        Some(viewers) if from_viewer(comment_event) => format!(
+            "{}\n{}",
+            viewers.prompt_context(&comment_event.user, comment_event.name(), unix_now()),
+            comment_event.text
+        ),
+        // let the character know who is talking to them out loud
+        _ if comment_event.kind == CommentKind::Host => {
+            format!("【{}】{}", comment_event.user, comment_event.text)
+        }
+        _ => comment_event.text.clone(),
+    };
+    speaker.set_topic(topic.clone());
+    if terse {
+        prompt = format!("{prompt}\n{}", pacing.terse_prompt);
+    }
+
+    // Generate response
+    let mut responses = if comment_event.kind == CommentKind::Announcement {
+        vec![AIResponse {
+            response: comment_event.text.clone(),
+            japanese_response: comment_event.text.clone(),
+            layers: Vec::new(),
+            poll: None,
+            effect: None,
+        }]
+    } else {
+        match speaker.llm.chat(&prompt, speaker.model.clone()).await {
+            Ok(r) => {
+                services.usage.record(speaker.llm.last_usage()).await;
+                r
+            }
+            Err(err) => {
+                services.metrics.llm_errors.inc();
+                let _ = ui_tx.send(UiEvent::Error(err.to_string()));
+                return Ok(Vec::new());
+            }
+        }
+    };
+    for res in &mut responses {
+        services.plugins.response(comment_event, res).await;
+    }
+    // after the plugins, which may rewrite what is said
+    let mut responses = match context.safety {
+        Some(safety) => {
+            review_responses(
+                responses,
+                &prompt,
+                comment_event,
+                speaker,
+                &mut *safety.lock().await,
+                context,
+            )
+            .await?
+        }
+        None => responses,
+    };
+    let llm_done_at = Instant::now();
+    trace.mark(Stage::Llm, llm_done_at);
+    services
+        .metrics
+        .comment_to_llm
+        .observe(llm_done_at - comment_event.received_at);
+
+    log::info!("AI responsed with {} messages", responses.len());
+    if let Some(max_sentences) = pacing.max_sentences
+        && responses.len() > max_sentences
+    {
+        log::info!(
+            "Dropped {} lines over the limit",
+            responses.len() - max_sentences
+        );
+        responses.truncate(max_sentences);
+    }
+
+    if app_config.poll.ai
+        && let Some(proposal) = responses.iter().find_map(|res| res.poll.clone())
+    {
+        match services.polls.start(
+            proposal.question,
+            proposal.options,
+            app_config.poll.duration,
+        ) {
+            Ok(poll) => {
+                log::info!("AI started poll {}", poll.question);
+                let _ = ui_tx.send(UiEvent::Poll(poll));
+            }
+            Err(e) => log::info!("Ignored poll proposed by the AI: {e}"),
+        }
+    }
+
+    let mut said = Vec::with_capacity(responses.len());
+    let mut spoken = Duration::ZERO;
+    for res in responses {
+        // Generate voice
+        log::info!("Generate voice for text {}", &res.japanese_response);
+        let tts_started_at = Instant::now();
+        let mut voice =
+            synthesize(&res.japanese_response, character.voice.as_deref(), context).await;
+        if let Some(name) = &res.effect {
+            match app_config.audio.effects.get(name) {
+                Some(effect) => {
+                    let (unprocessed, effect) = (voice.clone(), effect.clone());
+                    let processed =
+                        tokio::task::spawn_blocking(move || effects::apply(&unprocessed, &effect))
+                            .await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|result| result);
+                    match processed {
+                        Ok(processed) => voice = processed,
+                        Err(e) => log::error!("Failed to apply voice effect {name}: {e}"),
+                    }
+                }
+                None => log::info!("Ignored unknown voice effect {name}"),
+            }
+        }
+        let synthesized_at = Instant::now();
+        services
+            .metrics
+            .llm_to_tts
+            .observe(synthesized_at - tts_started_at);
+        let mut trace = trace.clone();
+        trace.mark(Stage::Tts, synthesized_at);
+        log::debug!("{trace}");
+
+        let duration = audio_duration(&voice);
+        let line_duration = duration.unwrap_or_default();
+        if !fits_speech_budget(spoken, line_duration, pacing.max_speech) {
+            log::info!("Stopped the answer after {spoken:?} of speech");
+            break;
+        }
+        spoken += line_duration;
+
+        // gifts and subscriptions may show a fixed expression
+        let reaction_layers = app_config.reactions.layers(comment_event.kind);
+        let layers = if reaction_layers.is_empty() {
+            res.layers
+        } else {
+            reaction_layers.to_vec()
+        };
+
+        if let Some(storage) = &services.storage {
+            let (comment_id, tts_latency) = (comment_event.id, synthesized_at - tts_started_at);
+            let (response, japanese_response, layers) = (
+                res.response.clone(),
+                res.japanese_response.clone(),
+                layers.clone(),
+            );
+            let recorded = storage
+                .blocking(move |storage| {
+                    storage.record_response(&ResponseRecord {
+                        comment_id: Some(comment_id),
+                        response: &response,
+                        japanese_response: &japanese_response,
+                        layers: &layers,
+                        tts_latency,
+                        audio_duration: duration,
+                    })
+                })
+                .await;
+            if let Err(e) = recorded {
+                log::error!("Failed to record response: {e}");
+            }
+        }
+
+        log::info!("Send reply to frontend");
+        services.metrics.replies.inc();
+        said.push(res.response.clone());
+        let _ = ui_tx.send(UiEvent::AiReply {
+            character: speaker.index,
+            text: res.response,
+            layers,
+            voice,
+            priority: comment_event.priority,
+            trace,
+        });
+    }
+
+    Ok(said)
+}
+
+/// Let only what passes the safety filter reach the voice and subtitles, [`Unsafe`] if nothing
+/// does.
+/// Announcements fill in what was notified, they are reviewed too but dropped instead of
+/// regenerated.
+async fn review_responses(
+    mut responses: Vec<AIResponse>,
+    prompt: &str,
+    comment_event: &CommentEvent,
+    speaker: &mut Speaker,
+    safety: &mut SafetyFilter,
+    context: &AnswerContext<'_>,
+) -> Result<Vec<AIResponse>, Unsafe> {
+    let AnswerContext {
+        ui_tx,
+        services,
+        app_config,
+        ..
+    } = *context;
+    let name = &app_config.characters[speaker.index].name;
+    let can_regenerate = comment_event.kind != CommentKind::Announcement;
+    let mut regenerations = 0;
+    loop {
+        let review = safety.review(&responses).await;
+        let reason = match &review {
+            Review::Safe => return Ok(responses),
+            Review::Matched(reason) | Review::Rejected(reason) => reason.clone(),
+        };
+        services.metrics.unsafe_responses.inc();
+
+        match safety.action() {
+            SafetyAction::Redact if matches!(review, Review::Matched(_)) => {
+                log::warn!("Redacted the response of {name}: {reason}");
+                for res in &mut responses {
+                    res.response = safety.redact(&res.response);
+                    res.japanese_response = safety.redact(&res.japanese_response);
+                }
+                return Ok(responses);
+            }
+            SafetyAction::Regenerate if can_regenerate && regenerations < MAX_REGENERATIONS => {
+                regenerations += 1;
+                log::warn!("Regenerating the response of {name}: {reason}");
+                let prompt = format!("{prompt}\n{REGENERATE_PROMPT}");
+                match speaker.llm.chat(&prompt, speaker.model.clone()).await {
+                    Ok(regenerated) => {
+                        services.usage.record(speaker.llm.last_usage()).await;
+                        responses = regenerated;
+                        for res in &mut responses {
+                            services.plugins.response(comment_event, res).await;
+                        }
+                    }
+                    Err(err) => {
+                        services.metrics.llm_errors.inc();
+                        let _ = ui_tx.send(UiEvent::Error(err.to_string()));
+                        return Ok(Vec::new());
+                    }
+                }
+            }
+            SafetyAction::Redact => {
+                // the classifier can't tell which words to redact
+                log::warn!("Dropped the response of {name} rejected by the classifier: {reason}");
+                let _ = ui_tx.send(UiEvent::Error(format!(
+                    "Dropped an unsafe response of {name}: {reason}"
+                )));
+                return Err(Unsafe);
+            }
+            _ => {
+                log::warn!("Dropped the response of {name}: {reason}");
+                let _ = ui_tx.send(UiEvent::Error(format!(
+                    "Dropped an unsafe response of {name}: {reason}"
+                )));
+                return Err(Unsafe);
+            }
+        }
+    }
+}
+
+/// Generate the voice of a line, or take it from the cache.
+async fn synthesize(text: &str, voice: Option<&str>, context: &AnswerContext<'_>) -> Bytes {
+    let speed = context.tts_client.speed();
+    if let Some(cached) = context
+        .tts_cache
+        .and_then(|cache| cache.get(text, voice, speed))
+    {
+        return cached;
+    }
+
+    match context.tts_client.generate(text, voice).await {
+        Ok(tts_out) => {
+            if let Some(cache) = context.tts_cache {
+                cache.put(text, voice, speed, &tts_out);
+            }
+            tts_out
+        }
+        Err(e) => {
+            // keep the line so subtitles and timing still work
+            log::error!("Failed to invoke tts, using fallback audio: {e}");
+            context.services.metrics.tts_errors.inc();
+            let _ = context.ui_tx.send(UiEvent::Error(e.to_string()));
+            context.app_config.tts.fallback_audio.clone()
+        }
+    }
+}
+
+/// Whether a line still fits the speaking time of an answer, the first line always does.
+fn fits_speech_budget(spoken: Duration, line: Duration, max: Option<Duration>) -> bool {
+    spoken.is_zero() || max.is_none_or(|max| spoken + line <= max)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::pipeline::{changed_templates, fits_speech_budget, pick_character};
+
+    #[test]
+    fn speech_budget() {
+        let secs = Duration::from_secs;
+        assert!(fits_speech_budget(secs(0), secs(20), Some(secs(10))));
+        assert!(fits_speech_budget(secs(4), secs(6), Some(secs(10))));
+        assert!(!fits_speech_budget(secs(4), secs(7), Some(secs(10))));
+        assert!(fits_speech_budget(secs(40), secs(7), None));
+    }
+
+    #[test]
+    fn pick_mentioned_or_next() {
+        let names = ["ムラサメ".to_string(), "Yoshino".to_string()];
+        let mut next = 0;
+
+        assert_eq!(pick_character("hi yoshino", &names, &mut next), 1);
+        assert_eq!(pick_character("hello", &names, &mut next), 0);
+        assert_eq!(pick_character("hello", &names, &mut next), 1);
+        assert_eq!(pick_character("hello", &names, &mut next), 0);
+        // the first mention in the text wins
+        assert_eq!(
+            pick_character("yoshino, ask ムラサメ", &names, &mut next),
+            1
+        );
+    }
+
+    #[test]
+    fn find_changed_templates() {
+        let templates = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            changed_templates(&templates(&["a", "b", "c"]), &templates(&["a", "B", "c"])),
+            [1]
+        );
+        // characters added since the start are left alone
+        assert!(changed_templates(&templates(&["a"]), &templates(&["a", "b"])).is_empty());
+    }
+}
@@ -0,0 +1,785 @@
+use std::{
+    collections::VecDeque,
+    io::{BufReader, Cursor},
+    sync::{Arc, mpsc},
+    time::{Duration, Instant, SystemTime},
+};
+
+use bytes::Bytes;
+use rodio::{
+    Decoder, Sink, Source,
+    cpal::{
+        self,
+        traits::{DeviceTrait, HostTrait},
+    },
+    source::{EmptyCallback, Zero},
+};
+use tokio::sync::broadcast;
+
+use crate::{
+    bus::{ControlCommand, Priority, UiEvent},
+    config::{AppConfig, PreemptConfig},
+    journal::ReplyJournal,
+    latency::{Stage, Trace},
+    lipsync::Envelope,
+    meter::{LevelMeter, Metered},
+    metrics::Metrics,
+    output::Output,
+    soundboard::{SoundCommand, Soundboard},
+    subtitle::SubtitleWriter,
+    transcript::CaptionFile,
+    utils::audio_duration,
+};
+
+/// A reply line waiting to be spoken.
+#[derive(Debug, Clone)]
+pub struct Line {
+    /// Index of the speaking character
+    pub character: usize,
+    pub text: String,
+    pub layers: Vec<String>,
+    pub voice: Bytes,
+    /// Priority of the comment this line answers
+    pub priority: Priority,
+    /// Stages of the comment this line answers, unknown for lines resumed from the journal
+    pub trace: Option<Trace>,
+    /// Set once the line is saved in the [`ReplyJournal`]
+    pub journal_seq: Option<u64>,
+}
+
+/// How often a missing output device is looked for.
+const OUTPUT_RETRY: Duration = Duration::from_secs(10);
+
+/// What a character shows once it is done speaking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RestStage {
+    /// Still the expression of the line
+    Holding,
+    Default,
+    /// Settled after a while of silence
+    Resting,
+}
+
+/// Speaks reply lines one after another, shared by the GUI and headless frontends.
+pub struct Player {
+    /// Silent while no device can be opened
+    output: Output,
+    /// The device switched away from, kept until the line playing on it is over
+    old_output: Option<Output>,
+    /// Name of the device, the default one if unset
+    device: Option<String>,
+    /// When to try opening the device again while playing silently
+    next_output_retry: Instant,
+    /// Told to the desktop user, see [`Player::take_audio_notice`]
+    audio_notice: Option<String>,
+    /// Level of the voice before the volume is applied
+    meter: LevelMeter,
+    pending: VecDeque<Line>,
+    current: Option<Line>,
+    is_playing: bool,
+    /// When the current line started and was done speaking
+    started_at: Option<Instant>,
+    finished_at: Option<Instant>,
+    /// Length of the current voice, if known
+    duration: Option<Duration>,
+    current_sink: Option<Arc<Sink>>,
+    /// Volume of the voice before muting and ducking
+    volume: f32,
+    muted: bool,
+    /// Also holds back the next line while set
+    paused: bool,
+    /// The voice is quieter while a sound effect plays
+    ducked: bool,
+    soundboard: Option<Soundboard>,
+    /// Only the base layer is shown while set
+    neutral: bool,
+    /// Per character, ordered from closed to open, empty without lip-sync
+    mouth_layers: Vec<Vec<String>>,
+    /// Per character, shown once there is nothing left to say
+    default_layers: Vec<Vec<String>>,
+    /// Per character, shown after a while of silence
+    rest_layers: Vec<Vec<String>>,
+    /// How long the expression of a line stays after it
+    expression_hold: Duration,
+    rest_after: Option<Duration>,
+    /// Where the expression is since the last line, see [`rest_stage`]
+    stage: RestStage,
+    /// Loudness of the current voice, drives the mouth
+    envelope: Option<Envelope>,
+    /// Index into the mouth layers of the speaking character
+    mouth: Option<usize>,
+    /// See [`Player::shown_layers`]
+    shown: Vec<String>,
+    /// Signalled by the sink once a line played to its end or was skipped
+    finished_rx: mpsc::Receiver<Instant>,
+    finished_tx: mpsc::Sender<Instant>,
+    /// Told when a character starts and stops speaking
+    ui_tx: broadcast::Sender<UiEvent>,
+    subtitle_writer: Option<SubtitleWriter>,
+    caption_file: Option<CaptionFile>,
+    journal: Option<ReplyJournal>,
+    preempt: PreemptConfig,
+    metrics: Arc<Metrics>,
+}
+
+impl Player {
+    pub fn new(
+        app_config: &AppConfig,
+        metrics: Arc<Metrics>,
+        ui_tx: broadcast::Sender<UiEvent>,
+    ) -> anyhow::Result<Self> {
+        let device = app_config.audio.device.clone();
+        let mut audio_notice = None;
+        let output = match Output::open(device.as_deref()) {
+            Ok(output) => output,
+            Err(e) => {
+                // e.g. on a server, the lines are still shown for as long as they'd be heard
+                log::warn!("Running without audio: {e}");
+                metrics.audio.set_error(Some(e.to_string()));
+                audio_notice = Some(format!("Running without audio: {e}"));
+                Output::silent()
+            }
+        };
+        let (finished_tx, finished_rx) = mpsc::channel();
+
+        let subtitle_writer = app_config.subtitle.as_ref().and_then(|cfg| {
+            SubtitleWriter::create(&cfg.path, cfg.format)
+                .inspect_err(|e| log::error!("Failed to create subtitle file: {e}"))
+                .ok()
+        });
+
+        let caption_file = app_config.caption_file.as_deref().map(CaptionFile::new);
+
+        let mut pending = VecDeque::new();
+        let journal = match &app_config.reply_journal {
+            Some(dir) => {
+                let (journal, lines) = ReplyJournal::open(dir)?;
+                for line in lines {
+                    if line.character < app_config.characters.len() {
+                        pending.push_back(line);
+                    } else if let Some(seq) = line.journal_seq {
+                        journal.remove(seq);
+                    }
+                }
+                if !pending.is_empty() {
+                    log::info!("Resuming {} unplayed lines", pending.len());
+                }
+                Some(journal)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            output,
+            old_output: None,
+            device,
+            next_output_retry: Instant::now() + OUTPUT_RETRY,
+            audio_notice,
+            meter: LevelMeter::default(),
+            pending,
+            current: None,
+            is_playing: false,
+            started_at: None,
+            finished_at: None,
+            duration: None,
+            current_sink: None,
+            volume: app_config.audio.voice_volume,
+            muted: false,
+            paused: false,
+            ducked: false,
+            soundboard: app_config.soundboard.clone().map(Soundboard::new),
+            neutral: false,
+            mouth_layers: app_config
+                .characters
+                .iter()
+                .map(|character| character.render.mouth_layers.clone())
+                .collect(),
+            default_layers: app_config
+                .characters
+                .iter()
+                .map(|character| character.render.default_layers.clone())
+                .collect(),
+            rest_layers: app_config
+                .characters
+                .iter()
+                .map(|character| {
+                    if character.render.rest_layers.is_empty() {
+                        character.render.default_layers.clone()
+                    } else {
+                        character.render.rest_layers.clone()
+                    }
+                })
+                .collect(),
+            expression_hold: app_config.idle.expression_hold,
+            rest_after: app_config.idle.rest_after,
+            stage: RestStage::Default,
+            envelope: None,
+            mouth: None,
+            shown: Vec::new(),
+            finished_rx,
+            finished_tx,
+            ui_tx,
+            subtitle_writer,
+            caption_file,
+            journal,
+            preempt: app_config.preempt.clone(),
+            metrics,
+        })
+    }
+
+    /// Queue a line, important lines are spoken before everything less important that is still
+    /// pending.
+    pub fn enqueue(&mut self, mut line: Line) {
+        if let Some(journal) = &mut self.journal {
+            line.journal_seq = Some(journal.append(&line));
+        }
+
+        if self.preempt.priority.is_some_and(|p| line.priority >= p) {
+            let index = jump_position(&self.pending, line.priority);
+            let postponed = self.pending.len() - index;
+            if postponed > 0 {
+                log::info!(
+                    "Postponed {postponed} pending lines for a {:?} reply",
+                    line.priority
+                );
+            }
+
+            // otherwise the current sentence is finished first
+            if let Some(fade_out) = self.preempt.fade_out
+                && self.is_playing
+                && self
+                    .current
+                    .as_ref()
+                    .is_some_and(|current| current.priority < line.priority)
+            {
+                self.fade_out_current(fade_out);
+            }
+            self.pending.insert(index, line);
+            return;
+        }
+        self.pending.push_back(line);
+    }
+
+    /// Whether a line is spoken or about to be.
+    pub fn is_busy(&self) -> bool {
+        !self.paused && (self.is_playing || !self.pending.is_empty())
+    }
+
+    /// The line spoken last, kept until the next one starts.
+    pub fn current(&self) -> Option<&Line> {
+        self.current.as_ref()
+    }
+
+    /// When the current line started, and when it was done speaking if it was.
+    pub fn current_timing(&self) -> Option<(Instant, Option<Instant>)> {
+        self.started_at
+            .map(|started_at| (started_at, self.finished_at))
+    }
+
+    /// How much of the current line was spoken, 0 to 1. Lines of unknown length count as
+    /// spoken right away.
+    pub fn progress(&self) -> f32 {
+        match (&self.current_sink, self.duration) {
+            (Some(sink), Some(duration)) if self.is_playing && !duration.is_zero() => {
+                sink.get_pos().as_secs_f32() / duration.as_secs_f32()
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Note that a frame of `character` is shown. The first one after the current line started
+    /// shows its expression and ends its trace.
+    pub fn frame_shown(&mut self, character: usize) {
+        let Some(line) = &mut self.current else {
+            return;
+        };
+        if line.character != character {
+            return;
+        }
+        if let Some(trace) = &mut line.trace
+            && let Some(played_at) = trace.marked(Stage::Playback)
+            && trace.marked(Stage::Render).is_none()
+        {
+            let now = Instant::now();
+            trace.mark(Stage::Render, now);
+            self.metrics.playback_to_render.observe(now - played_at);
+            self.metrics.end_to_end.observe(trace.total());
+            log::info!("{trace}");
+        }
+    }
+
+    /// Layers to show on top of the base layer, the mouth goes last.
+    pub fn shown_layers(&self) -> &[String] {
+        &self.shown
+    }
+
+    /// The character's expression while nothing is spoken.
+    pub fn default_layers(&self, character: usize) -> &[String] {
+        &self.default_layers[character]
+    }
+
+    /// Start the next line when idle, returns true if the shown line or the mouth changed.
+    pub fn poll(&mut self) -> bool {
+        let finished = self.check_finished();
+        self.check_output();
+        if let Some(soundboard) = &mut self.soundboard {
+            soundboard.set_speaking(self.is_playing);
+        }
+        let ducked = self
+            .soundboard
+            .as_mut()
+            .is_some_and(Soundboard::is_playing_effect);
+        if ducked != self.ducked {
+            self.ducked = ducked;
+            if let Some(sink) = &self.current_sink {
+                sink.set_volume(self.voice_volume());
+            }
+        }
+
+        if self.is_playing || self.paused {
+            return self.move_mouth();
+        }
+
+        let Some(mut line) = self.pending.pop_front() else {
+            // back to the default expression
+            if finished {
+                self.write_caption("");
+                self.mouth = None;
+                self.update_shown();
+                return true;
+            }
+            // the expression settles while nothing is said
+            if self.current.is_some() && self.rest_stage() != self.stage {
+                self.update_shown();
+                return true;
+            }
+            return self.move_mouth();
+        };
+        self.play(&mut line);
+        self.current = Some(line);
+        self.mouth = None;
+        self.update_shown();
+
+        // free memory
+        self.pending.shrink_to_fit();
+        true
+    }
+
+    /// Follow the loudness of the voice, returns true if the mouth changed.
+    fn move_mouth(&mut self) -> bool {
+        let mouth = match (&self.current, &self.envelope, &self.current_sink) {
+            (Some(line), Some(envelope), Some(sink)) if self.is_playing => {
+                let layers = &self.mouth_layers[line.character];
+                Some(envelope.mouth_at(sink.get_pos(), layers.len()))
+            }
+            _ => None,
+        };
+        if mouth == self.mouth {
+            return false;
+        }
+        self.mouth = mouth;
+        self.update_shown();
+        true
+    }
+
+    fn update_shown(&mut self) {
+        self.shown.clear();
+        let Some(line) = &self.current else {
+            return;
+        };
+        if self.neutral {
+            return;
+        }
+        if !self.is_playing {
+            self.stage = self.rest_stage();
+            let layers = match self.stage {
+                RestStage::Holding => &line.layers,
+                RestStage::Default => &self.default_layers[line.character],
+                RestStage::Resting => &self.rest_layers[line.character],
+            };
+            self.shown.extend_from_slice(layers);
+            return;
+        }
+        self.shown.extend_from_slice(&line.layers);
+        if let Some(mouth) = self.mouth {
+            self.shown
+                .push(self.mouth_layers[line.character][mouth].clone());
+        }
+    }
+
+    fn rest_stage(&self) -> RestStage {
+        match self.finished_at {
+            Some(finished_at) => {
+                rest_stage(finished_at.elapsed(), self.expression_hold, self.rest_after)
+            }
+            None => RestStage::Default,
+        }
+    }
+
+    /// Drop pending lines for shutting down, returns true once the current line is over.
+    ///
+    /// Journaled lines are kept and resumed after the restart.
+    pub fn wind_down(&mut self) -> bool {
+        self.pending.clear();
+        self.check_finished();
+        !self.is_playing
+    }
+
+    /// Apply an operator command, returns true if the shown layers changed.
+    pub fn handle_control(&mut self, command: ControlCommand) -> bool {
+        match command {
+            ControlCommand::Skip => self.stop_current(),
+            ControlCommand::Regenerate => {
+                let dropped: Vec<_> = self.pending.drain(..).collect();
+                self.forget(&dropped);
+                self.stop_current();
+            }
+            ControlCommand::ToggleMute => {
+                self.muted = !self.muted;
+                if let Some(sink) = &self.current_sink {
+                    sink.set_volume(self.voice_volume());
+                }
+            }
+            ControlCommand::TogglePause => {
+                self.paused = !self.paused;
+                if let Some(sink) = &self.current_sink {
+                    if self.paused {
+                        sink.pause();
+                    } else {
+                        sink.play();
+                    }
+                }
+            }
+            ControlCommand::ToggleNeutral => {
+                self.neutral = !self.neutral;
+                self.update_shown();
+                return true;
+            }
+            // handled by the window
+            ControlCommand::NextMonitor
+            | ControlCommand::Screenshot
+            | ControlCommand::Clip
+            | ControlCommand::Activate => {}
+            // handled by the pipeline
+            ControlCommand::Requeue(_)
+            | ControlCommand::ReadSelection
+            | ControlCommand::TogglePomodoro => {}
+            // handled by speech recognition, the window shows it
+            ControlCommand::StartListening | ControlCommand::StopListening => {}
+        }
+        false
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        if let Some(sink) = &self.current_sink {
+            sink.set_volume(self.voice_volume());
+        }
+    }
+
+    /// Play on another output device, the default one if `None`. The current line finishes on
+    /// the old device, sounds playing on it stop.
+    pub fn set_device(&mut self, device: Option<&str>) -> anyhow::Result<()> {
+        let output = Output::open(device)?;
+        let old_output = std::mem::replace(&mut self.output, output);
+        if self.is_playing {
+            self.old_output = Some(old_output);
+        }
+        self.device = device.map(str::to_string);
+        self.metrics.audio.set_error(None);
+        Ok(())
+    }
+
+    /// A change of the audio output the desktop user should know about, once.
+    pub fn take_audio_notice(&mut self) -> Option<String> {
+        self.audio_notice.take()
+    }
+
+    /// Play silently once the device went away, and try to get one back now and then.
+    fn check_output(&mut self) {
+        let now = Instant::now();
+        if self.output.is_lost() {
+            let message = "The audio device went away, playing silently";
+            log::warn!("{message}");
+            self.metrics.audio.set_error(Some(message.to_string()));
+            self.audio_notice = Some(message.to_string());
+            self.output = Output::silent();
+            self.old_output = None;
+            // nothing plays the line to its end anymore
+            if self.is_playing {
+                let _ = self.finished_tx.send(now);
+            }
+            self.next_output_retry = now + OUTPUT_RETRY;
+            return;
+        }
+
+        // between lines only, so a line never finishes on two outputs
+        if !self.output.is_silent() || self.is_playing || now < self.next_output_retry {
+            return;
+        }
+        self.next_output_retry = now + OUTPUT_RETRY;
+        match Output::open(self.device.as_deref()) {
+            Ok(output) => {
+                log::info!("Audio output is back");
+                self.output = output;
+                self.metrics.audio.set_error(None);
+                self.audio_notice = Some("Audio output is back".to_string());
+            }
+            Err(e) => log::debug!("Still no audio output: {e}"),
+        }
+    }
+
+    /// Loudness of the voice as it is played, 0 to 1.
+    pub fn output_level(&self) -> f32 {
+        if self.is_playing && !self.paused {
+            self.meter.level() * self.voice_volume()
+        } else {
+            0.0
+        }
+    }
+
+    /// Play a soundboard request through the same output as the voice.
+    pub fn handle_sound(&mut self, command: &SoundCommand) {
+        if let Some(soundboard) = &mut self.soundboard {
+            soundboard.handle(command, self.output.mixer());
+        }
+    }
+
+    fn voice_volume(&self) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        match &self.soundboard {
+            Some(soundboard) if self.ducked => self.volume * soundboard.duck_volume(),
+            _ => self.volume,
+        }
+    }
+
+    /// Show `text` in the caption file, if there is one.
+    fn write_caption(&self, text: &str) {
+        if let Some(caption_file) = &self.caption_file
+            && let Err(e) = caption_file.write(text)
+        {
+            log::error!("Failed to write caption: {e}");
+        }
+    }
+
+    /// Note that the current line ended, it is done with once spoken. Returns true if it just
+    /// ended.
+    fn check_finished(&mut self) -> bool {
+        let mut finished = false;
+        while let Ok(finished_at) = self.finished_rx.try_recv() {
+            self.is_playing = false;
+            self.finished_at = Some(finished_at);
+            finished = true;
+            if let Some(line) = &self.current {
+                self.send_speaking(line.character, false, finished_at);
+            }
+        }
+        if finished {
+            self.old_output = None;
+        }
+        if finished
+            && let Some(journal) = &self.journal
+            && let Some(seq) = self
+                .current
+                .as_mut()
+                .and_then(|line| line.journal_seq.take())
+        {
+            journal.remove(seq);
+        }
+        finished
+    }
+
+    fn forget(&self, lines: &[Line]) {
+        if let Some(journal) = &self.journal {
+            for seq in lines.iter().filter_map(|line| line.journal_seq) {
+                journal.remove(seq);
+            }
+        }
+    }
+
+    fn fade_out_current(&self, duration: Duration) {
+        const STEPS: u32 = 10;
+
+        let Some(sink) = self.current_sink.clone() else {
+            return;
+        };
+        std::thread::spawn(move || {
+            let volume = sink.volume();
+            for step in 1..=STEPS {
+                sink.set_volume(volume * (1.0 - step as f32 / STEPS as f32));
+                std::thread::sleep(duration / STEPS);
+            }
+            skip(&sink);
+        });
+    }
+
+    fn stop_current(&self) {
+        if let Some(sink) = &self.current_sink {
+            skip(sink);
+        }
+    }
+
+    fn play(&mut self, line: &mut Line) {
+        /// How long lines without a playable voice are shown
+        const SILENT_LINE: Duration = Duration::from_secs(3);
+
+        let now = Instant::now();
+        self.is_playing = true;
+        self.started_at = Some(now);
+        self.finished_at = None;
+        if let Some(trace) = &mut line.trace {
+            if let Some(synthesized_at) = trace.marked(Stage::Tts) {
+                self.metrics.tts_to_playback.observe(now - synthesized_at);
+            }
+            trace.mark(Stage::Playback, now);
+        }
+
+        self.envelope = if self.mouth_layers[line.character].is_empty() {
+            None
+        } else {
+            Envelope::analyze(&line.voice)
+        };
+
+        let sink = Arc::new(Sink::connect_new(self.output.mixer()));
+        sink.set_volume(self.voice_volume());
+        if self.paused {
+            sink.pause();
+        }
+
+        self.duration = audio_duration(&line.voice);
+        if let Some(writer) = &mut self.subtitle_writer {
+            let duration = self.duration.unwrap_or(SILENT_LINE);
+            if let Err(e) = writer.push(&line.text, Instant::now(), duration) {
+                log::error!("Failed to write subtitle: {e}");
+            }
+        }
+        self.write_caption(&line.text);
+
+        match Decoder::new(BufReader::new(Cursor::new(line.voice.clone()))) {
+            Ok(source) => sink.append(Metered::new(source, self.meter.clone())),
+            Err(e) => {
+                log::error!("Failed to decode voice: {e}");
+                sink.append(Zero::new(1, 44100).take_duration(SILENT_LINE));
+            }
+        }
+        // runs once the voice played to its end or was skipped
+        let finished_tx = self.finished_tx.clone();
+        sink.append(EmptyCallback::new(Box::new(move || {
+            let _ = finished_tx.send(Instant::now());
+        })));
+        self.current_sink = Some(sink);
+        self.send_speaking(line.character, true, now);
+    }
+
+    fn send_speaking(&self, character: usize, speaking: bool, at: Instant) {
+        let at = SystemTime::now() - at.elapsed();
+        let _ = self.ui_tx.send(UiEvent::Speaking {
+            character,
+            speaking,
+            at,
+        });
+    }
+}
+
+/// Skip the voice of a sink, its finish callback still runs.
+fn skip(sink: &Sink) {
+    sink.skip_one();
+    sink.play();
+}
+
+/// Names of the output devices, for picking one in the settings.
+pub fn output_devices() -> Vec<String> {
+    match cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            log::error!("Failed to list output devices: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Where a line of `priority` goes to be spoken before the pending lines less important than
+/// it, which keep their order after it.
+fn jump_position(pending: &VecDeque<Line>, priority: Priority) -> usize {
+    pending
+        .iter()
+        .position(|line| line.priority < priority)
+        .unwrap_or(pending.len())
+}
+
+/// Where the expression is after `silence` since the last line.
+fn rest_stage(silence: Duration, hold: Duration, rest_after: Option<Duration>) -> RestStage {
+    match rest_after {
+        Some(rest_after) if silence >= rest_after => RestStage::Resting,
+        _ if silence < hold => RestStage::Holding,
+        _ => RestStage::Default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, time::Duration};
+
+    use bytes::Bytes;
+
+    use crate::{
+        bus::Priority,
+        player::{Line, RestStage, jump_position, rest_stage},
+    };
+
+    fn line(text: &str, priority: Priority) -> Line {
+        Line {
+            character: 0,
+            text: text.to_string(),
+            layers: Vec::new(),
+            voice: Bytes::new(),
+            priority,
+            trace: None,
+            journal_seq: None,
+        }
+    }
+
+    #[test]
+    fn jump_lower_priority_lines() {
+        let pending = VecDeque::from([
+            line("a", Priority::Superchat),
+            line("b", Priority::Mention),
+            line("c", Priority::Normal),
+            line("d", Priority::Mention),
+        ]);
+
+        assert_eq!(jump_position(&pending, Priority::Mention), 2);
+        assert_eq!(jump_position(&pending, Priority::Superchat), 1);
+        assert_eq!(jump_position(&pending, Priority::Normal), 4);
+    }
+
+    #[test]
+    fn settle_after_speaking() {
+        let secs = Duration::from_secs;
+        let hold = secs(2);
+        assert_eq!(
+            rest_stage(secs(1), hold, Some(secs(30))),
+            RestStage::Holding
+        );
+        assert_eq!(
+            rest_stage(secs(2), hold, Some(secs(30))),
+            RestStage::Default
+        );
+        assert_eq!(
+            rest_stage(secs(30), hold, Some(secs(30))),
+            RestStage::Resting
+        );
+        assert_eq!(rest_stage(secs(3600), hold, None), RestStage::Default);
+        // resting wins over a longer hold
+        assert_eq!(
+            rest_stage(secs(5), secs(10), Some(secs(5))),
+            RestStage::Resting
+        );
+        assert_eq!(
+            rest_stage(Duration::ZERO, Duration::ZERO, None),
+            RestStage::Default
+        );
+    }
+}
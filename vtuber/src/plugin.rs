@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use ai::AIResponse;
+use async_trait::async_trait;
+use tokio::sync::broadcast;
+
+use crate::bus::{CommentEvent, UiEvent};
+
+pub mod command;
+
+/// What a plugin wants done with a comment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommentAction {
+    Keep,
+    /// Not answered, the reason is shown like a moderation verdict
+    Drop(String),
+}
+
+/// Third-party code hooked into the pipeline, e.g. posting every reply to social media or
+/// coloring smart lights by emotion. Every hook does nothing unless implemented.
+#[async_trait]
+pub trait Plugin: Send + Sync + 'static {
+    /// Name of the plugin, for the logs.
+    fn name(&self) -> &str;
+
+    /// Called for every comment that passed moderation, may edit or drop it.
+    async fn on_comment(&self, _comment: &mut CommentEvent) -> anyhow::Result<CommentAction> {
+        Ok(CommentAction::Keep)
+    }
+
+    /// Called for every line the AI answered `comment` with, before its voice is synthesized.
+    async fn on_response(
+        &self,
+        _comment: &CommentEvent,
+        _response: &mut AIResponse,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called for every event sent to the frontend, after it was sent.
+    async fn on_ui_event(&self, _event: &UiEvent) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the hooks of every registered plugin in order, a failing plugin is logged and skipped.
+#[derive(Clone, Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Arc<dyn Plugin>>,
+}
+
+impl PluginRegistry {
+    pub fn register(&mut self, plugin: impl Plugin) {
+        self.plugins.push(Arc::new(plugin));
+    }
+
+    /// Pass the comment through every plugin, until one drops it.
+    pub async fn comment(&self, comment: &mut CommentEvent) -> CommentAction {
+        for plugin in &self.plugins {
+            match plugin.on_comment(comment).await {
+                Ok(CommentAction::Keep) => {}
+                Ok(CommentAction::Drop(reason)) => {
+                    log::info!(
+                        "Plugin {} dropped comment {}: {reason}",
+                        plugin.name(),
+                        comment.id
+                    );
+                    return CommentAction::Drop(reason);
+                }
+                Err(e) => log::error!("Plugin {} failed on a comment: {e}", plugin.name()),
+            }
+        }
+        CommentAction::Keep
+    }
+
+    pub async fn response(&self, comment: &CommentEvent, response: &mut AIResponse) {
+        for plugin in &self.plugins {
+            if let Err(e) = plugin.on_response(comment, response).await {
+                log::error!("Plugin {} failed on a response: {e}", plugin.name());
+            }
+        }
+    }
+
+    /// Hand every event on the bus to the plugins, in the background so a slow one doesn't
+    /// hold up the frontend.
+    pub fn spawn_event_listener(&self, mut ui_rx: broadcast::Receiver<UiEvent>) {
+        if self.plugins.is_empty() {
+            return;
+        }
+        let plugins = self.plugins.clone();
+        tokio::spawn(async move {
+            loop {
+                let event = match ui_rx.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("Plugins missed {skipped} events");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+                for plugin in &plugins {
+                    if let Err(e) = plugin.on_ui_event(&event).await {
+                        log::error!("Plugin {} failed on an event: {e}", plugin.name());
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ai::AIResponse;
+    use async_trait::async_trait;
+
+    use crate::{
+        bus::{CommentEvent, Priority},
+        plugin::{CommentAction, Plugin, PluginRegistry},
+    };
+
+    struct Shout;
+
+    #[async_trait]
+    impl Plugin for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        async fn on_comment(&self, comment: &mut CommentEvent) -> anyhow::Result<CommentAction> {
+            if comment.text.contains("spam") {
+                return Ok(CommentAction::Drop("no spam".to_string()));
+            }
+            comment.text = comment.text.to_uppercase();
+            Ok(CommentAction::Keep)
+        }
+
+        async fn on_response(
+            &self,
+            _comment: &CommentEvent,
+            response: &mut AIResponse,
+        ) -> anyhow::Result<()> {
+            response.response.push('!');
+            Ok(())
+        }
+    }
+
+    struct Broken;
+
+    #[async_trait]
+    impl Plugin for Broken {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        async fn on_comment(&self, _comment: &mut CommentEvent) -> anyhow::Result<CommentAction> {
+            anyhow::bail!("offline")
+        }
+    }
+
+    #[tokio::test]
+    async fn run_hooks_in_order() {
+        let mut plugins = PluginRegistry::default();
+        plugins.register(Broken);
+        plugins.register(Shout);
+
+        let mut comment = CommentEvent::new("viewer", "hello", "test", Priority::Normal);
+        assert_eq!(plugins.comment(&mut comment).await, CommentAction::Keep);
+        assert_eq!(comment.text, "HELLO");
+
+        let mut spam = CommentEvent::new("viewer", "buy spam", "test", Priority::Normal);
+        assert_eq!(
+            plugins.comment(&mut spam).await,
+            CommentAction::Drop("no spam".to_string())
+        );
+
+        let mut response = AIResponse {
+            response: "hi".to_string(),
+            japanese_response: "やあ".to_string(),
+            layers: Vec::new(),
+            poll: None,
+            effect: None,
+        };
+        plugins.response(&comment, &mut response).await;
+        assert_eq!(response.response, "hi!");
+    }
+}
@@ -0,0 +1,292 @@
+use std::{process::Stdio, time::Duration};
+
+use ai::AIResponse;
+use anyhow::{Context, anyhow};
+use async_trait::async_trait;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    process::{Child, ChildStdin, ChildStdout, Command},
+    sync::Mutex,
+};
+
+use crate::{
+    bus::{CommentEvent, UiEvent},
+    handler::events::OverlayEvent,
+    plugin::{CommentAction, Plugin},
+};
+
+/// How long a program may take to answer a comment or response hook.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Hook {
+    Comment,
+    Response,
+    Event,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct CommandConfig {
+    /// For the logs
+    pub name: String,
+    /// The program and its arguments
+    pub command: Vec<String>,
+    /// The hooks sent to the program, all of them if empty
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+}
+
+/// Sent to the program as one line of json each.
+#[derive(serde::Serialize)]
+#[serde(tag = "hook", rename_all = "snake_case")]
+enum Request<'a> {
+    Comment {
+        comment: &'a CommentEvent,
+    },
+    Response {
+        comment: &'a CommentEvent,
+        response: &'a str,
+        japanese_response: &'a str,
+        layers: &'a [String],
+    },
+    Event {
+        event: OverlayEvent<'a>,
+    },
+}
+
+/// Answer to a comment hook, `{}` keeps the comment as it is.
+#[derive(serde::Deserialize, Debug, Default, PartialEq)]
+struct CommentReply {
+    /// Drop the comment for this reason
+    drop: Option<String>,
+    /// Replace the text of the comment
+    text: Option<String>,
+}
+
+impl CommentReply {
+    fn apply(self, comment: &mut CommentEvent) -> CommentAction {
+        if let Some(reason) = self.drop {
+            return CommentAction::Drop(reason);
+        }
+        if let Some(text) = self.text {
+            comment.text = text;
+        }
+        CommentAction::Keep
+    }
+}
+
+/// Answer to a response hook, the fields left out are kept.
+#[derive(serde::Deserialize, Debug, Default, PartialEq)]
+struct ResponseReply {
+    response: Option<String>,
+    japanese_response: Option<String>,
+    layers: Option<Vec<String>>,
+}
+
+impl ResponseReply {
+    fn apply(self, response: &mut AIResponse) {
+        if let Some(text) = self.response {
+            response.response = text;
+        }
+        if let Some(text) = self.japanese_response {
+            response.japanese_response = text;
+        }
+        if let Some(layers) = self.layers {
+            response.layers = layers;
+        }
+    }
+}
+
+/// The running program, replaced by a new one when it gets out of step.
+struct Process {
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    // killed when replaced or dropped
+    _child: Child,
+}
+
+impl Process {
+    fn spawn(config: &CommandConfig) -> anyhow::Result<Self> {
+        let (program, args) = config
+            .command
+            .split_first()
+            .with_context(|| format!("Plugin {} has no command", config.name))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start plugin {}", config.name))?;
+        Ok(Self {
+            stdin: child.stdin.take().context("stdin is piped")?,
+            stdout: BufReader::new(child.stdout.take().context("stdout is piped")?).lines(),
+            _child: child,
+        })
+    }
+
+    async fn send(&mut self, line: &[u8]) -> anyhow::Result<()> {
+        self.stdin.write_all(line).await?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+
+    async fn call(&mut self, line: &[u8]) -> anyhow::Result<String> {
+        self.send(line).await?;
+        self.stdout.next_line().await?.context("The program exited")
+    }
+}
+
+/// A plugin in any language: an external program receiving every hook as a line of json on
+/// stdin, e.g. `{"hook": "event", "event": {"type": "reply", ...}}`. Comment and response hooks
+/// wait for a line of json on stdout, see [`CommentReply`] and [`ResponseReply`].
+///
+/// A program that doesn't take a request or answer it in time is restarted, its late answer
+/// would be taken for the answer to the next request otherwise.
+pub struct CommandPlugin {
+    config: CommandConfig,
+    process: Mutex<Process>,
+}
+
+impl CommandPlugin {
+    pub fn spawn(config: &CommandConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            config: config.clone(),
+            process: Mutex::new(Process::spawn(config)?),
+        })
+    }
+
+    fn wants(&self, hook: Hook) -> bool {
+        self.config.hooks.is_empty() || self.config.hooks.contains(&hook)
+    }
+
+    fn encode(request: &Request<'_>) -> anyhow::Result<Vec<u8>> {
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        Ok(line)
+    }
+
+    /// Start the program again, the failed one is killed.
+    fn restart(&self, process: &mut Process, error: anyhow::Error) -> anyhow::Error {
+        log::warn!("Restarting plugin {}: {error:#}", self.config.name);
+        match Process::spawn(&self.config) {
+            Ok(restarted) => *process = restarted,
+            Err(e) => log::error!("{e:#}"),
+        }
+        error
+    }
+
+    async fn send(&self, request: &Request<'_>) -> anyhow::Result<()> {
+        let line = Self::encode(request)?;
+        let mut process = self.process.lock().await;
+        match tokio::time::timeout(REPLY_TIMEOUT, process.send(&line)).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(self.restart(&mut process, e)),
+            Err(_) => Err(self.restart(&mut process, anyhow!("Not taking requests"))),
+        }
+    }
+
+    async fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        request: &Request<'_>,
+    ) -> anyhow::Result<T> {
+        let line = Self::encode(request)?;
+        let mut process = self.process.lock().await;
+        // sending counts as well, a program not reading stdin blocks it once the pipe is full
+        let reply = match tokio::time::timeout(REPLY_TIMEOUT, process.call(&line)).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(e)) => return Err(self.restart(&mut process, e)),
+            Err(_) => return Err(self.restart(&mut process, anyhow!("No reply in time"))),
+        };
+        Ok(serde_json::from_str(&reply)?)
+    }
+}
+
+#[async_trait]
+impl Plugin for CommandPlugin {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn on_comment(&self, comment: &mut CommentEvent) -> anyhow::Result<CommentAction> {
+        if !self.wants(Hook::Comment) {
+            return Ok(CommentAction::Keep);
+        }
+        let reply: CommentReply = self.call(&Request::Comment { comment }).await?;
+        Ok(reply.apply(comment))
+    }
+
+    async fn on_response(
+        &self,
+        comment: &CommentEvent,
+        response: &mut AIResponse,
+    ) -> anyhow::Result<()> {
+        if !self.wants(Hook::Response) {
+            return Ok(());
+        }
+        let reply: ResponseReply = self
+            .call(&Request::Response {
+                comment,
+                response: &response.response,
+                japanese_response: &response.japanese_response,
+                layers: &response.layers,
+            })
+            .await?;
+        reply.apply(response);
+        Ok(())
+    }
+
+    async fn on_ui_event(&self, event: &UiEvent) -> anyhow::Result<()> {
+        if !self.wants(Hook::Event) {
+            return Ok(());
+        }
+        let request = Request::Event {
+            event: OverlayEvent::from_ui_event(event, false),
+        };
+        self.send(&request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::{CommentEvent, Priority},
+        plugin::{
+            CommentAction,
+            command::{CommentReply, ResponseReply},
+        },
+    };
+
+    #[test]
+    fn apply_replies() {
+        let mut comment = CommentEvent::new("viewer", "hello", "test", Priority::Normal);
+        let reply: CommentReply = serde_json::from_str("{}").unwrap();
+        assert_eq!(reply.apply(&mut comment), CommentAction::Keep);
+        assert_eq!(comment.text, "hello");
+
+        let reply: CommentReply = serde_json::from_str(r#"{"text": "hi"}"#).unwrap();
+        assert_eq!(reply.apply(&mut comment), CommentAction::Keep);
+        assert_eq!(comment.text, "hi");
+
+        let reply: CommentReply = serde_json::from_str(r#"{"drop": "off topic"}"#).unwrap();
+        assert_eq!(
+            reply.apply(&mut comment),
+            CommentAction::Drop("off topic".to_string())
+        );
+
+        let mut response = ai::AIResponse {
+            response: "hi".to_string(),
+            japanese_response: "やあ".to_string(),
+            layers: vec!["a.png".to_string()],
+            poll: None,
+            effect: None,
+        };
+        let reply: ResponseReply =
+            serde_json::from_str(r#"{"response": "hello", "layers": []}"#).unwrap();
+        reply.apply(&mut response);
+        assert_eq!(response.response, "hello");
+        assert_eq!(response.japanese_response, "やあ");
+        assert!(response.layers.is_empty());
+    }
+}
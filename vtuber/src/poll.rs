@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Notify;
+
+/// Source of the comments announcing poll results.
+pub const POLL_SOURCE: &str = "poll";
+
+#[derive(thiserror::Error, Debug)]
+pub enum PollError {
+    #[error("A poll is already running")]
+    Running,
+    #[error("A poll needs at least two options")]
+    TooFewOptions,
+    #[error("No poll is running")]
+    NotRunning,
+}
+
+#[derive(Debug, Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct PollOption {
+    pub text: String,
+    pub votes: usize,
+}
+
+/// Snapshot of a poll for the frontends.
+#[derive(Debug, Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct PollView {
+    pub question: String,
+    pub options: Vec<PollOption>,
+    #[serde(skip)]
+    pub ends_at: Instant,
+    pub remaining_secs: u64,
+    pub closed: bool,
+}
+
+impl PollView {
+    pub fn total_votes(&self) -> usize {
+        self.options.iter().map(|option| option.votes).sum()
+    }
+
+    /// Tells the AI how the poll went, answered like a comment.
+    pub fn result_prompt(&self) -> String {
+        let results = self
+            .options
+            .iter()
+            .map(|option| format!("{} {}票", option.text, option.votes))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "【投票结果】{}: {results}。共 {} 人投票, 请宣布并评论结果。",
+            self.question,
+            self.total_votes()
+        )
+    }
+}
+
+struct Poll {
+    question: String,
+    options: Vec<String>,
+    /// The option each viewer voted for, a new vote replaces the old one
+    votes: HashMap<String, usize>,
+    ends_at: Instant,
+}
+
+impl Poll {
+    fn view(&self, closed: bool) -> PollView {
+        let mut counts = vec![0; self.options.len()];
+        for &option in self.votes.values() {
+            counts[option] += 1;
+        }
+        PollView {
+            question: self.question.clone(),
+            options: self
+                .options
+                .iter()
+                .zip(counts)
+                .map(|(text, votes)| PollOption {
+                    text: text.clone(),
+                    votes,
+                })
+                .collect(),
+            ends_at: self.ends_at,
+            remaining_secs: self
+                .ends_at
+                .saturating_duration_since(Instant::now())
+                .as_secs(),
+            closed,
+        }
+    }
+}
+
+/// The option a chat message votes for: its number or its text.
+fn parse_vote(text: &str, options: &[String]) -> Option<usize> {
+    let text = text.trim();
+    if let Ok(number) = text.parse::<usize>() {
+        return (1..=options.len()).contains(&number).then(|| number - 1);
+    }
+    options
+        .iter()
+        .position(|option| option.trim().to_lowercase() == text.to_lowercase())
+}
+
+/// The running poll, started by the operator or the AI and voted on in chat.
+#[derive(Default)]
+pub struct Polls {
+    current: Mutex<Option<Poll>>,
+    /// Wakes the intake to check the deadline again
+    changed: Notify,
+}
+
+impl Polls {
+    pub fn start(
+        &self,
+        question: String,
+        options: Vec<String>,
+        duration: Duration,
+    ) -> Result<PollView, PollError> {
+        if options.len() < 2 {
+            return Err(PollError::TooFewOptions);
+        }
+        let mut current = self.current.lock().unwrap();
+        if current.is_some() {
+            return Err(PollError::Running);
+        }
+
+        let poll = Poll {
+            question,
+            options,
+            votes: HashMap::new(),
+            ends_at: Instant::now() + duration,
+        };
+        let view = poll.view(false);
+        *current = Some(poll);
+        self.changed.notify_one();
+        Ok(view)
+    }
+
+    /// Count the message as a vote, returns the updated poll if it was one.
+    pub fn vote(&self, user: &str, text: &str) -> Option<PollView> {
+        let mut current = self.current.lock().unwrap();
+        let poll = current.as_mut()?;
+        let option = parse_vote(text, &poll.options)?;
+        poll.votes.insert(user.to_lowercase(), option);
+        Some(poll.view(false))
+    }
+
+    pub fn view(&self) -> Option<PollView> {
+        self.current
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|poll| poll.view(false))
+    }
+
+    /// End the running poll early, it is closed like one that ran out.
+    pub fn finish_now(&self) -> Result<(), PollError> {
+        let mut current = self.current.lock().unwrap();
+        let poll = current.as_mut().ok_or(PollError::NotRunning)?;
+        poll.ends_at = Instant::now();
+        self.changed.notify_one();
+        Ok(())
+    }
+
+    /// Close the poll once it ran out, returns the final results.
+    pub fn close_due(&self) -> Option<PollView> {
+        let mut current = self.current.lock().unwrap();
+        if current.as_ref()?.ends_at > Instant::now() {
+            return None;
+        }
+        current.take().map(|poll| poll.view(true))
+    }
+
+    /// Resolves when the running poll is due or a poll was started or ended.
+    pub async fn wait(&self) {
+        let ends_at = self
+            .current
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|poll| poll.ends_at);
+        match ends_at {
+            Some(ends_at) => tokio::select! {
+                _ = tokio::time::sleep_until(ends_at.into()) => {}
+                _ = self.changed.notified() => {}
+            },
+            None => self.changed.notified().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::poll::{PollError, Polls};
+
+    #[test]
+    fn vote_and_close() {
+        let polls = Polls::default();
+        let options = vec!["Cats".to_string(), "Dogs".to_string()];
+        polls
+            .start("Cats or dogs?".to_string(), options.clone(), Duration::ZERO)
+            .unwrap();
+        assert!(matches!(
+            polls.start("again".to_string(), options, Duration::ZERO),
+            Err(PollError::Running)
+        ));
+
+        assert!(polls.vote("a", "1").is_some());
+        assert!(polls.vote("b", " dogs ").is_some());
+        // changing the vote replaces the old one
+        assert!(polls.vote("A", "2").is_some());
+        assert!(polls.vote("c", "3").is_none());
+        assert!(polls.vote("c", "hello").is_none());
+
+        let result = polls.close_due().unwrap();
+        assert!(result.closed);
+        assert_eq!(result.options[0].votes, 0);
+        assert_eq!(result.options[1].votes, 2);
+        assert!(result.result_prompt().contains("Dogs 2票"));
+        assert!(polls.view().is_none());
+    }
+}
@@ -0,0 +1,308 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    time::Instant,
+};
+
+use crate::{
+    bus::{CommentEvent, Priority},
+    config::QueueConfig,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    RateLimited,
+    Duplicate,
+    /// Not picked by the attention policy, see [`QueueConfig::answer_probability`]
+    Ignored,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueueStats {
+    pub queued: u64,
+    pub dropped_rate_limited: u64,
+    pub dropped_duplicate: u64,
+    pub dropped_ignored: u64,
+    pub dropped_overflow: u64,
+}
+
+/// Comments waiting for an answer.
+///
+/// Higher priorities are answered first, comments of the same priority in arrival order.
+/// When the queue is full the oldest comment of the lowest priority is dropped.
+pub struct CommentQueue {
+    config: QueueConfig,
+    mention_keywords: Vec<String>,
+    items: VecDeque<CommentEvent>,
+    last_by_user: HashMap<String, Instant>,
+    recent_texts: VecDeque<(String, Instant)>,
+    /// When the attention policy last let an ordinary comment through
+    last_attended: Option<Instant>,
+    rng: fastrand::Rng,
+    stats: QueueStats,
+}
+
+impl CommentQueue {
+    /// Comments mentioning one of `mention_keywords` or the configured triggers are answered
+    /// first and always.
+    pub fn new(config: QueueConfig, mention_keywords: Vec<String>) -> Self {
+        Self {
+            mention_keywords: mention_keywords
+                .iter()
+                .chain(&config.triggers)
+                .map(|k| k.to_lowercase())
+                .collect(),
+            config,
+            items: VecDeque::new(),
+            last_by_user: HashMap::new(),
+            recent_texts: VecDeque::new(),
+            last_attended: None,
+            rng: fastrand::Rng::new(),
+            stats: QueueStats::default(),
+        }
+    }
+
+    pub fn push(&mut self, mut comment: CommentEvent, now: Instant) -> PushOutcome {
+        let text = normalize(&comment.text);
+        if comment.priority < Priority::Mention
+            && self
+                .mention_keywords
+                .iter()
+                .any(|k| text.contains(k.as_str()))
+        {
+            comment.priority = Priority::Mention;
+        }
+
+        // superchats are paid for, never throttle them
+        if comment.priority < Priority::Superchat {
+            if let Some(last) = self.last_by_user.get(&comment.user)
+                && now.duration_since(*last) < self.config.user_interval
+            {
+                self.stats.dropped_rate_limited += 1;
+                return PushOutcome::RateLimited;
+            }
+
+            self.recent_texts
+                .retain(|(_, at)| now.duration_since(*at) < self.config.dedupe_window);
+            if self.recent_texts.iter().any(|(t, _)| *t == text) {
+                self.stats.dropped_duplicate += 1;
+                return PushOutcome::Duplicate;
+            }
+        }
+
+        if comment.priority < Priority::Mention {
+            if !self.attend(now) {
+                self.stats.dropped_ignored += 1;
+                return PushOutcome::Ignored;
+            }
+            self.last_attended = Some(now);
+        }
+
+        self.last_by_user.insert(comment.user.clone(), now);
+        self.recent_texts.push_back((text, now));
+
+        self.items.push_back(comment);
+        self.stats.queued += 1;
+
+        while self.items.len() > self.config.max_depth {
+            self.drop_oldest_lowest();
+        }
+
+        PushOutcome::Queued
+    }
+
+    /// Take the next comment to answer.
+    pub fn pop(&mut self) -> Option<CommentEvent> {
+        let max = self.items.iter().map(|c| c.priority).max()?;
+        let index = self.items.iter().position(|c| c.priority == max)?;
+        self.items.remove(index)
+    }
+
+    /// Put a comment back so it is answered next within its priority, skipping all checks.
+    pub fn requeue(&mut self, comment: CommentEvent) {
+        self.items.push_front(comment);
+    }
+
+    /// Take every waiting comment, in arrival order.
+    pub fn drain(&mut self) -> Vec<CommentEvent> {
+        self.items.drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Ids and priorities of the waiting comments, in the order they will be answered.
+    pub fn waiting(&self) -> Vec<(u64, Priority)> {
+        let mut waiting: Vec<_> = self.items.iter().map(|c| (c.id, c.priority)).collect();
+        waiting.sort_by_key(|(_, priority)| Reverse(*priority));
+        waiting
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.stats
+    }
+
+    /// Whether an ordinary comment gets the character's attention.
+    fn attend(&mut self, now: Instant) -> bool {
+        if let Some(last) = self.last_attended
+            && now.duration_since(last) < self.config.reply_interval
+        {
+            return false;
+        }
+        self.rng.f32() < self.config.answer_probability
+    }
+
+    fn drop_oldest_lowest(&mut self) {
+        let Some(min) = self.items.iter().map(|c| c.priority).min() else {
+            return;
+        };
+        if let Some(index) = self.items.iter().position(|c| c.priority == min)
+            && let Some(dropped) = self.items.remove(index)
+        {
+            log::warn!(
+                "Comment queue full, dropped comment from {}: {}",
+                dropped.user,
+                dropped.text
+            );
+            self.stats.dropped_overflow += 1;
+        }
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        bus::{CommentEvent, Priority},
+        config::QueueConfig,
+        queue::{CommentQueue, PushOutcome},
+    };
+
+    fn comment(user: &str, text: &str, priority: Priority) -> CommentEvent {
+        CommentEvent::new(user, text, "test", priority)
+    }
+
+    #[test]
+    fn rate_limit_and_dedupe() {
+        let mut queue = CommentQueue::new(QueueConfig::default(), vec![]);
+        let now = Instant::now();
+
+        assert_eq!(
+            queue.push(comment("a", "hello", Priority::Normal), now),
+            PushOutcome::Queued
+        );
+        assert_eq!(
+            queue.push(comment("a", "again", Priority::Normal), now),
+            PushOutcome::RateLimited
+        );
+        assert_eq!(
+            queue.push(comment("b", "  Hello ", Priority::Normal), now),
+            PushOutcome::Duplicate
+        );
+        assert_eq!(
+            queue.push(
+                comment("a", "later", Priority::Normal),
+                now + Duration::from_secs(11)
+            ),
+            PushOutcome::Queued
+        );
+        assert_eq!(
+            queue.push(comment("a", "paid", Priority::Superchat), now),
+            PushOutcome::Queued
+        );
+    }
+
+    #[test]
+    fn priority_and_overflow() {
+        let config = QueueConfig {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let mut queue = CommentQueue::new(config, vec!["Murasame".to_string()]);
+        let now = Instant::now();
+
+        queue.push(comment("a", "first", Priority::Normal), now);
+        queue.push(comment("b", "hi murasame", Priority::Normal), now);
+        queue.push(comment("c", "second", Priority::Normal), now);
+
+        assert_eq!(queue.stats().dropped_overflow, 1);
+        let waiting: Vec<_> = queue.waiting().into_iter().map(|(_, p)| p).collect();
+        assert_eq!(waiting, [Priority::Mention, Priority::Normal]);
+        assert_eq!(queue.pop().unwrap().user, "b");
+        assert_eq!(queue.pop().unwrap().user, "c");
+        assert!(queue.pop().is_none());
+    }
+    #[test]
+    fn drain_and_restore() {
+        let mut queue = CommentQueue::new(QueueConfig::default(), vec![]);
+        let now = Instant::now();
+        queue.push(comment("a", "first", Priority::Normal), now);
+        queue.push(comment("b", "second", Priority::Superchat), now);
+
+        let saved = serde_json::to_string(&queue.drain()).unwrap();
+        assert!(queue.pop().is_none());
+
+        let restored: Vec<CommentEvent> = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored[0].text, "first");
+        assert_eq!(restored[1].priority, Priority::Superchat);
+        // ids are only unique within one run
+        assert_ne!(restored[0].id, restored[1].id);
+    }
+
+    #[test]
+    fn attention_policy() {
+        let config = QueueConfig {
+            answer_probability: 0.0,
+            triggers: vec!["Question".to_string()],
+            ..Default::default()
+        };
+        let mut queue = CommentQueue::new(config, vec!["Murasame".to_string()]);
+        let now = Instant::now();
+        assert_eq!(
+            queue.push(comment("a", "hello", Priority::Normal), now),
+            PushOutcome::Ignored
+        );
+        assert_eq!(
+            queue.push(comment("b", "hi murasame", Priority::Normal), now),
+            PushOutcome::Queued
+        );
+        assert_eq!(
+            queue.push(comment("c", "question: why?", Priority::Normal), now),
+            PushOutcome::Queued
+        );
+        assert_eq!(queue.stats().dropped_ignored, 1);
+
+        let config = QueueConfig {
+            reply_interval: Duration::from_secs(30),
+            ..Default::default()
+        };
+        let mut queue = CommentQueue::new(config, vec![]);
+        let seconds = |secs| now + Duration::from_secs(secs);
+        assert_eq!(
+            queue.push(comment("a", "first", Priority::Normal), now),
+            PushOutcome::Queued
+        );
+        assert_eq!(
+            queue.push(comment("b", "second", Priority::Normal), seconds(20)),
+            PushOutcome::Ignored
+        );
+        assert_eq!(
+            queue.push(comment("c", "paid", Priority::Superchat), seconds(20)),
+            PushOutcome::Queued
+        );
+        assert_eq!(
+            queue.push(comment("d", "third", Priority::Normal), seconds(31)),
+            PushOutcome::Queued
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use crate::{
+    bus::{CommentEvent, CommentKind, GiftEvent, NotificationEvent, Priority, SubscriptionEvent},
+    notification::NOTIFICATION_SOURCE,
+};
+
+const DEFAULT_GIFT_PROMPT: &str =
+    "【供奉】{user} 供奉了 {amount} {currency}。留言: {message}\n请真诚地向其道谢。";
+const DEFAULT_SUBSCRIPTION_PROMPT: &str =
+    "【结缘】{user} 与你结缘 ({tier}), 已经 {months} 个月了。留言: {message}\n请真诚地向其道谢。";
+const DEFAULT_NOTIFICATION_PROMPT: &str = "【通知】{title}: {message}\n请用一两句话提醒主人。";
+
+/// How the character reacts to a gift, subscription or notification.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct Reaction {
+    /// Sent to the AI instead of a comment, see [`gift_comment`] and [`subscription_comment`]
+    pub prompt: String,
+    /// Shown instead of the layers the AI picked, keeps the AI's choice if empty
+    #[serde(default)]
+    pub layers: Vec<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct Reactions {
+    #[serde(default = "default_gift")]
+    pub gift: Reaction,
+    #[serde(default = "default_subscription")]
+    pub subscription: Reaction,
+    /// Notifications without a line are sent to the AI with this prompt
+    #[serde(default = "default_notification")]
+    pub notification: Reaction,
+    /// Said as they are for these notification events, e.g. `battery_low`, see
+    /// [`notification_comment`]
+    #[serde(default)]
+    pub notification_lines: HashMap<String, String>,
+}
+
+impl Default for Reactions {
+    fn default() -> Self {
+        Self {
+            gift: default_gift(),
+            subscription: default_subscription(),
+            notification: default_notification(),
+            notification_lines: HashMap::new(),
+        }
+    }
+}
+
+impl Reactions {
+    /// Layers overriding the AI's choice for comments of this kind.
+    pub fn layers(&self, kind: CommentKind) -> &[String] {
+        match kind {
+            CommentKind::Chat | CommentKind::Host | CommentKind::Touch => &[],
+            CommentKind::Gift => &self.gift.layers,
+            CommentKind::Subscription => &self.subscription.layers,
+            CommentKind::Notification | CommentKind::Announcement => &self.notification.layers,
+        }
+    }
+}
+
+fn default_gift() -> Reaction {
+    Reaction {
+        prompt: DEFAULT_GIFT_PROMPT.to_string(),
+        layers: Vec::new(),
+    }
+}
+
+fn default_subscription() -> Reaction {
+    Reaction {
+        prompt: DEFAULT_SUBSCRIPTION_PROMPT.to_string(),
+        layers: Vec::new(),
+    }
+}
+
+fn default_notification() -> Reaction {
+    Reaction {
+        prompt: DEFAULT_NOTIFICATION_PROMPT.to_string(),
+        layers: Vec::new(),
+    }
+}
+
+/// Turn a gift into a comment, replacing `{user}` with the display name and `{amount}`,
+/// `{currency}` and `{message}`.
+pub fn gift_comment(gift: &GiftEvent, name: &str, reaction: &Reaction) -> CommentEvent {
+    let text = reaction
+        .prompt
+        .replace("{user}", name)
+        .replace("{amount}", &gift.amount.to_string())
+        .replace("{currency}", &gift.currency)
+        .replace("{message}", gift.message.as_deref().unwrap_or("-"));
+    let mut comment = CommentEvent::new(&gift.user, text, &gift.source, Priority::Superchat)
+        .with_kind(CommentKind::Gift);
+    comment.display_name = Some(name.to_string());
+    comment
+}
+
+/// Turn a subscription into a comment, replacing `{user}` with the display name and `{tier}`,
+/// `{months}` and `{message}`.
+pub fn subscription_comment(
+    sub: &SubscriptionEvent,
+    name: &str,
+    reaction: &Reaction,
+) -> CommentEvent {
+    let text = reaction
+        .prompt
+        .replace("{user}", name)
+        .replace("{tier}", &sub.tier)
+        .replace("{months}", &sub.months.to_string())
+        .replace("{message}", sub.message.as_deref().unwrap_or("-"));
+    let mut comment = CommentEvent::new(&sub.user, text, &sub.source, Priority::Superchat)
+        .with_kind(CommentKind::Subscription);
+    comment.display_name = Some(name.to_string());
+    comment
+}
+
+/// Turn a notification into a line to say if its event has one, or a prompt for the AI,
+/// replacing `{title}` and `{message}`.
+pub fn notification_comment(
+    notification: &NotificationEvent,
+    reactions: &Reactions,
+) -> CommentEvent {
+    let (template, kind) = match reactions.notification_lines.get(&notification.event) {
+        Some(line) => (line, CommentKind::Announcement),
+        None => (&reactions.notification.prompt, CommentKind::Notification),
+    };
+    let text = template
+        .replace("{title}", &notification.title)
+        .replace("{message}", notification.message.as_deref().unwrap_or("-"));
+    // the desktop user wants to know right away
+    CommentEvent::new(
+        &notification.event,
+        text,
+        NOTIFICATION_SOURCE,
+        Priority::Superchat,
+    )
+    .with_kind(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::{CommentKind, GiftEvent, NotificationEvent, Priority},
+        reaction::{Reactions, gift_comment, notification_comment},
+    };
+
+    #[test]
+    fn render_gift() {
+        let reactions: Reactions = serde_json::from_str(
+            r#"{"gift": {"prompt": "{user} sent {amount} {currency}: {message}", "layers": ["blush.png"]}}"#,
+        )
+        .unwrap();
+        let gift = GiftEvent {
+            user: "xx_viewer_xx".to_string(),
+            amount: 5.0,
+            currency: "USD".to_string(),
+            message: None,
+            source: "http".to_string(),
+        };
+
+        let comment = gift_comment(&gift, "viewer", &reactions.gift);
+        assert_eq!(comment.text, "viewer sent 5 USD: -");
+        assert_eq!(comment.user, "xx_viewer_xx");
+        assert_eq!(comment.priority, Priority::Superchat);
+        assert_eq!(reactions.layers(comment.kind), ["blush.png"]);
+        // untouched reactions keep their defaults
+        assert!(reactions.subscription.prompt.contains("{months}"));
+        assert!(reactions.layers(CommentKind::Chat).is_empty());
+    }
+
+    #[test]
+    fn say_or_prompt_notifications() {
+        let reactions: Reactions = serde_json::from_str(
+            r#"{"notification_lines": {"battery_low": "Only {message} left, plug me in!"}}"#,
+        )
+        .unwrap();
+        let mut notification = NotificationEvent {
+            event: "battery_low".to_string(),
+            title: "Battery low".to_string(),
+            message: Some("15%".to_string()),
+            source: "battery".to_string(),
+        };
+
+        let comment = notification_comment(&notification, &reactions);
+        assert_eq!(comment.text, "Only 15% left, plug me in!");
+        assert_eq!(comment.kind, CommentKind::Announcement);
+        assert_eq!(comment.user, "battery_low");
+
+        notification.event = "calendar".to_string();
+        notification.message = None;
+        let comment = notification_comment(&notification, &reactions);
+        assert!(comment.text.contains("Battery low: -"));
+        assert_eq!(comment.kind, CommentKind::Notification);
+    }
+}
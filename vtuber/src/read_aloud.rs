@@ -0,0 +1,62 @@
+use crate::config::ReadAloudConfig;
+
+/// Source of the comments made from the selected text, see
+/// [`crate::bus::ControlCommand::ReadSelection`].
+pub const READ_ALOUD_SOURCE: &str = "read_aloud";
+
+/// The selected text, or the clipboard when nothing is selected. Only X11 and Wayland have a
+/// selection apart from the clipboard.
+pub fn read_selection() -> anyhow::Result<String> {
+    let mut clipboard = arboard::Clipboard::new()?;
+    #[cfg(target_os = "linux")]
+    {
+        use arboard::{GetExtLinux, LinuxClipboardKind};
+
+        if let Ok(text) = clipboard
+            .get()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text()
+            && !text.trim().is_empty()
+        {
+            return Ok(text);
+        }
+    }
+    Ok(clipboard.get_text()?)
+}
+
+/// The prompt asking the character about `text`, cut to the configured length. `None` if there is
+/// no text.
+pub fn read_aloud_prompt(text: &str, config: &ReadAloudConfig) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let text = match text.char_indices().nth(config.max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    };
+    Some(config.prompt.replace("{text}", &text))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::ReadAloudConfig, read_aloud::read_aloud_prompt};
+
+    #[test]
+    fn prompt_with_the_cut_text() {
+        let config = ReadAloudConfig {
+            prompt: "读一下: {text}".to_string(),
+            max_chars: 4,
+        };
+
+        assert_eq!(
+            read_aloud_prompt("  你好  ", &config).as_deref(),
+            Some("读一下: 你好")
+        );
+        assert_eq!(
+            read_aloud_prompt("今天天气很好", &config).as_deref(),
+            Some("读一下: 今天天气…")
+        );
+        assert_eq!(read_aloud_prompt(" \n", &config), None);
+    }
+}
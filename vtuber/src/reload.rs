@@ -0,0 +1,105 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use tokio::sync::{broadcast, watch};
+
+use crate::{
+    bus::UiEvent,
+    config::{LiveConfig, Vars, character_template_paths},
+    utils::{find_env_file, get_env},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Files referenced by the config whose content is part of the [`LiveConfig`], besides the
+/// templates of the other characters.
+const WATCHED_FILES: [&str; 5] = [
+    "VTUBER_AI_SYSTEM_INSTRUCTION_TEMPLATE",
+    "VTUBER_CHARACTERS",
+    "VTUBER_MODERATION_BLOCKLIST",
+    "VTUBER_MODERATION_PATTERNS",
+    "VTUBER_MODERATION_BANNED_USERS",
+];
+
+/// Watch the `.env` file and the files it points to, applying the [`LiveConfig`] when they change.
+///
+/// Everything else in the config still needs a restart. The file is read into a map instead
+/// of the environment, which can't be changed safely while other threads read it. Variables
+/// removed from the file keep their old value.
+pub fn spawn_config_watcher(
+    initial: LiveConfig,
+    ui_tx: broadcast::Sender<UiEvent>,
+) -> watch::Receiver<Arc<LiveConfig>> {
+    let (live_tx, live_rx) = watch::channel(Arc::new(initial));
+    // the variables are already loaded, this only finds the file
+    let Some(env_path) = find_env_file() else {
+        log::warn!("No config file to watch, hot reload is disabled");
+        return live_rx;
+    };
+    log::info!("Watching {} for changes", env_path.display());
+
+    std::thread::spawn(move || {
+        let mut vars = HashMap::new();
+        let mut snapshot = modified_times(&env_path, &|name| lookup(&vars, name));
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = modified_times(&env_path, &|name| lookup(&vars, name));
+            if current == snapshot {
+                continue;
+            }
+            snapshot = current;
+
+            vars = match read_vars(&env_path) {
+                Ok(vars) => vars,
+                Err(e) => {
+                    log::error!("Failed to read {}: {e}", env_path.display());
+                    continue;
+                }
+            };
+            let var = |name: &str| lookup(&vars, name);
+            // the paths may have changed with the reload
+            snapshot = modified_times(&env_path, &var);
+
+            match LiveConfig::from_vars(&var) {
+                Ok(live) => {
+                    log::info!("Config reloaded");
+                    let live = Arc::new(live);
+                    live_tx.send_replace(live.clone());
+                    let _ = ui_tx.send(UiEvent::ConfigReloaded(live));
+                }
+                Err(e) => log::error!("Failed to reload config, keeping the old settings: {e}"),
+            }
+        }
+    });
+
+    live_rx
+}
+
+fn read_vars(env_path: &Path) -> Result<HashMap<String, String>, dotenvy::Error> {
+    dotenvy::from_path_iter(env_path)?.collect()
+}
+
+/// The variable from the reloaded file, from the environment if it isn't in there.
+fn lookup(vars: &HashMap<String, String>, name: &str) -> Option<String> {
+    vars.get(name).cloned().or_else(|| get_env(name).ok())
+}
+
+fn modified_times(env_path: &Path, var: Vars) -> Vec<(PathBuf, Option<SystemTime>)> {
+    std::iter::once(env_path.to_path_buf())
+        .chain(
+            WATCHED_FILES
+                .iter()
+                .filter_map(|name| var(name).map(PathBuf::from)),
+        )
+        .chain(character_template_paths(var))
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect()
+}
@@ -0,0 +1,251 @@
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicU64, Ordering},
+    mpsc,
+};
+
+use eframe::egui::ColorImage;
+use image::{RgbaImage, imageops::FilterType};
+use layer_composer::Model;
+
+use crate::{config::RenderConfig, scaling::shrunk_size};
+
+struct RenderRequest {
+    id: u64,
+    character: usize,
+    layers: Vec<String>,
+}
+
+/// A rendered character, prepared for the GPU on the render thread.
+#[derive(Clone)]
+pub struct Frame {
+    /// For captures
+    pub image: RgbaImage,
+    /// The same pixels premultiplied, handed to egui as they are
+    pub texture: Arc<ColorImage>,
+    /// Before shrinking, hit areas are given in these pixels
+    pub full_size: [u32; 2],
+}
+
+/// Composites the characters on a thread owning their models.
+///
+/// Requests queued while a frame is rendered are coalesced, only the newest one per character
+/// is rendered, and frames superseded by a newer request are dropped instead of delivered.
+/// Frames larger than the character is shown are shrunk before they are delivered, see
+/// [`RenderWorker::set_display_size`].
+pub struct RenderWorker {
+    requests: mpsc::Sender<RenderRequest>,
+    frames: mpsc::Receiver<(usize, Frame)>,
+    /// Id of the newest request per character
+    latest: Arc<Vec<AtomicU64>>,
+    /// In physical pixels, per character
+    display_sizes: Arc<Mutex<Vec<Option<[u32; 2]>>>>,
+    next_id: u64,
+    base_layers: Vec<String>,
+}
+
+impl RenderWorker {
+    /// `on_frame` is called on the render thread whenever a frame is ready.
+    pub fn spawn(render_configs: Vec<RenderConfig>, on_frame: impl Fn() + Send + 'static) -> Self {
+        let (requests, request_rx) = mpsc::channel();
+        let (frame_tx, frames) = mpsc::channel();
+        let latest: Arc<Vec<AtomicU64>> =
+            Arc::new(render_configs.iter().map(|_| AtomicU64::new(0)).collect());
+        let display_sizes = Arc::new(Mutex::new(vec![None; render_configs.len()]));
+
+        let (models, base_layers) = render_configs
+            .into_iter()
+            .map(|render_config| (render_config.model, render_config.base_layer))
+            .unzip();
+        std::thread::Builder::new()
+            .name("render".to_string())
+            .spawn({
+                let (latest, display_sizes) = (latest.clone(), display_sizes.clone());
+                move || {
+                    run_worker(
+                        models,
+                        request_rx,
+                        frame_tx,
+                        &latest,
+                        &display_sizes,
+                        on_frame,
+                    )
+                }
+            })
+            .expect("failed to spawn the render thread");
+
+        Self {
+            requests,
+            frames,
+            latest,
+            display_sizes,
+            next_id: 1,
+            base_layers,
+        }
+    }
+
+    pub fn characters(&self) -> usize {
+        self.base_layers.len()
+    }
+
+    /// Render the character's base layer with the given layers on top, replacing any frame of
+    /// the character that is still pending.
+    pub fn render(&mut self, character: usize, layers: &[String]) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.latest[character].store(id, Ordering::Release);
+
+        let mut layers_to_render = Vec::with_capacity(1 + layers.len());
+        layers_to_render.push(self.base_layers[character].clone());
+        layers_to_render.extend_from_slice(layers);
+        let _ = self.requests.send(RenderRequest {
+            id,
+            character,
+            layers: layers_to_render,
+        });
+    }
+
+    /// Swap the character's base layer, returns whether it changed. Takes effect with the next
+    /// [`RenderWorker::render`].
+    pub fn set_base_layer(&mut self, character: usize, base_layer: &str) -> bool {
+        if self.base_layers[character] == base_layer {
+            return false;
+        }
+        self.base_layers[character] = base_layer.to_string();
+        true
+    }
+
+    /// Shrink the next frames of the character to this size in physical pixels, `None` for
+    /// the full size. Returns whether it changed.
+    pub fn set_display_size(&mut self, character: usize, size: Option<[u32; 2]>) -> bool {
+        let mut display_sizes = self.display_sizes.lock().unwrap();
+        let changed = display_sizes[character] != size;
+        display_sizes[character] = size;
+        changed
+    }
+
+    /// A finished frame, if any.
+    pub fn try_frame(&self) -> Option<(usize, Frame)> {
+        self.frames.try_recv().ok()
+    }
+}
+
+impl Frame {
+    /// Shrink the image if it's larger than `display`, filtering premultiplied pixels so the
+    /// transparent ones don't bleed into the edges.
+    fn prepare(image: RgbaImage, display: Option<[u32; 2]>) -> Self {
+        let full_size: [u32; 2] = image.dimensions().into();
+        let shrunk = display.and_then(|display| shrunk_size(full_size, display));
+        let Some([width, height]) = shrunk else {
+            let texture = ColorImage::from_rgba_unmultiplied(
+                [image.width() as usize, image.height() as usize],
+                image.as_raw(),
+            );
+            return Self {
+                image,
+                texture: Arc::new(texture),
+                full_size,
+            };
+        };
+
+        let mut premultiplied = image;
+        for pixel in premultiplied.pixels_mut() {
+            let alpha = pixel[3] as u16;
+            for channel in &mut pixel.0[..3] {
+                *channel = (*channel as u16 * alpha / 255) as u8;
+            }
+        }
+        let premultiplied =
+            image::imageops::resize(&premultiplied, width, height, FilterType::Triangle);
+        let texture = ColorImage::from_rgba_premultiplied(
+            [width as usize, height as usize],
+            premultiplied.as_raw(),
+        );
+
+        let mut image = premultiplied;
+        for pixel in image.pixels_mut() {
+            let alpha = pixel[3] as u16;
+            for channel in &mut pixel.0[..3] {
+                if let Some(value) = (*channel as u16 * 255).checked_div(alpha) {
+                    *channel = value.min(255) as u8;
+                }
+            }
+        }
+        Self {
+            image,
+            texture: Arc::new(texture),
+            full_size,
+        }
+    }
+}
+
+fn run_worker(
+    mut models: Vec<Model>,
+    request_rx: mpsc::Receiver<RenderRequest>,
+    frame_tx: mpsc::Sender<(usize, Frame)>,
+    latest: &[AtomicU64],
+    display_sizes: &Mutex<Vec<Option<[u32; 2]>>>,
+    on_frame: impl Fn(),
+) {
+    let mut pending: Vec<Option<RenderRequest>> = models.iter().map(|_| None).collect();
+    while let Ok(request) = request_rx.recv() {
+        let character = request.character;
+        pending[character] = Some(request);
+        // only the newest request of every character is worth rendering
+        while let Ok(request) = request_rx.try_recv() {
+            let character = request.character;
+            pending[character] = Some(request);
+        }
+
+        for request in pending.iter_mut().filter_map(Option::take) {
+            let is_current = || latest[request.character].load(Ordering::Acquire) == request.id;
+            if !is_current() {
+                continue;
+            }
+            match models[request.character].render(&request.layers) {
+                // a newer request may have come in while rendering
+                Ok(image) if is_current() => {
+                    let display = display_sizes.lock().unwrap()[request.character];
+                    let frame = Frame::prepare(image.into_rgba8(), display);
+                    if frame_tx.send((request.character, frame)).is_err() {
+                        return;
+                    }
+                    on_frame();
+                }
+                Ok(_) => {}
+                Err(e) => log::error!("Failed to render layers {:?}: {e}", request.layers),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use crate::render::Frame;
+
+    #[test]
+    fn shrink_without_dark_edges() {
+        // white on the left, transparent black on the right
+        let image = RgbaImage::from_fn(4, 2, |x, _| {
+            if x < 2 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+
+        let frame = Frame::prepare(image.clone(), Some([2, 1]));
+        assert_eq!(frame.image.dimensions(), (2, 1));
+        assert_eq!(frame.full_size, [4, 2]);
+        assert_eq!(frame.texture.size, [2, 1]);
+        // the edge is half transparent, not grey
+        let edge = frame.image.get_pixel(1, 0);
+        assert!(edge[3] > 0 && edge[3] < 255);
+        assert!(edge[0] > 250);
+
+        let frame = Frame::prepare(image, None);
+        assert_eq!(frame.image.dimensions(), (4, 2));
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::sync::mpsc;
+
+use crate::bus::InEvent;
+
+pub const REPLAY_SOURCE: &str = "replay";
+
+/// A line of an event recording, e.g. `{"at": 1.5, "time": 1760000000000, "event": {...}}`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct RecordedEvent {
+    /// Seconds after the start of the recording
+    pub at: f64,
+    /// Unix time in milliseconds the event reached the app
+    #[serde(default)]
+    pub time: u64,
+    pub event: InEvent,
+}
+
+/// Writes every incoming [`InEvent`] to a JSONL file, to be replayed with `--simulate`.
+pub struct EventRecorder {
+    file: BufWriter<File>,
+    started_at: Instant,
+}
+
+impl EventRecorder {
+    /// Start a new recording next to `path`, named after the current time so earlier runs are
+    /// kept, see [`session_path`].
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let path = session_path(path, chrono::Local::now());
+        log::info!("Recording incoming events to {}", path.display());
+        Ok(Self {
+            file: BufWriter::new(File::create_new(&path)?),
+            started_at: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, event: &InEvent) {
+        let recorded = RecordedEvent {
+            at: self.started_at.elapsed().as_secs_f64(),
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64),
+            event: event.clone(),
+        };
+        let result = serde_json::to_string(&recorded)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| {
+                // flushed right away, the recording is most useful after a crash
+                writeln!(self.file, "{line}")?;
+                Ok(self.file.flush()?)
+            });
+        if let Err(e) = result {
+            log::error!("Failed to record event: {e}");
+        }
+    }
+}
+
+/// `events.jsonl` becomes `events-20251009-201500.jsonl`.
+fn session_path(path: &Path, now: chrono::DateTime<chrono::Local>) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map_or_else(|| "events".into(), |stem| stem.to_string_lossy());
+    let mut name = format!("{stem}-{}", now.format("%Y%m%d-%H%M%S"));
+    if let Some(extension) = path.extension() {
+        name = format!("{name}.{}", extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// Whether the log is an event recording rather than a comment log, judged by its first line.
+pub fn is_recording(log: &str) -> bool {
+    log.lines()
+        .find(|line| !line.trim().is_empty())
+        .and_then(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .is_some_and(|line| line.get("event").is_some())
+}
+
+/// Parse an event recording, sorted by time.
+pub fn parse_recording(log: &str) -> anyhow::Result<Vec<RecordedEvent>> {
+    let mut events = log
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str::<RecordedEvent>(line)
+                .map_err(|e| anyhow::anyhow!("Bad event on line {}: {e}", index + 1))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    events.sort_by(|a, b| a.at.total_cmp(&b.at));
+    Ok(events)
+}
+
+/// Sources are not read back from recordings, mark the events as replayed.
+fn mark_replayed(event: &mut InEvent) {
+    let source = match event {
+        InEvent::Comment(comment) => &mut comment.source,
+        InEvent::Gift(gift) => &mut gift.source,
+        InEvent::Subscription(subscription) => &mut subscription.source,
+        InEvent::Notification(notification) => &mut notification.source,
+        InEvent::HostSpeech { .. } | InEvent::Control(_) | InEvent::Touch { .. } => return,
+    };
+    if source.is_empty() {
+        *source = REPLAY_SOURCE.to_string();
+    }
+}
+
+/// Send the recorded events again at their original pace.
+pub fn spawn_replay(events: Vec<RecordedEvent>, in_tx: mpsc::Sender<InEvent>) {
+    tokio::spawn(async move {
+        let started_at = tokio::time::Instant::now();
+        for RecordedEvent { at, mut event, .. } in events {
+            let Ok(at) = Duration::try_from_secs_f64(at) else {
+                continue;
+            };
+            tokio::time::sleep_until(started_at + at).await;
+            mark_replayed(&mut event);
+            if in_tx.send(event).await.is_err() {
+                return;
+            }
+        }
+        log::info!("Replayed the whole event recording");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        bus::{CommentEvent, ControlCommand, InEvent, Priority},
+        replay::{RecordedEvent, is_recording, parse_recording, session_path},
+    };
+
+    #[test]
+    fn name_recordings_after_the_time() {
+        use chrono::TimeZone;
+
+        let now = chrono::Local
+            .with_ymd_and_hms(2025, 10, 9, 20, 15, 0)
+            .unwrap();
+        assert_eq!(
+            session_path(Path::new("cache/events.jsonl"), now),
+            Path::new("cache/events-20251009-201500.jsonl")
+        );
+        assert_eq!(
+            session_path(Path::new("events"), now),
+            Path::new("events-20251009-201500")
+        );
+    }
+
+    #[test]
+    fn round_trip_recording() {
+        let events = [
+            InEvent::Comment(CommentEvent::new(
+                "viewer",
+                "hello",
+                "twitch",
+                Priority::Mention,
+            )),
+            InEvent::Control(ControlCommand::Skip),
+            InEvent::HostSpeech {
+                speaker: "host".to_string(),
+                text: "welcome".to_string(),
+            },
+        ];
+        let log: String = events
+            .into_iter()
+            .enumerate()
+            .rev()
+            .map(|(index, event)| {
+                let recorded = RecordedEvent {
+                    at: index as f64,
+                    time: 0,
+                    event,
+                };
+                serde_json::to_string(&recorded).unwrap() + "\n"
+            })
+            .collect();
+
+        assert!(is_recording(&log));
+        assert!(!is_recording(
+            r#"{"at": 0, "user": "viewer", "text": "hi"}"#
+        ));
+
+        let parsed = parse_recording(&log).unwrap();
+        assert_eq!(parsed.len(), 3);
+        let InEvent::Comment(comment) = &parsed[0].event else {
+            panic!("expected the comment first");
+        };
+        assert_eq!(
+            (
+                comment.text.as_str(),
+                comment.source.as_str(),
+                comment.priority
+            ),
+            ("hello", "twitch", Priority::Mention)
+        );
+        assert!(matches!(
+            parsed[1].event,
+            InEvent::Control(ControlCommand::Skip)
+        ));
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    pipeline::APP_SOURCES,
+    storage::{
+        SessionSummary, SessionUsage, Storage, Transcript, TranscriptEntryKind, format_offset,
+    },
+};
+
+/// The report lists this many of the most active viewers.
+const TOP_CHATTERS: usize = 10;
+/// Width of the longest bar of the expression histogram, in characters.
+const BAR_WIDTH: usize = 20;
+
+#[derive(serde::Serialize, Debug, Clone, PartialEq, schemars::JsonSchema)]
+pub struct ChatterCount {
+    pub user: String,
+    pub comments: usize,
+}
+
+#[derive(serde::Serialize, Debug, Clone, PartialEq, schemars::JsonSchema)]
+pub struct LayerCount {
+    pub layer: String,
+    pub count: usize,
+}
+
+/// Statistics of a stored session, see [`SessionReport::new`].
+#[derive(serde::Serialize, Debug, Clone, schemars::JsonSchema)]
+pub struct SessionReport {
+    pub session: SessionSummary,
+    /// From the start of the session to its last comment or response
+    pub duration_ms: i64,
+    /// Comments written by viewers, not made up by the app
+    pub viewer_comments: usize,
+    /// Viewer comments the characters answered
+    pub answered: usize,
+    /// Share of the viewer comments answered, from 0 to 1
+    pub response_rate: f64,
+    /// Most comments first
+    pub top_chatters: Vec<ChatterCount>,
+    /// How often each layer was shown, most used first
+    pub layers: Vec<LayerCount>,
+    /// From receiving a viewer comment to the first spoken answer
+    pub average_latency_ms: Option<f64>,
+    pub average_tts_ms: Option<f64>,
+    pub usage: SessionUsage,
+}
+
+impl SessionReport {
+    pub fn new(transcript: &Transcript, usage: SessionUsage) -> Self {
+        let mut viewer_comments = HashMap::new();
+        let mut chatters = HashMap::<&str, usize>::new();
+        let mut first_answers = HashMap::new();
+        let mut layers = BTreeMap::<&str, usize>::new();
+        let mut tts_ms = Vec::new();
+        for entry in &transcript.entries {
+            match &entry.kind {
+                TranscriptEntryKind::Comment {
+                    comment_id,
+                    user,
+                    source,
+                    ..
+                } if !APP_SOURCES.contains(&source.as_str()) => {
+                    viewer_comments.insert(*comment_id, entry.time);
+                    *chatters.entry(user).or_default() += 1;
+                }
+                TranscriptEntryKind::Comment { .. } => {}
+                TranscriptEntryKind::Response {
+                    comment_id,
+                    layers: shown,
+                    tts_ms: tts,
+                    ..
+                } => {
+                    if let Some(comment_id) = comment_id {
+                        first_answers.entry(*comment_id).or_insert(entry.time);
+                    }
+                    for layer in shown {
+                        *layers.entry(layer).or_default() += 1;
+                    }
+                    tts_ms.push(*tts as f64);
+                }
+            }
+        }
+
+        let latencies: Vec<f64> = viewer_comments
+            .iter()
+            .filter_map(|(comment_id, received)| {
+                Some((first_answers.get(comment_id)? - received) as f64)
+            })
+            .collect();
+        let mut top_chatters: Vec<ChatterCount> = chatters
+            .into_iter()
+            .map(|(user, comments)| ChatterCount {
+                user: user.to_string(),
+                comments,
+            })
+            .collect();
+        top_chatters.sort_by(|a, b| b.comments.cmp(&a.comments).then(a.user.cmp(&b.user)));
+        top_chatters.truncate(TOP_CHATTERS);
+        let mut layers: Vec<LayerCount> = layers
+            .into_iter()
+            .map(|(layer, count)| LayerCount {
+                layer: layer.to_string(),
+                count,
+            })
+            .collect();
+        // stable: equally used layers stay sorted by name
+        layers.sort_by_key(|layer| std::cmp::Reverse(layer.count));
+
+        Self {
+            session: transcript.session.clone(),
+            duration_ms: transcript
+                .entries
+                .last()
+                .map_or(0, |entry| entry.time - transcript.session.started_at),
+            viewer_comments: viewer_comments.len(),
+            answered: latencies.len(),
+            response_rate: latencies.len() as f64 / viewer_comments.len().max(1) as f64,
+            top_chatters,
+            layers,
+            average_latency_ms: average(&latencies),
+            average_tts_ms: average(&tts_ms),
+            usage,
+        }
+    }
+
+    /// The overview as label and value pairs, shared by the markdown and HTML reports.
+    fn overview(&self) -> Vec<(&'static str, String)> {
+        let seconds = |millis: Option<f64>| {
+            millis.map_or_else(
+                || "-".to_string(),
+                |millis| format!("{:.1} s", millis / 1000.0),
+            )
+        };
+        let started = chrono::DateTime::from_timestamp_millis(self.session.started_at)
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let mut overview = vec![
+            ("Started", started),
+            ("Duration", format_offset(self.duration_ms)),
+            ("Viewer comments", self.viewer_comments.to_string()),
+            (
+                "Answered",
+                format!("{} ({:.1}%)", self.answered, self.response_rate * 100.0),
+            ),
+            ("Average latency", seconds(self.average_latency_ms)),
+            ("Average TTS", seconds(self.average_tts_ms)),
+            (
+                "Tokens",
+                format!(
+                    "{} in, {} out",
+                    self.usage.prompt_tokens, self.usage.output_tokens
+                ),
+            ),
+        ];
+        if let Some(cost) = self.usage.cost {
+            overview.push(("Cost", format!("${cost:.4}")));
+        }
+        overview
+    }
+
+    fn bar(&self, count: usize) -> String {
+        let most = self.layers.first().map_or(1, |layer| layer.count.max(1));
+        "█".repeat((count * BAR_WIDTH).div_ceil(most))
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let cell = |text: &str| text.replace('|', "\\|");
+        let mut out = String::new();
+        let _ = writeln!(out, "# Session {} report\n", self.session.id);
+        for (label, value) in self.overview() {
+            let _ = writeln!(out, "- {label}: {value}");
+        }
+
+        if !self.top_chatters.is_empty() {
+            let _ = writeln!(out, "\n## Top chatters\n");
+            let _ = writeln!(out, "| Viewer | Comments |\n| --- | ---: |");
+            for chatter in &self.top_chatters {
+                let _ = writeln!(out, "| {} | {} |", cell(&chatter.user), chatter.comments);
+            }
+        }
+
+        if !self.layers.is_empty() {
+            let _ = writeln!(out, "\n## Expressions\n");
+            let _ = writeln!(out, "| Layer | Shown | |\n| --- | ---: | --- |");
+            for layer in &self.layers {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} |",
+                    cell(&layer.layer),
+                    layer.count,
+                    self.bar(layer.count)
+                );
+            }
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        let title = format!("Session {} report", self.session.id);
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+             <style>body {{ font-family: sans-serif; margin: 2em; }} \
+             td, th {{ padding: 0.2em 0.8em; text-align: left; }} \
+             .bar {{ color: #e88; }}</style>\n</head>\n<body>\n<h1>{title}</h1>"
+        );
+        let _ = writeln!(out, "<table>");
+        for (label, value) in self.overview() {
+            let _ = writeln!(out, "<tr><th>{label}</th><td>{}</td></tr>", escape(&value));
+        }
+        let _ = writeln!(out, "</table>");
+
+        if !self.top_chatters.is_empty() {
+            let _ = writeln!(
+                out,
+                "<h2>Top chatters</h2>\n<table>\n<tr><th>Viewer</th><th>Comments</th></tr>"
+            );
+            for chatter in &self.top_chatters {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td></tr>",
+                    escape(&chatter.user),
+                    chatter.comments
+                );
+            }
+            let _ = writeln!(out, "</table>");
+        }
+
+        if !self.layers.is_empty() {
+            let _ = writeln!(
+                out,
+                "<h2>Expressions</h2>\n<table>\n<tr><th>Layer</th><th>Shown</th><th></th></tr>"
+            );
+            for layer in &self.layers {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td class=\"bar\">{}</td></tr>",
+                    escape(&layer.layer),
+                    layer.count,
+                    self.bar(layer.count)
+                );
+            }
+            let _ = writeln!(out, "</table>");
+        }
+        let _ = writeln!(out, "</body>\n</html>");
+        out
+    }
+}
+
+fn average(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The report of a stored session, `None` if there is no such session.
+pub fn session_report(
+    storage: &Storage,
+    session_id: i64,
+) -> rusqlite::Result<Option<SessionReport>> {
+    let Some(transcript) = storage.transcript(session_id)? else {
+        return Ok(None);
+    };
+    Ok(Some(SessionReport::new(
+        &transcript,
+        storage.usage(session_id)?,
+    )))
+}
+
+/// Write the markdown and HTML reports of the running session into `dir`, returns the HTML one.
+pub fn write_report(storage: &Storage, dir: &Path) -> anyhow::Result<PathBuf> {
+    let report = session_report(storage, storage.session_id())?
+        .ok_or_else(|| anyhow::anyhow!("The running session is not stored"))?;
+    fs::create_dir_all(dir)?;
+    let name = format!("session-{}", report.session.id);
+    fs::write(dir.join(format!("{name}.md")), report.to_markdown())?;
+    let html = dir.join(format!("{name}.html"));
+    fs::write(&html, report.to_html())?;
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        report::{ChatterCount, LayerCount, SessionReport},
+        storage::{SessionSummary, SessionUsage, Transcript, TranscriptEntry, TranscriptEntryKind},
+    };
+
+    fn comment(time: i64, comment_id: i64, user: &str, source: &str) -> TranscriptEntry {
+        TranscriptEntry {
+            time,
+            kind: TranscriptEntryKind::Comment {
+                comment_id,
+                user: user.to_string(),
+                source: source.to_string(),
+                text: "hello".to_string(),
+            },
+        }
+    }
+
+    fn response(time: i64, comment_id: i64, layers: &[&str]) -> TranscriptEntry {
+        TranscriptEntry {
+            time,
+            kind: TranscriptEntryKind::Response {
+                comment_id: Some(comment_id),
+                response: "hi".to_string(),
+                japanese_response: "やあ".to_string(),
+                layers: layers.iter().map(|layer| layer.to_string()).collect(),
+                tts_ms: 500,
+                audio_ms: None,
+            },
+        }
+    }
+
+    #[test]
+    fn count_the_session() {
+        let transcript = Transcript {
+            session: SessionSummary {
+                id: 1,
+                started_at: 0,
+                comments: 4,
+                responses: 4,
+            },
+            entries: vec![
+                comment(1000, 1, "alice", "bilibili"),
+                response(3000, 1, &["smile.png"]),
+                response(4000, 1, &["smile.png", "blush.png"]),
+                comment(5000, 2, "bob", "bilibili"),
+                comment(6000, 3, "alice", "bilibili"),
+                response(10000, 3, &["smile.png"]),
+                // made up by the app, not counted as a viewer
+                comment(11000, 4, "scheduler", "scheduler"),
+                response(12000, 4, &["sleepy.png"]),
+            ],
+        };
+        let usage = SessionUsage {
+            prompt_tokens: 3000,
+            output_tokens: 600,
+            cost: Some(0.01),
+        };
+
+        let report = SessionReport::new(&transcript, usage);
+        assert_eq!(report.duration_ms, 12000);
+        assert_eq!(report.viewer_comments, 3);
+        assert_eq!(report.answered, 2);
+        assert_eq!(report.average_latency_ms, Some(3000.0));
+        assert_eq!(report.average_tts_ms, Some(500.0));
+        assert_eq!(
+            report.top_chatters,
+            [
+                ChatterCount {
+                    user: "alice".to_string(),
+                    comments: 2
+                },
+                ChatterCount {
+                    user: "bob".to_string(),
+                    comments: 1
+                }
+            ]
+        );
+        assert_eq!(
+            report.layers[0],
+            LayerCount {
+                layer: "smile.png".to_string(),
+                count: 3
+            }
+        );
+        assert_eq!(report.layers.len(), 3);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("- Answered: 2 (66.7%)"));
+        assert!(markdown.contains("| alice | 2 |"));
+        assert!(markdown.contains("- Cost: $0.0100"));
+        assert!(report.to_html().contains("<td>smile.png</td><td>3</td>"));
+    }
+}
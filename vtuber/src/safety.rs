@@ -0,0 +1,160 @@
+use ai::{AIResponse, LLM, ModerationResponseModel, UsageExample, gemini::Gemini};
+use regex::Regex;
+
+use crate::{config::SafetyConfig, storage::UsageRecorder};
+
+const CLASSIFIER_PROMPT: &str = "You check what the character of a live stream is about to say. \
+Decide whether the lines you receive can be said on stream. Reject sexual or NSFW content, hate \
+speech, harassment, personal information, dangerous advice and anything else that breaks the \
+rules of streaming platforms. Allow everything else, including jokes and teasing. \
+Respond with JSON like: ";
+
+/// Said instead of a redacted word.
+const REDACTION: &str = "……";
+
+/// What to do with a response that can't be said on stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyAction {
+    /// Replace the matched words, responses rejected by the classifier are dropped
+    #[default]
+    Redact,
+    /// Ask the character again, dropping the response if it stays unsafe
+    Regenerate,
+    /// Say nothing and tell the operator
+    Drop,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Review {
+    Safe,
+    /// A blocked word or pattern matched, redacting it makes the response safe
+    Matched(String),
+    /// The classifier rejected the response as a whole
+    Rejected(String),
+}
+
+/// Checks the responses of the characters before they are spoken and shown.
+pub struct SafetyFilter {
+    rules: Vec<Regex>,
+    classifier: Option<Gemini<'static>>,
+    usage: UsageRecorder,
+    action: SafetyAction,
+}
+
+impl SafetyFilter {
+    pub fn new(config: &SafetyConfig, api_key: &str, usage: UsageRecorder) -> anyhow::Result<Self> {
+        let blocklist = config
+            .blocklist
+            .iter()
+            .map(|word| Regex::new(&format!("(?i){}", regex::escape(word))));
+        let patterns = config.patterns.iter().map(|pattern| Regex::new(pattern));
+        let rules = blocklist.chain(patterns).collect::<Result<Vec<_>, _>>()?;
+
+        let classifier = config.classifier_model.as_deref().map(|model| {
+            let system_prompt = format!(
+                "{CLASSIFIER_PROMPT}{}",
+                ModerationResponseModel::generate_example()
+            );
+            let mut llm = Gemini::new(
+                api_key.to_string(),
+                model.to_string(),
+                Some(system_prompt.into()),
+            );
+            llm.set_thinking(false);
+            llm.set_json_schema::<ModerationResponseModel>();
+            llm
+        });
+
+        Ok(Self {
+            rules,
+            classifier,
+            usage,
+            action: config.action,
+        })
+    }
+
+    pub fn action(&self) -> SafetyAction {
+        self.action
+    }
+
+    /// Check every line of a response, the rules first, then the classifier.
+    pub async fn review(&mut self, responses: &[AIResponse]) -> Review {
+        if let Some(review) = responses
+            .iter()
+            .flat_map(|res| [&res.response, &res.japanese_response])
+            .map(|text| self.check_rules(text))
+            .find(|review| *review != Review::Safe)
+        {
+            return review;
+        }
+
+        let Some(classifier) = &mut self.classifier else {
+            return Review::Safe;
+        };
+
+        // every response is classified on its own
+        classifier.clear_history();
+        let lines: Vec<&str> = responses.iter().map(|res| res.response.as_str()).collect();
+        let outcome = classifier.chat(&lines.join("\n")).await;
+        if outcome.is_ok() {
+            self.usage.record(classifier.last_usage()).await;
+        }
+        match outcome
+            .map_err(anyhow::Error::from)
+            .and_then(|res| Ok(serde_json::from_str::<ModerationResponseModel>(&res)?))
+        {
+            Ok(res) if res.allowed => Review::Safe,
+            Ok(res) => Review::Rejected(format!("classifier: {}", res.reason)),
+            Err(e) => {
+                // the rules still apply, a flaky classifier doesn't silence the characters
+                log::error!("Failed to classify response, allowing it: {e}");
+                Review::Safe
+            }
+        }
+    }
+
+    fn check_rules(&self, text: &str) -> Review {
+        match self.rules.iter().find(|rule| rule.is_match(text)) {
+            Some(rule) => Review::Matched(format!("matched {rule}")),
+            None => Review::Safe,
+        }
+    }
+
+    /// Replace everything the rules match.
+    pub fn redact(&self, text: &str) -> String {
+        self.rules.iter().fold(text.to_string(), |text, rule| {
+            rule.replace_all(&text, REDACTION).into_owned()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::SafetyConfig,
+        safety::{Review, SafetyAction, SafetyFilter},
+        storage::UsageRecorder,
+    };
+
+    #[test]
+    fn check_and_redact() {
+        let config = SafetyConfig {
+            blocklist: vec!["Secret".to_string()],
+            patterns: vec![r"\d{3}-\d{4}".to_string()],
+            classifier_model: None,
+            action: SafetyAction::Redact,
+        };
+        let filter = SafetyFilter::new(&config, "", UsageRecorder::default()).unwrap();
+
+        assert_eq!(filter.check_rules("hello"), Review::Safe);
+        assert!(matches!(
+            filter.check_rules("my SECRET is out"),
+            Review::Matched(_)
+        ));
+        assert_eq!(
+            filter.redact("my secret number is 555-1234"),
+            "my …… number is ……"
+        );
+    }
+}
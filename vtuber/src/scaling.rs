@@ -0,0 +1,125 @@
+use eframe::egui::{self, Vec2};
+
+/// How the characters are fitted into their part of the window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FitMode {
+    /// Fill the space, distorting the image
+    Stretch,
+    /// As large as fits while keeping the aspect ratio
+    #[default]
+    Contain,
+    /// Whole multiples of the image's pixels on the screen, sharp for pixel art
+    Integer,
+}
+
+impl FitMode {
+    pub const ALL: [FitMode; 3] = [FitMode::Stretch, FitMode::Contain, FitMode::Integer];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FitMode::Stretch => "stretch",
+            FitMode::Contain => "contain",
+            FitMode::Integer => "integer",
+        }
+    }
+
+    pub fn texture_options(self) -> egui::TextureOptions {
+        match self {
+            FitMode::Integer => egui::TextureOptions::NEAREST,
+            _ => egui::TextureOptions::LINEAR,
+        }
+    }
+}
+
+/// Size in points to draw an image of `image` pixels at, in `available` points of a screen
+/// with `pixels_per_point`.
+pub fn fit_size(
+    image: Vec2,
+    available: Vec2,
+    pixels_per_point: f32,
+    mode: FitMode,
+    zoom: f32,
+) -> Vec2 {
+    if image.x <= 0.0 || image.y <= 0.0 {
+        return Vec2::ZERO;
+    }
+    // in physical pixels, so the scale factor of the monitor doesn't blur the image
+    let available = available * pixels_per_point;
+    let contain = (available.x / image.x).min(available.y / image.y) * zoom;
+    let pixels = match mode {
+        FitMode::Stretch => available * zoom,
+        FitMode::Contain => (image * contain).round(),
+        // too small for whole multiples, shrink like contain
+        FitMode::Integer if contain < 1.0 => (image * contain).round(),
+        FitMode::Integer => image * contain.floor(),
+    };
+    pixels / pixels_per_point
+}
+
+/// Display sizes are rounded up to this many pixels, so resizing the window doesn't re-render
+/// on every step.
+const DISPLAY_STEP: f32 = 64.0;
+
+/// The most physical pixels a character is shown at in `available` points, see [`shrunk_size`].
+pub fn display_pixels(available: Vec2, pixels_per_point: f32, zoom: f32) -> [u32; 2] {
+    let pixels = available * pixels_per_point * zoom;
+    let step = |length: f32| ((length / DISPLAY_STEP).ceil() * DISPLAY_STEP).max(DISPLAY_STEP);
+    [step(pixels.x) as u32, step(pixels.y) as u32]
+}
+
+/// Size to shrink an `image` to before uploading it, keeping its aspect ratio and enough pixels
+/// to fill `display` in every fit mode. `None` if it isn't larger.
+pub fn shrunk_size(image: [u32; 2], display: [u32; 2]) -> Option<[u32; 2]> {
+    let [width, height] = image.map(|length| length as f32);
+    let scale = (display[0] as f32 / width).max(display[1] as f32 / height);
+    if scale >= 1.0 || !scale.is_finite() {
+        return None;
+    }
+    Some([width, height].map(|length| ((length * scale).round() as u32).max(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui;
+
+    use crate::scaling::{FitMode, display_pixels, fit_size, shrunk_size};
+
+    #[test]
+    fn fit_keeping_aspect_ratio() {
+        let image = egui::vec2(400.0, 600.0);
+        let available = egui::vec2(500.0, 300.0);
+
+        let size = fit_size(image, available, 1.0, FitMode::Contain, 1.0);
+        assert_eq!(size, egui::vec2(200.0, 300.0));
+        let size = fit_size(image, available, 1.0, FitMode::Stretch, 1.0);
+        assert_eq!(size, available);
+        // 1.5 physical pixels per point give room for 1.875 times the image, so only once
+        let size = fit_size(image, egui::vec2(500.0, 1000.0), 1.5, FitMode::Integer, 1.0);
+        assert_eq!(size, image / 1.5);
+        let size = fit_size(
+            image,
+            egui::vec2(1000.0, 1300.0),
+            1.0,
+            FitMode::Integer,
+            1.0,
+        );
+        assert_eq!(size, image * 2.0);
+        let size = fit_size(image, available, 1.0, FitMode::Contain, 0.5);
+        assert_eq!(size, egui::vec2(100.0, 150.0));
+    }
+
+    #[test]
+    fn shrink_to_the_display() {
+        assert_eq!(
+            display_pixels(egui::vec2(300.0, 500.0), 2.0, 1.0),
+            [640, 1024]
+        );
+        assert_eq!(display_pixels(egui::vec2(0.0, 10.0), 1.0, 1.0), [64, 64]);
+
+        assert_eq!(shrunk_size([2000, 3000], [640, 1024]), Some([683, 1024]));
+        // a wide window needs the width
+        assert_eq!(shrunk_size([2000, 3000], [1000, 600]), Some([1000, 1500]));
+        assert_eq!(shrunk_size([400, 600], [640, 1024]), None);
+    }
+}
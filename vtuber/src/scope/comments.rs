@@ -1,7 +1,26 @@
-use actix_web::{Scope, web};
+use actix_web::{dev::HttpServiceFactory, middleware::from_fn, web};
+use http_common::{auth::require_token, cors::cors};
 
-use crate::handler::comments::add_comment;
+use crate::handler::comments::{
+    add_comment, add_gift, add_subscription, comments_ws, get_comment_status,
+};
 
-pub fn comments_scope() -> Scope {
-    web::scope("comments").route("add", web::post().to(add_comment))
+/// Browser chat widgets may send comments from another origin.
+pub fn comments_scope() -> impl HttpServiceFactory {
+    web::scope("comments")
+        .wrap(from_fn(cors))
+        .route("add", web::post().to(add_comment))
+        // only the platform integrations of the operator send gifts
+        .service(
+            web::scope("gift")
+                .wrap(from_fn(require_token))
+                .route("", web::post().to(add_gift)),
+        )
+        .service(
+            web::scope("subscription")
+                .wrap(from_fn(require_token))
+                .route("", web::post().to(add_subscription)),
+        )
+        .route("ws", web::get().to(comments_ws))
+        .route("status/{id}", web::get().to(get_comment_status))
 }
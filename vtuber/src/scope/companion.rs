@@ -0,0 +1,16 @@
+use actix_web::{dev::HttpServiceFactory, middleware::from_fn, web};
+use http_common::auth::require_token;
+
+use crate::handler::companion::{
+    add_reminder, cancel_reminder, get_companion, start_pomodoro, stop_pomodoro,
+};
+
+pub fn companion_scope() -> impl HttpServiceFactory {
+    web::scope("companion")
+        .wrap(from_fn(require_token))
+        .route("", web::get().to(get_companion))
+        .route("pomodoro", web::post().to(start_pomodoro))
+        .route("pomodoro", web::delete().to(stop_pomodoro))
+        .route("reminders", web::post().to(add_reminder))
+        .route("reminders/{id}", web::delete().to(cancel_reminder))
+}
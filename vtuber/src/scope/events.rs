@@ -0,0 +1,11 @@
+use actix_web::{dev::HttpServiceFactory, middleware::from_fn, web};
+use http_common::cors::cors;
+
+use crate::handler::events::events_ws;
+
+/// Browser overlays may follow the events from another origin.
+pub fn events_scope() -> impl HttpServiceFactory {
+    web::scope("events")
+        .wrap(from_fn(cors))
+        .route("ws", web::get().to(events_ws))
+}
@@ -0,0 +1,7 @@
+use actix_web::{Scope, web};
+
+use crate::handler::health::get_health;
+
+pub fn health_scope() -> Scope {
+    web::scope("health").route("", web::get().to(get_health))
+}
@@ -0,0 +1,7 @@
+use actix_web::{Scope, web};
+
+use crate::handler::metrics::get_metrics;
+
+pub fn metrics_scope() -> Scope {
+    web::scope("metrics").route("", web::get().to(get_metrics))
+}
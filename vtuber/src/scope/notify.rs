@@ -0,0 +1,10 @@
+use actix_web::{dev::HttpServiceFactory, middleware::from_fn, web};
+use http_common::auth::require_token;
+
+use crate::handler::notify::notify;
+
+pub fn notify_scope() -> impl HttpServiceFactory {
+    web::scope("notify")
+        .wrap(from_fn(require_token))
+        .route("", web::post().to(notify))
+}
@@ -0,0 +1,17 @@
+use actix_web::{dev::HttpServiceFactory, guard, middleware::from_fn, web};
+use http_common::auth::require_token;
+
+use crate::handler::polls::{end_poll, get_poll, start_poll};
+
+/// Anyone may see the poll, only the operator starts and ends them.
+pub fn polls_scope() -> impl HttpServiceFactory {
+    web::scope("polls")
+        .route("", web::get().to(get_poll))
+        .service(
+            web::resource("")
+                .guard(guard::Any(guard::Post()).or(guard::Delete()))
+                .wrap(from_fn(require_token))
+                .route(web::post().to(start_poll))
+                .route(web::delete().to(end_poll)),
+        )
+}
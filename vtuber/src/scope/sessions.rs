@@ -0,0 +1,13 @@
+use actix_web::{dev::HttpServiceFactory, middleware::from_fn, web};
+use http_common::auth::require_token;
+
+use crate::handler::sessions::{get_report, get_transcript, list_sessions};
+
+/// The transcripts hold every viewer's name and messages, only the operator reads them.
+pub fn sessions_scope() -> impl HttpServiceFactory {
+    web::scope("sessions")
+        .wrap(from_fn(require_token))
+        .route("", web::get().to(list_sessions))
+        .route("{id}/report", web::get().to(get_report))
+        .route("{id}/transcript", web::get().to(get_transcript))
+}
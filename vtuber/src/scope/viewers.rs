@@ -0,0 +1,13 @@
+use actix_web::{dev::HttpServiceFactory, middleware::from_fn, web};
+use http_common::auth::require_token;
+
+use crate::handler::viewers::{get_viewer, list_viewers, update_viewer};
+
+/// Profiles hold what viewers said and go into the prompt, only the operator sees and edits them.
+pub fn viewers_scope() -> impl HttpServiceFactory {
+    web::scope("viewers")
+        .wrap(from_fn(require_token))
+        .route("", web::get().to(list_viewers))
+        .route("{name}", web::get().to(get_viewer))
+        .route("{name}", web::patch().to(update_viewer))
+}
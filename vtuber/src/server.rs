@@ -3,28 +3,122 @@ use std::net::TcpListener;
 use actix_web::{
     App, HttpServer,
     dev::Server,
+    middleware::from_fn,
     web::{self, ServiceConfig},
 };
-use tokio::sync::mpsc;
+use http_common::{
+    auth::OperatorToken,
+    cors::Cors,
+    error::json_errors,
+    forwarded::{ClientLimiter, TrustedProxies},
+};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::{
+    bus::{CommentEvent, InEvent, UiEvent},
+    config::ServerConfig,
+    openapi,
+    pipeline::PipelineServices,
+    scope::{
+        capture::capture_scope, comments::comments_scope, companion::companion_scope,
+        events::events_scope, health::health_scope, metrics::metrics_scope, notify::notify_scope,
+        polls::polls_scope, sessions::sessions_scope, viewers::viewers_scope,
+    },
+};
 
-use crate::{bus::InEvent, scope::comments::comments_scope};
+/// Comments and events are small, larger bodies are refused before parsing.
+const MAX_BODY: usize = 64 * 1024;
 
-fn config_server(config: &mut ServiceConfig) {
-    config.service(comments_scope());
+pub(crate) fn config_server(config: &mut ServiceConfig) {
+    config
+        .service(capture_scope())
+        .service(comments_scope())
+        .service(companion_scope())
+        .service(events_scope())
+        .service(health_scope())
+        .service(metrics_scope())
+        .service(notify_scope())
+        .service(polls_scope())
+        .service(sessions_scope())
+        .service(viewers_scope());
+    http_common::openapi::configure(config, &openapi::api_doc());
 }
 
 pub struct EventSender(pub mpsc::Sender<InEvent>);
 
+/// Senders of the [`HttpSource`](crate::source::http::HttpSource)s the comment routes post to.
+#[derive(Clone)]
+pub struct CommentSenders {
+    pub http: mpsc::Sender<CommentEvent>,
+    pub websocket: mpsc::Sender<CommentEvent>,
+}
+
+pub struct UiEventSender(pub broadcast::Sender<UiEvent>);
+
 pub fn create_server(
     listener: TcpListener,
+    config: &ServerConfig,
+    comment_senders: CommentSenders,
     in_tx: mpsc::Sender<InEvent>,
+    ui_tx: broadcast::Sender<UiEvent>,
+    services: PipelineServices,
 ) -> anyhow::Result<Server> {
+    let comment_senders = web::Data::new(comment_senders);
     let event_sender = web::Data::new(EventSender(in_tx));
+    let ui_event_sender = web::Data::new(UiEventSender(ui_tx));
+    let storage = services.storage.map(web::Data::from);
+    let viewers = services.viewers.map(web::Data::from);
+    let metrics = web::Data::from(services.metrics);
+    let polls = web::Data::from(services.polls);
+    let comments = web::Data::from(services.comments);
+    let companion = web::Data::from(services.companion);
+    let allowed_origins = web::Data::new(Cors::new(config.cors_origins.clone()));
+    let trusted_proxies = web::Data::new(TrustedProxies(config.trusted_proxies.clone()));
+    let operator_token = web::Data::new(OperatorToken(config.token.clone()));
+    let client_limiter = config
+        .rate_limit
+        .map(|per_minute| web::Data::new(ClientLimiter::new(per_minute)));
+    let base_path = config.base_path.clone();
     let server = HttpServer::new(move || {
-        App::new()
-            .configure(config_server)
+        let base_path = base_path.clone();
+        let mut app = App::new()
+            .wrap(from_fn(json_errors))
+            .configure(move |config| {
+                // behind a proxy passing on the whole path, e.g. /vtuber/comments/add
+                if base_path.is_empty() {
+                    config_server(config);
+                } else {
+                    config.service(web::scope(&base_path).configure(config_server));
+                }
+            })
+            .app_data(web::JsonConfig::default().limit(MAX_BODY))
+            .app_data(comment_senders.clone())
             .app_data(event_sender.clone())
+            .app_data(ui_event_sender.clone())
+            .app_data(metrics.clone())
+            .app_data(polls.clone())
+            .app_data(comments.clone())
+            .app_data(companion.clone())
+            .app_data(allowed_origins.clone())
+            .app_data(trusted_proxies.clone())
+            .app_data(operator_token.clone());
+        // optional subsystems, handlers extract them as `Option<web::Data<_>>`
+        if let Some(storage) = &storage {
+            app = app.app_data(storage.clone());
+        }
+        if let Some(viewers) = &viewers {
+            app = app.app_data(viewers.clone());
+        }
+        if let Some(client_limiter) = &client_limiter {
+            app = app.app_data(client_limiter.clone());
+        }
+        app
     });
 
-    Ok(server.listen(listener)?.run())
+    // shutdown is driven by the app, not by actix's own signal handling
+    Ok(server
+        .disable_signals()
+        .shutdown_timeout(5)
+        .listen(listener)?
+        .run())
 }
@@ -0,0 +1,394 @@
+use std::fs;
+
+use eframe::egui::{self, Color32};
+use layer_composer::LayerManifest;
+
+use crate::{
+    config::AppConfig,
+    player::output_devices,
+    scaling::FitMode,
+    subtitle_style::SubtitleStyle,
+    theme::{PRESETS, Theme},
+    utils::find_env_file,
+};
+
+/// The options of the settings window, saved to the `.env` file.
+///
+/// The voice speed and base layer are applied by the config watcher once saved, the rest is
+/// previewed right away.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// Only the size, color and background opacity are changed here
+    pub subtitle: SubtitleStyle,
+    pub voice_volume: f32,
+    /// Output device name, the default output if `None`
+    pub audio_device: Option<String>,
+    pub tts_speed: f32,
+    /// Base layer of the main character
+    pub base_layer: String,
+    /// How many recent comments are kept
+    pub comment_count: usize,
+    pub fit: FitMode,
+    pub zoom: f32,
+    pub idle_fps: u32,
+    /// A preset or a theme file
+    pub theme_name: String,
+    pub theme: Theme,
+}
+
+impl Settings {
+    pub fn from_config(app_config: &AppConfig) -> Self {
+        Self {
+            subtitle: app_config.gui.subtitle.clone(),
+            voice_volume: app_config.audio.voice_volume,
+            audio_device: app_config.audio.device.clone(),
+            tts_speed: app_config.tts.speed.unwrap_or(1.0),
+            base_layer: app_config.characters[0].render.base_layer.clone(),
+            comment_count: app_config.gui.comment_count,
+            fit: app_config.gui.fit,
+            zoom: app_config.gui.zoom,
+            idle_fps: app_config.gui.idle_fps,
+            theme_name: app_config.gui.theme_name.clone(),
+            theme: app_config.gui.theme.clone(),
+        }
+    }
+
+    /// Write the settings changed since `saved` into the `.env` file, the others are left as
+    /// they are written there.
+    pub fn save(&self, saved: &Settings) -> anyhow::Result<()> {
+        let path = find_env_file().ok_or_else(|| anyhow::anyhow!("No .env file found"))?;
+        let saved_values = saved.env_values();
+        let values: Vec<_> = self
+            .env_values()
+            .into_iter()
+            .filter(|value| !saved_values.contains(value))
+            .collect();
+        if values.is_empty() {
+            return Ok(());
+        }
+        let content = fs::read_to_string(&path)?;
+        fs::write(&path, update_env(&content, &values))?;
+        Ok(())
+    }
+
+    /// The variables of the settings, as written into the `.env` file.
+    fn env_values(&self) -> [(&'static str, String); 12] {
+        let [r, g, b] = self.subtitle.color;
+        [
+            ("VTUBER_GUI_FONT_SIZE", self.subtitle.font_size.to_string()),
+            (
+                "VTUBER_GUI_TEXT_COLOR",
+                format!("\"#{r:02x}{g:02x}{b:02x}\""),
+            ),
+            (
+                "VTUBER_GUI_OVERLAY_OPACITY",
+                self.subtitle.background.opacity.to_string(),
+            ),
+            ("VTUBER_AUDIO_VOICE_VOLUME", self.voice_volume.to_string()),
+            (
+                "VTUBER_AUDIO_DEVICE",
+                format!("\"{}\"", self.audio_device.as_deref().unwrap_or_default()),
+            ),
+            ("VTUBER_TTS_SPEED", self.tts_speed.to_string()),
+            (
+                "VTUBER_RENDER_BASE_LAYER",
+                format!("\"{}\"", self.base_layer),
+            ),
+            ("VTUBER_GUI_COMMENT_COUNT", self.comment_count.to_string()),
+            ("VTUBER_GUI_FIT", format!("\"{}\"", self.fit.name())),
+            ("VTUBER_GUI_ZOOM", self.zoom.to_string()),
+            ("VTUBER_GUI_IDLE_FPS", self.idle_fps.to_string()),
+            ("VTUBER_GUI_THEME", format!("\"{}\"", self.theme_name)),
+        ]
+    }
+}
+
+/// Replace the assignments of the given variables, appending the ones that aren't set yet.
+/// Comments and everything else are kept as they are.
+fn update_env(content: &str, values: &[(&str, String)]) -> String {
+    let mut written = vec![false; values.len()];
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| {
+            let name = line.split_once('=').map(|(name, _)| name.trim());
+            match values.iter().position(|(key, _)| Some(*key) == name) {
+                Some(index) => {
+                    written[index] = true;
+                    format!("{}={}", values[index].0, values[index].1)
+                }
+                None => line.to_string(),
+            }
+        })
+        .collect();
+    for ((key, value), written) in values.iter().zip(written) {
+        if !written {
+            lines.push(format!("{key}={value}"));
+        }
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
+}
+
+/// The settings window, opened with the gear in the corner.
+pub struct SettingsWindow {
+    open: bool,
+    /// Base layers of the main character's model
+    base_layers: Vec<String>,
+    /// Output devices, listed again with the refresh button
+    devices: Vec<String>,
+    /// The presets, and the theme file if one was loaded
+    themes: Vec<(String, Theme)>,
+    status: Option<String>,
+    /// The settings as in the `.env` file, only the changes to them are written
+    saved: Settings,
+}
+
+impl SettingsWindow {
+    pub fn new(app_config: &AppConfig) -> Self {
+        let base_layers = app_config.characters[0]
+            .render
+            .model
+            .manifest()
+            .layers
+            .iter()
+            .filter(|(_, layer)| matches!(layer, LayerManifest::BaseLayer { .. }))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let mut themes: Vec<(String, Theme)> = PRESETS
+            .iter()
+            .filter_map(|&name| Some((name.to_string(), Theme::preset(name)?)))
+            .collect();
+        if !PRESETS.contains(&app_config.gui.theme_name.as_str()) {
+            themes.push((
+                app_config.gui.theme_name.clone(),
+                app_config.gui.theme.clone(),
+            ));
+        }
+        Self {
+            open: false,
+            base_layers,
+            devices: output_devices(),
+            themes,
+            status: None,
+            saved: Settings::from_config(app_config),
+        }
+    }
+
+    /// Draw the gear, and the window if it is open. `level` is the loudness of the voice
+    /// played right now, shown next to the output device.
+    pub fn show(&mut self, ctx: &egui::Context, settings: &mut Settings, level: f32) {
+        egui::Area::new(egui::Id::new("settings_gear"))
+            .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(4.0, -4.0))
+            .show(ctx, |ui| {
+                let (rect, response) =
+                    ui.allocate_exact_size(egui::vec2(20.0, 20.0), egui::Sense::click());
+                let color = if response.hovered() {
+                    Color32::WHITE
+                } else {
+                    Color32::from_white_alpha(120)
+                };
+                draw_gear(ui.painter(), rect.center(), color);
+                if response.clicked() {
+                    self.open = !self.open;
+                }
+            });
+
+        let mut open = self.open;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("settings_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Theme");
+                        egui::ComboBox::from_id_salt("theme")
+                            .selected_text(&settings.theme_name)
+                            .show_ui(ui, |ui| {
+                                for (name, theme) in &self.themes {
+                                    if ui
+                                        .selectable_label(settings.theme_name == *name, name)
+                                        .clicked()
+                                    {
+                                        settings.theme_name = name.clone();
+                                        settings.theme = theme.clone();
+                                        // the colors can still be changed below
+                                        theme.apply(&mut settings.subtitle);
+                                    }
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Subtitle size");
+                        ui.add(egui::Slider::new(
+                            &mut settings.subtitle.font_size,
+                            12.0..=64.0,
+                        ));
+                        ui.end_row();
+
+                        ui.label("Subtitle color");
+                        ui.color_edit_button_srgb(&mut settings.subtitle.color);
+                        ui.end_row();
+
+                        ui.label("Overlay opacity");
+                        ui.add(egui::Slider::new(
+                            &mut settings.subtitle.background.opacity,
+                            0.0..=1.0,
+                        ));
+                        ui.end_row();
+
+                        ui.label("Voice volume");
+                        ui.add(egui::Slider::new(&mut settings.voice_volume, 0.0..=2.0));
+                        ui.end_row();
+
+                        ui.label("Output device");
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_salt("audio_device")
+                                .selected_text(
+                                    settings.audio_device.as_deref().unwrap_or("Default"),
+                                )
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut settings.audio_device,
+                                        None,
+                                        "Default",
+                                    );
+                                    for device in &self.devices {
+                                        ui.selectable_value(
+                                            &mut settings.audio_device,
+                                            Some(device.clone()),
+                                            device,
+                                        );
+                                    }
+                                });
+                            if ui
+                                .small_button("⟳")
+                                .on_hover_text("List the devices again")
+                                .clicked()
+                            {
+                                self.devices = output_devices();
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Level");
+                        draw_meter(ui, level);
+                        ui.end_row();
+
+                        ui.label("Voice speed");
+                        ui.add(egui::Slider::new(&mut settings.tts_speed, 0.5..=2.0));
+                        ui.end_row();
+
+                        ui.label("Base layer");
+                        egui::ComboBox::from_id_salt("base_layer")
+                            .selected_text(&settings.base_layer)
+                            .show_ui(ui, |ui| {
+                                for layer in &self.base_layers {
+                                    ui.selectable_value(
+                                        &mut settings.base_layer,
+                                        layer.clone(),
+                                        layer,
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Comments shown");
+                        ui.add(egui::Slider::new(&mut settings.comment_count, 1..=200));
+                        ui.end_row();
+
+                        ui.label("Fit");
+                        egui::ComboBox::from_id_salt("fit")
+                            .selected_text(settings.fit.name())
+                            .show_ui(ui, |ui| {
+                                for fit in FitMode::ALL {
+                                    ui.selectable_value(&mut settings.fit, fit, fit.name());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Zoom");
+                        ui.add(egui::Slider::new(&mut settings.zoom, 0.25..=2.0));
+                        ui.end_row();
+
+                        ui.label("Idle frame rate");
+                        ui.add(egui::Slider::new(&mut settings.idle_fps, 1..=60));
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        self.status = Some(match settings.save(&self.saved) {
+                            Ok(()) => {
+                                self.saved = settings.clone();
+                                "Saved".to_string()
+                            }
+                            Err(e) => format!("Failed to save: {e}"),
+                        });
+                    }
+                    if let Some(status) = &self.status {
+                        ui.label(status);
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
+
+/// A bar filling up with the loudness, green up to -12 dB, then yellow and red near clipping.
+fn draw_meter(ui: &mut egui::Ui, level: f32) {
+    const FLOOR_DB: f32 = -48.0;
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(160.0, 10.0), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 2.0, Color32::from_gray(40));
+    let db = 20.0 * level.max(1e-6).log10();
+    let filled = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+    if filled > 0.0 {
+        let color = if db > -3.0 {
+            Color32::RED
+        } else if db > -12.0 {
+            Color32::YELLOW
+        } else {
+            Color32::GREEN
+        };
+        let mut bar = rect;
+        bar.set_width(rect.width() * filled);
+        painter.rect_filled(bar, 2.0, color);
+    }
+}
+
+fn draw_gear(painter: &egui::Painter, center: egui::Pos2, color: Color32) {
+    const TEETH: usize = 8;
+
+    let stroke = egui::Stroke::new(2.0, color);
+    painter.circle_stroke(center, 5.5, stroke);
+    painter.circle_filled(center, 2.0, color);
+    for tooth in 0..TEETH {
+        let angle = tooth as f32 * std::f32::consts::TAU / TEETH as f32;
+        let direction = egui::vec2(angle.cos(), angle.sin());
+        painter.line_segment([center + direction * 5.5, center + direction * 9.0], stroke);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::update_env;
+
+    #[test]
+    fn update_env_file() {
+        let content = "# comment\nVTUBER_TTS_SPEED=1.0\n# VTUBER_GUI_FONT_SIZE=26\nOTHER=\"x\"\n";
+        let values = [
+            ("VTUBER_TTS_SPEED", "1.2".to_string()),
+            ("VTUBER_GUI_FONT_SIZE", "30".to_string()),
+        ];
+        assert_eq!(
+            update_env(content, &values),
+            "# comment\nVTUBER_TTS_SPEED=1.2\n# VTUBER_GUI_FONT_SIZE=26\nOTHER=\"x\"\nVTUBER_GUI_FONT_SIZE=30\n"
+        );
+    }
+}
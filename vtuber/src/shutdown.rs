@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// Tells tasks to wind down, triggered by ctrl-c or by closing the window.
+#[derive(Clone)]
+pub struct Shutdown {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        let (tx, _) = watch::channel(false);
+        Self { tx: Arc::new(tx) }
+    }
+}
+
+impl Shutdown {
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Resolves once shutdown was triggered.
+    pub async fn triggered(&self) {
+        let mut rx = self.tx.subscribe();
+        // the sender lives as long as self
+        let _ = rx.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Trigger on ctrl-c, a second ctrl-c quits right away.
+    pub fn listen_for_ctrl_c(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            log::info!("Shutting down, press ctrl-c again to quit immediately");
+            shutdown.trigger();
+
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        });
+    }
+}
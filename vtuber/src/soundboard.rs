@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use rodio::{Decoder, Sink, mixer::Mixer};
+
+/// A soundboard request from chat, see [`parse_command`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum SoundCommand {
+    /// `!sound <name>`, plays once on top of everything
+    Effect { name: String },
+    /// `!bgm <name>`, loops until replaced or stopped
+    Bgm { name: String },
+    /// `!bgm stop`
+    StopBgm,
+}
+
+/// Parse a chat command like `!sound fanfare` or `!bgm stop`.
+pub fn parse_command(text: &str) -> Option<SoundCommand> {
+    let mut words = text.split_whitespace();
+    let command = words.next()?.to_lowercase();
+    let name = words.next()?.to_string();
+    if words.next().is_some() {
+        return None;
+    }
+
+    match command.as_str() {
+        "!sound" => Some(SoundCommand::Effect { name }),
+        "!bgm" if name.eq_ignore_ascii_case("stop") => Some(SoundCommand::StopBgm),
+        "!bgm" => Some(SoundCommand::Bgm { name }),
+        _ => None,
+    }
+}
+
+/// The sound library, a json object like
+/// `{"sounds": {"fanfare": "./sounds/fanfare.wav"}, "bgm": {"lofi": "./sounds/lofi.mp3"}}`.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct SoundboardConfig {
+    #[serde(default)]
+    pub sounds: HashMap<String, PathBuf>,
+    #[serde(default)]
+    pub bgm: HashMap<String, PathBuf>,
+    #[serde(default = "default_effect_volume")]
+    pub effect_volume: f32,
+    #[serde(default = "default_bgm_volume")]
+    pub bgm_volume: f32,
+    /// Share of the voice volume left while an effect plays
+    #[serde(default = "default_duck_volume")]
+    pub duck_volume: f32,
+    /// Volume of the music while the character speaks
+    #[serde(default = "default_bgm_duck_volume")]
+    pub bgm_duck_volume: f32,
+}
+
+fn default_effect_volume() -> f32 {
+    1.0
+}
+
+fn default_bgm_volume() -> f32 {
+    0.3
+}
+
+fn default_duck_volume() -> f32 {
+    0.4
+}
+
+fn default_bgm_duck_volume() -> f32 {
+    0.1
+}
+
+impl SoundboardConfig {
+    /// Whether the command refers to a sound in the library.
+    pub fn knows(&self, command: &SoundCommand) -> bool {
+        match command {
+            SoundCommand::Effect { name } => self.sounds.contains_key(name),
+            SoundCommand::Bgm { name } => self.bgm.contains_key(name),
+            SoundCommand::StopBgm => true,
+        }
+    }
+}
+
+/// Plays effects and background music next to the voice.
+pub struct Soundboard {
+    config: SoundboardConfig,
+    effects: Vec<Sink>,
+    bgm: Option<Sink>,
+    /// The music is ducked while set
+    speaking: bool,
+}
+
+impl Soundboard {
+    pub fn new(config: SoundboardConfig) -> Self {
+        Self {
+            config,
+            effects: Vec::new(),
+            bgm: None,
+            speaking: false,
+        }
+    }
+
+    pub fn duck_volume(&self) -> f32 {
+        self.config.duck_volume
+    }
+
+    pub fn handle(&mut self, command: &SoundCommand, mixer: &Mixer) {
+        match command {
+            SoundCommand::Effect { name } => {
+                let Some(path) = self.config.sounds.get(name) else {
+                    return;
+                };
+                let sink = Sink::connect_new(mixer);
+                sink.set_volume(self.config.effect_volume);
+                match open(path) {
+                    Ok(source) => {
+                        sink.append(source);
+                        self.effects.push(sink);
+                    }
+                    Err(e) => log::error!("Failed to play sound {}: {e}", path.display()),
+                }
+            }
+            SoundCommand::Bgm { name } => {
+                let Some(path) = self.config.bgm.get(name) else {
+                    return;
+                };
+                let looped = File::open(path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|file| Ok(Decoder::new_looped(BufReader::new(file))?));
+                match looped {
+                    Ok(source) => {
+                        let sink = Sink::connect_new(mixer);
+                        sink.set_volume(self.bgm_volume());
+                        sink.append(source);
+                        // dropping the old sink stops it
+                        self.bgm = Some(sink);
+                    }
+                    Err(e) => log::error!("Failed to play music {}: {e}", path.display()),
+                }
+            }
+            SoundCommand::StopBgm => self.bgm = None,
+        }
+    }
+
+    /// Duck the music while the character speaks.
+    pub fn set_speaking(&mut self, speaking: bool) {
+        if speaking == self.speaking {
+            return;
+        }
+        self.speaking = speaking;
+        if let Some(bgm) = &self.bgm {
+            bgm.set_volume(self.bgm_volume());
+        }
+    }
+
+    fn bgm_volume(&self) -> f32 {
+        if self.speaking {
+            self.config.bgm_duck_volume
+        } else {
+            self.config.bgm_volume
+        }
+    }
+
+    /// Whether an effect is playing, the voice is ducked meanwhile.
+    pub fn is_playing_effect(&mut self) -> bool {
+        self.effects.retain(|sink| !sink.empty());
+        !self.effects.is_empty()
+    }
+}
+
+fn open(path: &Path) -> anyhow::Result<Decoder<BufReader<File>>> {
+    Ok(Decoder::new(BufReader::new(File::open(path)?))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::soundboard::{SoundCommand, parse_command};
+
+    #[test]
+    fn parse_chat_commands() {
+        assert_eq!(
+            parse_command("!sound fanfare"),
+            Some(SoundCommand::Effect {
+                name: "fanfare".to_string()
+            })
+        );
+        assert_eq!(
+            parse_command("  !BGM lofi "),
+            Some(SoundCommand::Bgm {
+                name: "lofi".to_string()
+            })
+        );
+        assert_eq!(parse_command("!bgm stop"), Some(SoundCommand::StopBgm));
+
+        assert_eq!(parse_command("!sound"), None);
+        assert_eq!(parse_command("!sound fanfare please"), None);
+        assert_eq!(parse_command("play !sound fanfare"), None);
+    }
+}
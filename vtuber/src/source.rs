@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::bus::{CommentEvent, InEvent};
+
+pub mod http;
+pub mod scheduler;
+pub mod simulation;
+pub mod twitch;
+
+/// A source of viewer comments, e.g. a chat platform.
+#[async_trait]
+pub trait CommentSource: Send + 'static {
+    /// Name of the source, attached to every event it produces.
+    fn name(&self) -> &str;
+
+    /// Produce comments into `tx` until the source ends or the channel closes.
+    async fn run(self: Box<Self>, tx: mpsc::Sender<CommentEvent>) -> anyhow::Result<()>;
+}
+
+/// Runs every registered source concurrently and merges their comments into the bus.
+#[derive(Default)]
+pub struct SourceRegistry {
+    sources: Vec<Box<dyn CommentSource>>,
+}
+
+impl SourceRegistry {
+    pub fn register(&mut self, source: impl CommentSource) {
+        self.sources.push(Box::new(source));
+    }
+
+    pub fn spawn_all(self, in_tx: mpsc::Sender<InEvent>) {
+        for source in self.sources {
+            let name = source.name().to_string();
+            let (tx, mut rx) = mpsc::channel::<CommentEvent>(64);
+
+            // tag and forward
+            let in_tx = in_tx.clone();
+            let source_name = name.clone();
+            tokio::spawn(async move {
+                while let Some(mut comment) = rx.recv().await {
+                    comment.source = source_name.clone();
+                    if in_tx.send(InEvent::Comment(comment)).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            tokio::spawn(async move {
+                log::info!("Starting comment source {name}");
+                match source.run(tx).await {
+                    Ok(()) => log::info!("Comment source {name} stopped"),
+                    Err(e) => log::error!("Comment source {name} failed: {e}"),
+                }
+            });
+        }
+    }
+}
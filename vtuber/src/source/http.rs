@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::{bus::CommentEvent, source::CommentSource};
+
+pub const HTTP_SOURCE: &str = "http";
+pub const WEBSOCKET_SOURCE: &str = "websocket";
+
+/// Comments posted to the HTTP API, handed over by the handlers through the sender of
+/// [`HttpSource::channel`].
+pub struct HttpSource {
+    name: &'static str,
+    rx: mpsc::Receiver<CommentEvent>,
+}
+
+impl HttpSource {
+    /// The source and the sender the handlers post its comments to. Once the source stops,
+    /// sending fails.
+    pub fn channel(name: &'static str) -> (Self, mpsc::Sender<CommentEvent>) {
+        let (tx, rx) = mpsc::channel(64);
+        (Self { name, rx }, tx)
+    }
+}
+
+#[async_trait]
+impl CommentSource for HttpSource {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn run(mut self: Box<Self>, tx: mpsc::Sender<CommentEvent>) -> anyhow::Result<()> {
+        while let Some(comment) = self.rx.recv().await {
+            if tx.send(comment).await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+use std::{str::FromStr, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    bus::{CommentEvent, Priority},
+    source::CommentSource,
+};
+
+/// Name of the source, comments from it are not attributed to a viewer.
+pub const SCHEDULER_SOURCE: &str = "scheduler";
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleTime {
+    /// Cron expression with seconds, e.g. `0 0 * * * *` for every full hour
+    Cron(String),
+    /// Repeat every n seconds after the stream started
+    Every(f64),
+    /// Fire once, n seconds after the stream started
+    After(f64),
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ScheduleEntry {
+    #[serde(flatten)]
+    pub when: ScheduleTime,
+    /// Sent to the AI like a comment, `{uptime}` is replaced with the time since start
+    pub prompt: String,
+    /// Who the prompt comes from
+    #[serde(default = "default_user")]
+    pub user: String,
+}
+
+fn default_user() -> String {
+    "system".to_string()
+}
+
+enum Timer {
+    Cron {
+        schedule: Box<cron::Schedule>,
+        /// The time it was last set to fire at, a sleep ending early doesn't fire it twice
+        scheduled: Option<DateTime<Local>>,
+    },
+    Every(Duration),
+    After(Duration),
+}
+
+impl Timer {
+    /// When to fire next after `last` (or the start), `None` once done.
+    fn next(&mut self, started_at: Instant, last: Option<Instant>) -> Option<Instant> {
+        match self {
+            Timer::Cron {
+                schedule,
+                scheduled,
+            } => {
+                let now = Local::now();
+                let after = scheduled.map_or(now, |scheduled| scheduled.max(now));
+                let next = schedule.after(&after).next()?;
+                *scheduled = Some(next);
+                let wait = (next - now).to_std().unwrap_or_default();
+                Some(Instant::now() + wait)
+            }
+            Timer::Every(interval) => Some(last.unwrap_or(started_at) + *interval),
+            Timer::After(delay) => last.is_none().then_some(started_at + *delay),
+        }
+    }
+}
+
+/// Injects scripted prompts on a schedule, e.g. hydration reminders.
+pub struct SchedulerSource {
+    entries: Vec<(Timer, ScheduleEntry)>,
+}
+
+impl SchedulerSource {
+    pub fn new(entries: Vec<ScheduleEntry>) -> anyhow::Result<Self> {
+        let entries = entries
+            .into_iter()
+            .map(|entry| {
+                let timer = match &entry.when {
+                    ScheduleTime::Cron(expression) => {
+                        let schedule = cron::Schedule::from_str(expression).map_err(|e| {
+                            anyhow::anyhow!("Bad cron expression {expression}: {e}")
+                        })?;
+                        Timer::Cron {
+                            schedule: Box::new(schedule),
+                            scheduled: None,
+                        }
+                    }
+                    ScheduleTime::Every(secs) => Timer::Every(Duration::try_from_secs_f64(*secs)?),
+                    ScheduleTime::After(secs) => Timer::After(Duration::try_from_secs_f64(*secs)?),
+                };
+                Ok((timer, entry))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { entries })
+    }
+}
+
+#[async_trait]
+impl CommentSource for SchedulerSource {
+    fn name(&self) -> &str {
+        SCHEDULER_SOURCE
+    }
+
+    async fn run(self: Box<Self>, tx: mpsc::Sender<CommentEvent>) -> anyhow::Result<()> {
+        let started_at = Instant::now();
+        let mut tasks = Vec::new();
+        for (mut timer, entry) in self.entries {
+            let tx = tx.clone();
+            tasks.push(tokio::spawn(async move {
+                let mut last = None;
+                while let Some(at) = timer.next(started_at, last) {
+                    tokio::time::sleep_until(at).await;
+                    last = Some(at);
+
+                    let prompt = entry
+                        .prompt
+                        .replace("{uptime}", &format_uptime(started_at.elapsed()));
+                    log::info!("Scheduled prompt: {prompt}");
+                    // scripted segments must not be throttled like viewers
+                    let comment = CommentEvent::new(
+                        &entry.user,
+                        prompt,
+                        SCHEDULER_SOURCE,
+                        Priority::Superchat,
+                    );
+                    if tx.send(comment).await.is_err() {
+                        break;
+                    }
+                }
+            }));
+        }
+
+        for task in tasks {
+            task.await?;
+        }
+        Ok(())
+    }
+}
+
+fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes} minutes"),
+        (hours, 0) => format!("{hours} hours"),
+        (hours, minutes) => format!("{hours} hours {minutes} minutes"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use crate::source::scheduler::{ScheduleEntry, ScheduleTime, SchedulerSource};
+
+    #[test]
+    fn parse_entries() {
+        let entries: Vec<ScheduleEntry> = serde_json::from_str(
+            r#"[
+                {"cron": "0 0 * * * *", "prompt": "drink water"},
+                {"every": 60, "prompt": "we have been live for {uptime}"},
+                {"after": 7200, "prompt": "two hours", "user": "主人"}
+            ]"#,
+        )
+        .unwrap();
+        assert!(matches!(entries[1].when, ScheduleTime::Every(_)));
+        assert_eq!(entries[2].user, "主人");
+
+        let mut source = SchedulerSource::new(entries).unwrap();
+        let start = Instant::now();
+        let (every, _) = &mut source.entries[1];
+        assert_eq!(
+            every.next(start, None),
+            Some(start + Duration::from_secs(60))
+        );
+        let (after, _) = &mut source.entries[2];
+        assert!(after.next(start, Some(start)).is_none());
+
+        assert!(
+            SchedulerSource::new(vec![ScheduleEntry {
+                when: ScheduleTime::Cron("not cron".to_string()),
+                prompt: String::new(),
+                user: String::new(),
+            }])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn fire_cron_once_per_time() {
+        let mut source = SchedulerSource::new(vec![ScheduleEntry {
+            when: ScheduleTime::Cron("* * * * * *".to_string()),
+            prompt: String::new(),
+            user: String::new(),
+        }])
+        .unwrap();
+        let start = Instant::now();
+        let (cron, _) = &mut source.entries[0];
+        // asked again right away, as after waking up a little early
+        let first = cron.next(start, None).unwrap();
+        let second = cron.next(start, Some(first)).unwrap();
+        assert!(second - first > Duration::from_millis(900));
+    }
+}
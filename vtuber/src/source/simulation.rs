@@ -0,0 +1,102 @@
+use std::{fs, path::Path, time::Duration};
+
+use async_trait::async_trait;
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    bus::{CommentEvent, CommentKind, Priority},
+    source::CommentSource,
+};
+
+pub const SIMULATION_SOURCE: &str = "simulation";
+
+/// A line of a comment log, e.g. `{"at": 12.5, "user": "viewer", "text": "hello"}`.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct RecordedComment {
+    /// Seconds after the start of the replay
+    pub at: f64,
+    pub user: String,
+    pub text: String,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub kind: CommentKind,
+}
+
+/// Replays a recorded comment log in real time, see `--simulate`.
+pub struct SimulationSource {
+    comments: Vec<RecordedComment>,
+}
+
+impl SimulationSource {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let comments = parse_log(&fs::read_to_string(path)?)?;
+        log::info!(
+            "Replaying {} comments from {}",
+            comments.len(),
+            path.display()
+        );
+        Ok(Self { comments })
+    }
+}
+
+/// Parse a JSONL comment log, sorted by time.
+fn parse_log(log: &str) -> anyhow::Result<Vec<RecordedComment>> {
+    let mut comments = log
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str::<RecordedComment>(line)
+                .map_err(|e| anyhow::anyhow!("Bad comment on line {}: {e}", index + 1))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    comments.sort_by(|a, b| a.at.total_cmp(&b.at));
+    Ok(comments)
+}
+
+#[async_trait]
+impl CommentSource for SimulationSource {
+    fn name(&self) -> &str {
+        SIMULATION_SOURCE
+    }
+
+    async fn run(self: Box<Self>, tx: mpsc::Sender<CommentEvent>) -> anyhow::Result<()> {
+        let started_at = Instant::now();
+        for comment in self.comments {
+            tokio::time::sleep_until(started_at + Duration::try_from_secs_f64(comment.at)?).await;
+            let event = CommentEvent::new(
+                comment.user,
+                comment.text,
+                SIMULATION_SOURCE,
+                comment.priority,
+            )
+            .with_kind(comment.kind);
+            if tx.send(event).await.is_err() {
+                break;
+            }
+        }
+        log::info!("Replayed the whole comment log");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{bus::Priority, source::simulation::parse_log};
+
+    #[test]
+    fn parse_comment_log() {
+        let log = r#"{"at": 5, "user": "b", "text": "second", "priority": "superchat"}
+
+{"at": 1.5, "user": "a", "text": "first"}
+"#;
+        let comments = parse_log(log).unwrap();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].text, "first");
+        assert_eq!(comments[0].priority, Priority::Normal);
+        assert_eq!(comments[1].priority, Priority::Superchat);
+
+        assert!(parse_log("{\"at\": 1}").is_err());
+    }
+}
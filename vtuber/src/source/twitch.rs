@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    sync::mpsc,
+};
+
+use crate::{
+    bus::{CommentEvent, Priority},
+    config::TwitchConfig,
+    source::CommentSource,
+};
+
+const TWITCH_IRC_HOST: &str = "irc.chat.twitch.tv";
+const TWITCH_IRC_PORT: u16 = 6697;
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+pub struct TwitchSource {
+    config: TwitchConfig,
+}
+
+impl TwitchSource {
+    pub fn new(config: TwitchConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl CommentSource for TwitchSource {
+    fn name(&self) -> &str {
+        "twitch"
+    }
+
+    /// Forward Twitch chat messages, reconnecting with backoff until the channel closes.
+    async fn run(self: Box<Self>, tx: mpsc::Sender<CommentEvent>) -> anyhow::Result<()> {
+        let mut backoff = MIN_BACKOFF;
+        loop {
+            match connect_and_read(&self.config, &tx, &mut backoff).await {
+                Ok(()) => log::warn!("Twitch chat connection closed"),
+                Err(e) => log::error!("Twitch chat error: {e}"),
+            }
+
+            if tx.is_closed() {
+                return Ok(());
+            }
+
+            log::info!("Reconnecting to Twitch chat in {}s", backoff.as_secs());
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+async fn connect_and_read(
+    config: &TwitchConfig,
+    tx: &mpsc::Sender<CommentEvent>,
+    backoff: &mut Duration,
+) -> anyhow::Result<()> {
+    let tcp = TcpStream::connect((TWITCH_IRC_HOST, TWITCH_IRC_PORT)).await?;
+    let connector =
+        tokio_native_tls::TlsConnector::from(tokio_native_tls::native_tls::TlsConnector::new()?);
+    let stream = connector.connect(TWITCH_IRC_HOST, tcp).await?;
+
+    read_chat(stream, config, tx, backoff).await
+}
+
+async fn read_chat<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    config: &TwitchConfig,
+    tx: &mpsc::Sender<CommentEvent>,
+    backoff: &mut Duration,
+) -> anyhow::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    // login, anonymous users can read chat without a token
+    let (pass, nick) = match &config.oauth_token {
+        Some(token) => (
+            format!("oauth:{}", token.trim_start_matches("oauth:")),
+            config.nick.clone(),
+        ),
+        None => ("SCHMOOPIIE".to_string(), "justinfan12345".to_string()),
+    };
+    let login = format!(
+        "CAP REQ :twitch.tv/tags twitch.tv/commands\r\nPASS {pass}\r\nNICK {nick}\r\nJOIN #{}\r\n",
+        config.channel.to_lowercase()
+    );
+    writer.write_all(login.as_bytes()).await?;
+
+    log::info!("Connected to Twitch chat #{}", config.channel);
+
+    while let Some(line) = lines.next_line().await? {
+        let message = IrcMessage::parse(&line);
+        match message.command {
+            "PING" => {
+                writer
+                    .write_all(format!("PONG :{}\r\n", message.trailing).as_bytes())
+                    .await?;
+            }
+            "RECONNECT" => {
+                log::info!("Twitch requested a reconnect");
+                return Ok(());
+            }
+            "NOTICE" if message.trailing.contains("authentication failed") => {
+                anyhow::bail!("Twitch authentication failed");
+            }
+            "001" => {
+                // logged in successfully
+                *backoff = MIN_BACKOFF;
+            }
+            "PRIVMSG" => {
+                if let Some(comment) = message.to_comment() {
+                    tx.send(comment).await?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// A minimal IRCv3 message: `@tags :prefix COMMAND params :trailing`
+#[derive(Debug, PartialEq)]
+struct IrcMessage<'a> {
+    tags: Vec<(&'a str, &'a str)>,
+    prefix: &'a str,
+    command: &'a str,
+    trailing: &'a str,
+}
+
+impl<'a> IrcMessage<'a> {
+    fn parse(line: &'a str) -> Self {
+        let mut rest = line.trim_end();
+
+        let mut tags = Vec::new();
+        if let Some(stripped) = rest.strip_prefix('@') {
+            let (raw_tags, remaining) = stripped.split_once(' ').unwrap_or((stripped, ""));
+            tags = raw_tags
+                .split(';')
+                .filter_map(|tag| tag.split_once('='))
+                .collect();
+            rest = remaining;
+        }
+
+        let mut prefix = "";
+        if let Some(stripped) = rest.strip_prefix(':') {
+            (prefix, rest) = stripped.split_once(' ').unwrap_or((stripped, ""));
+        }
+
+        let (head, trailing) = rest.split_once(" :").unwrap_or((rest, ""));
+        let command = head.split(' ').next().unwrap_or_default();
+
+        Self {
+            tags,
+            prefix,
+            command,
+            trailing,
+        }
+    }
+
+    fn tag(&self, name: &str) -> Option<&'a str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+            .filter(|value| !value.is_empty())
+    }
+
+    fn to_comment(&self) -> Option<CommentEvent> {
+        let login = self.prefix.split('!').next().filter(|s| !s.is_empty())?;
+        let user = self.tag("display-name").unwrap_or(login);
+
+        Some(CommentEvent::new(
+            user,
+            self.trailing,
+            String::new(),
+            Priority::Normal,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::twitch::IrcMessage;
+
+    #[test]
+    fn parse_privmsg() {
+        let line = "@badge-info=;color=#FF0000;display-name=Viewer_1;mod=0 :viewer_1!viewer_1@viewer_1.tmi.twitch.tv PRIVMSG #murasame :hello there: world\r\n";
+        let message = IrcMessage::parse(line);
+
+        assert_eq!(message.command, "PRIVMSG");
+        assert_eq!(message.trailing, "hello there: world");
+
+        let comment = message.to_comment().unwrap();
+        assert_eq!(comment.user, "Viewer_1");
+        assert_eq!(comment.text, "hello there: world");
+    }
+
+    #[test]
+    fn parse_ping() {
+        let message = IrcMessage::parse("PING :tmi.twitch.tv");
+
+        assert_eq!(message.command, "PING");
+        assert_eq!(message.trailing, "tmi.twitch.tv");
+        assert!(message.to_comment().is_none());
+    }
+}
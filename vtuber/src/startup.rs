@@ -1,137 +1,344 @@
-use std::{borrow::Cow, net::TcpListener, sync::Arc};
+use std::{fs, net::TcpListener, path::PathBuf, sync::Arc, time::Duration};
 
-use ai::{SystemPromptRenderer, gemini::Gemini};
-use tokio::sync::{broadcast, mpsc};
-use tts_client::TtsClient;
+use tokio::{
+    sync::{broadcast, mpsc},
+    task::JoinHandle,
+};
 
 use crate::{
     bus::{Bus, FrontendHandle, InEvent, UiEvent},
-    config::AppConfig,
-    gui,
-    server::create_server,
+    companion::Companion,
+    config::{AppConfig, LiveConfig, ServerConfig},
+    crash, gui, headless,
+    hotkey::Hotkeys,
+    instance, notification, obs,
+    pipeline::{self, PipelineServices},
+    plugin::{PluginRegistry, command::CommandPlugin},
+    reload, replay, report,
+    server::{CommentSenders, create_server},
+    shutdown::Shutdown,
+    source::{
+        SourceRegistry,
+        http::{HTTP_SOURCE, HttpSource, WEBSOCKET_SOURCE},
+        scheduler::SchedulerSource,
+        simulation::SimulationSource,
+        twitch::TwitchSource,
+    },
+    storage::{Storage, UsageRecorder},
+    stt,
+    supervisor::Supervisor,
+    viewers::{self, ViewerRegistry},
 };
 
-pub async fn run() -> anyhow::Result<()> {
-    let config = AppConfig::from_env()?;
+/// Why the app could not start, each subsystem reports its own failure.
+#[derive(thiserror::Error, Debug)]
+pub enum StartupError {
+    #[error("Invalid configuration: {0}")]
+    Config(anyhow::Error),
+    #[error("Failed to open the transcript database: {0}")]
+    Storage(#[from] rusqlite::Error),
+    #[error("Failed to load viewer profiles: {0}")]
+    Viewers(#[from] std::io::Error),
+    #[error("Failed to start the HTTP server on {addr}: {source}")]
+    Server { addr: String, source: anyhow::Error },
+    #[error("Failed to start comment sources: {0}")]
+    Sources(anyhow::Error),
+    #[error("Failed to start plugins: {0}")]
+    Plugins(anyhow::Error),
+    #[error("Failed to start speech recognition: {0}")]
+    Stt(anyhow::Error),
+    #[error("Failed to start the AI pipeline: {0}")]
+    Pipeline(anyhow::Error),
+}
+
+/// How long to wait for the pipeline to save its queue when quitting.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Start the app, replaying the comment log at `simulate` instead of going live if given.
+pub async fn run(simulate: Option<PathBuf>) -> anyhow::Result<()> {
+    let mut config = AppConfig::from_env().map_err(StartupError::Config)?;
+    if let Some(log) = simulate {
+        log::info!("Simulating with the comments in {}", log.display());
+        config.simulate(log);
+    }
+    // a simulation may run next to the live app
+    let activations = match config.instance_address {
+        Some(addr) if config.simulation.is_none() => match instance::acquire(addr)? {
+            Some(listener) => Some(listener),
+            None => {
+                log::info!("Already running, raised the window of the running app");
+                return Ok(());
+            }
+        },
+        _ => None,
+    };
+    let config = Arc::new(config);
+    let shutdown = Shutdown::default();
+    shutdown.listen_for_ctrl_c();
+
     // start workers
-    let config = Box::leak(Box::new(config));
-    let frontend_handle = start_orchestrator(config).await?;
+    let (frontend_handle, orchestrator) = start_orchestrator(config.clone(), &shutdown).await?;
+    if let Some(listener) = activations {
+        instance::spawn_activation_listener(listener, frontend_handle.in_tx.clone());
+    }
 
-    // start gui
-    gui::run_gui(frontend_handle.ui_rx, config).map_err(|e| anyhow::anyhow!("Gui error: {e}"))?;
-    Ok(())
-}
+    // hotkeys are optional, keep running without them
+    let hotkey_config = config.hotkeys.as_ref().filter(|_| {
+        let supported = cfg!(target_os = "linux") || config.headless.is_none();
+        if !supported {
+            log::warn!("Hotkeys are delivered through the window on this platform, they are off in headless mode");
+        }
+        supported
+    });
+    let _hotkeys = hotkey_config.and_then(|hotkey_config| {
+        Hotkeys::register(hotkey_config, frontend_handle.in_tx.clone())
+            .inspect_err(|e| log::error!("Failed to register hotkeys: {e}"))
+            .ok()
+    });
 
-async fn start_orchestrator(cfg: &'static AppConfig) -> anyhow::Result<FrontendHandle> {
-    let bus = Bus::new(1024);
+    let result = match &config.headless {
+        Some(headless_config) => headless::run_headless(
+            frontend_handle.ui_rx,
+            frontend_handle.ui_tx,
+            &config,
+            headless_config,
+            shutdown.clone(),
+            frontend_handle.metrics,
+        ),
+        // start gui
+        None => gui::run_gui(frontend_handle, &config, shutdown.clone())
+            .map_err(|e| anyhow::anyhow!("Gui error: {e}")),
+    };
 
-    spawn_http_server(cfg.server.addr.clone(), bus.in_tx.clone()).await?;
-    spawn_ai_pipeline(bus.in_rx, bus.ui_tx.clone(), cfg).await?;
+    // the window may have been closed instead
+    shutdown.trigger();
+    orchestrator.stop().await;
+    result
+}
 
-    Ok(FrontendHandle { ui_rx: bus.ui_rx })
+/// Background tasks that need to be stopped in order, both stop on their own once shutdown is
+/// triggered.
+struct Orchestrator {
+    server: JoinHandle<()>,
+    pipeline: JoinHandle<()>,
+    /// Reported on once the pipeline stopped, see [`report::write_report`]
+    report: Option<(Arc<Storage>, PathBuf)>,
+    /// Saved once more after the pipeline stopped counting messages
+    viewers: Option<Arc<ViewerRegistry>>,
 }
 
-async fn spawn_http_server(addr: String, in_tx: mpsc::Sender<InEvent>) -> anyhow::Result<()> {
-    tokio::spawn(async move {
-        let listener = TcpListener::bind(addr)?;
-        // Create the server
-        let server = create_server(listener, in_tx)?;
+impl Orchestrator {
+    async fn stop(self) {
+        log::info!("Stopping the HTTP server");
+        let _ = self.server.await;
 
-        // Run the server
-        server.await?;
+        if tokio::time::timeout(STOP_TIMEOUT, self.pipeline)
+            .await
+            .is_err()
+        {
+            log::warn!("The AI pipeline did not stop in time");
+        }
 
-        Ok::<(), anyhow::Error>(())
-    });
-    Ok(())
+        if let Some(viewers) = self.viewers {
+            let saved = tokio::task::spawn_blocking(move || viewers.flush()).await;
+            if let Err(e) = saved.map_err(std::io::Error::other).and_then(|saved| saved) {
+                log::error!("Failed to save viewer profiles: {e}");
+            }
+        }
+
+        if let Some((storage, dir)) = self.report {
+            match storage
+                .blocking(move |storage| report::write_report(storage, &dir))
+                .await
+            {
+                Ok(path) => log::info!("Wrote the session report to {}", path.display()),
+                Err(e) => log::error!("Failed to write the session report: {e}"),
+            }
+        }
+    }
 }
 
-fn init_llm<'a>(config: &'a AppConfig) -> Result<Gemini<'a>, anyhow::Error> {
-    let user_title = config.ai.user_title.to_owned().unwrap_or_else(|| {
-        config
-            .ai
-            .user_title
-            .to_owned()
-            .unwrap_or_else(|| "<unknown>".to_string())
-    });
-    let system_prompt_renderer =
-        SystemPromptRenderer::new(&config.ai.character_name, &user_title, &config.ai.dataset);
-    let system_prompt = system_prompt_renderer.format_with_template(
-        &config.ai.system_instruction_template,
-        Some(
-            config
-                .render
-                .model
-                .layer_descriptions()
-                .iter()
-                .map(|(k, v)| (*k, v.description.to_owned()))
-                .collect(),
-        ),
-    )?;
-    let mut llm = Gemini::new(
-        &config.ai.api_key,
-        &config.ai.model,
-        Some(Cow::Owned(system_prompt)),
+async fn start_orchestrator(
+    cfg: Arc<AppConfig>,
+    shutdown: &Shutdown,
+) -> Result<(FrontendHandle, Orchestrator), StartupError> {
+    let bus = Bus::new(1024);
+    let supervisor = Supervisor::new(bus.ui_tx.clone(), shutdown.clone());
+    let storage = cfg
+        .storage
+        .as_ref()
+        .map(|storage_config| Storage::open(&storage_config.database))
+        .transpose()?
+        .map(Arc::new);
+    if let Some(storage) = &storage {
+        log::info!("Recording transcript as session {}", storage.session_id());
+    }
+    let report = storage.clone().zip(
+        cfg.storage
+            .as_ref()
+            .and_then(|storage_config| storage_config.report_dir.clone()),
     );
-    llm.set_thinking(config.ai.thinking);
-    llm.set_json_schema::<Vec<ai::AIResponseModel>>();
-    Ok(llm)
+    let viewers = cfg
+        .viewers
+        .as_ref()
+        .map(|viewers_config| ViewerRegistry::load(viewers_config.path.clone()))
+        .transpose()?
+        .map(Arc::new);
+    if let Some(viewers) = &viewers {
+        viewers::spawn_saver(viewers.clone(), shutdown.clone());
+    }
+    let orchestrated_viewers = viewers.clone();
+    let plugins = start_plugins(&cfg).map_err(StartupError::Plugins)?;
+    plugins.spawn_event_listener(bus.ui_tx.subscribe());
+    crash::spawn_event_recorder(bus.ui_tx.subscribe());
+    let services = PipelineServices {
+        usage: UsageRecorder::new(storage.clone(), cfg.ai.prices),
+        storage,
+        viewers,
+        metrics: Arc::default(),
+        polls: Arc::default(),
+        plugins,
+        comments: Arc::default(),
+        companion: Arc::new(Companion::new(cfg.pomodoro.clone())),
+    };
+
+    let (http_source, http) = HttpSource::channel(HTTP_SOURCE);
+    let (websocket_source, websocket) = HttpSource::channel(WEBSOCKET_SOURCE);
+    let server = spawn_http_server(
+        cfg.server.clone(),
+        CommentSenders { http, websocket },
+        bus.in_tx.clone(),
+        bus.ui_tx.clone(),
+        services.clone(),
+        shutdown.clone(),
+        &supervisor,
+    )
+    .map_err(|source| StartupError::Server {
+        addr: cfg.server.addr.clone(),
+        source,
+    })?;
+    spawn_comment_sources(&cfg, [http_source, websocket_source], bus.in_tx.clone())
+        .map_err(StartupError::Sources)?;
+    if let Some(stt_config) = &cfg.stt {
+        stt::spawn_stt(stt_config.clone(), bus.in_tx.clone(), bus.ui_tx.subscribe())
+            .map_err(StartupError::Stt)?;
+    }
+    if let Some(battery_config) = &cfg.battery {
+        notification::spawn_battery_watcher(battery_config.clone(), bus.in_tx.clone());
+    }
+    if let Some(obs_config) = &cfg.obs {
+        tokio::spawn(obs::run_obs_bridge(
+            obs_config.clone(),
+            bus.ui_tx.subscribe(),
+        ));
+    }
+    let live_rx =
+        reload::spawn_config_watcher(LiveConfig::from_app_config(&cfg), bus.ui_tx.clone());
+    let metrics = services.metrics.clone();
+    let pipeline = pipeline::spawn_ai_pipeline(
+        bus.in_rx,
+        bus.ui_tx.clone(),
+        live_rx,
+        services,
+        cfg,
+        shutdown.clone(),
+        &supervisor,
+    )
+    .map_err(StartupError::Pipeline)?;
+
+    Ok((
+        FrontendHandle {
+            in_tx: bus.in_tx,
+            ui_tx: bus.ui_tx,
+            ui_rx: bus.ui_rx,
+            metrics,
+        },
+        Orchestrator {
+            server,
+            pipeline,
+            report,
+            viewers: orchestrated_viewers,
+        },
+    ))
 }
 
-async fn spawn_ai_pipeline(
-    mut in_rx: mpsc::Receiver<InEvent>,
+/// Bind right away so a taken port fails the startup, then serve in the background.
+///
+/// The server binds again when it is restarted after a failure.
+fn spawn_http_server(
+    config: ServerConfig,
+    comment_senders: CommentSenders,
+    in_tx: mpsc::Sender<InEvent>,
     ui_tx: broadcast::Sender<UiEvent>,
-    app_config: &'static AppConfig,
-) -> anyhow::Result<()> {
-    let model = Arc::new(app_config.render.model.clone());
-    let mut llm = init_llm(app_config)?;
-    let tts_client = TtsClient::new(app_config.tts.base_url.as_str());
-    tokio::spawn(async move {
-        while let Some(evt) = in_rx.recv().await {
-            match evt {
-                InEvent::Comment(comment_event) => {
-                    log::info!(
-                        "Received comment from user {}: {}",
-                        comment_event.user,
-                        comment_event.text
-                    );
-                    // send events
-                    let _ = ui_tx.send(UiEvent::NewComment(comment_event.clone()));
-                    let _ = ui_tx.send(UiEvent::AiThinking);
-
-                    // Generate response
-                    let responses =
-                        match ai::chat(&comment_event.text, &mut llm, Some(model.clone())).await {
-                            Ok(r) => r,
-                            Err(err) => {
-                                let _ = ui_tx.send(UiEvent::Error(err.to_string()));
-                                continue;
-                            }
-                        };
-
-                    log::info!("AI responsed with {} messages", responses.len());
-
-                    for res in responses {
-                        // Generate voice
-                        log::info!("Generate voice for text {}", &res.japanese_response);
-                        match tts_client.generate(&res.japanese_response).await {
-                            Ok(tts_out) => {
-                                log::info!("Send reply to frontend");
-                                let _ = ui_tx.send(UiEvent::AiReply {
-                                    text: res.response,
-                                    layers: res.layers,
-                                    voice: tts_out,
-                                });
-                            }
-                            Err(e) => {
-                                log::error!("Failed to invoke tts: {e}");
-                                let _ = ui_tx.send(UiEvent::Error(e.to_string()));
-                            }
-                        }
-                    }
-                }
-            }
+    services: PipelineServices,
+    shutdown: Shutdown,
+    supervisor: &Supervisor,
+) -> anyhow::Result<JoinHandle<()>> {
+    let mut listener = Some(TcpListener::bind(&config.addr)?);
+    Ok(supervisor.supervise("HTTP server", move || {
+        let listener = listener
+            .take()
+            .map_or_else(|| TcpListener::bind(&config.addr), Ok);
+        let (config, comment_senders, in_tx, ui_tx, services, shutdown) = (
+            config.clone(),
+            comment_senders.clone(),
+            in_tx.clone(),
+            ui_tx.clone(),
+            services.clone(),
+            shutdown.clone(),
+        );
+        async move {
+            // Create the server
+            let server =
+                create_server(listener?, &config, comment_senders, in_tx, ui_tx, services)?;
+            let handle = server.handle();
+            let stop = tokio::spawn(async move {
+                shutdown.triggered().await;
+                handle.stop(true).await;
+            });
+
+            // Run the server
+            let result = server.await;
+            stop.abort();
+            Ok(result?)
         }
-    });
+    }))
+}
 
+fn start_plugins(cfg: &AppConfig) -> anyhow::Result<PluginRegistry> {
+    let mut registry = PluginRegistry::default();
+    for plugin_config in &cfg.plugins {
+        log::info!("Starting plugin {}", plugin_config.name);
+        registry.register(CommandPlugin::spawn(plugin_config)?);
+    }
+    Ok(registry)
+}
+
+fn spawn_comment_sources(
+    cfg: &AppConfig,
+    http_sources: impl IntoIterator<Item = HttpSource>,
+    in_tx: mpsc::Sender<InEvent>,
+) -> anyhow::Result<()> {
+    let mut registry = SourceRegistry::default();
+    for source in http_sources {
+        registry.register(source);
+    }
+    if let Some(twitch_config) = &cfg.twitch {
+        registry.register(TwitchSource::new(twitch_config.clone()));
+    }
+    if !cfg.schedule.is_empty() {
+        registry.register(SchedulerSource::new(cfg.schedule.clone())?);
+    }
+    if let Some(log) = &cfg.simulation {
+        let content = fs::read_to_string(log)?;
+        if replay::is_recording(&content) {
+            let events = replay::parse_recording(&content)?;
+            log::info!("Replaying {} events from {}", events.len(), log.display());
+            replay::spawn_replay(events, in_tx.clone());
+        } else {
+            registry.register(SimulationSource::load(log)?);
+        }
+    }
+    registry.spawn_all(in_tx);
     Ok(())
 }
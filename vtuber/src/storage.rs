@@ -0,0 +1,423 @@
+use std::{
+    fmt::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ai::gemini::Usage;
+use config::TokenPrices;
+use rusqlite::{Connection, params};
+
+use crate::bus::CommentEvent;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS comments (
+    session_id INTEGER NOT NULL REFERENCES sessions(id),
+    comment_id INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    user TEXT NOT NULL,
+    source TEXT NOT NULL,
+    text TEXT NOT NULL,
+    PRIMARY KEY (session_id, comment_id)
+);
+CREATE TABLE IF NOT EXISTS responses (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id INTEGER NOT NULL REFERENCES sessions(id),
+    comment_id INTEGER,
+    created_at INTEGER NOT NULL,
+    response TEXT NOT NULL,
+    japanese_response TEXT NOT NULL,
+    layers TEXT NOT NULL,
+    tts_ms INTEGER NOT NULL,
+    audio_ms INTEGER
+);
+CREATE TABLE IF NOT EXISTS usage (
+    session_id INTEGER NOT NULL REFERENCES sessions(id),
+    created_at INTEGER NOT NULL,
+    prompt_tokens INTEGER NOT NULL,
+    output_tokens INTEGER NOT NULL,
+    cost REAL
+);
+";
+
+/// Records comments and responses of the running session into SQLite.
+pub struct Storage {
+    conn: Mutex<Connection>,
+    session_id: i64,
+}
+
+#[derive(serde::Serialize, Debug, Clone, schemars::JsonSchema)]
+pub struct SessionSummary {
+    pub id: i64,
+    pub started_at: i64,
+    pub comments: i64,
+    pub responses: i64,
+}
+
+#[derive(serde::Serialize, Debug, Clone, schemars::JsonSchema)]
+pub struct TranscriptEntry {
+    /// Unix timestamp in milliseconds
+    pub time: i64,
+    #[serde(flatten)]
+    pub kind: TranscriptEntryKind,
+}
+
+#[derive(serde::Serialize, Debug, Clone, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TranscriptEntryKind {
+    Comment {
+        comment_id: i64,
+        user: String,
+        source: String,
+        text: String,
+    },
+    Response {
+        comment_id: Option<i64>,
+        response: String,
+        japanese_response: String,
+        layers: Vec<String>,
+        tts_ms: i64,
+        audio_ms: Option<i64>,
+    },
+}
+
+/// Tokens the AI was billed for in a session.
+#[derive(serde::Serialize, Debug, Clone, Default, PartialEq, schemars::JsonSchema)]
+pub struct SessionUsage {
+    pub prompt_tokens: i64,
+    pub output_tokens: i64,
+    /// In USD, `None` when no prices were configured
+    pub cost: Option<f64>,
+}
+
+#[derive(serde::Serialize, Debug, Clone, schemars::JsonSchema)]
+pub struct Transcript {
+    pub session: SessionSummary,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+/// A spoken line, as stored in the transcript.
+pub struct ResponseRecord<'a> {
+    pub comment_id: Option<u64>,
+    pub response: &'a str,
+    pub japanese_response: &'a str,
+    pub layers: &'a [String],
+    pub tts_latency: Duration,
+    pub audio_duration: Option<Duration>,
+}
+
+impl Storage {
+    /// Open (or create) the database and start a new session.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        conn.execute(
+            "INSERT INTO sessions (started_at) VALUES (?1)",
+            params![now_millis()],
+        )?;
+        let session_id = conn.last_insert_rowid();
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            session_id,
+        })
+    }
+
+    pub fn session_id(&self) -> i64 {
+        self.session_id
+    }
+
+    /// Run `query` on a blocking thread, SQLite would hold up the async workers.
+    pub async fn blocking<T, F>(self: &Arc<Self>, query: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&Storage) -> T + Send + 'static,
+    {
+        let storage = self.clone();
+        match tokio::task::spawn_blocking(move || query(&storage)).await {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    pub fn record_comment(&self, comment: &CommentEvent) -> rusqlite::Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR IGNORE INTO comments (session_id, comment_id, created_at, user, source, text)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                self.session_id,
+                comment.id as i64,
+                now_millis(),
+                comment.user,
+                comment.source,
+                comment.text
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_response(&self, record: &ResponseRecord) -> rusqlite::Result<()> {
+        let layers = serde_json::to_string(record.layers).expect("layers are serializable");
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO responses
+             (session_id, comment_id, created_at, response, japanese_response, layers, tts_ms, audio_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                self.session_id,
+                record.comment_id.map(|id| id as i64),
+                now_millis(),
+                record.response,
+                record.japanese_response,
+                layers,
+                record.tts_latency.as_millis() as i64,
+                record.audio_duration.map(|d| d.as_millis() as i64),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_usage(&self, usage: Usage, cost: Option<f64>) -> rusqlite::Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO usage (session_id, created_at, prompt_tokens, output_tokens, cost)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                self.session_id,
+                now_millis(),
+                usage.prompt_tokens as i64,
+                usage.output_tokens as i64,
+                cost
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn usage(&self, session_id: i64) -> rusqlite::Result<SessionUsage> {
+        self.conn.lock().unwrap().query_row(
+            "SELECT COALESCE(SUM(prompt_tokens), 0), COALESCE(SUM(output_tokens), 0), SUM(cost)
+             FROM usage WHERE session_id = ?1",
+            params![session_id],
+            |row| {
+                Ok(SessionUsage {
+                    prompt_tokens: row.get(0)?,
+                    output_tokens: row.get(1)?,
+                    cost: row.get(2)?,
+                })
+            },
+        )
+    }
+
+    pub fn sessions(&self) -> rusqlite::Result<Vec<SessionSummary>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT s.id, s.started_at,
+                (SELECT COUNT(*) FROM comments c WHERE c.session_id = s.id),
+                (SELECT COUNT(*) FROM responses r WHERE r.session_id = s.id)
+             FROM sessions s ORDER BY s.id DESC",
+        )?;
+        stmt.query_map([], |row| {
+            Ok(SessionSummary {
+                id: row.get(0)?,
+                started_at: row.get(1)?,
+                comments: row.get(2)?,
+                responses: row.get(3)?,
+            })
+        })?
+        .collect()
+    }
+
+    pub fn transcript(&self, session_id: i64) -> rusqlite::Result<Option<Transcript>> {
+        let Some(session) = self
+            .sessions()?
+            .into_iter()
+            .find(|session| session.id == session_id)
+        else {
+            return Ok(None);
+        };
+
+        let conn = self.conn.lock().unwrap();
+        let mut entries = Vec::new();
+
+        let mut stmt = conn.prepare(
+            "SELECT created_at, comment_id, user, source, text FROM comments WHERE session_id = ?1",
+        )?;
+        for entry in stmt.query_map(params![session_id], |row| {
+            Ok(TranscriptEntry {
+                time: row.get(0)?,
+                kind: TranscriptEntryKind::Comment {
+                    comment_id: row.get(1)?,
+                    user: row.get(2)?,
+                    source: row.get(3)?,
+                    text: row.get(4)?,
+                },
+            })
+        })? {
+            entries.push(entry?);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT created_at, comment_id, response, japanese_response, layers, tts_ms, audio_ms
+             FROM responses WHERE session_id = ?1",
+        )?;
+        for entry in stmt.query_map(params![session_id], |row| {
+            let layers: String = row.get(4)?;
+            Ok(TranscriptEntry {
+                time: row.get(0)?,
+                kind: TranscriptEntryKind::Response {
+                    comment_id: row.get(1)?,
+                    response: row.get(2)?,
+                    japanese_response: row.get(3)?,
+                    layers: serde_json::from_str(&layers).unwrap_or_default(),
+                    tts_ms: row.get(5)?,
+                    audio_ms: row.get(6)?,
+                },
+            })
+        })? {
+            entries.push(entry?);
+        }
+
+        // stable: comments were inserted first, so they stay before their answers
+        entries.sort_by_key(|entry| entry.time);
+
+        Ok(Some(Transcript { session, entries }))
+    }
+}
+
+impl Transcript {
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Session {}\n", self.session.id);
+        for entry in &self.entries {
+            let offset = format_offset(entry.time - self.session.started_at);
+            match &entry.kind {
+                TranscriptEntryKind::Comment {
+                    user, source, text, ..
+                } => {
+                    let _ = writeln!(out, "- `{offset}` **{user}** ({source}): {text}");
+                }
+                TranscriptEntryKind::Response {
+                    response,
+                    japanese_response,
+                    layers,
+                    ..
+                } => {
+                    let _ = writeln!(out, "- `{offset}` > {response}");
+                    let _ = writeln!(out, "  - ja: {japanese_response}");
+                    if !layers.is_empty() {
+                        let _ = writeln!(out, "  - layers: {}", layers.join(", "));
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+pub fn format_offset(millis: i64) -> String {
+    let secs = millis.max(0) / 1000;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Stores the tokens of every Gemini request for the session report, priced if prices are set.
+///
+/// Does nothing without transcript storage.
+#[derive(Clone, Default)]
+pub struct UsageRecorder {
+    storage: Option<Arc<Storage>>,
+    prices: Option<TokenPrices>,
+}
+
+impl UsageRecorder {
+    pub fn new(storage: Option<Arc<Storage>>, prices: Option<TokenPrices>) -> Self {
+        Self { storage, prices }
+    }
+
+    pub async fn record(&self, usage: Option<Usage>) {
+        if let Some(storage) = &self.storage
+            && let Some(usage) = usage
+        {
+            let cost = self
+                .prices
+                .map(|prices| prices.cost(usage.prompt_tokens, usage.output_tokens));
+            if let Err(e) = storage
+                .blocking(move |storage| storage.record_usage(usage, cost))
+                .await
+            {
+                log::error!("Failed to record token usage: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ai::gemini::Usage;
+
+    use crate::{
+        bus::{CommentEvent, Priority},
+        storage::{ResponseRecord, SessionUsage, Storage},
+    };
+
+    #[test]
+    fn record_transcript() {
+        let storage = Storage::open(":memory:").unwrap();
+        let comment = CommentEvent::new("viewer", "hello", "test", Priority::Normal);
+        storage.record_comment(&comment).unwrap();
+        storage
+            .record_response(&ResponseRecord {
+                comment_id: Some(comment.id),
+                response: "hi",
+                japanese_response: "やあ",
+                layers: &["smile.png".to_string()],
+                tts_latency: Duration::from_millis(800),
+                audio_duration: None,
+            })
+            .unwrap();
+
+        let transcript = storage.transcript(storage.session_id()).unwrap().unwrap();
+        assert_eq!(transcript.session.comments, 1);
+        assert_eq!(transcript.session.responses, 1);
+        assert_eq!(transcript.entries.len(), 2);
+
+        let markdown = transcript.to_markdown();
+        assert!(markdown.contains("**viewer** (test): hello"));
+        assert!(markdown.contains("layers: smile.png"));
+    }
+
+    #[test]
+    fn sum_the_usage() {
+        let storage = Storage::open(":memory:").unwrap();
+        assert_eq!(
+            storage.usage(storage.session_id()).unwrap(),
+            SessionUsage::default()
+        );
+
+        let usage = Usage {
+            prompt_tokens: 1000,
+            output_tokens: 200,
+        };
+        storage.record_usage(usage, Some(0.5)).unwrap();
+        storage.record_usage(usage, Some(0.25)).unwrap();
+        assert_eq!(
+            storage.usage(storage.session_id()).unwrap(),
+            SessionUsage {
+                prompt_tokens: 2000,
+                output_tokens: 400,
+                cost: Some(0.75),
+            }
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc as std_mpsc,
+    },
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use bytes::{BufMut, Bytes, BytesMut};
+use reqwest::multipart::{Form, Part};
+use rodio::cpal::{
+    self, FromSample, SampleFormat, SizedSample,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::{
+    bus::{ControlCommand, InEvent, UiEvent},
+    config::{Listen, SttConfig},
+};
+
+/// Source of the comments transcribed from the microphone.
+pub const HOST_SOURCE: &str = "host";
+
+/// Length of the chunks the loudness is measured over.
+const FRAME: Duration = Duration::from_millis(20);
+/// Utterances are cut here even if the host keeps talking.
+const MAX_UTTERANCE: Duration = Duration::from_secs(30);
+/// Shorter sounds are coughs and clicks rather than speech.
+const MIN_UTTERANCE: Duration = Duration::from_millis(300);
+/// After the wake word alone, the next utterance this soon is meant for the character.
+const FOLLOW_UP: Duration = Duration::from_secs(8);
+
+/// Splits the microphone signal into utterances separated by pauses.
+pub struct Segmenter {
+    frame_len: usize,
+    pause_frames: usize,
+    min_len: usize,
+    max_len: usize,
+    threshold: f32,
+    frame: Vec<f32>,
+    utterance: Vec<f32>,
+    silent_frames: usize,
+}
+
+impl Segmenter {
+    pub fn new(sample_rate: u32, threshold: f32, pause: Duration) -> Self {
+        let samples = |d: Duration| (d.as_secs_f64() * sample_rate as f64) as usize;
+        let frame_len = samples(FRAME).max(1);
+        Self {
+            frame_len,
+            pause_frames: samples(pause).div_ceil(frame_len).max(1),
+            min_len: samples(MIN_UTTERANCE),
+            max_len: samples(MAX_UTTERANCE),
+            threshold,
+            frame: Vec::with_capacity(frame_len),
+            utterance: Vec::new(),
+            silent_frames: 0,
+        }
+    }
+
+    /// Feed mono samples, returning the utterances completed by them.
+    pub fn push(&mut self, samples: &[f32]) -> Vec<Vec<f32>> {
+        let mut done = Vec::new();
+        for &sample in samples {
+            self.frame.push(sample);
+            if self.frame.len() < self.frame_len {
+                continue;
+            }
+
+            let rms =
+                (self.frame.iter().map(|s| s * s).sum::<f32>() / self.frame.len() as f32).sqrt();
+            let speaking = rms >= self.threshold;
+            if speaking || !self.utterance.is_empty() {
+                self.utterance.append(&mut self.frame);
+            }
+            self.frame.clear();
+            self.silent_frames = if speaking { 0 } else { self.silent_frames + 1 };
+
+            let paused = !self.utterance.is_empty() && self.silent_frames >= self.pause_frames;
+            if paused || self.utterance.len() >= self.max_len {
+                let utterance = std::mem::take(&mut self.utterance);
+                if utterance.len() >= self.min_len {
+                    done.push(utterance);
+                }
+            }
+        }
+        done
+    }
+}
+
+/// Records the microphone while the push-to-talk key is held.
+pub struct PushToTalk {
+    min_len: usize,
+    max_len: usize,
+    recording: Vec<f32>,
+}
+
+impl PushToTalk {
+    pub fn new(sample_rate: u32) -> Self {
+        let samples = |d: Duration| (d.as_secs_f64() * sample_rate as f64) as usize;
+        Self {
+            min_len: samples(MIN_UTTERANCE),
+            max_len: samples(MAX_UTTERANCE),
+            recording: Vec::new(),
+        }
+    }
+
+    /// Feed mono samples, returning the recording once the key is let go.
+    pub fn push(&mut self, samples: &[f32], held: bool) -> Option<Vec<f32>> {
+        if held {
+            // the rest is cut off, like a long utterance
+            let room = self.max_len - self.recording.len();
+            self.recording
+                .extend_from_slice(&samples[..samples.len().min(room)]);
+            return None;
+        }
+        let recording = std::mem::take(&mut self.recording);
+        (recording.len() >= self.min_len).then_some(recording)
+    }
+}
+
+/// What was said after the wake word, `None` if it wasn't said. The wake word may come after a
+/// greeting, e.g. "hey Murasame, ...".
+fn after_wake_word<'a>(text: &'a str, wake_word: &str) -> Option<&'a str> {
+    let lower = text.to_lowercase();
+    let start = lower.find(&wake_word.to_lowercase())?;
+    // lowercasing may change lengths, fall back to the whole text then
+    let rest = if lower.len() == text.len() {
+        &text[start + wake_word.len()..]
+    } else {
+        text
+    };
+    Some(rest.trim_start_matches(|c: char| {
+        c.is_whitespace() || c.is_ascii_punctuation() || "，。、！？～…".contains(c)
+    }))
+}
+
+/// Encode mono samples as a 16-bit WAV file.
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Bytes {
+    let data_len = samples.len() as u32 * 2;
+    let mut buf = BytesMut::with_capacity(44 + data_len as usize);
+    // RIFF header
+    buf.put_slice(b"RIFF");
+    buf.put_u32_le(36 + data_len);
+    buf.put_slice(b"WAVE");
+    // fmt chunk: PCM, mono, 16 bits
+    buf.put_slice(b"fmt ");
+    buf.put_u32_le(16);
+    buf.put_u16_le(1);
+    buf.put_u16_le(1);
+    buf.put_u32_le(sample_rate);
+    buf.put_u32_le(sample_rate * 2);
+    buf.put_u16_le(2);
+    buf.put_u16_le(16);
+    // data chunk
+    buf.put_slice(b"data");
+    buf.put_u32_le(data_len);
+    for sample in samples {
+        buf.put_i16_le((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16);
+    }
+    buf.freeze()
+}
+
+#[derive(serde::Deserialize)]
+struct Transcription {
+    text: String,
+}
+
+/// Client of a Whisper server, either whisper.cpp or an OpenAI compatible API.
+struct Transcriber {
+    client: reqwest::Client,
+    config: SttConfig,
+}
+
+impl Transcriber {
+    async fn transcribe(&self, wav: Bytes) -> anyhow::Result<String> {
+        let file = Part::stream(wav)
+            .file_name("speech.wav")
+            .mime_str("audio/wav")?;
+        let mut form = Form::new()
+            .part("file", file)
+            .text("response_format", "json");
+        if let Some(model) = &self.config.model {
+            form = form.text("model", model.clone());
+        }
+        if let Some(language) = &self.config.language {
+            form = form.text("language", language.clone());
+        }
+
+        let mut request = self.client.post(&self.config.url).multipart(form);
+        if let Some(api_key) = &self.config.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let body = request.send().await?.error_for_status()?.bytes().await?;
+        let transcription: Transcription = serde_json::from_slice(&body)?;
+        Ok(transcription.text.trim().to_string())
+    }
+}
+
+/// Listen to the microphone and send what the host says as [`InEvent::HostSpeech`].
+///
+/// With push-to-talk only while [`ControlCommand::StartListening`] is held, with a wake word only
+/// what is said to the character. Fails if the input device can't be opened.
+pub fn spawn_stt(
+    config: SttConfig,
+    in_tx: mpsc::Sender<InEvent>,
+    mut ui_rx: broadcast::Receiver<UiEvent>,
+) -> anyhow::Result<()> {
+    let (wav_tx, mut wav_rx) = mpsc::channel::<Bytes>(8);
+    let (ready_tx, ready_rx) = std_mpsc::channel();
+
+    let held = Arc::new(AtomicBool::new(false));
+    if config.listen == Listen::PushToTalk {
+        let held = held.clone();
+        tokio::spawn(async move {
+            loop {
+                match ui_rx.recv().await {
+                    Ok(UiEvent::Control(ControlCommand::StartListening)) => {
+                        held.store(true, Ordering::Relaxed)
+                    }
+                    Ok(UiEvent::Control(ControlCommand::StopListening)) => {
+                        held.store(false, Ordering::Relaxed)
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    // the cpal stream is not Send, it lives on its own thread
+    let capture_config = config.clone();
+    std::thread::spawn(move || {
+        // the stream records as long as it is alive, which is until the app exits
+        let _stream = match open_input(&capture_config, wav_tx, held) {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        let _ = ready_tx.send(Ok(()));
+        loop {
+            std::thread::park();
+        }
+    });
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow!("Microphone thread exited"))??;
+
+    let transcriber = Transcriber {
+        client: reqwest::Client::new(),
+        config,
+    };
+    tokio::spawn(async move {
+        // the wake word was said alone, the next utterance is taken as it is
+        let mut awake = false;
+        loop {
+            let wav = if awake {
+                match tokio::time::timeout(FOLLOW_UP, wav_rx.recv()).await {
+                    Ok(wav) => wav,
+                    Err(_) => {
+                        awake = false;
+                        let _ = in_tx
+                            .send(InEvent::Control(ControlCommand::StopListening))
+                            .await;
+                        continue;
+                    }
+                }
+            } else {
+                wav_rx.recv().await
+            };
+            let Some(wav) = wav else {
+                break;
+            };
+            let text = match transcriber.transcribe(wav).await {
+                Ok(text) => text,
+                Err(e) => {
+                    log::error!("Failed to transcribe speech: {e}");
+                    continue;
+                }
+            };
+            let text = match &transcriber.config.listen {
+                Listen::WakeWord(_) if awake => {
+                    awake = false;
+                    let _ = in_tx
+                        .send(InEvent::Control(ControlCommand::StopListening))
+                        .await;
+                    text
+                }
+                Listen::WakeWord(wake_word) => match after_wake_word(&text, wake_word) {
+                    Some("") => {
+                        log::info!("Heard the wake word, listening");
+                        awake = true;
+                        let _ = in_tx
+                            .send(InEvent::Control(ControlCommand::StartListening))
+                            .await;
+                        continue;
+                    }
+                    Some(rest) => rest.to_string(),
+                    None => continue,
+                },
+                Listen::Always | Listen::PushToTalk => text,
+            };
+            if text.is_empty() {
+                continue;
+            }
+            let evt = InEvent::HostSpeech {
+                speaker: transcriber.config.host_name.clone(),
+                text,
+            };
+            if in_tx.send(evt).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn open_input(
+    config: &SttConfig,
+    wav_tx: mpsc::Sender<Bytes>,
+    held: Arc<AtomicBool>,
+) -> anyhow::Result<cpal::Stream> {
+    let host = cpal::default_host();
+    let device = match &config.device {
+        Some(name) => host
+            .input_devices()?
+            .find(|device| device.name().is_ok_and(|n| &n == name))
+            .ok_or_else(|| anyhow!("Input device {name} not found"))?,
+        None => host
+            .default_input_device()
+            .ok_or_else(|| anyhow!("No default input device"))?,
+    };
+    let supported = device.default_input_config()?;
+    log::info!(
+        "Listening on {} ({} Hz, {} channels)",
+        device.name().unwrap_or_default(),
+        supported.sample_rate().0,
+        supported.channels()
+    );
+
+    let stream = match supported.sample_format() {
+        SampleFormat::F32 => {
+            build_input::<f32>(&device, &supported.config(), config, wav_tx, held)?
+        }
+        SampleFormat::I16 => {
+            build_input::<i16>(&device, &supported.config(), config, wav_tx, held)?
+        }
+        SampleFormat::U16 => {
+            build_input::<u16>(&device, &supported.config(), config, wav_tx, held)?
+        }
+        format => return Err(anyhow!("Unsupported sample format {format}")),
+    };
+    stream.play()?;
+    Ok(stream)
+}
+
+fn build_input<T>(
+    device: &cpal::Device,
+    stream_config: &cpal::StreamConfig,
+    config: &SttConfig,
+    wav_tx: mpsc::Sender<Bytes>,
+    held: Arc<AtomicBool>,
+) -> anyhow::Result<cpal::Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let sample_rate = stream_config.sample_rate.0;
+    let channels = stream_config.channels as usize;
+    let mut segmenter = Segmenter::new(sample_rate, config.threshold, config.pause);
+    let mut push_to_talk = PushToTalk::new(sample_rate);
+    let listen = config.listen.clone();
+    let mut mono = Vec::new();
+
+    let stream = device.build_input_stream(
+        stream_config,
+        move |data: &[T], _| {
+            // downmix to mono
+            mono.clear();
+            mono.extend(data.chunks(channels).map(|frame| {
+                frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / channels as f32
+            }));
+            let utterances = match listen {
+                Listen::PushToTalk => push_to_talk
+                    .push(&mono, held.load(Ordering::Relaxed))
+                    .into_iter()
+                    .collect(),
+                Listen::Always | Listen::WakeWord(_) => segmenter.push(&mono),
+            };
+            for utterance in utterances {
+                // drop speech rather than block the audio callback
+                if wav_tx
+                    .try_send(encode_wav(&utterance, sample_rate))
+                    .is_err()
+                {
+                    log::warn!("Transcription is falling behind, dropped an utterance");
+                }
+            }
+        },
+        |e| log::error!("Microphone error: {e}"),
+        None,
+    )?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::stt::{PushToTalk, Segmenter, after_wake_word};
+
+    #[test]
+    fn split_on_pause() {
+        // 1 kHz keeps the numbers small: 20 samples per frame, 100 for the pause
+        let mut segmenter = Segmenter::new(1000, 0.1, Duration::from_millis(100));
+        let speech = vec![0.5; 500];
+        let silence = vec![0.0; 200];
+
+        assert!(segmenter.push(&speech).is_empty());
+        let done = segmenter.push(&silence);
+        assert_eq!(done.len(), 1);
+        // the speech plus the pause that ended it
+        assert_eq!(done[0].len(), 600);
+
+        // a click is too short to be speech
+        assert!(segmenter.push(&[0.5; 40]).is_empty());
+        assert!(segmenter.push(&silence).is_empty());
+    }
+
+    #[test]
+    fn record_while_held() {
+        let mut push_to_talk = PushToTalk::new(1000);
+        assert!(push_to_talk.push(&[0.1; 200], true).is_none());
+        assert!(push_to_talk.push(&[0.0; 200], true).is_none());
+        // silence counts as well, only letting go ends the recording
+        assert_eq!(push_to_talk.push(&[0.1; 20], false).unwrap().len(), 400);
+        assert!(push_to_talk.push(&[0.0; 20], false).is_none());
+
+        // a tap is too short
+        push_to_talk.push(&[0.1; 100], true);
+        assert!(push_to_talk.push(&[], false).is_none());
+    }
+
+    #[test]
+    fn strip_the_wake_word() {
+        assert_eq!(
+            after_wake_word("Hey Murasame, what time is it?", "murasame"),
+            Some("what time is it?")
+        );
+        assert_eq!(
+            after_wake_word("丛雨，今天吃什么", "丛雨"),
+            Some("今天吃什么")
+        );
+        assert_eq!(after_wake_word("丛雨。", "丛雨"), Some(""));
+        assert_eq!(after_wake_word("今天吃什么", "丛雨"), None);
+    }
+}
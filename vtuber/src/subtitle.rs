@@ -0,0 +1,107 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+}
+
+impl SubtitleFormat {
+    /// Guess the format from the file extension, falling back to SRT.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("vtt") => Self::WebVtt,
+            _ => Self::Srt,
+        }
+    }
+
+    fn timestamp(&self, time: Duration) -> String {
+        let millis = time.as_millis();
+        let (h, m, s, ms) = (
+            millis / 3_600_000,
+            millis / 60_000 % 60,
+            millis / 1000 % 60,
+            millis % 1000,
+        );
+        match self {
+            Self::Srt => format!("{h:02}:{m:02}:{s:02},{ms:03}"),
+            Self::WebVtt => format!("{h:02}:{m:02}:{s:02}.{ms:03}"),
+        }
+    }
+
+    fn cue(&self, index: usize, start: Duration, end: Duration, text: &str) -> String {
+        format!(
+            "{index}\n{} --> {}\n{}\n\n",
+            self.timestamp(start),
+            self.timestamp(end),
+            text.trim()
+        )
+    }
+}
+
+/// Writes one subtitle cue per spoken line, timed relative to the session start.
+///
+/// Cues are appended and flushed immediately, so the file stays usable even if the
+/// process dies mid-stream.
+pub struct SubtitleWriter {
+    file: File,
+    format: SubtitleFormat,
+    session_start: Instant,
+    next_index: usize,
+}
+
+impl SubtitleWriter {
+    pub fn create(path: &Path, format: SubtitleFormat) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        if format == SubtitleFormat::WebVtt {
+            file.write_all(b"WEBVTT\n\n")?;
+        }
+
+        Ok(Self {
+            file,
+            format,
+            session_start: Instant::now(),
+            next_index: 1,
+        })
+    }
+
+    /// Record a line which started playing at `started_at` and lasts `duration`.
+    pub fn push(&mut self, text: &str, started_at: Instant, duration: Duration) -> io::Result<()> {
+        let start = started_at.saturating_duration_since(self.session_start);
+        let cue = self
+            .format
+            .cue(self.next_index, start, start + duration, text);
+        self.file.write_all(cue.as_bytes())?;
+        self.file.flush()?;
+        self.next_index += 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::subtitle::SubtitleFormat;
+
+    #[test]
+    fn format_cues() {
+        let start = Duration::from_millis(3_723_004);
+        let end = start + Duration::from_millis(1500);
+
+        assert_eq!(
+            SubtitleFormat::Srt.cue(1, start, end, "hello\n"),
+            "1\n01:02:03,004 --> 01:02:04,504\nhello\n\n"
+        );
+        assert_eq!(
+            SubtitleFormat::WebVtt.cue(2, start, end, "hello"),
+            "2\n01:02:03.004 --> 01:02:04.504\nhello\n\n"
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use std::{sync::Arc, time::Duration};
+
+use eframe::egui::{self, Color32, FontFamily, FontId, text::LayoutJob};
+
+/// Font family the subtitles are drawn with, see [`crate::gui::load_system_fonts`].
+pub const SUBTITLE_FONT: &str = "subtitle";
+
+/// How the spoken line is drawn over the characters, loaded from `VTUBER_GUI_SUBTITLE_STYLE`.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct SubtitleStyle {
+    /// A system font, the default one is used if it isn't installed
+    pub font_family: Option<String>,
+    pub font_size: f32,
+    pub color: [u8; 3],
+    pub outline: Option<Outline>,
+    pub shadow: Option<Shadow>,
+    pub background: Background,
+    pub anchor: Anchor,
+    /// Distance from the anchored edges, as a share of the window size
+    pub margin: [f32; 2],
+    /// Rows every line wraps to at most, the rest is cut off
+    pub max_lines: Option<usize>,
+    /// Seconds to fade in once a line starts
+    pub fade_in: f32,
+    /// Seconds to fade out once hidden
+    pub fade_out: f32,
+    /// Hide the line this many seconds after it was spoken, otherwise it stays until the next one
+    pub hide_after: Option<f32>,
+    pub reveal: Reveal,
+}
+
+impl Default for SubtitleStyle {
+    fn default() -> Self {
+        Self {
+            font_family: None,
+            font_size: 26.0,
+            color: [255, 255, 255],
+            outline: None,
+            shadow: None,
+            background: Background::default(),
+            anchor: Anchor::BottomLeft,
+            margin: [0.0, 0.25],
+            max_lines: None,
+            fade_in: 0.2,
+            fade_out: 0.5,
+            hide_after: None,
+            reveal: Reveal::Character,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct Outline {
+    pub width: f32,
+    pub color: [u8; 3],
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct Shadow {
+    pub offset: [f32; 2],
+    pub color: [u8; 3],
+    pub opacity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct Background {
+    pub kind: BackgroundKind,
+    pub color: [u8; 3],
+    /// 0 to 1
+    pub opacity: f32,
+    pub corner_radius: f32,
+    /// Space around the text, horizontal and vertical
+    pub padding: [f32; 2],
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self {
+            kind: BackgroundKind::Box,
+            color: [0, 0, 0],
+            opacity: 160.0 / 255.0,
+            corner_radius: 10.0,
+            padding: [12.0, 10.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundKind {
+    None,
+    /// Fits the text
+    Box,
+    /// Spans the whole width of the window
+    Band,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    fn align(self) -> egui::Align2 {
+        match self {
+            Anchor::TopLeft => egui::Align2::LEFT_TOP,
+            Anchor::Top => egui::Align2::CENTER_TOP,
+            Anchor::TopRight => egui::Align2::RIGHT_TOP,
+            Anchor::Left => egui::Align2::LEFT_CENTER,
+            Anchor::Center => egui::Align2::CENTER_CENTER,
+            Anchor::Right => egui::Align2::RIGHT_CENTER,
+            Anchor::BottomLeft => egui::Align2::LEFT_BOTTOM,
+            Anchor::Bottom => egui::Align2::CENTER_BOTTOM,
+            Anchor::BottomRight => egui::Align2::RIGHT_BOTTOM,
+        }
+    }
+}
+
+/// How the text of a line appears while it is spoken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reveal {
+    /// All at once
+    Instant,
+    Character,
+    /// Whole words, every CJK character counts as one
+    Word,
+}
+
+impl Reveal {
+    /// The part of `text` shown once `progress` of the voice, 0 to 1, was played.
+    pub fn revealed(self, text: &str, progress: f32) -> &str {
+        if self == Reveal::Instant {
+            return text;
+        }
+        let count = text.chars().count();
+        let shown = (progress.clamp(0.0, 1.0) * count as f32).ceil() as usize;
+        let mut chars = text.char_indices().skip(shown).peekable();
+        if self == Reveal::Word {
+            // finish the word that was started
+            while chars
+                .next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '\'')
+                .is_some()
+            {}
+        }
+        match chars.peek() {
+            Some(&(end, _)) => &text[..end],
+            None => text,
+        }
+    }
+}
+
+fn to_color(color: [u8; 3], opacity: f32) -> Color32 {
+    Color32::from_rgb(color[0], color[1], color[2]).gamma_multiply(opacity.clamp(0.0, 1.0))
+}
+
+impl SubtitleStyle {
+    /// Opacity of a line that started `since_start` ago and, if it was spoken, finished
+    /// `since_finished` ago.
+    pub fn opacity(&self, since_start: Duration, since_finished: Option<Duration>) -> f32 {
+        let fade = |elapsed: f32, duration: f32| {
+            if duration > 0.0 {
+                (elapsed / duration).clamp(0.0, 1.0)
+            } else if elapsed >= 0.0 {
+                1.0
+            } else {
+                0.0
+            }
+        };
+
+        let shown = fade(since_start.as_secs_f32(), self.fade_in);
+        let hidden = match (self.hide_after, since_finished) {
+            (Some(hide_after), Some(since_finished)) => {
+                fade(since_finished.as_secs_f32() - hide_after, self.fade_out)
+            }
+            _ => 0.0,
+        };
+        shown * (1.0 - hidden)
+    }
+
+    /// Draw the lines in `area`, faded to `opacity`.
+    ///
+    /// Every line is the full text, which sizes the box so it doesn't grow while speaking, the
+    /// part of it shown so far and its color if it isn't the subtitle color.
+    pub fn draw(
+        &self,
+        ui: &egui::Ui,
+        area: egui::Rect,
+        lines: &[(&str, &str, Option<[u8; 3]>)],
+        opacity: f32,
+    ) {
+        if opacity <= 0.0 {
+            return;
+        }
+        let painter = ui.painter_at(area);
+        let align = self.anchor.align();
+        let padding = egui::vec2(self.background.padding[0], self.background.padding[1]);
+        let font_id = FontId::new(self.font_size, FontFamily::Name(SUBTITLE_FONT.into()));
+        let wrap_width = (area.width() * (1.0 - self.margin[0]) - 2.0 * padding.x).max(0.0);
+
+        let layout = |f: &egui::epaint::Fonts, line: &str| {
+            let mut job =
+                LayoutJob::simple(line.to_owned(), font_id.clone(), Color32::WHITE, wrap_width);
+            if let Some(max_lines) = self.max_lines {
+                job.wrap.max_rows = max_lines.max(1);
+            }
+            f.layout_job(job)
+        };
+        let (sizes, galleys): (Vec<egui::Vec2>, Vec<Arc<egui::Galley>>) = ui.fonts(|f| {
+            lines
+                .iter()
+                .map(|&(full, shown, _)| {
+                    // keep the height of empty lines
+                    let full = if full.is_empty() { " " } else { full };
+                    (layout(f, full).size(), layout(f, shown))
+                })
+                .unzip()
+        });
+        let text_size = egui::vec2(
+            sizes.iter().map(|size| size.x).fold(0.0, f32::max),
+            sizes.iter().map(|size| size.y).sum(),
+        );
+
+        let margin = area.size() * egui::vec2(self.margin[0], self.margin[1]);
+        let anchor = egui::pos2(
+            match align.x() {
+                egui::Align::Min => area.left() + margin.x,
+                egui::Align::Center => area.center().x,
+                egui::Align::Max => area.right() - margin.x,
+            },
+            match align.y() {
+                egui::Align::Min => area.top() + margin.y,
+                egui::Align::Center => area.center().y,
+                egui::Align::Max => area.bottom() - margin.y,
+            },
+        );
+        let box_rect = align.anchor_size(anchor, text_size + padding * 2.0);
+
+        let background = match self.background.kind {
+            BackgroundKind::None => None,
+            BackgroundKind::Box => Some(box_rect),
+            BackgroundKind::Band => Some(egui::Rect::from_x_y_ranges(
+                area.x_range(),
+                box_rect.y_range(),
+            )),
+        };
+        if let Some(rect) = background {
+            painter.rect_filled(
+                rect,
+                self.background.corner_radius,
+                to_color(self.background.color, self.background.opacity * opacity),
+            );
+        }
+
+        let mut y = box_rect.top() + padding.y;
+        for ((size, galley), &(_, _, color)) in sizes.into_iter().zip(galleys).zip(lines) {
+            let color = to_color(color.unwrap_or(self.color), opacity);
+            let x = box_rect.left() + padding.x + (text_size.x - size.x) * align.x().to_factor();
+            let pos = egui::pos2(x, y);
+            y += size.y;
+
+            if let Some(shadow) = &self.shadow {
+                let offset = egui::vec2(shadow.offset[0], shadow.offset[1]);
+                painter.galley_with_override_text_color(
+                    pos + offset,
+                    galley.clone(),
+                    to_color(shadow.color, shadow.opacity * opacity),
+                );
+            }
+            if let Some(outline) = &self.outline {
+                const DIRECTIONS: usize = 8;
+
+                let outline_color = to_color(outline.color, opacity);
+                for direction in 0..DIRECTIONS {
+                    let angle = direction as f32 * std::f32::consts::TAU / DIRECTIONS as f32;
+                    let offset = egui::vec2(angle.cos(), angle.sin()) * outline.width;
+                    painter.galley_with_override_text_color(
+                        pos + offset,
+                        galley.clone(),
+                        outline_color,
+                    );
+                }
+            }
+            painter.galley_with_override_text_color(pos, galley, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::subtitle_style::{Reveal, SubtitleStyle};
+
+    #[test]
+    fn fade_in_and_out() {
+        let style = SubtitleStyle {
+            fade_in: 0.2,
+            fade_out: 0.5,
+            hide_after: Some(2.0),
+            ..Default::default()
+        };
+        let ms = Duration::from_millis;
+
+        assert_eq!(style.opacity(ms(0), None), 0.0);
+        assert_eq!(style.opacity(ms(100), None), 0.5);
+        assert_eq!(style.opacity(ms(5000), None), 1.0);
+        // shown for a while after it was spoken
+        assert_eq!(style.opacity(ms(5000), Some(ms(1000))), 1.0);
+        assert_eq!(style.opacity(ms(5000), Some(ms(2250))), 0.5);
+        assert_eq!(style.opacity(ms(5000), Some(ms(3000))), 0.0);
+
+        let kept = SubtitleStyle {
+            hide_after: None,
+            ..style
+        };
+        assert_eq!(kept.opacity(ms(5000), Some(ms(60_000))), 1.0);
+    }
+
+    #[test]
+    fn reveal_with_the_voice() {
+        let text = "Hello world";
+        assert_eq!(Reveal::Character.revealed(text, 0.0), "");
+        assert_eq!(Reveal::Character.revealed(text, 0.3), "Hell");
+        assert_eq!(Reveal::Word.revealed(text, 0.3), "Hello");
+        assert_eq!(Reveal::Word.revealed(text, 0.6), "Hello world");
+        assert_eq!(Reveal::Instant.revealed(text, 0.0), text);
+        assert_eq!(Reveal::Character.revealed(text, 1.0), text);
+
+        assert_eq!(Reveal::Word.revealed("你好，主人", 0.5), "你好，");
+    }
+}
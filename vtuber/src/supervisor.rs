@@ -0,0 +1,101 @@
+use std::{
+    any::Any,
+    time::{Duration, Instant},
+};
+
+use tokio::{sync::broadcast, task::JoinHandle};
+
+use crate::{bus::UiEvent, shutdown::Shutdown};
+
+/// Delay before the first restart, doubled for every failure in a row.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// A task running this long is healthy again, its next failure restarts it right away.
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// Restarts failed or panicked background tasks and reports them to the frontends.
+#[derive(Clone)]
+pub struct Supervisor {
+    ui_tx: broadcast::Sender<UiEvent>,
+    shutdown: Shutdown,
+}
+
+impl Supervisor {
+    pub fn new(ui_tx: broadcast::Sender<UiEvent>, shutdown: Shutdown) -> Self {
+        Self { ui_tx, shutdown }
+    }
+
+    /// Run the task made by `start` until it finishes with `Ok`, restarting it when it fails or
+    /// panics. The returned handle resolves once the task finished for good.
+    pub fn supervise<F, Fut>(&self, name: &'static str, mut start: F) -> JoinHandle<()>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let supervisor = self.clone();
+        tokio::spawn(async move {
+            let mut failures = 0;
+            loop {
+                let started_at = Instant::now();
+                let error = match tokio::spawn(start()).await {
+                    Ok(Ok(())) => return,
+                    Ok(Err(e)) => e.to_string(),
+                    Err(e) if e.is_panic() => {
+                        format!("panicked: {}", panic_message(e.into_panic()))
+                    }
+                    Err(e) => e.to_string(),
+                };
+                if supervisor.shutdown.is_triggered() {
+                    log::error!("{name} failed while shutting down: {error}");
+                    return;
+                }
+
+                if started_at.elapsed() >= STABLE_AFTER {
+                    failures = 0;
+                }
+                let delay = backoff(failures);
+                failures += 1;
+                log::error!("{name} is down, restarting in {delay:?}: {error}");
+                let _ = supervisor
+                    .ui_tx
+                    .send(UiEvent::Error(format!("{name} is down: {error}")));
+
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = supervisor.shutdown.triggered() => return,
+                }
+                log::info!("Restarting {name}");
+            }
+        })
+    }
+}
+
+fn backoff(failures: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(failures))
+        .min(MAX_BACKOFF)
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map_or_else(|| "<unknown>".to_string(), |message| message.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::supervisor::backoff;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(6), Duration::from_secs(60));
+        assert_eq!(backoff(100), Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,71 @@
+use std::{collections::HashMap, fmt::Display, hash::Hash};
+
+use eframe::egui::{self, ImageData, TextureHandle, TextureOptions};
+
+/// One texture per key. A new image goes into the texture of the last one under its key, so
+/// the GPU memory stays the same however many frames are shown. The textures are freed when
+/// dropped.
+pub struct Textures<K> {
+    /// Prefix of the texture names, for egui's debug views
+    name: &'static str,
+    textures: HashMap<K, TextureHandle>,
+}
+
+impl<K: Eq + Hash + Display> Textures<K> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            textures: HashMap::new(),
+        }
+    }
+
+    /// Upload the newest image under `key`, reusing its texture.
+    pub fn set(
+        &mut self,
+        ctx: &egui::Context,
+        key: K,
+        image: impl Into<ImageData>,
+        options: TextureOptions,
+    ) {
+        match self.textures.get_mut(&key) {
+            Some(texture) => texture.set(image, options),
+            None => {
+                let texture = ctx.load_texture(format!("{}_{key}", self.name), image, options);
+                self.textures.insert(key, texture);
+            }
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&TextureHandle> {
+        self.textures.get(key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::{self, ColorImage};
+
+    use crate::textures::Textures;
+
+    #[test]
+    fn reuse_and_free_textures() {
+        let ctx = egui::Context::default();
+        let allocated = || ctx.tex_manager().read().num_allocated();
+        let before = allocated();
+
+        let mut textures = Textures::new("test");
+        for _ in 0..3 {
+            textures.set(&ctx, 0, ColorImage::example(), Default::default());
+            textures.set(&ctx, 1, ColorImage::example(), Default::default());
+        }
+        assert_eq!(allocated(), before + 2);
+        assert_eq!(textures.get(&1).unwrap().size(), ColorImage::example().size);
+
+        drop(textures);
+        assert_eq!(allocated(), before);
+    }
+}
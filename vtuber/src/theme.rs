@@ -0,0 +1,273 @@
+use std::fs;
+
+use eframe::egui::{self, Color32};
+
+use crate::subtitle_style::SubtitleStyle;
+
+/// The themes to pick from in the settings window.
+pub const PRESETS: [&str; 3] = ["dark", "light", "streamer"];
+
+/// Colors of everything drawn around the characters, one of the [`PRESETS`] or a JSON or
+/// TOML file set in `VTUBER_GUI_THEME`. Colors are `[r, g, b, a]`.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Light or dark settings and transcript windows
+    pub dark_windows: bool,
+    pub subtitle: SubtitleColors,
+    pub name_tag: NameTag,
+    pub thinking: ThinkingColors,
+    pub toast: ToastColors,
+    /// The chat panel, the text input, the poll and the debug overlay
+    pub panel: PanelColors,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct SubtitleColors {
+    pub text: [u8; 3],
+    pub background: [u8; 3],
+    /// 0 to 1
+    pub background_opacity: f32,
+}
+
+/// The speaker's name above the line.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct NameTag {
+    /// `{name}` is replaced with the character's name
+    pub format: String,
+    /// The subtitle color if unset
+    pub color: Option<[u8; 3]>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct ThinkingColors {
+    pub background: [u8; 4],
+    pub dots: [u8; 4],
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct ToastColors {
+    pub background: [u8; 4],
+    pub text: [u8; 4],
+    /// The details header
+    pub muted: [u8; 4],
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default)]
+pub struct PanelColors {
+    pub background: [u8; 4],
+    pub text: [u8; 4],
+    pub muted: [u8; 4],
+    /// Behind the comment being answered
+    pub highlight: [u8; 4],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Default for SubtitleColors {
+    fn default() -> Self {
+        Theme::dark().subtitle
+    }
+}
+
+impl Default for NameTag {
+    fn default() -> Self {
+        Theme::dark().name_tag
+    }
+}
+
+impl Default for ThinkingColors {
+    fn default() -> Self {
+        Theme::dark().thinking
+    }
+}
+
+impl Default for ToastColors {
+    fn default() -> Self {
+        Theme::dark().toast
+    }
+}
+
+impl Default for PanelColors {
+    fn default() -> Self {
+        Theme::dark().panel
+    }
+}
+
+impl Theme {
+    fn dark() -> Self {
+        Self {
+            dark_windows: true,
+            subtitle: SubtitleColors {
+                text: [255, 255, 255],
+                background: [0, 0, 0],
+                background_opacity: 160.0 / 255.0,
+            },
+            name_tag: NameTag {
+                format: "【{name}】".to_string(),
+                color: None,
+            },
+            thinking: ThinkingColors {
+                background: [0, 0, 0, 160],
+                dots: [255, 255, 255, 255],
+            },
+            toast: ToastColors {
+                background: [120, 20, 20, 220],
+                text: [255, 255, 255, 255],
+                muted: [160, 160, 160, 255],
+            },
+            panel: PanelColors {
+                background: [0, 0, 0, 180],
+                text: [255, 255, 255, 255],
+                muted: [160, 160, 160, 255],
+                highlight: [255, 200, 80, 70],
+            },
+        }
+    }
+
+    fn light() -> Self {
+        Self {
+            dark_windows: false,
+            subtitle: SubtitleColors {
+                text: [30, 30, 40],
+                background: [255, 255, 255],
+                background_opacity: 0.85,
+            },
+            name_tag: NameTag {
+                format: "【{name}】".to_string(),
+                color: Some([200, 60, 110]),
+            },
+            thinking: ThinkingColors {
+                background: [255, 255, 255, 200],
+                dots: [200, 60, 110, 255],
+            },
+            toast: ToastColors {
+                background: [255, 225, 225, 235],
+                text: [120, 20, 20, 255],
+                muted: [110, 80, 80, 255],
+            },
+            panel: PanelColors {
+                background: [250, 250, 250, 210],
+                text: [30, 30, 40, 255],
+                muted: [100, 100, 110, 255],
+                highlight: [255, 170, 60, 90],
+            },
+        }
+    }
+
+    /// Nothing but the characters and their words on the transparent window, for capturing
+    /// it without a chroma key.
+    fn streamer() -> Self {
+        Self {
+            dark_windows: true,
+            subtitle: SubtitleColors {
+                text: [255, 255, 255],
+                background: [0, 0, 0],
+                background_opacity: 0.0,
+            },
+            name_tag: NameTag {
+                format: "{name}".to_string(),
+                color: Some([255, 210, 90]),
+            },
+            thinking: ThinkingColors {
+                background: [0, 0, 0, 0],
+                dots: [255, 255, 255, 230],
+            },
+            toast: ToastColors {
+                background: [120, 20, 20, 160],
+                text: [255, 255, 255, 255],
+                muted: [200, 200, 200, 255],
+            },
+            panel: PanelColors {
+                background: [0, 0, 0, 0],
+                text: [255, 255, 255, 255],
+                muted: [200, 200, 200, 255],
+                highlight: [255, 200, 80, 50],
+            },
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "streamer" => Some(Self::streamer()),
+            _ => None,
+        }
+    }
+
+    /// A preset by its name, or a theme file, `.toml` or JSON.
+    pub fn load(name_or_path: &str) -> anyhow::Result<Self> {
+        if let Some(theme) = Self::preset(name_or_path) {
+            return Ok(theme);
+        }
+        let path = fs::canonicalize(name_or_path)?;
+        let content = fs::read_to_string(&path)?;
+        Ok(
+            if path.extension().and_then(|ext| ext.to_str()) == Some("toml") {
+                toml::from_str(&content)?
+            } else {
+                serde_json::from_str(&content)?
+            },
+        )
+    }
+
+    /// Take the subtitle colors, the rest of the style stays.
+    pub fn apply(&self, style: &mut SubtitleStyle) {
+        style.color = self.subtitle.text;
+        style.background.color = self.subtitle.background;
+        style.background.opacity = self.subtitle.background_opacity;
+    }
+
+    pub fn visuals(&self) -> egui::Visuals {
+        if self.dark_windows {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        }
+    }
+
+    pub fn name_tag(&self, name: &str) -> String {
+        self.name_tag.format.replace("{name}", name)
+    }
+}
+
+pub fn color([r, g, b, a]: [u8; 4]) -> Color32 {
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::theme::{PRESETS, Theme};
+
+    #[test]
+    fn load_partial_themes() {
+        let json: Theme =
+            serde_json::from_str(r#"{"panel": {"background": [10, 20, 30, 40]}}"#).unwrap();
+        assert_eq!(json.panel.background, [10, 20, 30, 40]);
+        assert_eq!(json.panel.text, Theme::default().panel.text);
+        assert_eq!(json.toast, Theme::default().toast);
+
+        let toml: Theme = toml::from_str(
+            "dark_windows = false\n[name_tag]\nformat = \"{name}:\"\ncolor = [255, 0, 0]\n",
+        )
+        .unwrap();
+        assert!(!toml.dark_windows);
+        assert_eq!(toml.name_tag("Murasame"), "Murasame:");
+        assert_eq!(toml.name_tag.color, Some([255, 0, 0]));
+
+        for preset in PRESETS {
+            assert!(Theme::preset(preset).is_some());
+        }
+        assert_ne!(Theme::preset("light"), Theme::preset("dark"));
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+use crate::theme::{self, ToastColors};
+
+/// How long a toast stays unless its details are opened.
+const SHOWN: Duration = Duration::from_secs(8);
+/// Older toasts are dropped beyond this.
+const MAX_TOASTS: usize = 4;
+/// Longer messages are cut in the summary, the details have all of it.
+const SUMMARY_LENGTH: usize = 60;
+
+struct Toast {
+    id: u64,
+    message: String,
+    time: chrono::DateTime<chrono::Local>,
+    shown_at: Instant,
+    /// Kept until closed once the details were opened
+    pinned: bool,
+}
+
+/// Pipeline errors shown in the corner without getting in the way.
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+    next_id: u64,
+}
+
+impl Toasts {
+    pub fn push(&mut self, message: String, now: Instant) {
+        self.toasts.push(Toast {
+            id: self.next_id,
+            // errors may quote a request url with the api key
+            message: config::redact(&message).into_owned(),
+            time: chrono::Local::now(),
+            shown_at: now,
+            pinned: false,
+        });
+        self.next_id += 1;
+        if self.toasts.len() > MAX_TOASTS {
+            let excess = self.toasts.len() - MAX_TOASTS;
+            self.toasts.drain(..excess);
+        }
+    }
+
+    /// Drop the toasts that were shown long enough.
+    fn expire(&mut self, now: Instant) {
+        self.toasts
+            .retain(|toast| toast.pinned || now.duration_since(toast.shown_at) < SHOWN);
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, colors: &ToastColors) {
+        self.expire(Instant::now());
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut closed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-4.0, -4.0))
+            .show(ctx, |ui| {
+                ui.set_max_width(280.0);
+                for toast in &mut self.toasts {
+                    egui::Frame::default()
+                        .fill(theme::color(colors.background))
+                        .corner_radius(6.0)
+                        .inner_margin(6.0)
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(summary(&toast.message))
+                                        .color(theme::color(colors.text)),
+                                );
+                                if ui.small_button("x").clicked() {
+                                    closed = Some(toast.id);
+                                }
+                            });
+                            let details = egui::CollapsingHeader::new(
+                                egui::RichText::new("Details")
+                                    .size(11.0)
+                                    .color(theme::color(colors.muted)),
+                            )
+                            .id_salt(toast.id)
+                            .show(ui, |ui| {
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "{}\n{}",
+                                        toast.time.format("%H:%M:%S"),
+                                        toast.message
+                                    ))
+                                    .size(11.0)
+                                    .color(theme::color(colors.text)),
+                                );
+                            });
+                            if details.body_returned.is_some() {
+                                toast.pinned = true;
+                            }
+                        });
+                }
+            });
+        if let Some(id) = closed {
+            self.toasts.retain(|toast| toast.id != id);
+        }
+    }
+}
+
+/// The first line of a message, cut to fit a toast.
+fn summary(message: &str) -> String {
+    let line = message.lines().next().unwrap_or_default();
+    if line.chars().count() > SUMMARY_LENGTH || line.len() < message.trim_end().len() {
+        let cut: String = line.chars().take(SUMMARY_LENGTH).collect();
+        format!("{cut}…")
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::toast::{Toasts, summary};
+
+    #[test]
+    fn expire_old_toasts() {
+        let start = Instant::now();
+        let mut toasts = Toasts::default();
+        for i in 0..6 {
+            toasts.push(format!("error {i}"), start);
+        }
+        // only the newest are kept
+        assert_eq!(toasts.toasts.len(), 4);
+        assert_eq!(toasts.toasts[0].message, "error 2");
+
+        toasts.toasts[1].pinned = true;
+        toasts.expire(start + Duration::from_secs(10));
+        assert_eq!(toasts.toasts.len(), 1);
+        assert_eq!(toasts.toasts[0].message, "error 3");
+    }
+
+    #[test]
+    fn summarize_messages() {
+        assert_eq!(summary("LLM timed out"), "LLM timed out");
+        assert_eq!(summary("Request failed\ncaused by: 500"), "Request failed…");
+    }
+}
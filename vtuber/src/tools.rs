@@ -0,0 +1,197 @@
+use std::{fs, sync::Arc, time::Duration};
+
+use ai::Tool;
+use async_trait::async_trait;
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde_json::{Value as JsonValue, json};
+
+use crate::config::ToolsConfig;
+
+/// Longer weather reports are cut off, the AI only needs the gist.
+const MAX_WEATHER_CHARS: usize = 2000;
+/// CPU usage is measured over this long.
+const CPU_SAMPLE: Duration = Duration::from_millis(250);
+
+/// The tools the characters may call, so they can tell the time and weather truthfully.
+pub fn builtin_tools(config: &ToolsConfig) -> Vec<Arc<dyn Tool>> {
+    vec![
+        Arc::new(CurrentTime),
+        Arc::new(Weather {
+            config: config.clone(),
+            client: reqwest::Client::new(),
+        }),
+        Arc::new(SystemStatus),
+    ]
+}
+
+struct CurrentTime;
+
+#[async_trait]
+impl Tool for CurrentTime {
+    fn name(&self) -> &str {
+        "current_time"
+    }
+
+    fn description(&self) -> &str {
+        "The current local date, time and weekday of the user's computer"
+    }
+
+    async fn call(&self, _args: JsonValue) -> anyhow::Result<JsonValue> {
+        let now = chrono::Local::now();
+        Ok(json!({
+            "time": now.format("%Y-%m-%d %H:%M:%S").to_string(),
+            "weekday": now.format("%A").to_string(),
+            "utc_offset": now.format("%:z").to_string(),
+        }))
+    }
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct WeatherArgs {
+    /// City to look up, leave empty for the user's home town if it is known
+    #[serde(default)]
+    location: String,
+}
+
+struct Weather {
+    config: ToolsConfig,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl Tool for Weather {
+    fn name(&self) -> &str {
+        "weather"
+    }
+
+    fn description(&self) -> &str {
+        "The current weather and forecast at a place"
+    }
+
+    fn parameters(&self) -> Option<JsonValue> {
+        Some(ai::parameters_for::<WeatherArgs>())
+    }
+
+    async fn call(&self, args: JsonValue) -> anyhow::Result<JsonValue> {
+        let args: WeatherArgs = serde_json::from_value(args)?;
+        // the weather API would guess the place from the user's address otherwise
+        let location = match args.location.trim() {
+            "" => self.config.weather_location.as_str(),
+            location => location,
+        };
+        if location.is_empty() {
+            anyhow::bail!("Name the place, the user's home town is unknown");
+        }
+        let url = weather_url(&self.config.weather_url, location);
+        let body = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let body: String = body.chars().take(MAX_WEATHER_CHARS).collect();
+        Ok(serde_json::from_str(&body).unwrap_or_else(|_| json!({ "weather": body })))
+    }
+}
+
+/// The weather API's url for `location`, substituted into `{location}`.
+fn weather_url(template: &str, location: &str) -> String {
+    template.replace(
+        "{location}",
+        &utf8_percent_encode(location, NON_ALPHANUMERIC).to_string(),
+    )
+}
+
+struct SystemStatus;
+
+#[async_trait]
+impl Tool for SystemStatus {
+    fn name(&self) -> &str {
+        "system_status"
+    }
+
+    fn description(&self) -> &str {
+        "CPU and memory usage of the user's computer"
+    }
+
+    async fn call(&self, _args: JsonValue) -> anyhow::Result<JsonValue> {
+        let read_stat = || {
+            fs::read_to_string("/proc/stat")
+                .ok()
+                .as_deref()
+                .and_then(parse_cpu_times)
+                .ok_or_else(|| anyhow::anyhow!("CPU usage is only known on Linux"))
+        };
+        let (idle_before, total_before) = read_stat()?;
+        tokio::time::sleep(CPU_SAMPLE).await;
+        let (idle, total) = read_stat()?;
+        let busy = 1.0 - (idle - idle_before) as f64 / (total - total_before).max(1) as f64;
+
+        let (memory_total, memory_available) = fs::read_to_string("/proc/meminfo")
+            .ok()
+            .as_deref()
+            .and_then(parse_meminfo)
+            .ok_or_else(|| anyhow::anyhow!("Memory usage is only known on Linux"))?;
+        let memory_used = memory_total.saturating_sub(memory_available);
+        let gib = |kib: u64| (kib as f64 / 1024.0 / 1024.0 * 10.0).round() / 10.0;
+        Ok(json!({
+            "cpu_percent": (busy * 100.0).round(),
+            "memory_used_gb": gib(memory_used),
+            "memory_total_gb": gib(memory_total),
+            "memory_percent": (memory_used as f64 / memory_total.max(1) as f64 * 100.0).round(),
+        }))
+    }
+}
+
+/// Idle and total time of all CPUs from `/proc/stat`, in ticks since boot.
+fn parse_cpu_times(stat: &str) -> Option<(u64, u64)> {
+    let times = stat
+        .lines()
+        .find_map(|line| line.strip_prefix("cpu "))?
+        .split_whitespace()
+        .map(|time| time.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    // user nice system idle iowait ...
+    let idle = times.get(3)? + times.get(4).unwrap_or(&0);
+    Some((idle, times.iter().sum()))
+}
+
+/// Total and available memory from `/proc/meminfo`, in KiB.
+fn parse_meminfo(meminfo: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))?
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .ok()
+    };
+    Some((field("MemTotal")?, field("MemAvailable")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tools::{parse_cpu_times, parse_meminfo, weather_url};
+
+    #[test]
+    fn parse_proc_files() {
+        let stat = "cpu  100 5 50 800 45 0 0 0 0 0\ncpu0 50 2 25 400 20 0 0 0 0 0\n";
+        assert_eq!(parse_cpu_times(stat), Some((845, 1000)));
+        assert_eq!(parse_cpu_times("intr 1 2 3"), None);
+
+        let meminfo = "MemTotal:       16000000 kB\nMemFree:         2000000 kB\nMemAvailable:    8000000 kB\n";
+        assert_eq!(parse_meminfo(meminfo), Some((16000000, 8000000)));
+    }
+
+    #[test]
+    fn encode_the_location() {
+        assert_eq!(
+            weather_url("https://wttr.in/{location}?format=3", "New York"),
+            "https://wttr.in/New%20York?format=3"
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use ai::{LLM, TopicResponseModel, UsageExample, gemini::Gemini};
+use tokio::sync::OwnedMutexGuard;
+
+use crate::{config::TopicsConfig, storage::UsageRecorder};
+
+const SUMMARIZER_PROMPT: &str = "You follow the chat of a live stream. You receive the latest \
+    messages and the topic planned for the stream, if any. Tell what the conversation is about \
+    in a few words, whether it is still about the planned topic, and whether the planned topic \
+    has been talked through. Reply in the language of the chat with JSON like ";
+
+/// Messages kept for the next summary, per turn of the tracker.
+const LINES_PER_TURN: usize = 2;
+
+/// Where the conversation is compared to the plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopicState {
+    /// Not summarized yet
+    Unknown,
+    OnTopic(String),
+    /// Chat moved away from the planned topic
+    Drifted(String),
+}
+
+/// The current topic and the planned ones left, updated from the summaries.
+#[derive(Debug)]
+pub struct Topics {
+    state: TopicState,
+    plan: VecDeque<String>,
+}
+
+impl Topics {
+    pub fn new(plan: Vec<String>) -> Self {
+        Self {
+            state: TopicState::Unknown,
+            plan: plan.into(),
+        }
+    }
+
+    pub fn planned(&self) -> Option<&str> {
+        self.plan.front().map(String::as_str)
+    }
+
+    pub fn state(&self) -> &TopicState {
+        &self.state
+    }
+
+    pub fn apply(&mut self, summary: TopicResponseModel) {
+        if summary.plan_covered && self.plan.pop_front().is_some() {
+            match self.planned() {
+                Some(next) => log::info!("Planned topic covered, moving on to {next}"),
+                None => log::info!("Every planned topic covered"),
+            }
+        }
+        self.state = match self.planned() {
+            // a new planned topic only starts when steering towards it
+            Some(_) if !summary.on_plan || summary.plan_covered => {
+                TopicState::Drifted(summary.topic)
+            }
+            _ => TopicState::OnTopic(summary.topic),
+        };
+    }
+
+    /// Put in front of the comment for the character, nothing before the first summary without
+    /// a plan.
+    pub fn prompt_context(&self) -> Option<String> {
+        match (&self.state, self.planned()) {
+            (TopicState::Drifted(topic), Some(planned)) => Some(format!(
+                "【当前话题: {topic}。直播计划的话题是「{planned}」, 请自然地把话题引回去】"
+            )),
+            (TopicState::OnTopic(topic) | TopicState::Drifted(topic), _) => {
+                Some(format!("【当前话题: {topic}】"))
+            }
+            (TopicState::Unknown, Some(planned)) => Some(format!("【直播计划的话题: {planned}】")),
+            (TopicState::Unknown, None) => None,
+        }
+    }
+}
+
+/// Summarizes the conversation every few turns to keep [`Topics`] up to date.
+pub struct TopicTracker {
+    /// Locked while a summary runs in the background
+    summarizer: Arc<tokio::sync::Mutex<Gemini<'static>>>,
+    usage: UsageRecorder,
+    every: usize,
+    recent: VecDeque<String>,
+    turns: usize,
+    topics: Arc<Mutex<Topics>>,
+}
+
+impl TopicTracker {
+    pub fn new(config: &TopicsConfig, api_key: &str, usage: UsageRecorder) -> Self {
+        let system_prompt = format!(
+            "{SUMMARIZER_PROMPT}{}",
+            TopicResponseModel::generate_example()
+        );
+        let mut summarizer = Gemini::new(
+            api_key.to_string(),
+            config.model.clone(),
+            Some(system_prompt.into()),
+        );
+        summarizer.set_thinking(false);
+        summarizer.set_json_schema::<TopicResponseModel>();
+        Self {
+            summarizer: Arc::new(tokio::sync::Mutex::new(summarizer)),
+            usage,
+            every: config.every.max(1),
+            recent: VecDeque::new(),
+            turns: 0,
+            topics: Arc::new(Mutex::new(Topics::new(config.plan.clone()))),
+        }
+    }
+
+    pub fn prompt_context(&self) -> Option<String> {
+        self.topics.lock().unwrap().prompt_context()
+    }
+
+    /// Note a comment and the character's answer, summarizing every few turns without waiting
+    /// for the summary.
+    pub fn record(&mut self, user: &str, comment: &str, character: &str, answer: &str) {
+        self.recent.push_back(format!("{user}: {comment}"));
+        self.recent.push_back(format!("{character}: {answer}"));
+        while self.recent.len() > self.every * LINES_PER_TURN {
+            self.recent.pop_front();
+        }
+
+        self.turns += 1;
+        if self.turns < self.every {
+            return;
+        }
+        self.turns = 0;
+        // a slow summary is skipped rather than queued up
+        let Ok(summarizer) = self.summarizer.clone().try_lock_owned() else {
+            log::warn!("The last topic summary is still running, skipping this one");
+            return;
+        };
+        let lines = self.recent.iter().cloned().collect::<Vec<_>>().join("\n");
+        let (topics, usage) = (self.topics.clone(), self.usage.clone());
+        tokio::spawn(async move {
+            if let Err(e) = summarize(summarizer, lines, &topics, &usage).await {
+                log::error!("Failed to summarize the topic: {e}");
+            }
+        });
+    }
+}
+
+async fn summarize(
+    mut summarizer: OwnedMutexGuard<Gemini<'static>>,
+    lines: String,
+    topics: &Mutex<Topics>,
+    usage: &UsageRecorder,
+) -> anyhow::Result<()> {
+    let planned = topics.lock().unwrap().planned().map(str::to_string);
+    let prompt = match planned {
+        Some(planned) => format!("Planned topic: {planned}\n\n{lines}"),
+        None => lines,
+    };
+
+    // every summary stands on its own
+    summarizer.clear_history();
+    let reply = summarizer.chat(&prompt).await?;
+    usage.record(summarizer.last_usage()).await;
+    let summary: TopicResponseModel = serde_json::from_str(&reply)?;
+    let mut topics = topics.lock().unwrap();
+    topics.apply(summary);
+    log::info!("Topic: {:?}", topics.state());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ai::TopicResponseModel;
+
+    use crate::topics::{TopicState, Topics};
+
+    fn summary(topic: &str, on_plan: bool, plan_covered: bool) -> TopicResponseModel {
+        TopicResponseModel {
+            topic: topic.to_string(),
+            on_plan,
+            plan_covered,
+        }
+    }
+
+    #[test]
+    fn steer_back_to_the_plan() {
+        let mut topics = Topics::new(vec!["新游戏".to_string(), "抽奖".to_string()]);
+        assert_eq!(
+            topics.prompt_context().as_deref(),
+            Some("【直播计划的话题: 新游戏】")
+        );
+
+        topics.apply(summary("游戏的画面", true, false));
+        assert_eq!(
+            topics.state(),
+            &TopicState::OnTopic("游戏的画面".to_string())
+        );
+        assert_eq!(
+            topics.prompt_context().as_deref(),
+            Some("【当前话题: 游戏的画面】")
+        );
+
+        topics.apply(summary("晚饭吃什么", false, false));
+        assert_eq!(
+            topics.prompt_context().as_deref(),
+            Some("【当前话题: 晚饭吃什么。直播计划的话题是「新游戏」, 请自然地把话题引回去】")
+        );
+
+        // moving on to the next planned topic steers towards it
+        topics.apply(summary("游戏的结局", true, true));
+        assert_eq!(topics.planned(), Some("抽奖"));
+        assert_eq!(
+            topics.state(),
+            &TopicState::Drifted("游戏的结局".to_string())
+        );
+
+        topics.apply(summary("抽奖", true, true));
+        assert_eq!(topics.planned(), None);
+        assert_eq!(
+            topics.prompt_context().as_deref(),
+            Some("【当前话题: 抽奖】")
+        );
+    }
+
+    #[test]
+    fn track_without_plan() {
+        let mut topics = Topics::new(Vec::new());
+        assert!(topics.prompt_context().is_none());
+        topics.apply(summary("猫", true, false));
+        assert_eq!(topics.prompt_context().as_deref(), Some("【当前话题: 猫】"));
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use layer_composer::HitArea;
+
+use crate::config::TouchConfig;
+
+/// Source of the comments made up when the desktop user touches a character.
+pub const TOUCH_SOURCE: &str = "touch";
+/// How long the expression of a touched area stays.
+const REACTION_SHOWN: Duration = Duration::from_secs(2);
+
+/// Reacts to clicks on the hit areas of the characters' models.
+pub struct Touches {
+    /// Per character
+    hit_areas: Vec<BTreeMap<String, HitArea>>,
+    cooldown: Duration,
+    /// When every area of every character was last touched
+    touched_at: HashMap<(usize, String), Instant>,
+    /// Per character, the shown reaction and when it goes away
+    reactions: Vec<Option<(Vec<String>, Instant)>>,
+}
+
+impl Touches {
+    pub fn new(hit_areas: Vec<BTreeMap<String, HitArea>>, config: &TouchConfig) -> Self {
+        Self {
+            reactions: vec![None; hit_areas.len()],
+            hit_areas,
+            cooldown: config.cooldown,
+            touched_at: HashMap::new(),
+        }
+    }
+
+    /// Touch the character at `x`, `y` pixels of its image, returns the touched area unless it
+    /// was touched less than the cooldown ago.
+    pub fn touch(&mut self, character: usize, x: f32, y: f32, now: Instant) -> Option<&HitArea> {
+        let (name, area) = self.hit_areas[character]
+            .iter()
+            .find(|(_, area)| area.contains(x, y))?;
+        let key = (character, name.clone());
+        if self
+            .touched_at
+            .get(&key)
+            .is_some_and(|&touched_at| now.duration_since(touched_at) < self.cooldown)
+        {
+            return None;
+        }
+        self.touched_at.insert(key, now);
+
+        if !area.layers.is_empty() {
+            self.reactions[character] = Some((area.layers.clone(), now + REACTION_SHOWN));
+        }
+        Some(area)
+    }
+
+    /// The expression the character shows after being touched, if any.
+    pub fn reaction(&self, character: usize) -> Option<&[String]> {
+        self.reactions[character]
+            .as_ref()
+            .map(|(layers, _)| layers.as_slice())
+    }
+
+    /// Drop the reactions shown long enough, returns the characters that changed.
+    pub fn expire(&mut self, now: Instant) -> Vec<usize> {
+        let mut expired = Vec::new();
+        for (character, reaction) in self.reactions.iter_mut().enumerate() {
+            if reaction.as_ref().is_some_and(|(_, until)| now >= *until) {
+                *reaction = None;
+                expired.push(character);
+            }
+        }
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        time::{Duration, Instant},
+    };
+
+    use layer_composer::HitArea;
+
+    use crate::{config::TouchConfig, touch::Touches};
+
+    #[test]
+    fn touch_with_cooldown() {
+        let head = HitArea {
+            rect: [100, 0, 200, 150],
+            layers: vec!["blush.png".to_string()],
+            prompt: Some("The user patted your head".to_string()),
+        };
+        let config = TouchConfig {
+            cooldown: Duration::from_secs(10),
+            ai: true,
+        };
+        let mut touches = Touches::new(
+            vec![BTreeMap::from([("head".to_string(), head.clone())])],
+            &config,
+        );
+        let start = Instant::now();
+
+        assert!(touches.touch(0, 50.0, 50.0, start).is_none());
+        assert_eq!(touches.touch(0, 150.0, 50.0, start), Some(&head));
+        assert_eq!(touches.reaction(0).unwrap(), ["blush.png"]);
+        // cooling down
+        let later = start + Duration::from_secs(3);
+        assert!(touches.touch(0, 150.0, 50.0, later).is_none());
+        assert_eq!(touches.expire(later), [0]);
+        assert!(touches.reaction(0).is_none());
+        assert!(touches.expire(later).is_empty());
+
+        let after_cooldown = start + Duration::from_secs(11);
+        assert!(touches.touch(0, 150.0, 50.0, after_cooldown).is_some());
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use eframe::egui;
+
+/// Older lines are dropped from the transcript beyond this.
+const MAX_LINES: usize = 500;
+
+/// A plain text file holding the line being said, for OBS text sources and screen readers.
+pub struct CaptionFile {
+    path: PathBuf,
+}
+
+impl CaptionFile {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Replace the text, empty once nothing is said. Written to a temporary file first, so
+    /// readers never see half a line.
+    pub fn write(&self, text: &str) -> io::Result<()> {
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, text.trim())?;
+        fs::rename(temp, &self.path)
+    }
+}
+
+struct Entry {
+    time: chrono::DateTime<chrono::Local>,
+    name: String,
+    text: String,
+}
+
+/// Every line said this session in a window that stays readable and can be copied, F5 toggles
+/// it.
+pub struct Transcript {
+    open: bool,
+    entries: VecDeque<Entry>,
+    /// When the last added line started playing, so every line is added once
+    last_started_at: Option<Instant>,
+}
+
+impl Transcript {
+    pub fn new(open: bool) -> Self {
+        Self {
+            open,
+            entries: VecDeque::new(),
+            last_started_at: None,
+        }
+    }
+
+    /// Add the line being said unless it already is.
+    pub fn follow(&mut self, name: &str, text: &str, started_at: Instant) {
+        if self.last_started_at == Some(started_at) {
+            return;
+        }
+        self.last_started_at = Some(started_at);
+        self.entries.push_back(Entry {
+            time: chrono::Local::now(),
+            name: name.to_string(),
+            text: text.trim().to_string(),
+        });
+        if self.entries.len() > MAX_LINES {
+            self.entries.pop_front();
+        }
+    }
+
+    fn text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "[{}] {}: {}\n",
+                    entry.time.format("%H:%M:%S"),
+                    entry.name,
+                    entry.text
+                )
+            })
+            .collect()
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.key_pressed(egui::Key::F5)) {
+            self.open = !self.open;
+        }
+        if !self.open {
+            return;
+        }
+
+        let text = self.text();
+        egui::Window::new("Transcript")
+            .open(&mut self.open)
+            .default_size([360.0, 240.0])
+            .show(ctx, |ui| {
+                if ui.button("Copy all").clicked() {
+                    ctx.copy_text(text.clone());
+                }
+                egui::ScrollArea::vertical()
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        // selectable, but not editable
+                        ui.add(
+                            egui::TextEdit::multiline(&mut text.as_str())
+                                .desired_width(f32::INFINITY)
+                                .font(egui::TextStyle::Body),
+                        );
+                    });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::transcript::Transcript;
+
+    #[test]
+    fn add_every_line_once() {
+        let start = Instant::now();
+        let mut transcript = Transcript::new(false);
+        transcript.follow("Murasame", "hello ", start);
+        transcript.follow("Murasame", "hello ", start);
+        transcript.follow("Yoshino", "hi", start + Duration::from_secs(2));
+
+        let text = transcript.text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("] Murasame: hello"));
+        assert!(lines[1].ends_with("] Yoshino: hi"));
+    }
+}
@@ -0,0 +1,63 @@
+use ai::{LLM, gemini::Gemini};
+
+use crate::{config::TranslationConfig, storage::UsageRecorder};
+
+/// Translates foreign comments so the character keeps answering in its own language.
+pub struct Translator {
+    llm: Gemini<'static>,
+    usage: UsageRecorder,
+}
+
+impl Translator {
+    pub fn new(config: &TranslationConfig, api_key: &str, usage: UsageRecorder) -> Self {
+        let system_prompt = format!(
+            "Translate the viewer comment you receive into {}. Keep names, emotes and the tone of \
+             the comment. Reply with the translation only.",
+            config.language
+        );
+        let mut llm = Gemini::new(
+            api_key.to_string(),
+            config.model.clone(),
+            Some(system_prompt.into()),
+        );
+        llm.set_thinking(false);
+        Self { llm, usage }
+    }
+
+    pub async fn translate(&mut self, text: &str) -> anyhow::Result<String> {
+        // every comment is translated on its own
+        self.llm.clear_history();
+        let translation = self.llm.chat(text).await?;
+        self.usage.record(self.llm.last_usage()).await;
+        Ok(translation.trim().to_string())
+    }
+}
+
+/// Whether a comment is written in neither Chinese nor Japanese.
+///
+/// Comments without any letters, like emotes or numbers, are left alone.
+pub fn needs_translation(text: &str) -> bool {
+    let is_cjk = |c: char| {
+        matches!(c,
+            '\u{3040}'..='\u{30ff}' // kana
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}')
+    };
+    !text.chars().any(is_cjk) && text.chars().any(char::is_alphabetic)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::translation::needs_translation;
+
+    #[test]
+    fn detect_foreign_comments() {
+        assert!(needs_translation("hello, how are you?"));
+        assert!(needs_translation("Привет"));
+
+        assert!(!needs_translation("你好"));
+        assert!(!needs_translation("こんにちは"));
+        assert!(!needs_translation("ムラサメちゃん hello"));
+        assert!(!needs_translation("233 :) 888"));
+    }
+}
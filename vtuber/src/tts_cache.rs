@@ -0,0 +1,67 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+
+/// Synthesized voices on disk, so the same line is only generated once.
+pub struct TtsCache {
+    dir: PathBuf,
+}
+
+impl TtsCache {
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    pub fn get(&self, text: &str, voice: Option<&str>, speed: Option<f32>) -> Option<Bytes> {
+        fs::read(self.path(text, voice, speed))
+            .ok()
+            .map(Bytes::from)
+    }
+
+    pub fn put(&self, text: &str, voice: Option<&str>, speed: Option<f32>, audio: &Bytes) {
+        let path = self.path(text, voice, speed);
+        if let Err(e) = fs::write(&path, audio) {
+            log::error!("Failed to cache voice at {}: {e}", path.display());
+        }
+    }
+
+    fn path(&self, text: &str, voice: Option<&str>, speed: Option<f32>) -> PathBuf {
+        let key = format!(
+            "{}\0{}\0{text}",
+            voice.unwrap_or_default(),
+            speed.unwrap_or(1.0)
+        );
+        let hash: String = Sha256::digest(key)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        self.dir.join(format!("{hash}.wav"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::tts_cache::TtsCache;
+
+    #[test]
+    fn cache_per_voice_and_speed() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = TtsCache::open(dir.path()).unwrap();
+        let audio = Bytes::from_static(b"RIFF");
+
+        assert_eq!(cache.get("hello", None, None), None);
+        cache.put("hello", None, None, &audio);
+        assert_eq!(cache.get("hello", None, Some(1.0)), Some(audio));
+        assert_eq!(cache.get("hello", Some("yoshino"), None), None);
+        assert_eq!(cache.get("hello", None, Some(1.2)), None);
+    }
+}
@@ -1,5 +1,44 @@
-use std::env;
+use std::{
+    fs,
+    io::{BufReader, Cursor},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use rodio::Source;
 
 pub fn get_env(name: &str) -> anyhow::Result<String> {
-    env::var(name).map_err(|_| anyhow::anyhow!("Environment variable {name} not found"))
+    Ok(config::get_env(name)?)
+}
+
+/// Like [`get_env`], also looking in the OS keyring, see [`config::get_secret`].
+pub fn get_secret(name: &str) -> anyhow::Result<String> {
+    Ok(config::get_secret(name)?)
+}
+
+/// The `.env` file loaded at startup, in the working directory or one above it, without
+/// loading it again.
+pub fn find_env_file() -> Option<PathBuf> {
+    let dir = std::env::current_dir().ok()?;
+    dir.ancestors()
+        .map(|dir| dir.join(".env"))
+        .find(|path| path.is_file())
+}
+
+/// Read a list file: one entry per line, blank lines and `#` comments are skipped.
+pub fn read_list(path: impl AsRef<Path>) -> anyhow::Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Length of an encoded audio clip, if the decoder can tell.
+pub fn audio_duration(audio: &Bytes) -> Option<Duration> {
+    rodio::Decoder::new(BufReader::new(Cursor::new(audio.clone())))
+        .ok()
+        .and_then(|source| source.total_duration())
 }
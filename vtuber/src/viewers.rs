@@ -0,0 +1,238 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs, io,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::shutdown::Shutdown;
+
+/// Messages further apart than this start a new visit.
+const VISIT_GAP_SECS: u64 = 30 * 60;
+/// Counted messages are saved this often, not after every one.
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, schemars::JsonSchema)]
+pub struct ViewerProfile {
+    /// Unix timestamps in seconds
+    pub first_seen: u64,
+    pub last_seen: u64,
+    /// When the previous visit ended, unset during the first one
+    #[serde(default)]
+    pub previous_visit: Option<u64>,
+    pub visits: u64,
+    pub message_count: u64,
+    #[serde(default)]
+    pub nickname: Option<String>,
+    /// Things worth remembering, e.g. inside jokes
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+/// Viewers keyed by username, saved to a json file after every edit and every few seconds while
+/// messages are counted, see [`spawn_saver`].
+pub struct ViewerRegistry {
+    path: PathBuf,
+    viewers: Mutex<BTreeMap<String, ViewerProfile>>,
+    /// Messages were counted since the last save
+    dirty: AtomicBool,
+}
+
+impl ViewerRegistry {
+    /// Load the registry, starting empty if the file does not exist yet.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let viewers = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            path,
+            viewers: Mutex::new(viewers),
+            dirty: AtomicBool::new(false),
+        })
+    }
+
+    /// Count a message, saved with the next [`ViewerRegistry::flush`].
+    pub fn record_message(&self, user: &str, now: u64) {
+        let mut viewers = self.viewers.lock().unwrap();
+        let profile = viewers
+            .entry(user.to_string())
+            .or_insert_with(|| ViewerProfile {
+                first_seen: now,
+                last_seen: now,
+                ..Default::default()
+            });
+
+        if profile.visits == 0 || now.saturating_sub(profile.last_seen) > VISIT_GAP_SECS {
+            if profile.visits > 0 {
+                profile.previous_visit = Some(profile.last_seen);
+            }
+            profile.visits += 1;
+        }
+        profile.last_seen = now;
+        profile.message_count += 1;
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Save the counted messages if there are any.
+    pub fn flush(&self) -> io::Result<()> {
+        let viewers = self.viewers.lock().unwrap();
+        if self.dirty.swap(false, Ordering::Relaxed) {
+            self.save(&viewers)?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, user: &str) -> Option<ViewerProfile> {
+        self.viewers.lock().unwrap().get(user).cloned()
+    }
+
+    pub fn list(&self) -> BTreeMap<String, ViewerProfile> {
+        self.viewers.lock().unwrap().clone()
+    }
+
+    /// Edit a known viewer, returns the updated profile.
+    pub fn update(
+        &self,
+        user: &str,
+        f: impl FnOnce(&mut ViewerProfile),
+    ) -> io::Result<Option<ViewerProfile>> {
+        let mut viewers = self.viewers.lock().unwrap();
+        let Some(profile) = viewers.get_mut(user) else {
+            return Ok(None);
+        };
+        f(profile);
+        let profile = profile.clone();
+
+        self.dirty.store(false, Ordering::Relaxed);
+        self.save(&viewers)?;
+        Ok(Some(profile))
+    }
+
+    /// Describe the viewer for the LLM, prepended to their comment. `name` is their display name.
+    pub fn prompt_context(&self, user: &str, name: &str, now: u64) -> String {
+        let profile = self.get(user).unwrap_or_default();
+
+        let mut context = String::from("<viewer>\n");
+        let _ = writeln!(context, "name: {name}");
+        if let Some(nickname) = &profile.nickname {
+            let _ = writeln!(context, "nickname: {nickname}");
+        }
+        match profile.previous_visit {
+            None => {
+                let _ = writeln!(context, "first visit, greet them as a newcomer");
+            }
+            Some(previous) => {
+                let _ = writeln!(
+                    context,
+                    "returning viewer, visit #{}, last seen {}, first seen {}",
+                    profile.visits,
+                    format_ago(now.saturating_sub(previous)),
+                    format_ago(now.saturating_sub(profile.first_seen)),
+                );
+            }
+        }
+        let _ = writeln!(context, "messages so far: {}", profile.message_count);
+        for note in &profile.notes {
+            let _ = writeln!(context, "note: {note}");
+        }
+        context.push_str("</viewer>");
+        context
+    }
+
+    fn save(&self, viewers: &BTreeMap<String, ViewerProfile>) -> io::Result<()> {
+        // write a temporary file first so a crash can't leave a truncated registry
+        let tmp = self.path.with_extension("tmp");
+        let saved = serde_json::to_vec_pretty(viewers)
+            .map_err(io::Error::from)
+            .and_then(|json| fs::write(&tmp, json))
+            .and_then(|()| fs::rename(tmp, &self.path));
+        if saved.is_err() {
+            // tried again with the next flush
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        saved
+    }
+}
+
+/// Save the counted messages every few seconds until shutdown, the last ones are saved by
+/// whoever stops the app.
+pub fn spawn_saver(registry: Arc<ViewerRegistry>, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SAVE_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.triggered() => break,
+            }
+            let registry = registry.clone();
+            let saved = tokio::task::spawn_blocking(move || registry.flush()).await;
+            if let Err(e) = saved.map_err(io::Error::other).and_then(|saved| saved) {
+                log::error!("Failed to save viewer profiles: {e}");
+            }
+        }
+    });
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn format_ago(secs: u64) -> String {
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} minutes ago", secs / 60),
+        3600..86400 => format!("{} hours ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::viewers::ViewerRegistry;
+
+    #[test]
+    fn track_visits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("viewers.json");
+        let registry = ViewerRegistry::load(path.clone()).unwrap();
+
+        registry.record_message("viewer", 1_000);
+        registry.record_message("viewer", 1_100);
+        assert!(
+            registry
+                .prompt_context("viewer", "viewer", 1_100)
+                .contains("first visit")
+        );
+
+        // come back two days later
+        let later = 1_100 + 2 * 86400;
+        registry.record_message("viewer", later);
+        // nothing written until flushed
+        assert!(!path.exists());
+        registry.flush().unwrap();
+        registry
+            .update("viewer", |profile| {
+                profile.notes.push("likes melon bread".into())
+            })
+            .unwrap();
+
+        let context = ViewerRegistry::load(path.clone())
+            .unwrap()
+            .prompt_context("viewer", "Viewer", later);
+        assert!(context.contains("name: Viewer"));
+        assert!(context.contains("visit #2, last seen 2 days ago"));
+        assert!(context.contains("messages so far: 3"));
+        assert!(context.contains("note: likes melon bread"));
+    }
+}
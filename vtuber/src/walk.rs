@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui::{self, Pos2, Rect};
+
+use crate::config::WalkConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+struct Walk {
+    from: f32,
+    to: f32,
+    started_at: Instant,
+}
+
+/// Walks the window along the bottom of the screen now and then.
+pub struct Walker {
+    /// Average time standing between walks
+    interval: Duration,
+    /// Pixels per second
+    speed: f32,
+    next_walk: Instant,
+    walk: Option<Walk>,
+}
+
+impl Walker {
+    pub fn new(config: &WalkConfig, now: Instant) -> Self {
+        let mut walker = Self {
+            interval: config.interval,
+            speed: config.speed,
+            next_walk: now,
+            walk: None,
+        };
+        walker.schedule(now);
+        walker
+    }
+
+    /// Where the window goes next, somewhere along the bottom of the screen it is on. Returns the
+    /// new position and the walking direction, or nothing while standing.
+    pub fn update(
+        &mut self,
+        now: Instant,
+        window: Rect,
+        screen: Rect,
+    ) -> Option<(Pos2, Direction)> {
+        if self.walk.is_none() && now >= self.next_walk {
+            let room = (screen.width() - window.width()).max(0.0);
+            self.walk = Some(Walk {
+                from: window.min.x,
+                to: screen.min.x + fastrand::f32() * room,
+                started_at: now,
+            });
+        }
+        let walk = self.walk.as_ref()?;
+
+        let distance = (walk.to - walk.from).abs();
+        let walked = (now.duration_since(walk.started_at).as_secs_f32() * self.speed).min(distance);
+        let direction = if walk.to < walk.from {
+            Direction::Left
+        } else {
+            Direction::Right
+        };
+        let x = match direction {
+            Direction::Left => walk.from - walked,
+            Direction::Right => walk.from + walked,
+        };
+        if walked >= distance {
+            self.stop(now);
+        }
+        Some((egui::pos2(x, screen.max.y - window.height()), direction))
+    }
+
+    /// Stand still, e.g. while the window is held, and walk again later.
+    pub fn stop(&mut self, now: Instant) {
+        self.walk = None;
+        self.schedule(now);
+    }
+
+    pub fn is_walking(&self) -> bool {
+        self.walk.is_some()
+    }
+
+    fn schedule(&mut self, now: Instant) {
+        self.next_walk = now + self.interval.mul_f32(0.5 + fastrand::f32());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use eframe::egui::{self, Rect};
+
+    use crate::{
+        config::WalkConfig,
+        walk::{Direction, Walker},
+    };
+
+    #[test]
+    fn walk_to_somewhere_and_stop() {
+        let config = WalkConfig {
+            interval: Duration::from_secs(60),
+            speed: 100.0,
+        };
+        let screen = Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1300.0, 1000.0));
+        let window = Rect::from_min_size(egui::pos2(500.0, 800.0), egui::vec2(300.0, 200.0));
+        let start = Instant::now();
+        let mut walker = Walker::new(&config, start);
+        assert!(walker.update(start, window, screen).is_none());
+
+        // the first walk starts within one and a half intervals
+        let walk = start + Duration::from_secs(90);
+        let (position, direction) = walker.update(walk, window, screen).unwrap();
+        assert_eq!(position, egui::pos2(500.0, 800.0));
+        let second = walk + Duration::from_secs(1);
+        let (position, _) = walker.update(second, window, screen).unwrap();
+        // 100 pixels a second, unless the target was closer
+        let walked = match direction {
+            Direction::Left => 500.0 - position.x,
+            Direction::Right => position.x - 500.0,
+        };
+        assert!((0.0..=100.0).contains(&walked));
+
+        // at most 500 pixels away, so there by now
+        let window = window.translate(egui::vec2(position.x - 500.0, 0.0));
+        if let Some((position, _)) = walker.update(walk + Duration::from_secs(6), window, screen) {
+            assert!((0.0..=1000.0).contains(&position.x));
+        }
+        assert!(!walker.is_walking());
+        assert!(
+            walker
+                .update(walk + Duration::from_secs(7), window, screen)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn walk_along_the_bottom_of_the_screen_it_is_on() {
+        let config = WalkConfig {
+            interval: Duration::from_secs(1),
+            speed: 10_000.0,
+        };
+        // left of the primary screen and lower
+        let screen = Rect::from_min_size(egui::pos2(-1920.0, 360.0), egui::vec2(1920.0, 1080.0));
+        let mut window = Rect::from_min_size(egui::pos2(-1000.0, 500.0), egui::vec2(300.0, 200.0));
+        let mut now = Instant::now();
+        let mut walker = Walker::new(&config, now);
+        for _ in 0..20 {
+            now += Duration::from_secs(2);
+            // starts a walk and finishes it at once at this speed
+            let Some((position, _)) = walker.update(now, window, screen) else {
+                continue;
+            };
+            assert_eq!(position.y, 1240.0);
+            assert!((-1920.0..=-300.0).contains(&position.x), "{position:?}");
+            window = Rect::from_min_size(position, window.size());
+        }
+        assert_eq!(window.max.y, screen.max.y);
+    }
+}